@@ -0,0 +1,133 @@
+//! Throughput of the two analyzers on fixture articles of increasing size.
+//!
+//! `cargo bench --bench analyzers -- --save-baseline main` records a baseline;
+//! `cargo bench --bench analyzers -- --baseline main` then fails if any benchmark's mean time
+//! got slower than `WIKI_ENGINE_BENCH_MAX_REGRESSION_PCT` (default 10) percent.
+
+use criterion::{black_box, BenchmarkId, Criterion, Throughput};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use wiki_engine::analyzer::EngineeringAnalyzer;
+use wiki_engine::semantic_analyzer::SemanticAnalyzer;
+use wiki_engine::types::{DocumentSourceKind, WikipediaPage};
+
+const ARTICLE: &str = include_str!("fixtures/electric_motor.txt");
+
+/// Not in the knowledge base, so decomposition always extracts components from the text
+const UNKNOWN_CONCEPT: &str = "Fixture machine";
+
+const DEFAULT_MAX_REGRESSION_PCT: f64 = 10.0;
+
+/// The lead paragraph, the whole article, and the article repeated as a long page
+fn fixtures() -> Vec<(&'static str, String)> {
+    let lead = ARTICLE.split("\n\n").next().unwrap_or(ARTICLE).to_string();
+    vec![("lead", lead), ("article", ARTICLE.to_string()), ("long", ARTICLE.repeat(8))]
+}
+
+fn page(extract: &str) -> WikipediaPage {
+    WikipediaPage {
+        title: "Electric motor".to_string(),
+        extract: extract.to_string(),
+        url: "https://en.wikipedia.org/wiki/Electric_motor".to_string(),
+        page_id: 0,
+        revision_id: None,
+        fetched_at: chrono::Utc::now(),
+        thumbnail_url: None,
+        monthly_views: None,
+        curated_links: vec![],
+        etag: None,
+        last_modified: None,
+        source: DocumentSourceKind::default(),
+    }
+}
+
+fn analyze_page(c: &mut Criterion) {
+    let analyzer = EngineeringAnalyzer::new().expect("analyzer patterns compile");
+    let mut group = c.benchmark_group("analyze_page");
+    for (name, text) in fixtures() {
+        let page = page(&text);
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &page, |b, page| {
+            b.iter(|| analyzer.analyze_page(black_box(page)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn extract_components(c: &mut Criterion) {
+    let analyzer = SemanticAnalyzer::new().expect("semantic analyzer initializes");
+    let mut group = c.benchmark_group("extract_components_from_text");
+    for (name, text) in fixtures() {
+        group.throughput(Throughput::Bytes(text.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &text, |b, text| {
+            b.iter(|| analyzer.decompose_concept(UNKNOWN_CONCEPT, Some(black_box(text)), 2).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn main() {
+    let started = SystemTime::now();
+    let mut criterion = Criterion::default().configure_from_args();
+    analyze_page(&mut criterion);
+    extract_components(&mut criterion);
+    criterion.final_summary();
+
+    let regressions = regressions_since(started);
+    if !regressions.is_empty() {
+        for (benchmark, change) in &regressions {
+            eprintln!("regression: {} is {:.1}% slower than the baseline", benchmark, change);
+        }
+        std::process::exit(1);
+    }
+}
+
+/// Benchmarks compared against a baseline in this run whose mean slowed down by more than the
+/// allowed percentage, judged by the lower bound of criterion's confidence interval so noise
+/// alone does not fail the run
+fn regressions_since(started: SystemTime) -> Vec<(String, f64)> {
+    let max_regression = std::env::var("WIKI_ENGINE_BENCH_MAX_REGRESSION_PCT")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_MAX_REGRESSION_PCT);
+    let root = criterion_home();
+
+    let mut estimates = Vec::new();
+    collect_change_estimates(&root, &mut estimates);
+    estimates
+        .into_iter()
+        .filter(|path| {
+            let modified = std::fs::metadata(path).and_then(|metadata| metadata.modified());
+            modified.is_ok_and(|modified| modified >= started)
+        })
+        .filter_map(|path| {
+            let estimates: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).ok()?).ok()?;
+            let lower_bound = estimates["mean"]["confidence_interval"]["lower_bound"].as_f64()? * 100.0;
+            // <root>/<group>/<benchmark>/change/estimates.json
+            let benchmark = path.parent()?.parent()?.strip_prefix(&root).ok()?.display().to_string();
+            (lower_bound > max_regression).then_some((benchmark, lower_bound))
+        })
+        .collect()
+}
+
+/// Where criterion writes its reports, following the same lookup criterion itself uses
+fn criterion_home() -> PathBuf {
+    if let Some(home) = std::env::var_os("CRITERION_HOME") {
+        return PathBuf::from(home);
+    }
+    std::env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("target"))
+        .join("criterion")
+}
+
+fn collect_change_estimates(dir: &Path, estimates: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.is_dir() {
+            collect_change_estimates(&path, estimates);
+        } else if path.ends_with("change/estimates.json") {
+            estimates.push(path);
+        }
+    }
+}
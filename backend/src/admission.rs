@@ -0,0 +1,177 @@
+use crate::config::ConcurrencyConfig;
+use crate::types::{Result, WikiEngineError};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Admission control for analyses: a fixed number run at once, a bounded number wait for a
+/// slot, and anything beyond that (or waiting too long) is turned away as overloaded. The
+/// limits can be changed while analyses run (see `resize`).
+pub struct AnalysisLimiter {
+    permits: Semaphore,
+    /// Slots in total, running or free
+    capacity: AtomicUsize,
+    /// Slots still to be retired after a shrink, as running analyses give them back
+    retiring: AtomicUsize,
+    queued: AtomicUsize,
+    max_queued: AtomicUsize,
+    queue_timeout_ms: AtomicU64,
+}
+
+impl AnalysisLimiter {
+    pub fn new(config: &ConcurrencyConfig) -> Self {
+        let capacity = config.max_concurrent_analyses.max(1);
+        Self {
+            permits: Semaphore::new(capacity),
+            capacity: AtomicUsize::new(capacity),
+            retiring: AtomicUsize::new(0),
+            queued: AtomicUsize::new(0),
+            max_queued: AtomicUsize::new(config.max_queued_analyses),
+            queue_timeout_ms: AtomicU64::new(config.queue_timeout_secs * 1000),
+        }
+    }
+
+    /// Wait for a slot; the analysis may run while the returned slot is held
+    pub async fn acquire(&self) -> Result<AnalysisSlot<'_>> {
+        if let Ok(permit) = self.permits.try_acquire() {
+            return Ok(AnalysisSlot { limiter: self, permit: Some(permit) });
+        }
+
+        let _queued = QueueSlot::take(self).ok_or_else(|| self.overloaded())?;
+        match tokio::time::timeout(self.queue_timeout(), self.permits.acquire()).await {
+            Ok(Ok(permit)) => Ok(AnalysisSlot { limiter: self, permit: Some(permit) }),
+            _ => Err(self.overloaded()),
+        }
+    }
+
+    /// Apply new limits. Growing frees slots straight away; when shrinking, free slots are
+    /// retired at once and the rest as the analyses holding them finish.
+    pub fn resize(&self, config: &ConcurrencyConfig) {
+        let capacity = config.max_concurrent_analyses.max(1);
+        let previous = self.capacity.swap(capacity, Ordering::SeqCst);
+        if capacity > previous {
+            // Slots not yet retired cover part of the growth
+            let mut grow = capacity - previous;
+            let _ = self.retiring.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |retiring| {
+                let cancelled = retiring.min(capacity - previous);
+                grow = capacity - previous - cancelled;
+                Some(retiring - cancelled)
+            });
+            self.permits.add_permits(grow);
+        } else {
+            let shrink = previous - capacity;
+            let forgotten = self.permits.forget_permits(shrink);
+            self.retiring.fetch_add(shrink - forgotten, Ordering::SeqCst);
+        }
+        self.max_queued.store(config.max_queued_analyses, Ordering::SeqCst);
+        self.queue_timeout_ms.store(config.queue_timeout_secs * 1000, Ordering::SeqCst);
+    }
+
+    /// Longest an analysis waits for a slot before being turned away
+    pub fn queue_timeout(&self) -> Duration {
+        Duration::from_millis(self.queue_timeout_ms.load(Ordering::SeqCst))
+    }
+
+    /// Analyses currently waiting for a slot
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::SeqCst)
+    }
+
+    fn overloaded(&self) -> WikiEngineError {
+        WikiEngineError::Overloaded { retry_after: Some(self.queue_timeout().as_secs().max(1)) }
+    }
+}
+
+/// A running analysis's slot, given back (or retired, after a shrink) when dropped
+pub struct AnalysisSlot<'a> {
+    limiter: &'a AnalysisLimiter,
+    permit: Option<SemaphorePermit<'a>>,
+}
+
+impl Drop for AnalysisSlot<'_> {
+    fn drop(&mut self) {
+        let retire = self
+            .limiter
+            .retiring
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |retiring| retiring.checked_sub(1))
+            .is_ok();
+        if let Some(permit) = self.permit.take().filter(|_| retire) {
+            permit.forget();
+        }
+    }
+}
+
+/// A place in the queue, given back when the wait ends or the request is dropped
+struct QueueSlot<'a> {
+    limiter: &'a AnalysisLimiter,
+}
+
+impl<'a> QueueSlot<'a> {
+    fn take(limiter: &'a AnalysisLimiter) -> Option<Self> {
+        let max_queued = limiter.max_queued.load(Ordering::SeqCst);
+        limiter
+            .queued
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |queued| {
+                (queued < max_queued).then_some(queued + 1)
+            })
+            .ok()
+            .map(|_| Self { limiter })
+    }
+}
+
+impl Drop for QueueSlot<'_> {
+    fn drop(&mut self) {
+        self.limiter.queued.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn turns_away_requests_beyond_the_queue() {
+        let limiter = AnalysisLimiter::new(&ConcurrencyConfig {
+            max_concurrent_analyses: 1,
+            max_queued_analyses: 0,
+            queue_timeout_secs: 5,
+            ..Default::default()
+        });
+
+        let running = limiter.acquire().await.unwrap();
+        assert!(matches!(
+            limiter.acquire().await,
+            Err(WikiEngineError::Overloaded { retry_after: Some(5) })
+        ));
+
+        drop(running);
+        assert!(limiter.acquire().await.is_ok());
+        assert_eq!(limiter.queued(), 0);
+    }
+
+    #[tokio::test]
+    async fn resizing_takes_effect_as_slots_free_up() {
+        let config = |max_concurrent_analyses| ConcurrencyConfig {
+            max_concurrent_analyses,
+            max_queued_analyses: 0,
+            queue_timeout_secs: 1,
+            ..Default::default()
+        };
+        let limiter = AnalysisLimiter::new(&config(2));
+        let first = limiter.acquire().await.unwrap();
+        let second = limiter.acquire().await.unwrap();
+
+        limiter.resize(&config(1));
+        drop(first);
+        // The slot given back is retired, so the one still running uses up the new limit
+        assert!(limiter.acquire().await.is_err());
+        drop(second);
+        let only = limiter.acquire().await.unwrap();
+        assert!(limiter.acquire().await.is_err());
+
+        limiter.resize(&config(3));
+        let _more = [limiter.acquire().await.unwrap(), limiter.acquire().await.unwrap()];
+        assert!(limiter.acquire().await.is_err());
+        drop(only);
+    }
+}
@@ -1,78 +1,97 @@
-use crate::types::{EngineeringPrinciple, PrincipleCategory, Result, WikiEngineError, WikipediaPage};
+use crate::categories;
+use crate::config::ConceptFilterConfig;
+use crate::dedup::NearDuplicateIndex;
+use crate::intern::Term;
+use crate::language;
+use crate::laws;
+use crate::text;
+use crate::types::{CategoryClassifier, EngineeringPrinciple, GlossaryEntry, PrincipleCategory, Result, WikiEngineError, WikipediaPage};
 use regex::Regex;
 use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+
+/// Capitalized phrases that show up constantly in Wikipedia extracts but never lead anywhere useful
+const CONCEPT_STOP_LIST: &[&str] = &[
+    // Places and demonyms
+    "United States", "United Kingdom", "Soviet Union", "European Union", "America", "American",
+    "Europe", "European", "Asia", "Africa", "China", "Chinese", "Japan", "Japanese", "Germany",
+    "German", "France", "French", "Britain", "British", "England", "English", "Russia", "Russian",
+    "India", "Italy", "Italian", "Canada", "Australia", "Latin", "Greek", "Ancient Greek",
+    // Wars and eras
+    "World War", "First World War", "Second World War", "Cold War", "Middle Ages", "Renaissance",
+    "Industrial Revolution", "Century",
+    // Calendar
+    "January", "February", "March", "April", "June", "July", "August", "September", "October",
+    "November", "December", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday",
+    "Sunday",
+    // Sentence openers the capitalized-phrase regex picks up
+    "However", "Although", "During", "Since", "After", "Before", "There", "Their", "These", "Those",
+    "This", "That", "Many", "Most", "Some", "Other", "Today", "While", "Because", "Several", "Each",
+    "Both", "Such", "Following", "Wikipedia",
+];
+
+/// Confidence a sentence needs to be kept as a principle, unless a calibration sets another
+pub const MIN_PRINCIPLE_CONFIDENCE: f32 = 0.3;
+
+// Fixed patterns used on every sentence, compiled once rather than per call
+
+/// Capitalized phrases and hyphenated compounds
+static TECHNICAL_TERM: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b\p{Lu}\p{Ll}+(?:-\p{L}+)*(?:\s+\p{Lu}\p{Ll}+(?:-\p{L}+)*)*\b|\b\p{Ll}+(?:-\p{Ll}+)+\b").unwrap());
+static CAPITALIZED_PHRASE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b\p{Lu}\p{Ll}+(?:-\p{L}+)*(?:\s+\p{Lu}\p{Ll}+(?:-\p{L}+)*)*\b").unwrap());
+static PARENTHESIZED: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\(([^)]+)\)").unwrap());
+static MATH_NOTATION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[=<>±∆∇∑∏∫]|\\[a-zA-Z]+").unwrap());
+/// "X is a ...", capturing X
+static IS_A_DEFINITION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:An?\s+|The\s+)?(\p{L}[\w\-]*(?:\s+[\w\-]+){0,4}?)\s+(?:is|are|refers\s+to)\s+(?:an?|the)\s+\S").unwrap()
+});
+/// "words (a/the definition)", capturing the words and the definition
+static PARENTHETICAL_DEFINITION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(\p{Ll}[\w\-]*(?:\s+\p{Ll}[\w\-]*){0,3})\s+\(((?:an?|the)\s+[^)]{5,120})\)").unwrap()
+});
+
+/// A sentence of a page the patterns found principle indicators in, with the category and
+/// confidence they gave it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredSentence {
+    /// Byte range of the sentence in the page text
+    pub span: (usize, usize),
+    pub category: PrincipleCategory,
+    pub confidence: f32,
+}
 
 pub struct EngineeringAnalyzer {
-    structural_patterns: Vec<Regex>,
-    mechanical_patterns: Vec<Regex>,
-    electrical_patterns: Vec<Regex>,
-    thermal_patterns: Vec<Regex>,
-    chemical_patterns: Vec<Regex>,
-    material_patterns: Vec<Regex>,
-    system_patterns: Vec<Regex>,
-    process_patterns: Vec<Regex>,
-    design_patterns: Vec<Regex>,
     principle_extractors: Vec<Regex>,
     related_term_extractors: Vec<Regex>,
+    concept_stop_list: HashSet<String>,
+    min_concept_relevance: usize,
+    /// Configured stop words, case-folded, checked in every language
+    extra_stop_words: HashSet<String>,
+    /// Confidence a sentence needs to become a principle
+    min_confidence: f32,
 }
 
 impl EngineeringAnalyzer {
     pub fn new() -> Result<Self> {
+        Self::with_concept_filter(ConceptFilterConfig::from_env())
+    }
+
+    pub fn with_concept_filter(concept_filter: ConceptFilterConfig) -> Result<Self> {
+        let concept_stop_list = CONCEPT_STOP_LIST
+            .iter()
+            .map(|term| term.to_string())
+            .chain(concept_filter.extra_stop_terms)
+            .map(|term| text::stem_phrase(&term))
+            .collect();
+
+        let extra_stop_words = concept_filter.extra_stop_words.iter().map(|word| text::fold_case(word)).collect();
+
         Ok(Self {
-            structural_patterns: Self::compile_patterns(&[
-                r"(?i)(load|stress|strain|tension|compression|shear|moment|deflection)",
-                r"(?i)(beam|column|truss|frame|foundation|support)",
-                r"(?i)(buckling|fatigue|failure|strength|stiffness)",
-                r"(?i)(structural\s+integrity|bearing\s+capacity|factor\s+of\s+safety)",
-            ])?,
-            mechanical_patterns: Self::compile_patterns(&[
-                r"(?i)(force|torque|power|energy|motion|velocity|acceleration)",
-                r"(?i)(gear|lever|pulley|spring|damper|actuator)",
-                r"(?i)(friction|lubrication|wear|vibration|resonance)",
-                r"(?i)(mechanical\s+advantage|efficiency|work|momentum)",
-            ])?,
-            electrical_patterns: Self::compile_patterns(&[
-                r"(?i)(voltage|current|resistance|capacitance|inductance)",
-                r"(?i)(circuit|conductor|insulator|semiconductor|transistor)",
-                r"(?i)(electric\s+field|magnetic\s+field|electromagnetic)",
-                r"(?i)(ohm's\s+law|kirchhoff|maxwell|faraday)",
-            ])?,
-            thermal_patterns: Self::compile_patterns(&[
-                r"(?i)(heat|temperature|thermal|conduction|convection|radiation)",
-                r"(?i)(thermodynamic|entropy|enthalpy|specific\s+heat)",
-                r"(?i)(heat\s+transfer|thermal\s+expansion|insulation)",
-                r"(?i)(carnot|stefan.boltzmann|fourier)",
-            ])?,
-            chemical_patterns: Self::compile_patterns(&[
-                r"(?i)(reaction|catalyst|equilibrium|kinetics|stoichiometry)",
-                r"(?i)(acid|base|oxidation|reduction|pH|molarity)",
-                r"(?i)(chemical\s+bond|molecular|atomic|ionic)",
-                r"(?i)(mass\s+transfer|diffusion|absorption|distillation)",
-            ])?,
-            material_patterns: Self::compile_patterns(&[
-                r"(?i)(crystal|grain|microstructure|phase|alloy)",
-                r"(?i)(elastic\s+modulus|yield\s+strength|hardness|toughness)",
-                r"(?i)(composite|polymer|ceramic|metal|semiconductor)",
-                r"(?i)(corrosion|oxidation|creep|fracture|fatigue)",
-            ])?,
-            system_patterns: Self::compile_patterns(&[
-                r"(?i)(feedback|control|regulation|stability|response)",
-                r"(?i)(input|output|transfer\s+function|block\s+diagram)",
-                r"(?i)(system\s+dynamics|optimization|performance|reliability)",
-                r"(?i)(redundancy|fault\s+tolerance|safety\s+factor)",
-            ])?,
-            process_patterns: Self::compile_patterns(&[
-                r"(?i)(manufacturing|production|assembly|quality\s+control)",
-                r"(?i)(workflow|procedure|protocol|standard|specification)",
-                r"(?i)(efficiency|throughput|yield|waste|optimization)",
-                r"(?i)(automation|robotics|lean|six\s+sigma)",
-            ])?,
-            design_patterns: Self::compile_patterns(&[
-                r"(?i)(requirement|specification|constraint|objective)",
-                r"(?i)(iteration|prototype|validation|verification)",
-                r"(?i)(trade.off|optimization|design\s+space|parameter)",
-                r"(?i)(modularity|scalability|maintainability|sustainability)",
-            ])?,
+            concept_stop_list,
+            min_concept_relevance: concept_filter.min_relevance,
+            extra_stop_words,
+            min_confidence: MIN_PRINCIPLE_CONFIDENCE,
             principle_extractors: Self::compile_patterns(&[
                 r"(?i)(principle|law|theorem|rule|equation|formula)",
                 r"(?i)(based\s+on|according\s+to|governed\s+by|follows)",
@@ -88,6 +107,16 @@ impl EngineeringAnalyzer {
         })
     }
 
+    /// Keep sentences as principles from `min_confidence` rather than `MIN_PRINCIPLE_CONFIDENCE`
+    pub fn with_min_confidence(mut self, min_confidence: f32) -> Self {
+        self.min_confidence = min_confidence;
+        self
+    }
+
+    pub fn min_confidence(&self) -> f32 {
+        self.min_confidence
+    }
+
     fn compile_patterns(patterns: &[&str]) -> Result<Vec<Regex>> {
         patterns
             .iter()
@@ -97,49 +126,66 @@ impl EngineeringAnalyzer {
             .collect()
     }
 
+    #[tracing::instrument(name = "regex_analyze_page", skip_all, fields(page = %page.title))]
     pub fn analyze_page(&self, page: &WikipediaPage) -> Result<Vec<EngineeringPrinciple>> {
         let mut principles = Vec::new();
         let text = &page.extract;
+        let language = language::detect_language(text).unwrap_or("en");
+        let acronyms = text::acronyms(text);
         
-        // Split text into sentences for better analysis
-        let sentences: Vec<&str> = text.split(". ").collect();
-        
-        for (i, sentence) in sentences.iter().enumerate() {
-            if let Some(principle) = self.extract_principle_from_sentence(sentence, page, i)? {
+        // Split text into sentences for better analysis, keeping each one's offset in the text
+        for (start, sentence) in text::sentences(text) {
+            let span = (start, start + sentence.len());
+            if let Some(principle) = self.extract_principle_from_sentence(sentence, page, span, language, &acronyms)? {
                 principles.push(principle);
             }
         }
 
         // Deduplicate and rank principles
-        self.deduplicate_and_rank(principles)
+        self.deduplicate_and_rank(principles, &acronyms)
+    }
+
+    /// Sentences of the page whose confidence is within `margin` of the minimum, on either side:
+    /// those the patterns were least sure to keep or drop
+    pub fn borderline_sentences(&self, page: &WikipediaPage, margin: f32) -> Vec<ScoredSentence> {
+        text::sentences(&page.extract)
+            .into_iter()
+            .filter_map(|(start, sentence)| {
+                let (category, confidence) = self.score_sentence(sentence)?;
+                let span = (start, start + sentence.len());
+                ((confidence - self.min_confidence).abs() <= margin).then_some(ScoredSentence { span, category, confidence })
+            })
+            .collect()
+    }
+
+    /// Category and confidence of a sentence with principle indicators, `None` for one without
+    fn score_sentence(&self, sentence: &str) -> Option<(PrincipleCategory, f32)> {
+        if !self.principle_extractors.iter().any(|pattern| pattern.is_match(sentence)) {
+            return None;
+        }
+        let category = self.categorize_text(sentence);
+        let confidence = self.calculate_confidence(sentence, &category);
+        Some((category, confidence))
     }
 
     fn extract_principle_from_sentence(
         &self,
         sentence: &str,
         page: &WikipediaPage,
-        _index: usize,
+        span: (usize, usize),
+        language: &str,
+        acronyms: &HashMap<String, String>,
     ) -> Result<Option<EngineeringPrinciple>> {
-        // Check if sentence contains principle indicators
-        let has_principle_indicators = self.principle_extractors.iter()
-            .any(|pattern| pattern.is_match(sentence));
-
-        if !has_principle_indicators {
+        // Category and confidence from the principle indicators and category patterns
+        let Some((category, confidence)) = self.score_sentence(sentence) else {
+            return Ok(None);
+        };
+        if confidence < self.min_confidence {
             return Ok(None);
         }
 
-        // Determine category based on pattern matching
-        let category = self.categorize_text(sentence);
-        
         // Extract related terms
-        let related_terms = self.extract_related_terms(sentence);
-
-        // Calculate confidence based on multiple factors
-        let confidence = self.calculate_confidence(sentence, &category);
-
-        if confidence < 0.3 {
-            return Ok(None);
-        }
+        let related_terms = self.extract_related_terms(sentence, language, acronyms);
 
         // Generate a meaningful title
         let title = self.extract_principle_title(sentence);
@@ -152,49 +198,44 @@ impl EngineeringAnalyzer {
             confidence,
             source_url: page.url.clone(),
             related_terms,
+            source_span: span,
+            source_section: Self::section_at(&page.extract, span.0),
+            appears_in: vec![],
+            classified_by: Some(CategoryClassifier::Patterns),
+            named_law: laws::detect(sentence).map(str::to_string),
+            component: None,
         }))
     }
 
-    fn categorize_text(&self, text: &str) -> PrincipleCategory {
-        let categories = vec![
-            (&self.structural_patterns, PrincipleCategory::Structural),
-            (&self.mechanical_patterns, PrincipleCategory::Mechanical),
-            (&self.electrical_patterns, PrincipleCategory::Electrical),
-            (&self.thermal_patterns, PrincipleCategory::Thermal),
-            (&self.chemical_patterns, PrincipleCategory::Chemical),
-            (&self.material_patterns, PrincipleCategory::Material),
-            (&self.system_patterns, PrincipleCategory::System),
-            (&self.process_patterns, PrincipleCategory::Process),
-            (&self.design_patterns, PrincipleCategory::Design),
-        ];
-
-        let mut scores = HashMap::new();
-        
-        for (patterns, category) in categories {
-            let mut score = 0;
-            for pattern in patterns {
-                score += pattern.find_iter(text).count();
-            }
-            if score > 0 {
-                scores.insert(category, score);
-            }
-        }
+    /// Best-guess category for a related concept name, or `None` when nothing matches
+    pub fn infer_concept_category(&self, concept: &str) -> Option<PrincipleCategory> {
+        Some(self.categorize_text(concept)).filter(PrincipleCategory::is_registered)
+    }
 
-        scores.into_iter()
-            .max_by_key(|(_, score)| *score)
-            .map(|(category, _)| category)
-            .unwrap_or(PrincipleCategory::Other("General".to_string()))
+    fn categorize_text(&self, text: &str) -> PrincipleCategory {
+        categories::registry().categorize(text).unwrap_or_else(PrincipleCategory::general)
     }
 
-    fn extract_related_terms(&self, text: &str) -> Vec<String> {
-        let mut terms = HashSet::new();
+    /// Up to five technical terms of `text`, acronyms the page defines spelled out
+    fn extract_related_terms(&self, text: &str, language: &str, acronyms: &HashMap<String, String>) -> Vec<Term> {
+        // Preserve order of appearance so the selected terms are deterministic
+        let mut terms = Vec::new();
+        let mut seen = HashSet::new();
         
         // Extract technical terms (capitalized words, hyphenated terms)
-        let technical_term_pattern = Regex::new(r"\b[A-Z][a-z]+(?:\s+[A-Z][a-z]+)*\b|[a-z]+-[a-z]+").unwrap();
-        for mat in technical_term_pattern.find_iter(text) {
+        for mat in TECHNICAL_TERM.find_iter(text) {
             let term = mat.as_str().to_string();
-            if term.len() > 3 && !self.is_common_word(&term) {
-                terms.insert(term);
+            if term.len() > 3 && !self.is_common_word(&term, language) && seen.insert(term.clone()) {
+                terms.push(term);
+            }
+        }
+
+        // Acronyms the page defines, as the phrase they stand for
+        for word in text::words(text) {
+            if let Some(phrase) = acronyms.get(word) {
+                if seen.insert(phrase.clone()) {
+                    terms.push(phrase.clone());
+                }
             }
         }
 
@@ -204,18 +245,18 @@ impl EngineeringAnalyzer {
                 // Extract words following the pattern
                 let start = mat.end();
                 if let Some(following_text) = text.get(start..start.min(text.len()).min(start + 50)) {
-                    let words: Vec<&str> = following_text.split_whitespace().take(3).collect();
+                    let words: Vec<&str> = text::words(following_text).into_iter().take(3).collect();
                     if !words.is_empty() {
                         let term = words.join(" ");
-                        if !self.is_common_word(&term) {
-                            terms.insert(term);
+                        if !self.is_common_word(&term, language) && seen.insert(term.clone()) {
+                            terms.push(term);
                         }
                     }
                 }
             }
         }
 
-        terms.into_iter().take(5).collect()
+        terms.into_iter().take(5).map(Term::from).collect()
     }
 
     fn calculate_confidence(&self, text: &str, category: &PrincipleCategory) -> f32 {
@@ -228,20 +269,15 @@ impl EngineeringAnalyzer {
         confidence += principle_matches * 0.2;
 
         // Category-specific confidence
-        let category_patterns = match category {
-            PrincipleCategory::Structural => &self.structural_patterns,
-            PrincipleCategory::Mechanical => &self.mechanical_patterns,
-            PrincipleCategory::Electrical => &self.electrical_patterns,
-            PrincipleCategory::Thermal => &self.thermal_patterns,
-            PrincipleCategory::Chemical => &self.chemical_patterns,
-            PrincipleCategory::Material => &self.material_patterns,
-            PrincipleCategory::System => &self.system_patterns,
-            PrincipleCategory::Process => &self.process_patterns,
-            PrincipleCategory::Design => &self.design_patterns,
-            PrincipleCategory::Other(_) => return 0.3,
-        };
-
-        let category_matches = category_patterns.iter()
+        // Hits of a subcategory's own patterns and of those of the categories it refines
+        let registry = categories::registry();
+        if !category.is_registered() {
+            return 0.3;
+        }
+        let category_matches = std::iter::once(category.name())
+            .chain(registry.ancestors(category.name()))
+            .filter_map(|id| registry.patterns(id))
+            .flatten()
             .map(|pattern| pattern.find_iter(text).count())
             .sum::<usize>() as f32;
         confidence += category_matches * 0.15;
@@ -252,8 +288,7 @@ impl EngineeringAnalyzer {
         }
 
         // Mathematical expressions bonus
-        let math_pattern = Regex::new(r"[=<>±∆∇∑∏∫]|\\[a-zA-Z]+").unwrap();
-        if math_pattern.is_match(text) {
+        if MATH_NOTATION.is_match(text) {
             confidence += 0.2;
         }
 
@@ -266,30 +301,26 @@ impl EngineeringAnalyzer {
         let title = words.join(" ");
         
         // Clean up the title
-        title.trim_end_matches(&['.', ',', ';', ':']).to_string()
+        title.trim_end_matches(['.', ',', ';', ':']).to_string()
     }
 
-    fn is_common_word(&self, word: &str) -> bool {
-        let common_words = [
-            "the", "and", "that", "with", "for", "are", "can", "this", "will", "such",
-            "may", "also", "been", "have", "has", "was", "were", "from", "they", "these",
-            "more", "some", "other", "than", "only", "very", "when", "where", "what",
-        ];
-        common_words.contains(&word.to_lowercase().as_str())
+    /// Whether `word` is a stop word of `language` or one of the configured extra stop words
+    fn is_common_word(&self, word: &str, language: &str) -> bool {
+        let word = text::fold_case(word);
+        text::is_stop_word(&word, language) || self.extra_stop_words.contains(&word)
     }
 
-    fn deduplicate_and_rank(&self, mut principles: Vec<EngineeringPrinciple>) -> Result<Vec<EngineeringPrinciple>> {
+    fn deduplicate_and_rank(&self, mut principles: Vec<EngineeringPrinciple>, acronyms: &HashMap<String, String>) -> Result<Vec<EngineeringPrinciple>> {
         // Sort by confidence (highest first)
         principles.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap());
         
-        // Remove very similar principles
+        // Remove near-duplicate principles
+        let mut index = NearDuplicateIndex::new();
         let mut unique_principles = Vec::new();
         for principle in principles {
-            let is_duplicate = unique_principles.iter().any(|existing: &EngineeringPrinciple| {
-                self.similarity_score(&principle.description, &existing.description) > 0.8
-            });
-            
-            if !is_duplicate {
+            let signature = principle.signature_expanding(acronyms);
+            if !index.contains_near(signature) {
+                index.insert(signature, unique_principles.len());
                 unique_principles.push(principle);
             }
         }
@@ -300,44 +331,371 @@ impl EngineeringAnalyzer {
         Ok(unique_principles)
     }
 
-    fn similarity_score(&self, text1: &str, text2: &str) -> f32 {
-        let words1: HashSet<&str> = text1.split_whitespace().collect();
-        let words2: HashSet<&str> = text2.split_whitespace().collect();
-        
-        let intersection = words1.intersection(&words2).count();
-        let union = words1.union(&words2).count();
-        
-        if union == 0 {
-            0.0
-        } else {
-            intersection as f32 / union as f32
-        }
-    }
-
+    #[tracing::instrument(skip_all, fields(page = %page.title))]
     pub fn extract_related_concepts(&self, page: &WikipediaPage) -> Vec<String> {
-        let mut concepts = HashSet::new();
+        // Preserve order of appearance so the selected concepts are deterministic
+        let mut concepts = Vec::new();
+        let mut seen = HashSet::new();
         let text = &page.extract;
+        let language = language::detect_language(text).unwrap_or("en");
+        let acronyms = text::acronyms(text);
 
         // Extract capitalized terms that might be concepts
-        let concept_pattern = Regex::new(r"\b[A-Z][a-z]+(?:\s+[A-Z][a-z]+)*\b").unwrap();
-        for mat in concept_pattern.find_iter(text) {
+        for mat in CAPITALIZED_PHRASE.find_iter(text) {
             let concept = mat.as_str().to_string();
-            if concept.len() > 3 && !self.is_common_word(&concept) {
-                concepts.insert(concept);
+            if concept.len() > 3 && !self.is_common_word(&concept, language) && seen.insert(concept.clone()) {
+                concepts.push(concept);
             }
         }
 
         // Extract terms in parentheses (often definitions or clarifications)
-        let paren_pattern = Regex::new(r"\(([^)]+)\)").unwrap();
-        for caps in paren_pattern.captures_iter(text) {
+        for caps in PARENTHESIZED.captures_iter(text) {
             if let Some(content) = caps.get(1) {
-                let content_str = content.as_str();
-                if content_str.len() > 3 && content_str.len() < 50 {
-                    concepts.insert(content_str.to_string());
+                let content_str = acronyms.get(content.as_str()).map_or(content.as_str(), String::as_str);
+                if content_str.len() > 3 && content_str.len() < 50 && seen.insert(content_str.to_string()) {
+                    concepts.push(content_str.to_string());
                 }
             }
         }
 
-        concepts.into_iter().take(15).collect()
+        concepts
+            .into_iter()
+            .filter(|concept| self.is_candidate_concept(concept, text))
+            .take(15)
+            .collect()
+    }
+
+    /// Stop-list and relevance gate applied before a concept becomes a child candidate
+    fn is_candidate_concept(&self, concept: &str, text: &str) -> bool {
+        if self.concept_stop_list.contains(&text::stem_phrase(concept)) {
+            return false;
+        }
+        self.concept_relevance(concept, text) >= self.min_concept_relevance
     }
-}
\ No newline at end of file
+
+    /// Engineering keyword hits in the concept itself and in the sentences that mention it
+    fn concept_relevance(&self, concept: &str, text: &str) -> usize {
+        let context: String = text
+            .split_terminator(['.', '!', '?'])
+            .filter(|sentence| sentence.contains(concept))
+            .chain(std::iter::once(concept))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        categories::registry()
+            .pattern_sets()
+            .flat_map(|(_, patterns)| patterns)
+            .map(|pattern| pattern.find_iter(&context).count())
+            .sum()
+    }
+
+    /// 0.0-1.0 estimate of how advanced a topic is, from the length of its text, how many
+    /// sub-concepts and curated links it has, and the reading level of the prose
+    pub fn complexity_score(&self, page: &WikipediaPage) -> f32 {
+        let words = text::words(&page.extract);
+        if words.is_empty() {
+            return 0.0;
+        }
+        let sentences = page.extract.split_terminator(['.', '!', '?']).filter(|s| !s.trim().is_empty()).count().max(1);
+        let syllables: usize = words.iter().map(|word| Self::syllables(word)).sum();
+
+        // Flesch-Kincaid grade level; grade 20 and above counts as fully advanced
+        let grade = 0.39 * words.len() as f32 / sentences as f32 + 11.8 * syllables as f32 / words.len() as f32 - 15.59;
+        let reading_level = (grade / 20.0).clamp(0.0, 1.0);
+        let length = (words.len() as f32 / 500.0).min(1.0);
+        let sub_concepts = (self.extract_related_concepts(page).len() as f32 / 10.0).min(1.0);
+        // Curated links per 100 words
+        let link_density = (page.curated_links.len() as f32 * 100.0 / words.len() as f32 / 10.0).min(1.0);
+
+        0.4 * reading_level + 0.25 * sub_concepts + 0.2 * length + 0.15 * link_density
+    }
+
+    /// Rough syllable count: groups of consecutive vowels, at least one per word
+    fn syllables(word: &str) -> usize {
+        let mut count = 0;
+        let mut previous_vowel = false;
+        for c in word.chars().map(|c| c.to_ascii_lowercase()) {
+            let vowel = matches!(c, 'a' | 'e' | 'i' | 'o' | 'u' | 'y');
+            if vowel && !previous_vowel {
+                count += 1;
+            }
+            previous_vowel = vowel;
+        }
+        count.max(1)
+    }
+
+    /// Terms the page defines, from "X is a ..." sentences and "X (a/the ...)" parentheticals
+    pub fn extract_definitions(&self, page: &WikipediaPage) -> Vec<GlossaryEntry> {
+        let pronouns = ["it", "this", "that", "they", "these", "there", "which", "he", "she", "its"];
+
+        let entry = |term: &str, definition: &str| GlossaryEntry {
+            term: term.trim().to_string(),
+            definition: definition.trim().to_string(),
+            source_title: page.title.clone(),
+            source_url: page.url.clone(),
+        };
+
+        let mut definitions = Vec::new();
+        for (_, sentence) in text::sentences(&page.extract) {
+            if Self::heading(sentence).is_some() {
+                continue;
+            }
+            if let Some(term) = IS_A_DEFINITION.captures(sentence).and_then(|caps| caps.get(1)) {
+                let first_word = text::words(term.as_str()).first().map(|word| text::fold_case(word)).unwrap_or_default();
+                if !pronouns.contains(&first_word.as_str()) {
+                    definitions.push(entry(term.as_str(), sentence));
+                }
+            }
+            for caps in PARENTHETICAL_DEFINITION.captures_iter(sentence) {
+                // The term is what follows the last article ("the bypass ratio"), or failing
+                // that just the word before the parenthesis
+                let words = text::words(&caps[1]);
+                let start = words
+                    .iter()
+                    .rposition(|word| matches!(*word, "a" | "an" | "the"))
+                    .map_or(words.len() - 1, |article| article + 1);
+                if start < words.len() {
+                    definitions.push(entry(&words[start..].join(" "), &caps[2]));
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        definitions.retain(|definition| seen.insert(text::fold_case(&definition.term)));
+        definitions
+    }
+
+    /// Extractive summary of `text`: the `max_sentences` sentences whose words are most frequent
+    /// across the whole text, in their original order. Short texts are returned whole.
+    pub fn summarize(&self, text: &str, max_sentences: usize) -> String {
+        let sentences: Vec<&str> = text::sentences(text)
+            .into_iter()
+            .map(|(_, sentence)| sentence)
+            .filter(|sentence| Self::heading(sentence).is_none())
+            .collect();
+        if sentences.len() <= max_sentences {
+            return sentences.join(" ");
+        }
+
+        let language = language::detect_language(text).unwrap_or("en");
+        let words = |sentence: &str| -> Vec<String> {
+            text::words(sentence)
+                .into_iter()
+                .filter(|word| word.chars().count() > 3 && !self.is_common_word(word, language))
+                .map(text::fold_case)
+                .collect()
+        };
+        let mut frequencies: HashMap<String, usize> = HashMap::new();
+        for word in sentences.iter().flat_map(|sentence| words(sentence)) {
+            *frequencies.entry(word).or_default() += 1;
+        }
+
+        let mut scored: Vec<(usize, f32)> = sentences
+            .iter()
+            .enumerate()
+            .map(|(i, sentence)| {
+                let words = words(sentence);
+                let score: usize = words.iter().map(|word| frequencies[word]).sum();
+                // Normalize by length so long sentences don't win by size alone; the lead
+                // sentence usually defines the topic, so it gets a head start
+                let lead_bonus = if i == 0 { 1.5 } else { 1.0 };
+                (i, lead_bonus * score as f32 / (words.len() as f32).max(1.0).sqrt())
+            })
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let mut chosen: Vec<usize> = scored.into_iter().take(max_sentences).map(|(i, _)| i).collect();
+        chosen.sort_unstable();
+        chosen.into_iter().map(|i| sentences[i]).collect::<Vec<_>>().join(" ")
+    }
+
+    /// Byte range of the first sentence of `text` mentioning `concept` (case-insensitive)
+    pub fn sentence_mentioning(text: &str, concept: &str) -> Option<(usize, usize)> {
+        let concept = text::fold_case(concept);
+        text::sentences(text)
+            .into_iter()
+            .find(|(_, sentence)| text::fold_case(sentence).contains(&concept))
+            .map(|(start, sentence)| (start, start + sentence.len()))
+    }
+
+    /// Heading of the section of `text` that `offset` falls in: the nearest preceding Markdown
+    /// (`## Rotor`) or wikitext (`== Rotor ==`) heading line, or `None` in the lead
+    pub fn section_at(text: &str, offset: usize) -> Option<String> {
+        // Only whole lines before the one `offset` is on can be its heading
+        let (before, _) = text.get(..offset)?.rsplit_once('\n')?;
+        before.lines().rev().find_map(Self::heading).map(str::to_string)
+    }
+
+    /// The longest prefix of `text` within `max_bytes` that ends a sentence, or failing that a
+    /// word; `text` itself when it already fits
+    pub fn truncate_at_sentence(text: &str, max_bytes: usize) -> &str {
+        if text.len() <= max_bytes {
+            return text;
+        }
+        let mut end = max_bytes;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        // A terminator only ends a sentence when whitespace follows, so "3.5" and "e.g" don't count
+        let sentence_end = text[..end]
+            .rmatch_indices(['.', '!', '?'])
+            .map(|(i, _)| i + 1)
+            .find(|&i| text[i..].starts_with(char::is_whitespace));
+        let end = sentence_end
+            .or_else(|| text[..end].rfind(char::is_whitespace))
+            .unwrap_or(end);
+        text[..end].trim_end()
+    }
+
+    /// The title of a Markdown or wikitext heading line
+    fn heading(line: &str) -> Option<&str> {
+        let line = line.trim();
+        let heading = if line.starts_with('#') {
+            line.trim_start_matches('#')
+        } else if line.len() > 4 && line.starts_with("==") && line.ends_with("==") {
+            line.trim_matches('=')
+        } else {
+            return None;
+        };
+        Some(heading.trim()).filter(|heading| !heading.is_empty())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn principles_point_back_to_their_passage() {
+        let extract = "## Operation\nAn electric motor drives a shaft. The torque follows the fundamental principle of the Lorentz force on the rotor current. It is efficient.";
+        let page = WikipediaPage {
+            title: "Electric motor".to_string(),
+            extract: extract.to_string(),
+            url: "https://en.wikipedia.org/wiki/Electric_motor".to_string(),
+            page_id: 1,
+            revision_id: None,
+            fetched_at: chrono::Utc::now(),
+            thumbnail_url: None,
+            monthly_views: None,
+            curated_links: vec![],
+            etag: None,
+            last_modified: None,
+            source: Default::default(),
+        };
+
+        let principles = EngineeringAnalyzer::new().unwrap().analyze_page(&page).unwrap();
+        let principle = principles.iter().find(|p| p.description.contains("Lorentz")).unwrap();
+        let (start, end) = principle.source_span;
+        assert!(extract[start..end].starts_with("The torque follows"));
+        assert_eq!(principle.source_section.as_deref(), Some("Operation"));
+
+        assert_eq!(EngineeringAnalyzer::section_at(extract, 5), None);
+
+        let analyzer = EngineeringAnalyzer::new().unwrap();
+        let simple = WikipediaPage { extract: "A lever is a bar. It turns on a point.".to_string(), ..page.clone() };
+        assert!(analyzer.complexity_score(&simple) < analyzer.complexity_score(&page));
+        assert_eq!(analyzer.summarize(&simple.extract, 2), simple.extract);
+        assert_eq!(
+            analyzer.summarize(&page.extract, 1),
+            "An electric motor drives a shaft."
+        );
+
+        let glossary_page = WikipediaPage {
+            extract: "A jet engine is a type of reaction engine. It is a machine. Air passes the stator (the stationary blade row) first.".to_string(),
+            ..page.clone()
+        };
+        let terms: Vec<(String, String)> = analyzer
+            .extract_definitions(&glossary_page)
+            .into_iter()
+            .map(|entry| (entry.term, entry.definition))
+            .collect();
+        assert_eq!(
+            terms,
+            [
+                ("jet engine".to_string(), "A jet engine is a type of reaction engine.".to_string()),
+                ("stator".to_string(), "the stationary blade row".to_string()),
+            ]
+        );
+        let (start, end) = EngineeringAnalyzer::sentence_mentioning(extract, "lorentz force").unwrap();
+        assert_eq!(&extract[start..end], "The torque follows the fundamental principle of the Lorentz force on the rotor current.");
+    }
+
+    #[test]
+    fn non_ascii_terms_are_recognized() {
+        let page = WikipediaPage {
+            title: "Ångström".to_string(),
+            extract: "The Ångström unit is a unit of length. Its use follows the Poincaré-Bendixson theorem of dynamics.".to_string(),
+            url: "https://en.wikipedia.org/wiki/Ångström".to_string(),
+            page_id: 1,
+            revision_id: None,
+            fetched_at: chrono::Utc::now(),
+            thumbnail_url: None,
+            monthly_views: None,
+            curated_links: vec![],
+            etag: None,
+            last_modified: None,
+            source: Default::default(),
+        };
+        let analyzer = EngineeringAnalyzer::new().unwrap();
+
+        let definitions = analyzer.extract_definitions(&page);
+        assert_eq!(definitions[0].term, "Ångström unit");
+        let terms = analyzer.extract_related_terms(&page.extract, "en", &HashMap::new());
+        assert!(terms.iter().any(|term| *term == "Poincaré-Bendixson"), "{:?}", terms);
+    }
+
+    #[test]
+    fn acronyms_are_spelled_out_in_terms_and_dedup() {
+        let analyzer = EngineeringAnalyzer::new().unwrap();
+        let acronyms = text::acronyms("Each electronic speed controller (ESC) regulates the motor.");
+
+        let terms = analyzer.extract_related_terms("The ESC adjusts Motor Speed", "en", &acronyms);
+        assert!(terms.iter().any(|term| *term == "electronic speed controller"), "{:?}", terms);
+        assert!(terms.iter().all(|term| *term != "ESC"));
+
+        let principle = |description: &str| EngineeringPrinciple {
+            id: String::new(),
+            title: "Speed control".to_string(),
+            description: description.to_string(),
+            category: PrincipleCategory::new("Electrical"),
+            confidence: 0.8,
+            source_url: String::new(),
+            related_terms: vec![],
+            source_span: (0, 0),
+            source_section: None,
+            appears_in: vec![],
+            classified_by: None,
+            named_law: None,
+            component: None,
+        };
+        let principles = vec![
+            principle("The electronic speed controller varies the voltage applied to the motor windings"),
+            principle("The ESC varies the voltage applied to the motor windings"),
+        ];
+        assert_eq!(analyzer.deduplicate_and_rank(principles, &acronyms).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn stop_words_follow_the_language_and_config() {
+        let analyzer = EngineeringAnalyzer::with_concept_filter(ConceptFilterConfig {
+            extra_stop_words: vec!["Typically".to_string()],
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert!(analyzer.is_common_word("Eine", "de"));
+        assert!(analyzer.is_common_word("une", "fr"));
+        assert!(!analyzer.is_common_word("eine", "en"));
+        assert!(analyzer.is_common_word("typically", "de"));
+        assert!(!analyzer.is_common_word("Stator", "de"));
+    }
+
+    #[test]
+    fn long_text_is_cut_at_a_sentence_end() {
+        let text = "A rotor spins at 3.5 kHz. The stator stays still! Its windings carry current";
+        assert_eq!(EngineeringAnalyzer::truncate_at_sentence(text, text.len()), text);
+        assert_eq!(EngineeringAnalyzer::truncate_at_sentence(text, 60), "A rotor spins at 3.5 kHz. The stator stays still!");
+        assert_eq!(EngineeringAnalyzer::truncate_at_sentence(text, 24), "A rotor spins at 3.5");
+        assert_eq!(EngineeringAnalyzer::truncate_at_sentence("Ωμέγα", 3), "Ω");
+    }
+}
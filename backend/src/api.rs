@@ -1,47 +1,318 @@
-use crate::cache::WikiEngineCache;
-use crate::types::{AnalysisResult, SearchRequest, Result};
-use crate::WikiEngine;
+use crate::admission::AnalysisLimiter;
+use crate::audit::{AuditEntry, AuditLog, AuditQuery};
+use crate::cache::{self, CacheStats, WikiEngineCache};
+use crate::categories::{self, CategoryDefinition};
+use crate::events::EngineEvent;
+use crate::config::{tokens_match, AnalysisLimits, ConcurrencyConfig, RuntimeConfig, TenantQuotas, TenantTokens, TrustedProxies, WebhookConfig};
+use crate::feedback::{Feedback, FeedbackEntry, FeedbackQuery};
+use crate::fields::FieldSelection;
+use crate::health::{HealthChecker, HealthStatus, ReadinessReport};
+use crate::kb_import::{ImportOptions, ImportReport};
+use crate::semantic_analyzer::ConceptKnowledgeBase;
+use crate::tuning::TuningReport;
+use crate::flashcards::{self, Flashcard, FlashcardFormat};
+use crate::learning_path::LearningPath;
+use crate::results::{ResultRefresh, ResultStore};
+use crate::types::{
+    AnalysisPlan, AnalysisResult, BatchAccepted, BatchAnalysisItem, BatchAnalyzeRequest, FieldError, NetworkStats, SearchRequest, Result, WikiEngineError,
+};
+pub use crate::types::SearchSuggestion;
+use crate::validation::{validate_batch_request, validate_feedback, validate_search_request, validate_suggest_query, validate_tenant};
+use crate::webhooks::{WebhookEvent, WebhookNotifier};
+use crate::wikipedia::MediaWikiClient;
+use crate::{export, monitoring, WikiEngine, BATCH_CONCURRENCY};
+use arc_swap::ArcSwap;
 use axum::{
+    body::Body,
     debug_handler,
-    extract::{Query, State},
-    response::Json,
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts, MatchedPath, Path, Query, Request, State},
+    http::request::Parts,
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::sse::{Event, KeepAlive, Sse},
+    response::{IntoResponse, Json, Response},
     routing::{get, post},
     Router,
 };
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, oneshot, Semaphore};
 use tower_http::cors::CorsLayer;
+use tracing::Instrument;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
 
 pub type SharedState = Arc<WikiEngineState>;
 
 pub struct WikiEngineState {
     pub engine: WikiEngine,
     pub cache: Arc<WikiEngineCache>,
+    /// The runtime-tunable settings in effect; replaced as a whole by `PATCH /admin/config`
+    config: ArcSwap<RuntimeConfig>,
+    /// Serializes config patches, so concurrent ones can't undo each other
+    config_patches: Mutex<()>,
+    pub health: HealthChecker,
+    pub results: ResultStore,
+    /// Caps analyses running at once across all requests
+    pub analyses: AnalysisLimiter,
+    /// Caps Wikipedia requests in flight across all analyses
+    pub fetch_permits: Arc<Semaphore>,
+    pub audit: AuditLog,
+    /// Bearer token the `/admin` endpoints require; they are disabled without one
+    pub admin_token: Option<String>,
+    /// Bearer tokens letting tenants clear and inspect their own cache namespace
+    pub tenant_tokens: TenantTokens,
+    /// Proxies whose `X-Forwarded-For` header identifies callers
+    pub trusted_proxies: TrustedProxies,
+    /// Notifies the webhooks batches name when their analyses are done
+    pub webhooks: WebhookNotifier,
 }
 
 impl WikiEngineState {
     pub fn new() -> Result<Self> {
-        let cache = Arc::new(WikiEngineCache::new());
-        let engine = WikiEngine::new(Arc::clone(&cache))?;
-        
-        Ok(Self { engine, cache })
+        Self::with_concurrency(&ConcurrencyConfig::from_env())
+    }
+
+    pub fn with_concurrency(concurrency: &ConcurrencyConfig) -> Result<Self> {
+        let cache = Arc::new(WikiEngineCache::new().with_tenant_quotas(TenantQuotas::from_env()));
+        let fetch_permits = Arc::new(Semaphore::new(concurrency.max_concurrent_fetches.max(1)));
+        let engine = WikiEngine::builder()
+            .with_cache(Arc::clone(&cache))
+            .with_wikipedia_client(MediaWikiClient::new()?.with_fetch_permits(Arc::clone(&fetch_permits)))
+            .build()?;
+        let config = RuntimeConfig {
+            limits: AnalysisLimits::from_env(),
+            concurrency: concurrency.clone(),
+            cache_ttls: cache.ttls(),
+            similarity_threshold: engine.similarity_threshold(),
+        };
+
+        Ok(Self {
+            engine,
+            cache,
+            config: ArcSwap::from_pointee(config),
+            config_patches: Mutex::new(()),
+            health: HealthChecker::new(),
+            results: ResultStore::new(),
+            analyses: AnalysisLimiter::new(concurrency),
+            fetch_permits,
+            audit: AuditLog::from_env(),
+            admin_token: std::env::var("WIKI_ENGINE_ADMIN_TOKEN").ok().filter(|token| !token.is_empty()),
+            tenant_tokens: TenantTokens::from_env(),
+            trusted_proxies: TrustedProxies::from_env(),
+            webhooks: WebhookNotifier::new(WebhookConfig::from_env())?,
+        })
+    }
+
+    /// The runtime-tunable settings in effect
+    pub fn config(&self) -> Arc<RuntimeConfig> {
+        self.config.load_full()
+    }
+
+    pub fn limits(&self) -> AnalysisLimits {
+        self.config.load().limits.clone()
+    }
+
+    /// Apply `patch`, a JSON merge patch of the runtime config, to the limiter, cache and
+    /// engine, and make it the config in effect
+    pub fn patch_config(&self, patch: &serde_json::Value) -> Result<Arc<RuntimeConfig>> {
+        let _patching = self.config_patches.lock().unwrap();
+        let current = self.config.load_full();
+        let patched = Arc::new(current.patched(patch)?);
+        if patched.concurrency != current.concurrency {
+            self.analyses.resize(&patched.concurrency);
+        }
+        if patched.cache_ttls != current.cache_ttls {
+            self.cache.set_ttls(patched.cache_ttls.clone());
+        }
+        if patched.similarity_threshold != current.similarity_threshold {
+            self.engine.set_similarity_threshold(patched.similarity_threshold);
+        }
+        self.config.store(Arc::clone(&patched));
+        Ok(patched)
+    }
+
+    /// Record an analysis requested through `endpoint` in the audit log: the size of its tree,
+    /// or why it failed
+    fn record_audit(
+        &self,
+        endpoint: &str,
+        caller: &Caller,
+        request: &SearchRequest,
+        started: Instant,
+        outcome: std::result::Result<u32, String>,
+    ) {
+        let entry = AuditEntry::new(endpoint, &caller.0, request, started.elapsed(), outcome);
+        self.audit.record(entry.with_request_id(current_request_id()));
     }
 }
 
-#[derive(Debug, Deserialize)]
+/// Who sent a request, for the audit log and feedback: the peer address, or when the peer is a
+/// trusted proxy the client address it forwarded in `X-Forwarded-For` (`unknown` when the server
+/// wasn't started with connection info)
+#[derive(Debug, Clone)]
+pub struct Caller(pub String);
+
+#[async_trait]
+impl FromRequestParts<SharedState> for Caller {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &SharedState) -> std::result::Result<Self, Self::Rejection> {
+        let Some(ConnectInfo(peer)) = parts.extensions.get::<ConnectInfo<SocketAddr>>() else {
+            return Ok(Caller("unknown".to_string()));
+        };
+        let mut caller = peer.ip().to_canonical();
+        if state.trusted_proxies.trusts(caller) {
+            // Each proxy appends the address it got the request from, so the nearest address that
+            // isn't a trusted proxy's is the client's; the client may have written any before it
+            let forwarded: Vec<&str> = parts
+                .headers
+                .get_all("x-forwarded-for")
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .collect();
+            for address in forwarded.iter().rev() {
+                let Ok(address) = address.trim().parse::<IpAddr>() else { break };
+                caller = address.to_canonical();
+                if !state.trusted_proxies.trusts(caller) {
+                    break;
+                }
+            }
+        }
+        Ok(Caller(caller.to_string()))
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct AnalyzeQuery {
     term: String,
     max_depth: Option<u8>,
     max_results: Option<u8>,
+    preset: Option<String>,
+    /// Comma-separated fields of the result to return (see `FieldsQuery`)
+    fields: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct FieldsQuery {
+    /// Comma-separated dot paths of the result fields to return, e.g.
+    /// `tree.term,tree.children,principles.title`; paths not starting with a result field are
+    /// relative to the tree's nodes, and `children` alone repeats the node's selection
+    pub fields: Option<String>,
+}
+
+impl FieldsQuery {
+    fn selection(&self) -> Result<Option<FieldSelection>> {
+        self.fields.as_deref().map(FieldSelection::parse).transpose()
+    }
 }
 
-#[derive(Debug, Serialize)]
+/// `result` in the response envelope, trimmed to `fields` when the client selected some
+fn result_response(result: AnalysisResult, fields: Option<FieldSelection>, format: ResponseFormat) -> Result<Response> {
+    match fields {
+        None => format.encode(&ApiResponse::success(result)),
+        Some(fields) => format.encode(&ApiResponse::success(fields.apply(serde_json::to_value(&result)?))),
+    }
+}
+
+/// Encodings analysis results can be returned in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResponseFormat {
+    #[default]
+    Json,
+    /// MessagePack, with structs encoded as maps keyed by field name like in JSON
+    MessagePack,
+    Cbor,
+}
+
+impl ResponseFormat {
+    const MEDIA_TYPES: &'static [(&'static str, ResponseFormat)] = &[
+        ("application/json", ResponseFormat::Json),
+        ("application/msgpack", ResponseFormat::MessagePack),
+        ("application/x-msgpack", ResponseFormat::MessagePack),
+        ("application/vnd.msgpack", ResponseFormat::MessagePack),
+        ("application/cbor", ResponseFormat::Cbor),
+    ];
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ResponseFormat::Json => "application/json",
+            ResponseFormat::MessagePack => "application/msgpack",
+            ResponseFormat::Cbor => "application/cbor",
+        }
+    }
+
+    /// The format the request's `Accept` header prefers most; JSON when it names none of them
+    pub fn negotiate(headers: &HeaderMap) -> Self {
+        let Some(accept) = headers.get(header::ACCEPT).and_then(|value| value.to_str().ok()) else {
+            return Self::default();
+        };
+        let mut preferences: Vec<(f32, ResponseFormat)> = accept
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.split(';').map(str::trim);
+                let media_type = parts.next()?.to_ascii_lowercase();
+                let (_, format) = Self::MEDIA_TYPES.iter().find(|(name, _)| *name == media_type)?;
+                let quality = parts
+                    .find_map(|param| param.strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+                (quality > 0.0).then_some((quality, *format))
+            })
+            .collect();
+        // Stable, so equally preferred formats keep the header's order
+        preferences.sort_by(|a, b| b.0.total_cmp(&a.0));
+        preferences.first().map_or_else(Self::default, |(_, format)| *format)
+    }
+
+    fn encode<T: Serialize>(self, body: &T) -> Result<Response> {
+        let encoding_error = |error: String| WikiEngineError::Serialization(serde::ser::Error::custom(error));
+        let bytes = match self {
+            ResponseFormat::Json => return Ok(([(header::VARY, "accept")], Json(body)).into_response()),
+            ResponseFormat::MessagePack => rmp_serde::to_vec_named(body).map_err(|e| encoding_error(e.to_string()))?,
+            ResponseFormat::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(body, &mut bytes).map_err(|e| encoding_error(e.to_string()))?;
+                bytes
+            }
+        };
+        Ok(([(header::CONTENT_TYPE, self.content_type()), (header::VARY, "accept")], bytes).into_response())
+    }
+}
+
+/// Time allowed past an analysis deadline for in-flight fetches to finish before the request is dropped
+const DEADLINE_GRACE: Duration = Duration::from_secs(30);
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Names the tenant whose cache namespace a request uses
+pub const TENANT_HEADER: &str = "x-tenant";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Request id of the HTTP request currently being handled, if any
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Envelope wrapping every API response
+#[derive(Debug, Serialize, ToSchema)]
 pub struct ApiResponse<T> {
     success: bool,
     data: Option<T>,
     error: Option<String>,
     timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
 }
 
 impl<T> ApiResponse<T> {
@@ -51,6 +322,7 @@ impl<T> ApiResponse<T> {
             data: Some(data),
             error: None,
             timestamp: chrono::Utc::now().to_rfc3339(),
+            request_id: None,
         }
     }
 
@@ -60,15 +332,100 @@ impl<T> ApiResponse<T> {
             data: None,
             error: Some(message),
             timestamp: chrono::Utc::now().to_rfc3339(),
+            request_id: current_request_id(),
+        }
+    }
+
+    /// Error response carrying structured details about the failure
+    pub fn error_with_details(message: String, details: T) -> Self {
+        Self {
+            success: false,
+            data: Some(details),
+            error: Some(message),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            request_id: current_request_id(),
+        }
+    }
+}
+
+impl WikiEngineError {
+    /// HTTP status code the API responds with for this error
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            WikiEngineError::PageNotFound(_) | WikiEngineError::ResultNotFound(_) => StatusCode::NOT_FOUND,
+            WikiEngineError::Disambiguation(_) => StatusCode::CONFLICT,
+            WikiEngineError::RateLimited { .. } | WikiEngineError::Overloaded { .. } => StatusCode::TOO_MANY_REQUESTS,
+            WikiEngineError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            WikiEngineError::Cancelled
+            | WikiEngineError::ModelUnavailable(_)
+            | WikiEngineError::ReplicationLag { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            WikiEngineError::WikipediaApi(_) | WikiEngineError::Network(_) => StatusCode::BAD_GATEWAY,
+            WikiEngineError::InvalidRequest(_) | WikiEngineError::InvalidTitle(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            WikiEngineError::Analysis(_) | WikiEngineError::Serialization(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl IntoResponse for WikiEngineError {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        let message = self.to_string();
+        let body = Json(ApiResponse::<()>::error(message.clone()));
+
+        match self {
+            WikiEngineError::InvalidRequest(errors) => {
+                (status, Json(ApiResponse::error_with_details(message, errors))).into_response()
+            }
+            WikiEngineError::RateLimited { retry_after: Some(secs) }
+            | WikiEngineError::ReplicationLag { retry_after: Some(secs) }
+            | WikiEngineError::Overloaded { retry_after: Some(secs) } => {
+                (status, [(header::RETRY_AFTER, secs.to_string())], body).into_response()
+            }
+            _ => (status, body).into_response(),
         }
     }
 }
 
 pub fn create_router_with_state(state: SharedState) -> Result<Router> {
-    let router = Router::new()
+    let router = Router::new();
+
+    #[cfg(feature = "metrics")]
+    let router = {
+        // Install the recorder before any request is served
+        monitoring::prometheus_handle();
+        router.route("/metrics", get(metrics_endpoint))
+    };
+
+    let router = router
         .route("/health", get(health_check))
-        .route("/analyze", post(analyze_term))
+        .route("/health/live", get(health_check))
+        .route("/health/ready", get(readiness_check))
+        .route("/analyze", post(analyze_term).get(analyze_term_query))
+        .route("/analyze/batch", post(analyze_batch))
+        .route("/analyze/batch/principles", post(stream_batch_principles))
+        .route("/analyze/plan", post(plan_analysis))
+        .route("/results/:id", get(get_result))
+        .route("/results/:id/flashcards", get(result_flashcards))
+        .route("/results/:id/refresh", post(refresh_result))
+        .route("/learning-path", get(learning_path))
         .route("/suggest", get(suggest_terms))
+        .route("/categories", get(list_categories))
+        .route("/cache/stats", get(cache_stats))
+        .route("/stats/network", get(network_stats))
+        .route("/cache/clear", post(clear_cache))
+        .route("/admin/audit", get(audit_log))
+        .route("/admin/config", get(get_config).patch(patch_config))
+        .route("/admin/feedback", get(list_feedback))
+        .route("/admin/tuning", get(tuning_report).post(refit_thresholds))
+        .route("/admin/events", get(stream_events))
+        .route("/feedback", post(submit_feedback))
+        .route("/kb/export", get(export_knowledge_base))
+        .route("/kb/import", post(import_knowledge_base))
+        .merge(SwaggerUi::new("/docs").url("/openapi.json", crate::openapi::ApiDoc::openapi()))
+        .layer(middleware::from_fn(tenant_scope))
+        .layer(middleware::from_fn_with_state(Arc::clone(&state), request_deadline))
+        .layer(middleware::from_fn(track_metrics))
+        .layer(middleware::from_fn(request_context))
         .layer(CorsLayer::permissive())
         .with_state(state);
     
@@ -81,6 +438,101 @@ pub fn create_router() -> Result<Router> {
     create_router_with_state(state)
 }
 
+/// Assign a request id (honoring an incoming `X-Request-Id`), run the request inside a
+/// span carrying it, and log a structured summary once the response is ready
+async fn request_context(request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= 128)
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+
+    let span = tracing::info_span!("http_request", request_id = %request_id, method = %method, path = %path);
+    let mut response = REQUEST_ID
+        .scope(request_id.clone(), next.run(request))
+        .instrument(span)
+        .await;
+
+    tracing::info!(
+        request_id = %request_id,
+        method = %method,
+        path = %path,
+        status = response.status().as_u16(),
+        latency_ms = start.elapsed().as_millis() as u64,
+        "request completed"
+    );
+
+    if let Ok(value) = header::HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Drop requests that run past the analysis deadline in effect. Analyses stop expanding at their
+/// own deadline and return a partial tree; this only catches requests that overrun it anyway
+/// (queueing, or nodes still finishing their last fetch).
+async fn request_deadline(State(state): State<SharedState>, request: Request, next: Next) -> Response {
+    let timeout = state.analyses.queue_timeout()
+        + Duration::from_secs(state.config.load().limits.analysis_timeout_secs)
+        + DEADLINE_GRACE;
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => StatusCode::REQUEST_TIMEOUT.into_response(),
+    }
+}
+
+/// Run the request in the cache namespace of the tenant its `X-Tenant` header names, or the
+/// default tenant's without one
+async fn tenant_scope(request: Request, next: Next) -> Response {
+    let Some(value) = request.headers().get(TENANT_HEADER) else {
+        return next.run(request).await;
+    };
+    let tenant = match validate_tenant(value.to_str().unwrap_or_default()) {
+        Ok(tenant) => tenant.to_string(),
+        Err(e) => return e.into_response(),
+    };
+    cache::with_tenant(&tenant, next.run(request)).await
+}
+
+/// Record request count and latency per matched route
+async fn track_metrics(request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(request).await;
+    monitoring::record_http_request(&method, &route, response.status().as_u16(), start.elapsed());
+
+    response
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "system",
+    responses((status = 200, description = "Prometheus text exposition", body = String, content_type = "text/plain"))
+)]
+#[cfg(feature = "metrics")]
+pub async fn metrics_endpoint() -> String {
+    monitoring::prometheus_handle().render()
+}
+
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "system",
+    responses((status = 200, description = "Service is alive", body = ApiResponse<HashMap<String, String>>))
+)]
+/// Liveness probe: the process is up and serving requests
 pub async fn health_check() -> Json<ApiResponse<HashMap<String, String>>> {
     let mut health_data = HashMap::new();
     health_data.insert("status".to_string(), "healthy".to_string());
@@ -90,51 +542,934 @@ pub async fn health_check() -> Json<ApiResponse<HashMap<String, String>>> {
     Json(ApiResponse::success(health_data))
 }
 
+#[utoipa::path(
+    get,
+    path = "/health/ready",
+    tag = "system",
+    responses(
+        (status = 200, description = "All components ok or degraded", body = ApiResponse<ReadinessReport>),
+        (status = 503, description = "At least one component is down", body = ApiResponse<ReadinessReport>)
+    )
+)]
+/// Readiness probe: checks Wikipedia reachability, the embedding model and the cache.
+/// Responds 503 when any component is down so load balancers stop routing traffic.
+pub async fn readiness_check(State(state): State<SharedState>) -> (StatusCode, Json<ApiResponse<ReadinessReport>>) {
+    let report = state.health.readiness(&state.engine).await;
+    let status = match report.status {
+        HealthStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+        HealthStatus::Ok | HealthStatus::Degraded => StatusCode::OK,
+    };
+
+    (status, Json(ApiResponse::success(report)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/analyze",
+    tag = "analysis",
+    request_body = SearchRequest,
+    params(FieldsQuery),
+    responses(
+        (status = 200, description = "Analysis tree for the term, in the format the `Accept` header prefers", body = ApiResponse<AnalysisResult>),
+        (status = 404, description = "No Wikipedia page for the term", body = ApiResponse<serde_json::Value>),
+        (status = 409, description = "Term is ambiguous", body = ApiResponse<serde_json::Value>),
+        (status = 422, description = "Request failed validation", body = ApiResponse<Vec<FieldError>>),
+        (status = 429, description = "Rate limited by Wikipedia, or too many analyses in progress", body = ApiResponse<serde_json::Value>),
+        (status = 504, description = "Wikipedia request timed out", body = ApiResponse<serde_json::Value>)
+    )
+)]
 #[debug_handler]
 pub async fn analyze_term(
     State(state): State<SharedState>,
+    caller: Caller,
+    headers: HeaderMap,
+    Query(query): Query<FieldsQuery>,
     Json(request): Json<SearchRequest>,
-) -> Json<ApiResponse<AnalysisResult>> {
+) -> std::result::Result<Response, WikiEngineError> {
     tracing::info!("Analysis endpoint called for term: {}", request.term);
+    let fields = query.selection()?;
+    let format = ResponseFormat::negotiate(&headers);
+    let started = Instant::now();
+    let outcome = run_analysis(&state, &headers, request.clone()).await;
+    let summary = outcome.as_ref().map(|result| result.tree.node_count()).map_err(ToString::to_string);
+    state.record_audit("/analyze", &caller, &request, started, summary);
+
+    result_response(outcome?, fields, format)
+}
+
+/// Hold `request` to the server's limits and analyze it, keeping the result so it can be
+/// fetched or refreshed later
+async fn run_analysis(state: &WikiEngineState, headers: &HeaderMap, request: SearchRequest) -> Result<AnalysisResult> {
+    // Presets are applied first so their settings are held to the limits like the request's own
+    let request = state.engine.apply_preset(request)?;
+    let limits = state.limits();
+    let mut request = validate_search_request(request, &limits)?;
+    if request.locale.is_none() {
+        request.locale = headers
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| state.engine.negotiate_locale(value));
+    }
+    request.timeout_ms.get_or_insert(limits.analysis_timeout_secs * 1000);
+    let _slot = state.analyses.acquire().await?;
     
     // Use the real WikiEngine to analyze the term with Wikipedia API calls
     match state.engine.analyze_recursive(&request).await {
-        Ok(result) => Json(ApiResponse::success(result)),
+        Ok(mut result) => {
+            let id = state.results.insert(request.clone(), result.clone());
+            result.result_id = Some(id);
+            Ok(result)
+        }
         Err(e) => {
             tracing::error!("Analysis failed for term '{}': {}", request.term, e);
-            Json(ApiResponse::error(format!("Analysis failed: {}", e)))
+            Err(e)
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/analyze/plan",
+    tag = "analysis",
+    request_body = SearchRequest,
+    responses(
+        (status = 200, description = "Estimated nodes, Wikipedia calls and time the analysis would take", body = ApiResponse<AnalysisPlan>),
+        (status = 422, description = "Request failed validation", body = ApiResponse<Vec<FieldError>>)
+    )
+)]
+pub async fn plan_analysis(
+    State(state): State<SharedState>,
+    Json(request): Json<SearchRequest>,
+) -> std::result::Result<Json<ApiResponse<AnalysisPlan>>, WikiEngineError> {
+    let request = state.engine.apply_preset(request)?;
+    let limits = state.limits();
+    let mut request = validate_search_request(request, &limits)?;
+    request.timeout_ms.get_or_insert(limits.analysis_timeout_secs * 1000);
+    Ok(Json(ApiResponse::success(state.engine.plan_analysis(&request)?)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/analyze",
+    tag = "analysis",
+    params(AnalyzeQuery),
+    responses(
+        (status = 200, description = "Analysis tree for the term, in the format the `Accept` header prefers", body = ApiResponse<AnalysisResult>),
+        (status = 422, description = "Request failed validation", body = ApiResponse<Vec<FieldError>>)
+    )
+)]
+#[debug_handler]
+pub async fn analyze_term_query(
+    State(state): State<SharedState>,
+    caller: Caller,
+    headers: HeaderMap,
+    Query(params): Query<AnalyzeQuery>,
+) -> std::result::Result<Response, WikiEngineError> {
+    let request = SearchRequest {
+        term: params.term,
+        max_depth: params.max_depth,
+        max_results: params.max_results,
+        preset: params.preset,
+        ..Default::default()
+    };
+
+    analyze_term(State(state), caller, headers, Query(FieldsQuery { fields: params.fields }), Json(request)).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/analyze/batch",
+    tag = "analysis",
+    request_body = BatchAnalyzeRequest,
+    responses(
+        (status = 200, description = "Per-term results, including per-term errors", body = ApiResponse<Vec<BatchAnalysisItem>>),
+        (status = 202, description = "The batch names a `webhook_url`; its results will be POSTed there as a signed `WebhookEvent` carrying this id", body = ApiResponse<BatchAccepted>),
+        (status = 422, description = "Request failed validation", body = ApiResponse<Vec<FieldError>>),
+        (status = 429, description = "Too many analyses in progress", body = ApiResponse<serde_json::Value>)
+    )
+)]
+#[debug_handler]
+pub async fn analyze_batch(
+    State(state): State<SharedState>,
+    caller: Caller,
+    Json(request): Json<BatchAnalyzeRequest>,
+) -> std::result::Result<Response, WikiEngineError> {
+    tracing::info!("Batch analysis endpoint called for {} terms", request.terms.len());
+    let limits = state.limits();
+    let request = validate_batch_request(request, &limits)?;
+    let Some(webhook_url) = &request.webhook_url else {
+        let _slot = state.analyses.acquire().await?;
+        let items = run_batch(&state, &caller, &request, &limits, current_request_id()).await;
+        return Ok(Json(ApiResponse::success(items)).into_response());
+    };
+    let webhook_url = state.webhooks.check_url(webhook_url)?;
+
+    // As for the principle stream, the slot is taken in the task running the analyses and the
+    // outcome of queueing for it reported back before answering
+    let (admitted_tx, admitted) = oneshot::channel();
+    let id = uuid::Uuid::new_v4().to_string();
+    let event_id = id.clone();
+    let request_id = current_request_id();
+    let tenant = cache::current_tenant();
+    let analyses = async move {
+        let slot = match state.analyses.acquire().await {
+            Ok(slot) => slot,
+            Err(e) => {
+                let _ = admitted_tx.send(Err(e));
+                return;
+            }
+        };
+        let _ = admitted_tx.send(Ok(()));
+
+        let items = run_batch(&state, &caller, &request, &limits, request_id).await;
+        // Retrying a delivery can take minutes; other analyses needn't wait for it
+        drop(slot);
+        let event = WebhookEvent { id: event_id, endpoint: "/analyze/batch".to_string(), items };
+        if let Err(e) = state.webhooks.deliver(&webhook_url, &event).await {
+            tracing::error!("Giving up on webhook delivery {}: {}", event.id, e);
+        }
+    };
+    tokio::spawn(async move { cache::with_tenant(&tenant, analyses).await });
+    admitted.await.map_err(|_| WikiEngineError::Cancelled)??;
+
+    Ok((StatusCode::ACCEPTED, Json(ApiResponse::success(BatchAccepted { id }))).into_response())
+}
+
+/// Analyze a validated batch, recording each term in the audit log
+async fn run_batch(
+    state: &WikiEngineState,
+    caller: &Caller,
+    request: &BatchAnalyzeRequest,
+    limits: &AnalysisLimits,
+    request_id: Option<String>,
+) -> Vec<BatchAnalysisItem> {
+    let started = Instant::now();
+    let max_depth = request.max_depth.unwrap_or(2);
+    let max_results = request.max_results.unwrap_or(5); // Smaller for batch processing
+    let items = state.engine.batch_analyze(
+        &request.terms,
+        max_depth,
+        max_results,
+        Some(limits.analysis_timeout_secs * 1000),
+    ).await;
+
+    for item in &items {
+        let request = SearchRequest {
+            term: item.term.clone(),
+            max_depth: request.max_depth,
+            max_results: request.max_results,
+            ..Default::default()
+        };
+        let summary = match (&item.result, &item.error) {
+            (Some(result), _) => Ok(result.tree.node_count()),
+            (None, error) => Err(error.clone().unwrap_or_default()),
+        };
+        let entry = AuditEntry::new("/analyze/batch", &caller.0, &request, started.elapsed(), summary);
+        state.audit.record(entry.with_request_id(request_id.clone()));
+    }
+    items
+}
+
+#[utoipa::path(
+    post,
+    path = "/analyze/batch/principles",
+    tag = "analysis",
+    request_body = BatchAnalyzeRequest,
+    responses(
+        (status = 200, description = "NDJSON stream of every principle of every term, one per line with `root_term`, `term`, `parent` and `depth` alongside the principle's fields; a term that fails gets a `{\"root_term\", \"error\"}` line instead", body = String, content_type = "application/x-ndjson"),
+        (status = 422, description = "Request failed validation", body = ApiResponse<Vec<FieldError>>),
+        (status = 429, description = "Too many analyses in progress", body = ApiResponse<serde_json::Value>)
+    )
+)]
+/// Analyze the batch, streaming each term's principles as soon as its tree is done (terms in
+/// completion order), so large batches can be piped into other tools without buffering them.
+/// With a `webhook_url`, every term's result is also sent there once the stream is complete,
+/// in an event whose id is the response's `X-Request-Id`.
+pub async fn stream_batch_principles(
+    State(state): State<SharedState>,
+    caller: Caller,
+    Json(request): Json<BatchAnalyzeRequest>,
+) -> std::result::Result<Response, WikiEngineError> {
+    tracing::info!("Principle stream endpoint called for {} terms", request.terms.len());
+    let limits = state.limits();
+    let request = validate_batch_request(request, &limits)?;
+    let webhook_url = request.webhook_url.as_deref().map(|url| state.webhooks.check_url(url)).transpose()?;
+    let requests = request
+        .terms
+        .iter()
+        .map(|term| SearchRequest {
+            term: term.clone(),
+            max_depth: Some(request.max_depth.unwrap_or(2)),
+            max_results: Some(request.max_results.unwrap_or(5)),
+            timeout_ms: Some(limits.analysis_timeout_secs * 1000),
+            ..Default::default()
+        })
+        .collect();
+
+    // The analyses outlive the handler, so the slot is taken in the task running them and the
+    // outcome of queueing for it reported back before the response starts
+    let (admitted_tx, admitted) = oneshot::channel();
+    let (mut lines_tx, lines) = mpsc::channel::<std::result::Result<String, Infallible>>(BATCH_CONCURRENCY);
+    let request_id = current_request_id();
+    let tenant = cache::current_tenant();
+    let started = Instant::now();
+    let analyses = async move {
+        let slot = match state.analyses.acquire().await {
+            Ok(slot) => slot,
+            Err(e) => {
+                let _ = admitted_tx.send(Err(e));
+                return;
+            }
+        };
+        let _ = admitted_tx.send(Ok(()));
+
+        let mut items = Vec::new();
+        let mut results = state.engine.analyze_each(requests, BATCH_CONCURRENCY);
+        while let Some((request, result)) = results.next().await {
+            let summary = result.as_ref().map(|result| result.tree.node_count()).map_err(ToString::to_string);
+            let entry = AuditEntry::new("/analyze/batch/principles", &caller.0, &request, started.elapsed(), summary);
+            state.audit.record(entry.with_request_id(request_id.clone()));
+            let (lines, item) = match result {
+                Ok(result) => {
+                    let lines = export::to_ndjson(&result);
+                    (lines, BatchAnalysisItem { term: request.term, result: Some(result), error: None })
+                }
+                Err(e) => {
+                    tracing::error!("Streamed analysis failed for '{}': {}", request.term, e);
+                    let lines = format!("{}\n", serde_json::json!({ "root_term": request.term, "error": e.to_string() }));
+                    (lines, BatchAnalysisItem { term: request.term, result: None, error: Some(e.to_string()) })
+                }
+            };
+            if lines_tx.send(Ok(lines)).await.is_err() {
+                // The client disconnected; dropping the stream cancels the remaining analyses,
+                // and the webhook isn't told about a batch that never finished
+                return;
+            }
+            if webhook_url.is_some() {
+                items.push(item);
+            }
+        }
+
+        // End the stream before delivering, which may take minutes of retries
+        drop(lines_tx);
+        drop(slot);
+        if let Some(url) = webhook_url {
+            let event = WebhookEvent {
+                id: request_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+                endpoint: "/analyze/batch/principles".to_string(),
+                items,
+            };
+            if let Err(e) = state.webhooks.deliver(&url, &event).await {
+                tracing::error!("Giving up on webhook delivery {}: {}", event.id, e);
+            }
         }
+    };
+    tokio::spawn(async move { cache::with_tenant(&tenant, analyses).await });
+    admitted.await.map_err(|_| WikiEngineError::Cancelled)??;
+
+    Ok(([(header::CONTENT_TYPE, "application/x-ndjson")], Body::from_stream(lines)).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/results/{id}",
+    tag = "analysis",
+    params(("id" = String, Path, description = "Id returned as `result_id` by /analyze"), FieldsQuery),
+    responses(
+        (status = 200, description = "Stored analysis result, in the format the `Accept` header prefers", body = ApiResponse<AnalysisResult>),
+        (status = 404, description = "No stored result with this id", body = ApiResponse<serde_json::Value>)
+    )
+)]
+pub async fn get_result(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Query(query): Query<FieldsQuery>,
+) -> std::result::Result<Response, WikiEngineError> {
+    let fields = query.selection()?;
+    let stored = state.results.get(&id).ok_or(WikiEngineError::ResultNotFound(id))?;
+    result_response(stored.result, fields, ResponseFormat::negotiate(&headers))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct FlashcardQuery {
+    /// `json` (default) or `tsv` for an Anki import file
+    #[serde(default)]
+    pub format: FlashcardFormat,
+}
+
+#[utoipa::path(
+    get,
+    path = "/results/{id}/flashcards",
+    tag = "analysis",
+    params(("id" = String, Path, description = "Id returned as `result_id` by /analyze"), FlashcardQuery),
+    responses(
+        (status = 200, description = "Cloze flashcards for the result's principles (TSV when `format=tsv`)", body = ApiResponse<Vec<Flashcard>>),
+        (status = 404, description = "No stored result with this id", body = ApiResponse<serde_json::Value>)
+    )
+)]
+/// Turn a stored result's principles into cloze-deletion flashcards
+pub async fn result_flashcards(
+    State(state): State<SharedState>,
+    Path(id): Path<String>,
+    Query(params): Query<FlashcardQuery>,
+) -> std::result::Result<Response, WikiEngineError> {
+    let stored = state.results.get(&id).ok_or(WikiEngineError::ResultNotFound(id))?;
+    let cards = flashcards::flashcards(&stored.result);
+
+    Ok(match params.format {
+        FlashcardFormat::Json => Json(ApiResponse::success(cards)).into_response(),
+        FlashcardFormat::Tsv => (
+            [
+                (header::CONTENT_TYPE, "text/tab-separated-values; charset=utf-8"),
+                (header::CONTENT_DISPOSITION, "attachment; filename=\"flashcards.tsv\""),
+            ],
+            flashcards::to_anki_tsv(&cards),
+        )
+            .into_response(),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/results/{id}/refresh",
+    tag = "analysis",
+    params(("id" = String, Path, description = "Id returned as `result_id` by /analyze")),
+    responses(
+        (status = 200, description = "Result with stale nodes re-analyzed", body = ApiResponse<ResultRefresh>),
+        (status = 404, description = "No stored result with this id", body = ApiResponse<serde_json::Value>),
+        (status = 429, description = "Too many analyses in progress", body = ApiResponse<serde_json::Value>)
+    )
+)]
+/// Re-fetch only the pages whose cache entries are stale and splice the new principles into the stored tree
+pub async fn refresh_result(
+    State(state): State<SharedState>,
+    caller: Caller,
+    Path(id): Path<String>,
+) -> std::result::Result<Json<ApiResponse<ResultRefresh>>, WikiEngineError> {
+    let stored = state.results.get(&id).ok_or_else(|| WikiEngineError::ResultNotFound(id.clone()))?;
+    let _slot = state.analyses.acquire().await?;
+
+    let started = Instant::now();
+    let outcome = state.engine.refresh_result(&stored.request, &stored.result).await;
+    let summary = outcome.as_ref().map(|refresh| refresh.result.tree.node_count()).map_err(ToString::to_string);
+    state.record_audit("/results/{id}/refresh", &caller, &stored.request, started, summary);
+    let mut refresh = outcome?;
+    refresh.result.result_id = Some(id.clone());
+    state.results.update(&id, refresh.result.clone());
+
+    Ok(Json(ApiResponse::success(refresh)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/categories",
+    tag = "analysis",
+    responses((status = 200, description = "Principle categories, with their display names, colors and parents", body = ApiResponse<Vec<CategoryDefinition>>))
+)]
+pub async fn list_categories() -> Json<ApiResponse<Vec<CategoryDefinition>>> {
+    Json(ApiResponse::success(categories::registry().definitions().cloned().collect()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/stats/network",
+    tag = "system",
+    responses((status = 200, description = "Wikipedia API traffic since startup", body = ApiResponse<NetworkStats>))
+)]
+pub async fn network_stats() -> Json<ApiResponse<NetworkStats>> {
+    Json(ApiResponse::success(monitoring::global_network_stats()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/cache/stats",
+    tag = "cache",
+    params(("x-tenant" = Option<String>, Header, description = "Tenant whose entries to include, with its token")),
+    responses((status = 200, description = "Cache statistics, overall and per tenant", body = ApiResponse<CacheStats>))
+)]
+/// Cache statistics. Only the admin sees every tenant's under `tenants`; a tenant presenting its
+/// token sees its own, and anyone else none.
+pub async fn cache_stats(State(state): State<SharedState>, headers: HeaderMap) -> Json<ApiResponse<CacheStats>> {
+    let mut stats = state.cache.get_cache_stats();
+    if admin_refusal(&state, &headers).is_some() {
+        let tenant = cache::current_tenant();
+        let authenticated = tenant_authenticated(&state, &headers, &tenant);
+        stats.tenants.retain(|name, _| authenticated && **name == *tenant);
+    }
+    Json(ApiResponse::success(stats))
+}
+
+#[utoipa::path(
+    post,
+    path = "/cache/clear",
+    tag = "cache",
+    params(("x-tenant" = Option<String>, Header, description = "Clear only this tenant's entries")),
+    responses(
+        (status = 200, description = "Cache cleared", body = ApiResponse<String>),
+        (status = 401, description = "Neither the tenant's token nor the admin token was presented", body = ApiResponse<serde_json::Value>)
+    )
+)]
+/// Clear the caller's tenant's cache (the default tenant's without `X-Tenant`) with that tenant's
+/// token or the admin token, or with the admin token and no `X-Tenant` every tenant's
+pub async fn clear_cache(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    let is_admin = admin_refusal(&state, &headers).is_none();
+    if is_admin && !headers.contains_key(TENANT_HEADER) {
+        state.cache.clear_all();
+        tracing::info!("Cache cleared via API");
+        return Json(ApiResponse::success("Cache cleared".to_string())).into_response();
+    }
+
+    // The tenant scope has already rejected malformed names
+    let tenant = cache::current_tenant();
+    if !is_admin && !tenant_authenticated(&state, &headers, &tenant) {
+        let message = format!("Clearing the cache of tenant '{}' needs its token or the admin token", tenant);
+        let body = Json(ApiResponse::<()>::error(message));
+        return (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")], body).into_response();
+    }
+    state.cache.clear_tenant(&tenant);
+    tracing::info!("Cache of tenant '{}' cleared via API", tenant);
+    Json(ApiResponse::success(format!("Cache of tenant '{}' cleared", tenant))).into_response()
+}
+
+/// The token presented as `Authorization: Bearer <token>`, if any
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+/// Whether the request presents the token of `tenant`
+fn tenant_authenticated(state: &WikiEngineState, headers: &HeaderMap, tenant: &str) -> bool {
+    bearer_token(headers).is_some_and(|token| state.tenant_tokens.authenticates(tenant, token))
+}
+
+/// The response refusing an admin request, or `None` when it carries the admin token
+fn admin_refusal(state: &WikiEngineState, headers: &HeaderMap) -> Option<Response> {
+    let Some(token) = &state.admin_token else {
+        let message = "Admin endpoints are disabled; set WIKI_ENGINE_ADMIN_TOKEN to enable them".to_string();
+        return Some((StatusCode::FORBIDDEN, Json(ApiResponse::<()>::error(message))).into_response());
+    };
+    if bearer_token(headers).is_some_and(|given| tokens_match(token, given)) {
+        return None;
+    }
+    let body = Json(ApiResponse::<()>::error("Missing or wrong admin token".to_string()));
+    Some((StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")], body).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/audit",
+    tag = "admin",
+    params(AuditQuery),
+    responses(
+        (status = 200, description = "Recorded analyses matching the query, newest first", body = ApiResponse<Vec<AuditEntry>>),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token", body = ApiResponse<serde_json::Value>),
+        (status = 403, description = "No admin token is configured", body = ApiResponse<serde_json::Value>)
+    )
+)]
+/// Query the audit log of analyses: who asked for what, how long it took and how it ended
+pub async fn audit_log(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Query(query): Query<AuditQuery>,
+) -> std::result::Result<Response, WikiEngineError> {
+    if let Some(refusal) = admin_refusal(&state, &headers) {
+        return Ok(refusal);
+    }
+    let entries = tokio::task::spawn_blocking(move || state.audit.query(&query))
+        .await
+        .map_err(|e| WikiEngineError::Analysis(format!("Failed to read the audit log: {}", e)))?
+        .map_err(|e| WikiEngineError::Analysis(format!("Failed to read the audit log: {}", e)))?;
+    Ok(Json(ApiResponse::success(entries)).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/config",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The runtime-tunable settings in effect", body = ApiResponse<RuntimeConfig>),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token", body = ApiResponse<serde_json::Value>),
+        (status = 403, description = "No admin token is configured", body = ApiResponse<serde_json::Value>)
+    )
+)]
+/// Inspect the limits, concurrency caps, cache TTLs and thresholds the server runs with
+pub async fn get_config(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    if let Some(refusal) = admin_refusal(&state, &headers) {
+        return refusal;
+    }
+    Json(ApiResponse::success(state.config())).into_response()
+}
+
+#[utoipa::path(
+    patch,
+    path = "/admin/config",
+    tag = "admin",
+    request_body(content = serde_json::Value, description = "JSON merge patch (RFC 7396) of the runtime config"),
+    responses(
+        (status = 200, description = "The config in effect after the patch", body = ApiResponse<RuntimeConfig>),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token", body = ApiResponse<serde_json::Value>),
+        (status = 403, description = "No admin token is configured", body = ApiResponse<serde_json::Value>),
+        (status = 422, description = "The patch names unknown settings, leaves one invalid or changes one only read at startup", body = ApiResponse<serde_json::Value>)
+    )
+)]
+/// Adjust runtime-tunable settings without a restart. The patch applies as a whole or not at
+/// all, and requests already running keep the settings they started with.
+pub async fn patch_config(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(patch): Json<serde_json::Value>,
+) -> std::result::Result<Response, WikiEngineError> {
+    if let Some(refusal) = admin_refusal(&state, &headers) {
+        return Ok(refusal);
+    }
+    let config = state.patch_config(&patch)?;
+    tracing::info!(patch = %patch, "Runtime config changed via API");
+    Ok(Json(ApiResponse::success(config)).into_response())
+}
+
+/// How the knowledge base is exported
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum KnowledgeBaseFormat {
+    #[default]
+    Json,
+    Yaml,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct KnowledgeBaseExportQuery {
+    /// `json` (default) or `yaml`
+    #[serde(default)]
+    pub format: KnowledgeBaseFormat,
+    /// `kb_version` of an earlier version kept in the knowledge base store; defaults to the one in use
+    pub version: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/kb/export",
+    tag = "knowledge_base",
+    params(KnowledgeBaseExportQuery),
+    responses(
+        (status = 200, description = "The knowledge base with its concepts sorted, as a file `wiki-engine kb import` reads back; its stamp is in the `X-KB-Version` header", body = ConceptKnowledgeBase),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token", body = ApiResponse<serde_json::Value>),
+        (status = 403, description = "No admin token is configured", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "No knowledge base version with this stamp", body = ApiResponse<serde_json::Value>)
+    )
+)]
+/// Download the knowledge base, e.g. to check it into a repository and review edits as diffs
+pub async fn export_knowledge_base(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Query(query): Query<KnowledgeBaseExportQuery>,
+) -> std::result::Result<Response, WikiEngineError> {
+    if let Some(refusal) = admin_refusal(&state, &headers) {
+        return Ok(refusal);
+    }
+    let version = query.version.clone();
+    let knowledge = tokio::task::spawn_blocking(move || state.engine.knowledge_base(version.as_deref()))
+        .await
+        .map_err(|error| WikiEngineError::Analysis(format!("Knowledge base export failed: {}", error)))??;
+    let Some(knowledge) = knowledge else {
+        let message = format!("No knowledge base version {}", query.version.unwrap_or_default());
+        return Ok((StatusCode::NOT_FOUND, Json(ApiResponse::<()>::error(message))).into_response());
+    };
+
+    let kb_version = knowledge.version();
+    let (content_type, extension, body) = match query.format {
+        KnowledgeBaseFormat::Json => ("application/json", "json", serde_json::to_string_pretty(&knowledge)?),
+        KnowledgeBaseFormat::Yaml => (
+            "application/yaml",
+            "yaml",
+            serde_yaml::to_string(&knowledge).map_err(|e| WikiEngineError::Analysis(format!("YAML encoding failed: {}", e)))?,
+        ),
+    };
+    let disposition = format!("attachment; filename=\"knowledge-base-{}.{}\"", kb_version, extension);
+    let headers = [
+        (header::CONTENT_TYPE, content_type.to_string()),
+        (header::CONTENT_DISPOSITION, disposition),
+        (header::HeaderName::from_static("x-kb-version"), kb_version),
+    ];
+    Ok((headers, body).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/kb/import",
+    tag = "knowledge_base",
+    params(ImportOptions),
+    request_body(
+        content = String,
+        content_type = "text/csv",
+        description = "CSV whose header names its columns: concept, component and any of relation, confidence and category"
+    ),
+    responses(
+        (status = 200, description = "What the import changed, or with `dry_run` would change, and the entries it disagreed with", body = ApiResponse<ImportReport>),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token", body = ApiResponse<serde_json::Value>),
+        (status = 403, description = "No admin token is configured", body = ApiResponse<serde_json::Value>),
+        (status = 422, description = "The file failed validation; nothing was imported", body = ApiResponse<Vec<FieldError>>)
+    )
+)]
+/// Merge component hierarchies maintained in a spreadsheet into the knowledge base. Analyses
+/// started afterwards use the merged knowledge base and carry its new `kb_version`.
+pub async fn import_knowledge_base(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Query(options): Query<ImportOptions>,
+    csv: String,
+) -> std::result::Result<Response, WikiEngineError> {
+    if let Some(refusal) = admin_refusal(&state, &headers) {
+        return Ok(refusal);
+    }
+    // Saving a new version writes to the knowledge base store
+    let report = tokio::task::spawn_blocking(move || state.engine.import_knowledge(&csv, &options))
+        .await
+        .map_err(|error| WikiEngineError::Analysis(format!("Knowledge base import failed: {}", error)))??;
+    Ok(Json(ApiResponse::success(report)).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/feedback",
+    tag = "analysis",
+    request_body = Feedback,
+    responses(
+        (status = 201, description = "The feedback as recorded", body = ApiResponse<FeedbackEntry>),
+        (status = 422, description = "Feedback failed validation", body = ApiResponse<Vec<FieldError>>),
+        (status = 429, description = "The caller submitted its hourly allowance of feedback; `Retry-After` says when it renews", body = ApiResponse<serde_json::Value>)
+    )
+)]
+/// Flag a principle as wrong or irrelevant, or a child concept as off-topic. Later analyses of
+/// the same page, for the same tenant, lower the principle's confidence for each caller who
+/// flagged it and leave out principles and concepts enough callers flagged. Confirming a
+/// principle as correct changes nothing by itself, but like the flags it tunes the confidence
+/// thresholds (see `/admin/tuning`).
+pub async fn submit_feedback(
+    State(state): State<SharedState>,
+    caller: Caller,
+    Json(feedback): Json<Feedback>,
+) -> std::result::Result<Response, WikiEngineError> {
+    let feedback = validate_feedback(feedback, &state.limits())?;
+    if let Err(retry_after) = state.engine.feedback().throttle(&caller.0) {
+        let body = Json(ApiResponse::<()>::error("Too much feedback from this caller, try again later".to_string()));
+        let retry_after = retry_after.as_secs().max(1).to_string();
+        return Ok((StatusCode::TOO_MANY_REQUESTS, [(header::RETRY_AFTER, retry_after)], body).into_response());
+    }
+    let principle = feedback.principle.as_ref().and_then(|principle| state.engine.principle_on_page(&feedback.term, principle));
+    let mut entry = FeedbackEntry::new(feedback, &cache::current_tenant(), &caller.0);
+    if let Some(principle) = &principle {
+        entry = entry.with_principle(principle);
     }
+    tracing::info!("Feedback from {} on '{}': {:?}", entry.caller, entry.feedback.term, entry.feedback.kind);
+    state.engine.feedback().record(entry.clone());
+    Ok((StatusCode::CREATED, Json(ApiResponse::success(entry))).into_response())
 }
 
-#[derive(Debug, Serialize)]
-pub struct SearchSuggestion {
-    pub term: String,
-    pub confidence: f32,
-    pub category: String,
+#[utoipa::path(
+    get,
+    path = "/admin/feedback",
+    tag = "admin",
+    params(FeedbackQuery),
+    responses(
+        (status = 200, description = "Feedback matching the query, newest first", body = ApiResponse<Vec<FeedbackEntry>>),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token", body = ApiResponse<serde_json::Value>),
+        (status = 403, description = "No admin token is configured", body = ApiResponse<serde_json::Value>)
+    )
+)]
+/// Review the feedback users gave on analyses
+pub async fn list_feedback(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Query(query): Query<FeedbackQuery>,
+) -> std::result::Result<Response, WikiEngineError> {
+    if let Some(refusal) = admin_refusal(&state, &headers) {
+        return Ok(refusal);
+    }
+    // The feedback log may be read from disk
+    let entries = tokio::task::spawn_blocking(move || state.engine.feedback().query(&query))
+        .await
+        .map_err(|e| WikiEngineError::Analysis(format!("Failed to read the feedback log: {}", e)))?
+        .map_err(|e| WikiEngineError::Analysis(format!("Failed to read the feedback log: {}", e)))?;
+    Ok(Json(ApiResponse::success(entries)).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/tuning",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The confidence thresholds in effect, with each category's precision curve", body = ApiResponse<TuningReport>),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token", body = ApiResponse<serde_json::Value>),
+        (status = 403, description = "No admin token is configured", body = ApiResponse<serde_json::Value>)
+    )
+)]
+/// Estimated precision and recall of each category's principles, from users' feedback, and the
+/// minimum confidence tuned for each
+pub async fn tuning_report(State(state): State<SharedState>, headers: HeaderMap) -> Response {
+    if let Some(refusal) = admin_refusal(&state, &headers) {
+        return refusal;
+    }
+    Json(ApiResponse::success(state.engine.tuner().report())).into_response()
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/tuning",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The thresholds fitted to the feedback recorded so far", body = ApiResponse<TuningReport>),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token", body = ApiResponse<serde_json::Value>),
+        (status = 403, description = "No admin token is configured", body = ApiResponse<serde_json::Value>)
+    )
+)]
+/// Refit the confidence thresholds now rather than at the next scheduled refit
+pub async fn refit_thresholds(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+) -> std::result::Result<Response, WikiEngineError> {
+    if let Some(refusal) = admin_refusal(&state, &headers) {
+        return Ok(refusal);
+    }
+    // The feedback log may be read from disk
+    let report = tokio::task::spawn_blocking(move || state.engine.tuner().refit())
+        .await
+        .map_err(|error| WikiEngineError::Analysis(format!("Threshold tuning failed: {}", error)))?
+        .map_err(|error| WikiEngineError::Analysis(format!("Failed to read the feedback log: {}", error)))?;
+    Ok(Json(ApiResponse::success(report)).into_response())
+}
+
+/// Which engine events an event stream passes on
+#[derive(Debug, Default, Deserialize, IntoParams)]
+pub struct EventStreamQuery {
+    /// Only events of this analysis
+    pub analysis_id: Option<uuid::Uuid>,
+    /// Only these events, comma-separated, e.g. `node_completed,analysis_completed`
+    pub events: Option<String>,
+}
+
+impl EventStreamQuery {
+    fn matches(&self, event: &EngineEvent) -> bool {
+        self.analysis_id.is_none_or(|id| event.analysis_id() == Some(id))
+            && self.events.as_deref().is_none_or(|names| names.split(',').any(|name| name.trim() == event.name()))
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/events",
+    tag = "admin",
+    params(EventStreamQuery),
+    responses(
+        (status = 200, description = "Server-sent events, one per engine event, named after its `event` field; a `lagged` event with the number missed is sent when the stream falls behind", body = EngineEvent, content_type = "text/event-stream"),
+        (status = 401, description = "Missing or wrong `Authorization: Bearer` admin token", body = ApiResponse<serde_json::Value>),
+        (status = 403, description = "No admin token is configured", body = ApiResponse<serde_json::Value>)
+    )
+)]
+/// Follow the engine's events live: nodes started and completed, pages fetched, principles
+/// extracted and analyses finished, across every tenant
+pub async fn stream_events(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Query(query): Query<EventStreamQuery>,
+) -> Response {
+    if let Some(refusal) = admin_refusal(&state, &headers) {
+        return refusal;
+    }
+    let receiver = state.engine.events().subscribe();
+    let events = futures::stream::unfold((receiver, query), |(mut receiver, query)| async move {
+        let event = loop {
+            match receiver.recv().await {
+                Ok(event) if query.matches(&event) => {
+                    break Event::default().event(event.name()).json_data(&event).expect("engine events serialize to JSON")
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(missed)) => break Event::default().event("lagged").data(missed.to_string()),
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        };
+        Some((Ok::<_, Infallible>(event), (receiver, query)))
+    });
+    Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct LearningPathQuery {
+    /// Concept to build the curriculum for
+    pub concept: String,
+    /// Depth of the analysis the path is built from (defaults to 3)
+    pub max_depth: Option<u8>,
 }
 
-#[derive(Debug, Deserialize)]
+#[utoipa::path(
+    get,
+    path = "/learning-path",
+    tag = "analysis",
+    params(LearningPathQuery),
+    responses(
+        (status = 200, description = "Curriculum ordered from foundations to the concept", body = ApiResponse<LearningPath>),
+        (status = 422, description = "Query failed validation", body = ApiResponse<Vec<FieldError>>),
+        (status = 429, description = "Too many analyses in progress", body = ApiResponse<serde_json::Value>)
+    )
+)]
+#[debug_handler]
+/// Analyze the concept and order its tree into stages, prerequisites first
+pub async fn learning_path(
+    State(state): State<SharedState>,
+    caller: Caller,
+    Query(params): Query<LearningPathQuery>,
+) -> std::result::Result<Json<ApiResponse<LearningPath>>, WikiEngineError> {
+    let request = SearchRequest {
+        term: params.concept,
+        max_depth: params.max_depth,
+        ..Default::default()
+    };
+    let limits = state.limits();
+    let mut request = validate_search_request(request, &limits)?;
+    request.timeout_ms.get_or_insert(limits.analysis_timeout_secs * 1000);
+    let _slot = state.analyses.acquire().await?;
+
+    let started = Instant::now();
+    let outcome = state.engine.analyze_recursive(&request).await;
+    let summary = outcome.as_ref().map(|result| result.tree.node_count()).map_err(ToString::to_string);
+    state.record_audit("/learning-path", &caller, &request, started, summary);
+    Ok(Json(ApiResponse::success(crate::learning_path::learning_path(&outcome?))))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
 pub struct SuggestQuery {
     pub query: String,
     pub limit: Option<u8>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/suggest",
+    tag = "analysis",
+    params(SuggestQuery),
+    responses(
+        (status = 200, description = "Suggested search terms", body = ApiResponse<Vec<SearchSuggestion>>),
+        (status = 422, description = "Query failed validation", body = ApiResponse<Vec<FieldError>>)
+    )
+)]
 #[debug_handler]
 pub async fn suggest_terms(
     State(state): State<SharedState>,
     Query(params): Query<SuggestQuery>,
-) -> Json<ApiResponse<Vec<SearchSuggestion>>> {
+) -> std::result::Result<Json<ApiResponse<Vec<SearchSuggestion>>>, WikiEngineError> {
     tracing::info!("Suggest endpoint called for query: {}", params.query);
+    let query = validate_suggest_query(&params.query, params.limit, &state.limits())?;
     
     let limit = params.limit.unwrap_or(8);
     
     // Use the real WikiEngine to get search suggestions from Wikipedia API
-    match state.engine.suggest_terms(&params.query, limit).await {
-        Ok(suggestions) => Json(ApiResponse::success(suggestions)),
+    match state.engine.suggest_terms(&query, limit).await {
+        Ok(suggestions) => Ok(Json(ApiResponse::success(suggestions))),
         Err(e) => {
-            tracing::error!("Suggestion failed for query '{}': {}", params.query, e);
-            Json(ApiResponse::error(format!("Suggestion failed: {}", e)))
+            tracing::error!("Suggestion failed for query '{}': {}", query, e);
+            Err(e)
         }
     }
-}
\ No newline at end of file
+}
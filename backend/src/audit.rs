@@ -0,0 +1,224 @@
+use crate::types::SearchRequest;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use utoipa::{IntoParams, ToSchema};
+
+/// Entries kept in memory when the log isn't backed by a file
+const MAX_RECENT_ENTRIES: usize = 1000;
+
+/// Entries returned by a query unless it asks for fewer
+const DEFAULT_QUERY_LIMIT: usize = 100;
+
+/// One analysis the server ran (or refused), as recorded in the audit log
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    /// Route the analysis was requested through, e.g. `/analyze`
+    pub endpoint: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Client address: the peer's, or the one a trusted proxy forwarded (never a client's own
+    /// `X-Forwarded-For`)
+    pub caller: String,
+    /// The request as the client sent it
+    pub request: SearchRequest,
+    pub duration_ms: u64,
+    /// Nodes in the analyzed tree; `None` when the analysis failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_count: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl AuditEntry {
+    /// Entry for an analysis of `request` that took `duration` and produced a tree of
+    /// `outcome` nodes, or failed with `outcome`'s error
+    pub fn new(
+        endpoint: &str,
+        caller: &str,
+        request: &SearchRequest,
+        duration: Duration,
+        outcome: Result<u32, String>,
+    ) -> Self {
+        let (node_count, error) = match outcome {
+            Ok(node_count) => (Some(node_count), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            timestamp: Utc::now(),
+            endpoint: endpoint.to_string(),
+            request_id: None,
+            caller: caller.to_string(),
+            request: request.clone(),
+            duration_ms: duration.as_millis() as u64,
+            node_count,
+            error,
+        }
+    }
+
+    pub fn with_request_id(mut self, request_id: Option<String>) -> Self {
+        self.request_id = request_id;
+        self
+    }
+}
+
+/// Which audit entries to return, newest first
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+pub struct AuditQuery {
+    /// Only analyses of this term (case-insensitive)
+    pub term: Option<String>,
+    pub caller: Option<String>,
+    pub endpoint: Option<String>,
+    /// Only analyses that failed
+    #[serde(default)]
+    pub errors_only: bool,
+    /// Only entries recorded at or after this time (RFC 3339)
+    pub since: Option<DateTime<Utc>>,
+    /// Only entries recorded before this time (RFC 3339)
+    pub until: Option<DateTime<Utc>>,
+    /// Most entries to return (defaults to 100)
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, entry: &AuditEntry) -> bool {
+        self.term.as_ref().is_none_or(|term| entry.request.term.eq_ignore_ascii_case(term))
+            && self.caller.as_ref().is_none_or(|caller| &entry.caller == caller)
+            && self.endpoint.as_ref().is_none_or(|endpoint| &entry.endpoint == endpoint)
+            && (!self.errors_only || entry.error.is_some())
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+            && self.until.is_none_or(|until| entry.timestamp < until)
+    }
+}
+
+/// Append-only record of the analyses the server ran. Backed by a JSON Lines file it survives
+/// restarts and queries stream through the whole history; otherwise only the latest entries are
+/// kept in memory.
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    recent: Mutex<VecDeque<AuditEntry>>,
+    file: Option<(PathBuf, Mutex<File>)>,
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Log appending to the file at `WIKI_ENGINE_AUDIT_LOG`, if set. A file that can't be
+    /// opened is logged and the log is kept in memory instead.
+    pub fn from_env() -> Self {
+        let Some(path) = std::env::var_os("WIKI_ENGINE_AUDIT_LOG").filter(|path| !path.is_empty()) else {
+            return Self::new();
+        };
+        let path = Path::new(&path);
+        Self::with_file(path).unwrap_or_else(|error| {
+            tracing::warn!("Keeping the audit log in memory, can't open {}: {}", path.display(), error);
+            Self::new()
+        })
+    }
+
+    /// Log appending to `path`, creating it if needed; entries already in it are kept
+    pub fn with_file(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { recent: Mutex::default(), file: Some((path.to_path_buf(), Mutex::new(file))) })
+    }
+
+    pub fn record(&self, entry: AuditEntry) {
+        if let Some((path, file)) = &self.file {
+            let mut line = serde_json::to_vec(&entry).expect("audit entries serialize to JSON");
+            line.push(b'\n');
+            // One write per line, so concurrent servers appending to the same file don't interleave
+            if let Err(error) = file.lock().unwrap().write_all(&line) {
+                tracing::warn!("Failed to append to the audit log {}: {}", path.display(), error);
+            }
+            return;
+        }
+
+        let mut recent = self.recent.lock().unwrap();
+        if recent.len() >= MAX_RECENT_ENTRIES {
+            recent.pop_front();
+        }
+        recent.push_back(entry);
+    }
+
+    /// Entries matching `query`, newest first. The file is read a line at a time, holding only
+    /// the newest matches, so a long history costs time but not memory; it blocks, so call it
+    /// from a blocking task.
+    pub fn query(&self, query: &AuditQuery) -> io::Result<Vec<AuditEntry>> {
+        let limit = query.limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+        let Some((path, _)) = &self.file else {
+            let recent = self.recent.lock().unwrap();
+            return Ok(recent.iter().rev().filter(|entry| query.matches(entry)).take(limit).cloned().collect());
+        };
+
+        let mut newest = VecDeque::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            // Lines that don't parse (e.g. one cut short by a crash) are skipped
+            let Ok(entry) = serde_json::from_str::<AuditEntry>(&line?) else { continue };
+            if query.matches(&entry) {
+                newest.push_back(entry);
+                if newest.len() > limit {
+                    newest.pop_front();
+                }
+            }
+        }
+        Ok(newest.into_iter().rev().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(term: &str, caller: &str, outcome: Result<u32, String>) -> AuditEntry {
+        let request = SearchRequest { term: term.to_string(), max_depth: Some(2), ..Default::default() };
+        AuditEntry::new("/analyze", caller, &request, Duration::from_millis(40), outcome)
+    }
+
+    #[test]
+    fn file_backed_log_persists_and_filters() {
+        let path = std::env::temp_dir().join(format!("audit-{}.jsonl", uuid::Uuid::new_v4()));
+        let log = AuditLog::with_file(&path).unwrap();
+        log.record(entry("bridge", "10.0.0.1", Ok(12)));
+        log.record(entry("motor", "10.0.0.2", Err("Request timed out".to_string())));
+        log.record(entry("Bridge", "10.0.0.2", Ok(7)));
+        std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"truncated\n").unwrap();
+        drop(log);
+
+        let log = AuditLog::with_file(&path).unwrap();
+        let all = log.query(&AuditQuery::default()).unwrap();
+        assert_eq!(all.iter().map(|entry| entry.request.term.as_str()).collect::<Vec<_>>(), ["Bridge", "motor", "bridge"]);
+        assert_eq!(all[2].node_count, Some(12));
+        assert_eq!(all[2].request.max_depth, Some(2));
+        let latest = log.query(&AuditQuery { limit: Some(2), ..Default::default() }).unwrap();
+        assert_eq!(latest.iter().map(|entry| entry.request.term.as_str()).collect::<Vec<_>>(), ["Bridge", "motor"]);
+
+        let bridges = log.query(&AuditQuery { term: Some("BRIDGE".to_string()), limit: Some(1), ..Default::default() });
+        assert_eq!(bridges.unwrap()[0].caller, "10.0.0.2");
+        let errors = log.query(&AuditQuery { errors_only: true, ..Default::default() }).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].error.as_deref(), Some("Request timed out"));
+        let future = log.query(&AuditQuery { since: Some(Utc::now() + chrono::Duration::hours(1)), ..Default::default() });
+        assert!(future.unwrap().is_empty());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn memory_log_keeps_the_latest_entries() {
+        let log = AuditLog::new();
+        for i in 0..MAX_RECENT_ENTRIES + 5 {
+            log.record(entry(&format!("term {}", i), "local", Ok(1)));
+        }
+        let entries = log.query(&AuditQuery { limit: Some(usize::MAX), ..Default::default() }).unwrap();
+        assert_eq!(entries.len(), MAX_RECENT_ENTRIES);
+        assert_eq!(entries[0].request.term, format!("term {}", MAX_RECENT_ENTRIES + 4));
+    }
+}
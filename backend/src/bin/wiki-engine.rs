@@ -0,0 +1,641 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use tokio::sync::watch;
+use wiki_engine::cache::WikiEngineCache;
+use wiki_engine::config::AnalysisLimits;
+use wiki_engine::dedup::fnv1a;
+use wiki_engine::progress::{AnalysisProgress, ProgressReporter};
+use wiki_engine::types::{AnalysisResult, Result, SearchRequest, WikiEngineError};
+use wiki_engine::validation::{validate_search_request, validate_suggest_query};
+use wiki_engine::{export, WikiEngine, BATCH_CONCURRENCY};
+
+/// Generate engineering tech trees from the command line, without running the HTTP server
+#[derive(Debug, Parser)]
+#[command(name = "wiki-engine", version)]
+struct Cli {
+    /// JSON cache file loaded at startup and saved on exit, so repeated runs skip finished work
+    #[arg(long, global = true, env = "WIKI_ENGINE_CACHE_FILE")]
+    cache_file: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Recursively analyze a term and print its tech tree
+    Analyze {
+        term: String,
+        #[arg(long, default_value_t = 3)]
+        depth: u8,
+        #[arg(long)]
+        max_results: Option<u8>,
+        #[arg(long, value_enum, default_value_t = Format::Json)]
+        format: Format,
+    },
+    /// Analyze every term in a newline-delimited file ('#' starts a comment), writing one output
+    /// file per term. Terms whose output already exists are skipped, so an interrupted run can
+    /// simply be started again (use --cache-file to keep fetched pages too).
+    Batch {
+        file: PathBuf,
+        #[arg(long, default_value = "tech-trees")]
+        out_dir: PathBuf,
+        #[arg(long, default_value_t = 3)]
+        depth: u8,
+        #[arg(long)]
+        max_results: Option<u8>,
+        #[arg(long, value_enum, default_value_t = Format::Json)]
+        format: Format,
+        #[arg(long, default_value_t = BATCH_CONCURRENCY)]
+        concurrency: usize,
+        /// Also write every tree into a single `_combined.<format>` file
+        #[arg(long)]
+        combined: bool,
+        /// Re-analyze terms even if their output file exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Break a concept into its foundational components
+    Decompose {
+        concept: String,
+        #[arg(long, default_value_t = 2)]
+        depth: u8,
+    },
+    /// Suggest engineering terms matching a query
+    Suggest {
+        query: String,
+        #[arg(long)]
+        limit: Option<u8>,
+    },
+    /// Inspect or clear the cache file
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Inspect or edit the versioned knowledge base
+    #[cfg(feature = "sqlite")]
+    Kb {
+        /// SQLite database holding the knowledge base versions
+        #[arg(long, env = "WIKI_ENGINE_KB_DATABASE")]
+        database: PathBuf,
+        #[command(subcommand)]
+        action: KbAction,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum CacheAction {
+    Stats,
+    Clear,
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Subcommand)]
+enum KbAction {
+    /// List the saved versions, oldest first
+    History,
+    /// Print a version, the latest unless one is given, with its concepts sorted
+    Export {
+        /// `kb_version` stamp of the version, as analysis results carry it
+        #[arg(long)]
+        version: Option<String>,
+        #[arg(long, value_enum, default_value_t = KbFormat::Json)]
+        format: KbFormat,
+    },
+    /// Save a knowledge base file (as `export` prints it) as the latest version
+    Import {
+        /// JSON, or YAML when named `*.yaml` or `*.yml`
+        file: PathBuf,
+        /// What changed, kept in the history
+        #[arg(long, default_value = "")]
+        note: String,
+    },
+    /// Show how one knowledge base differs from another, e.g. an edited export from the version deployed
+    Diff {
+        /// `kb_version` stamp of a stored version, or an exported file
+        from: String,
+        /// Stamp or file to compare against; defaults to the latest version
+        to: Option<String>,
+        /// Print the differences as JSON instead of one line per change
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum KbFormat {
+    Json,
+    Yaml,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Dot,
+    Csv,
+    /// One JSON object per principle per line
+    Ndjson,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Dot => "dot",
+            Format::Csv => "csv",
+            Format::Ndjson => "ndjson",
+        }
+    }
+
+    fn render(self, result: &AnalysisResult) -> Result<String> {
+        Ok(match self {
+            Format::Json => serde_json::to_string_pretty(result)?,
+            Format::Dot => export::to_dot(result),
+            Format::Csv => export::to_csv(result),
+            Format::Ndjson => export::to_ndjson(result),
+        })
+    }
+
+    fn render_combined(self, results: &[AnalysisResult]) -> Result<String> {
+        Ok(match self {
+            Format::Json => serde_json::to_string_pretty(results)?,
+            Format::Dot => export::to_dot_combined(results),
+            Format::Csv => export::to_csv_combined(results),
+            Format::Ndjson => export::to_ndjson_combined(results),
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // Logs go to stderr so stdout stays clean for piping
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "wiki_engine=warn".into()),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<String> {
+    let cache = Arc::new(WikiEngineCache::new());
+    if let Some(path) = &cli.cache_file {
+        match cache.load_from_file(path) {
+            Ok(restored) => tracing::info!("Restored {} cache entries from {}", restored, path.display()),
+            Err(e) => tracing::warn!("Ignoring unreadable cache file {}: {}", path.display(), e),
+        }
+    }
+
+    // Save even when the command failed, so whatever was fetched before the failure is kept
+    let output = execute(cli.command, &cache, cli.cache_file.as_deref()).await;
+
+    if let Some(path) = &cli.cache_file {
+        if let Err(e) = cache.save_to_file(path) {
+            tracing::warn!("Failed to save cache file {}: {}", path.display(), e);
+        }
+    }
+    output
+}
+
+async fn execute(command: Command, cache: &Arc<WikiEngineCache>, cache_file: Option<&Path>) -> Result<String> {
+    let limits = AnalysisLimits::from_env();
+
+    match command {
+        Command::Analyze { term, depth, max_results, format } => {
+            let request = validate_search_request(
+                SearchRequest {
+                    term,
+                    max_depth: Some(depth),
+                    max_results,
+                    ..Default::default()
+                },
+                &limits,
+            )?;
+            let engine = WikiEngine::new(Arc::clone(cache))?;
+            let reporter = ProgressReporter::new();
+            let display = tokio::spawn(show_progress(reporter.subscribe()));
+            let result = engine.analyze_with_progress(&request, &reporter).await;
+            drop(reporter);
+            let _ = display.await;
+            format.render(&result?)
+        }
+        Command::Batch { file, out_dir, depth, max_results, format, concurrency, combined, force } => {
+            let options = BatchOptions { out_dir, depth, max_results, format, concurrency, combined, force };
+            run_batch(&file, options, cache, cache_file, &limits).await
+        }
+        Command::Decompose { concept, depth } => {
+            let engine = WikiEngine::new(Arc::clone(cache))?;
+            let decomposition = engine.get_engineering_concept_hierarchy(concept.trim(), depth).await?;
+            Ok(serde_json::to_string_pretty(&decomposition)?)
+        }
+        Command::Suggest { query, limit } => {
+            let query = validate_suggest_query(&query, limit, &limits)?;
+            let engine = WikiEngine::new(Arc::clone(cache))?;
+            let suggestions = engine.suggest_terms(&query, limit.unwrap_or(8)).await?;
+            Ok(serde_json::to_string_pretty(&suggestions)?)
+        }
+        Command::Cache { action: CacheAction::Stats } => Ok(serde_json::to_string_pretty(&cache.get_cache_stats())?),
+        Command::Cache { action: CacheAction::Clear } => {
+            let stats = cache.get_cache_stats();
+            cache.clear_all();
+            Ok(format!(
+                "Cleared {} pages, {} principle sets, {} analysis nodes and {} decompositions",
+                stats.wikipedia_pages_count, stats.principles_count, stats.analysis_nodes_count, stats.decompositions_count
+            ))
+        }
+        #[cfg(feature = "sqlite")]
+        Command::Kb { database, action } => run_kb(&database, action),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn run_kb(database: &Path, action: KbAction) -> Result<String> {
+    use wiki_engine::kb_diff;
+    use wiki_engine::kb_store::{load_or_seed, KnowledgeBaseStore, SqliteKnowledgeBaseStore};
+
+    let store = SqliteKnowledgeBaseStore::open(database)?;
+    match action {
+        KbAction::History => {
+            load_or_seed(&store, None)?;
+            Ok(serde_json::to_string_pretty(&store.history()?)?)
+        }
+        KbAction::Export { version, format } => {
+            let knowledge = load_or_seed(&store, version.as_deref())?;
+            match format {
+                KbFormat::Json => Ok(serde_json::to_string_pretty(&knowledge)?),
+                KbFormat::Yaml => serde_yaml::to_string(&knowledge)
+                    .map_err(|e| WikiEngineError::Analysis(format!("YAML encoding failed: {}", e))),
+            }
+        }
+        KbAction::Import { file, note } => {
+            let revision = store.save(&read_knowledge_base(&file)?, &note)?;
+            Ok(format!("Knowledge base revision {} is {}", revision.revision, revision.kb_version))
+        }
+        KbAction::Diff { from, to, json } => {
+            // A stored version's stamp, or else the path of an exported file
+            let resolve = |reference: &str| match store.load(reference)? {
+                Some((_, knowledge)) => Ok(knowledge),
+                None => read_knowledge_base(Path::new(reference)),
+            };
+            let before = resolve(&from)?;
+            let after = match to {
+                Some(to) => resolve(&to)?,
+                None => load_or_seed(&store, None)?,
+            };
+            let changes = kb_diff::diff(&before, &after);
+            if json {
+                Ok(serde_json::to_string_pretty(&changes)?)
+            } else {
+                Ok(changes.to_string().trim_end().to_string())
+            }
+        }
+    }
+}
+
+/// A knowledge base file as `kb export` writes it: YAML when named `*.yaml` or `*.yml`, else JSON
+#[cfg(feature = "sqlite")]
+fn read_knowledge_base(file: &Path) -> Result<wiki_engine::semantic_analyzer::ConceptKnowledgeBase> {
+    let contents = std::fs::read_to_string(file)
+        .map_err(|e| WikiEngineError::Analysis(format!("Cannot read {}: {}", file.display(), e)))?;
+    match file.extension().and_then(|extension| extension.to_str()) {
+        Some("yaml" | "yml") => serde_yaml::from_str(&contents)
+            .map_err(|e| WikiEngineError::Analysis(format!("Invalid knowledge base {}: {}", file.display(), e))),
+        _ => Ok(serde_json::from_str(&contents)?),
+    }
+}
+
+/// Keep a one-line progress display on stderr until the analysis finishes
+async fn show_progress(mut updates: watch::Receiver<AnalysisProgress>) {
+    if !std::io::stderr().is_terminal() {
+        return;
+    }
+    while updates.changed().await.is_ok() {
+        let progress = updates.borrow_and_update().clone();
+        eprint!(
+            "\r\x1b[2K[{}/{}] {} ({} Wikipedia calls, {} cache hits)",
+            progress.nodes_completed,
+            progress.estimated_total_nodes,
+            progress.current_term.unwrap_or_default(),
+            progress.wikipedia_calls,
+            progress.cache_hits
+        );
+    }
+    eprint!("\r\x1b[2K");
+}
+
+/// Longest name `output_path` gives a term before its hash and extension, in bytes; file
+/// systems refuse names over 255
+const MAX_NAME_BYTES: usize = 200;
+
+/// Stem of the `--combined` file; `output_path` hashes every term with an underscore in it, so
+/// no term's output can take this name
+const COMBINED_STEM: &str = "_combined";
+
+struct BatchOptions {
+    out_dir: PathBuf,
+    depth: u8,
+    max_results: Option<u8>,
+    format: Format,
+    concurrency: usize,
+    combined: bool,
+    force: bool,
+}
+
+async fn run_batch(
+    file: &Path,
+    options: BatchOptions,
+    cache: &Arc<WikiEngineCache>,
+    cache_file: Option<&Path>,
+    limits: &AnalysisLimits,
+) -> Result<String> {
+    let terms = std::fs::read_to_string(file)
+        .map_err(|e| WikiEngineError::Analysis(format!("Cannot read {}: {}", file.display(), e)))?;
+    std::fs::create_dir_all(&options.out_dir)
+        .map_err(|e| WikiEngineError::Analysis(format!("Cannot create {}: {}", options.out_dir.display(), e)))?;
+
+    let BatchPlan { requests, skipped, mut failed } = plan_batch(&terms, &options, limits);
+
+    let total = requests.len();
+    let engine = WikiEngine::new(Arc::clone(cache))?;
+    let mut analyses = engine.analyze_each(requests, options.concurrency);
+    let mut done = 0;
+    let mut succeeded = 0;
+    while let Some((request, result)) = analyses.next().await {
+        done += 1;
+        match result.and_then(|result| write_output(&options, &request.term, &result)) {
+            Ok(()) => {
+                succeeded += 1;
+                eprintln!("[{}/{}] {}", done, total, request.term);
+            }
+            Err(e) => {
+                eprintln!("[{}/{}] {} failed: {}", done, total, request.term, e);
+                failed.push((request.term, e.to_string()));
+            }
+        }
+        // Checkpoint the cache so an interrupted run resumes with everything fetched so far
+        if let Some(path) = cache_file {
+            if let Err(e) = cache.save_to_file(path) {
+                tracing::warn!("Failed to save cache file {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    if options.combined {
+        write_combined(&options)?;
+    }
+
+    let mut summary = format!(
+        "Analyzed {} terms, skipped {} already done, {} failed",
+        succeeded,
+        skipped,
+        failed.len()
+    );
+    for (term, error) in failed {
+        summary.push_str(&format!("\n  {}: {}", term, error));
+    }
+    Ok(summary)
+}
+
+/// What a batch run still has to do
+struct BatchPlan {
+    requests: Vec<SearchRequest>,
+    /// Terms whose output already exists
+    skipped: usize,
+    /// Terms refused before analysis, with the reason
+    failed: Vec<(String, String)>,
+}
+
+/// Requests for the terms of a batch file that have no output yet (or all of them with
+/// `--force`); terms that would share an output file with an earlier one are refused
+fn plan_batch(terms: &str, options: &BatchOptions, limits: &AnalysisLimits) -> BatchPlan {
+    let mut plan = BatchPlan { requests: Vec::new(), skipped: 0, failed: Vec::new() };
+    let mut outputs: HashMap<PathBuf, &str> = HashMap::new();
+    for term in terms.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let path = output_path(options, term);
+        match outputs.get(&path) {
+            Some(other) if *other != term => {
+                plan.failed.push((term.to_string(), format!("would overwrite the output of '{}'", other)));
+                continue;
+            }
+            Some(_) => continue,
+            None => {
+                outputs.insert(path.clone(), term);
+            }
+        }
+        if !options.force && path.exists() {
+            plan.skipped += 1;
+            continue;
+        }
+        let request = SearchRequest {
+            term: term.to_string(),
+            max_depth: Some(options.depth),
+            max_results: options.max_results,
+            ..Default::default()
+        };
+        match validate_search_request(request, limits) {
+            Ok(request) => plan.requests.push(request),
+            Err(e) => plan.failed.push((term.to_string(), e.to_string())),
+        }
+    }
+    plan
+}
+
+/// Output file for a term: its lowercased name with anything but letters and digits replaced,
+/// cut to `MAX_NAME_BYTES`. Names that lost anything but spaces that way get a hash of the term,
+/// so "C++" and "C--" don't share a file.
+fn output_path(options: &BatchOptions, term: &str) -> PathBuf {
+    let term = term.trim();
+    let sanitized: String = term
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let mut end = sanitized.len().min(MAX_NAME_BYTES);
+    while !sanitized.is_char_boundary(end) {
+        end -= 1;
+    }
+    let mut name = sanitized[..end].to_string();
+    if end < sanitized.len() || term.chars().any(|c| !c.is_alphanumeric() && c != ' ') {
+        name.push_str(&format!("-{:08x}", fnv1a(term) as u32));
+    }
+    options.out_dir.join(format!("{}.{}", name, options.format.extension()))
+}
+
+fn write_output(options: &BatchOptions, term: &str, result: &AnalysisResult) -> Result<()> {
+    let path = output_path(options, term);
+    let rendered = options.format.render(result)?;
+    // JSON trees are always kept alongside so the combined graph can be rebuilt from any format
+    if !matches!(options.format, Format::Json) {
+        write_file(&path.with_extension("json"), &serde_json::to_string(result)?)?;
+    }
+    write_file(&path, &rendered)
+}
+
+/// Combine every tree in the output folder, including those written by earlier runs
+fn write_combined(options: &BatchOptions) -> Result<()> {
+    let entries = std::fs::read_dir(&options.out_dir)
+        .map_err(|e| WikiEngineError::Analysis(format!("Cannot read {}: {}", options.out_dir.display(), e)))?;
+    let mut results: Vec<AnalysisResult> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json") && path.file_stem().is_some_and(|stem| stem != COMBINED_STEM))
+        .filter_map(|path| std::fs::read(&path).ok())
+        .filter_map(|json| serde_json::from_slice(&json).ok())
+        .collect();
+    results.sort_by(|a, b| a.root_term.cmp(&b.root_term));
+
+    let path = options.out_dir.join(format!("{}.{}", COMBINED_STEM, options.format.extension()));
+    write_file(&path, &options.format.render_combined(&results)?)
+}
+
+/// Write then rename, so an interrupted run never leaves a truncated file that the next run
+/// would take for a finished one
+fn write_file(path: &Path, contents: &str) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    std::fs::write(&tmp, contents)
+        .and_then(|()| std::fs::rename(&tmp, path))
+        .map_err(|e| WikiEngineError::Analysis(format!("Cannot write {}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiki_engine::types::AnalysisNode;
+
+    fn batch_options(out_dir: PathBuf) -> BatchOptions {
+        BatchOptions {
+            out_dir,
+            depth: 2,
+            max_results: None,
+            format: Format::Json,
+            concurrency: 1,
+            combined: false,
+            force: false,
+        }
+    }
+
+    fn scratch_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("batch-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn result(term: &str) -> AnalysisResult {
+        AnalysisResult::from_tree(term.to_string(), AnalysisNode::leaf(term, 0), 0)
+    }
+
+    fn file_name(path: &Path) -> String {
+        path.file_name().unwrap().to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn output_names_keep_terms_apart() {
+        let options = batch_options(PathBuf::from("trees"));
+
+        assert_eq!(output_path(&options, " Steam engine ").file_name().unwrap(), "steam_engine.json");
+        assert_ne!(output_path(&options, "C++"), output_path(&options, "C--"));
+        assert!(file_name(&output_path(&options, "C++")).starts_with("c__-"));
+        assert_ne!(file_name(&output_path(&options, "combined")), format!("{}.json", COMBINED_STEM));
+        assert_ne!(file_name(&output_path(&options, "_combined")), format!("{}.json", COMBINED_STEM));
+    }
+
+    #[test]
+    fn long_terms_are_cut_to_a_valid_file_name() {
+        let options = batch_options(PathBuf::from("trees"));
+        let term = "турбореактивный двигатель ".repeat(8);
+        let other = format!("{}x", term.trim());
+
+        let name = file_name(&output_path(&options, &term));
+        assert!(name.len() <= 255, "{} bytes", name.len());
+        assert_ne!(output_path(&options, &term), output_path(&options, &other));
+    }
+
+    #[test]
+    fn batch_skips_finished_terms_unless_forced() {
+        let dir = scratch_dir();
+        let mut options = batch_options(dir.clone());
+        write_output(&options, "Gearbox", &result("Gearbox")).unwrap();
+        let terms = "# parts\nGearbox\n\nClutch\n";
+
+        let plan = plan_batch(terms, &options, &AnalysisLimits::default());
+        assert_eq!(plan.skipped, 1);
+        assert_eq!(plan.requests.iter().map(|r| r.term.as_str()).collect::<Vec<_>>(), ["Clutch"]);
+
+        options.force = true;
+        let plan = plan_batch(terms, &options, &AnalysisLimits::default());
+        assert_eq!(plan.skipped, 0);
+        assert_eq!(plan.requests.iter().map(|r| r.term.as_str()).collect::<Vec<_>>(), ["Gearbox", "Clutch"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn terms_sharing_an_output_are_refused() {
+        let options = batch_options(scratch_dir());
+
+        let plan = plan_batch("Gear Box\nGear box\nGearbox\ngear box", &options, &AnalysisLimits::default());
+        assert_eq!(plan.requests.iter().map(|r| r.term.as_str()).collect::<Vec<_>>(), ["Gear Box", "Gearbox"]);
+        assert_eq!(plan.failed.len(), 2);
+        assert!(plan.failed.iter().all(|(_, reason)| reason == "would overwrite the output of 'Gear Box'"));
+        std::fs::remove_dir_all(&options.out_dir).unwrap();
+    }
+
+    #[test]
+    fn combined_output_holds_only_term_trees() {
+        let dir = scratch_dir();
+        let options = batch_options(dir.clone());
+        for term in ["Turbine", "Combined", "Boiler"] {
+            write_output(&options, term, &result(term)).unwrap();
+        }
+        std::fs::write(dir.join("pump.json.tmp"), serde_json::to_string(&result("Pump")).unwrap()).unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a tree").unwrap();
+        std::fs::write(dir.join("broken.json"), "{").unwrap();
+
+        // Twice, so the first combined file is not read back in as a tree
+        write_combined(&options).unwrap();
+        write_combined(&options).unwrap();
+
+        let combined: Vec<AnalysisResult> =
+            serde_json::from_slice(&std::fs::read(dir.join(format!("{}.json", COMBINED_STEM))).unwrap()).unwrap();
+        let terms: Vec<_> = combined.iter().map(|result| result.root_term.as_str()).collect();
+        assert_eq!(terms, ["Boiler", "Combined", "Turbine"]);
+        assert!(output_path(&options, "Combined").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn files_are_replaced_whole() {
+        let dir = scratch_dir();
+        let path = dir.join("turbine.json");
+        // Left over from a run interrupted mid-write
+        std::fs::write(dir.join("turbine.json.tmp"), "{\"root_").unwrap();
+
+        write_file(&path, "first").unwrap();
+        write_file(&path, "second").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second");
+        assert!(!dir.join("turbine.json.tmp").exists());
+
+        // A failed rename leaves what was there untouched
+        std::fs::create_dir(dir.join("taken.json")).unwrap();
+        assert!(write_file(&dir.join("taken.json"), "tree").is_err());
+        assert!(dir.join("taken.json").is_dir());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,7 +1,19 @@
-use crate::types::{AnalysisNode, EngineeringPrinciple, WikipediaPage};
+use crate::config::{CacheTtlConfig, TenantQuotas};
+use crate::monitoring;
+use crate::semantic_analyzer::ConceptDecomposition;
+use crate::types::{AnalysisNode, EngineeringPrinciple, SearchSuggestion, WikipediaPage};
+use arc_swap::ArcSwap;
+use dashmap::mapref::one::{Ref, RefMut};
 use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
+use web_time::Instant;
 
 #[derive(Clone)]
 pub struct CacheEntry<T> {
@@ -29,111 +41,330 @@ impl<T> CacheEntry<T> {
     }
 }
 
+/// Tenant every cache entry belongs to unless a request names another
+pub const DEFAULT_TENANT: &str = "default";
+
+tokio::task_local! {
+    static TENANT: Arc<str>;
+}
+
+/// Run `future` with its cache lookups and inserts in `tenant`'s namespace
+pub async fn with_tenant<F: Future>(tenant: &str, future: F) -> F::Output {
+    TENANT.scope(Arc::from(tenant), future).await
+}
+
+/// Tenant whose namespace the current task reads and writes the cache in
+pub fn current_tenant() -> Arc<str> {
+    TENANT.try_with(Arc::clone).unwrap_or_else(|_| Arc::from(DEFAULT_TENANT))
+}
+
+/// Tenants whose hits and misses are counted; lookups in further tenants only count towards the
+/// metrics, since anyone can make up tenant names
+const MAX_TRACKED_TENANTS: usize = 1024;
+
+/// Entries are keyed by tenant as well as by their own key, so teams or wikis sharing a server
+/// never see each other's pages or trees for the same term
+type TenantKey = (Arc<str>, String);
+
+/// One namespace of the cache, counting each tenant's entries as they come and go so quotas
+/// are checked without scanning the namespace
+struct TenantMap<T> {
+    entries: DashMap<TenantKey, CacheEntry<T>>,
+    /// Entries per tenant; tenants without any have none
+    counts: DashMap<Arc<str>, usize>,
+}
+
+impl<T> TenantMap<T> {
+    fn new() -> Self {
+        Self { entries: DashMap::new(), counts: DashMap::new() }
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn tenant_len(&self, tenant: &str) -> usize {
+        self.counts.get(tenant).map_or(0, |count| *count)
+    }
+
+    fn get(&self, key: &TenantKey) -> Option<Ref<'_, TenantKey, CacheEntry<T>>> {
+        self.entries.get(key)
+    }
+
+    fn get_mut(&self, key: &TenantKey) -> Option<RefMut<'_, TenantKey, CacheEntry<T>>> {
+        self.entries.get_mut(key)
+    }
+
+    fn iter(&self) -> dashmap::iter::Iter<'_, TenantKey, CacheEntry<T>> {
+        self.entries.iter()
+    }
+
+    fn insert(&self, key: TenantKey, entry: CacheEntry<T>) {
+        let tenant = Arc::clone(&key.0);
+        if self.entries.insert(key, entry).is_none() {
+            *self.counts.entry(tenant).or_default() += 1;
+        }
+    }
+
+    fn remove(&self, key: &TenantKey) {
+        self.remove_if(key, |_, _| true);
+    }
+
+    fn remove_if(&self, key: &TenantKey, f: impl FnOnce(&TenantKey, &CacheEntry<T>) -> bool) -> Option<CacheEntry<T>> {
+        let (_, entry) = self.entries.remove_if(key, f)?;
+        self.uncount(&key.0);
+        Some(entry)
+    }
+
+    fn retain(&self, mut f: impl FnMut(&TenantKey, &mut CacheEntry<T>) -> bool) {
+        self.entries.retain(|key, entry| {
+            let keep = f(key, entry);
+            if !keep {
+                self.uncount(&key.0);
+            }
+            keep
+        });
+    }
+
+    fn clear(&self) {
+        self.entries.clear();
+        self.counts.clear();
+    }
+
+    fn uncount(&self, tenant: &str) {
+        if let Some(mut count) = self.counts.get_mut(tenant) {
+            *count = count.saturating_sub(1);
+        }
+        self.counts.remove_if(tenant, |_, count| *count == 0);
+    }
+}
+
+#[derive(Default)]
+struct TenantLookups {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
 pub struct WikiEngineCache {
-    wikipedia_pages: Arc<DashMap<String, CacheEntry<WikipediaPage>>>,
-    principles: Arc<DashMap<String, CacheEntry<Vec<EngineeringPrinciple>>>>,
-    analysis_nodes: Arc<DashMap<String, CacheEntry<AnalysisNode>>>,
-    page_ttl: Duration,
-    principle_ttl: Duration,
+    wikipedia_pages: Arc<TenantMap<WikipediaPage>>,
+    principles: Arc<TenantMap<Vec<EngineeringPrinciple>>>,
+    analysis_nodes: Arc<TenantMap<Arc<AnalysisNode>>>,
+    suggestions: Arc<TenantMap<Vec<SearchSuggestion>>>,
+    decompositions: Arc<TenantMap<ConceptDecomposition>>,
+    /// Swapped as a whole by `set_ttls`, so lookups see either the old or the new TTLs
+    ttls: ArcSwap<CacheTtlConfig>,
     max_entries: usize,
+    quotas: TenantQuotas,
+    lookups: DashMap<Arc<str>, TenantLookups>,
 }
 
 impl WikiEngineCache {
     pub fn new() -> Self {
-        Self {
-            wikipedia_pages: Arc::new(DashMap::new()),
-            principles: Arc::new(DashMap::new()),
-            analysis_nodes: Arc::new(DashMap::new()),
-            page_ttl: Duration::from_secs(3600), // 1 hour
-            principle_ttl: Duration::from_secs(7200), // 2 hours
-            max_entries: 1000,
-        }
+        Self::with_ttls(CacheTtlConfig::default(), 1000)
     }
 
     pub fn with_config(page_ttl: Duration, principle_ttl: Duration, max_entries: usize) -> Self {
+        let ttls = CacheTtlConfig {
+            page_ttl_secs: page_ttl.as_secs(),
+            principle_ttl_secs: principle_ttl.as_secs(),
+            ..Default::default()
+        };
+        Self::with_ttls(ttls, max_entries)
+    }
+
+    fn with_ttls(ttls: CacheTtlConfig, max_entries: usize) -> Self {
         Self {
-            wikipedia_pages: Arc::new(DashMap::new()),
-            principles: Arc::new(DashMap::new()),
-            analysis_nodes: Arc::new(DashMap::new()),
-            page_ttl,
-            principle_ttl,
+            wikipedia_pages: Arc::new(TenantMap::new()),
+            principles: Arc::new(TenantMap::new()),
+            analysis_nodes: Arc::new(TenantMap::new()),
+            suggestions: Arc::new(TenantMap::new()),
+            decompositions: Arc::new(TenantMap::new()),
+            ttls: ArcSwap::from_pointee(ttls),
             max_entries,
+            quotas: TenantQuotas::default(),
+            lookups: DashMap::new(),
         }
     }
 
+    /// Cap the entries each tenant may keep per namespace; a tenant at its quota evicts its own
+    /// oldest entry rather than someone else's
+    pub fn with_tenant_quotas(mut self, quotas: TenantQuotas) -> Self {
+        self.quotas = quotas;
+        self
+    }
+
+    pub fn ttls(&self) -> CacheTtlConfig {
+        CacheTtlConfig::clone(&self.ttls.load())
+    }
+
+    /// Change how long entries stay fresh; applies to entries already cached too
+    pub fn set_ttls(&self, ttls: CacheTtlConfig) {
+        self.ttls.store(Arc::new(ttls));
+    }
+
     // Wikipedia page caching
     pub fn get_wikipedia_page(&self, title: &str) -> Option<WikipediaPage> {
-        if let Some(mut entry) = self.wikipedia_pages.get_mut(title) {
-            if !entry.is_expired(self.page_ttl) {
-                return Some(entry.access().clone());
-            } else {
-                // Entry expired, remove it
-                drop(entry);
-                self.wikipedia_pages.remove(title);
-            }
-        }
-        None
+        self.lookup(&self.wikipedia_pages, "pages", title, self.ttls.load().page_ttl())
+    }
+
+    /// Unexpired cached page under `title`, without counting as a lookup or an access
+    pub fn peek_wikipedia_page(&self, title: &str) -> Option<WikipediaPage> {
+        self.wikipedia_pages
+            .get(&(current_tenant(), title.to_string()))
+            .filter(|entry| !entry.is_expired(self.ttls.load().page_ttl()))
+            .map(|entry| entry.data.clone())
+    }
+
+    /// Monthly pageviews of a cached, unexpired page, without cloning the page
+    pub fn cached_monthly_views(&self, title: &str) -> Option<u64> {
+        self.wikipedia_pages
+            .get(&(current_tenant(), title.to_string()))
+            .filter(|entry| !entry.is_expired(self.ttls.load().page_ttl()))
+            .and_then(|entry| entry.data.monthly_views)
+    }
+
+    /// Remove and return an expired page, so it can be revalidated instead of fetched from scratch
+    pub fn take_expired_wikipedia_page(&self, title: &str) -> Option<WikipediaPage> {
+        self.wikipedia_pages
+            .remove_if(&(current_tenant(), title.to_string()), |_, entry| entry.is_expired(self.ttls.load().page_ttl()))
+            .map(|entry| entry.data)
+    }
+
+    pub fn remove_wikipedia_page(&self, title: &str) {
+        self.wikipedia_pages.remove(&(current_tenant(), title.to_string()));
+    }
+
+    /// Titles of the current tenant's cached Wikipedia pages
+    pub fn cached_page_titles(&self) -> Vec<String> {
+        let tenant = current_tenant();
+        self.wikipedia_pages
+            .iter()
+            .filter(|entry| entry.key().0 == tenant)
+            .map(|entry| entry.data.title.clone())
+            .collect()
     }
 
     pub fn cache_wikipedia_page(&self, title: String, page: WikipediaPage) {
-        self.ensure_capacity(&self.wikipedia_pages);
-        self.wikipedia_pages.insert(title, CacheEntry::new(page));
+        self.insert(&self.wikipedia_pages, title, page);
     }
 
     // Engineering principles caching
     pub fn get_principles(&self, page_title: &str) -> Option<Vec<EngineeringPrinciple>> {
-        if let Some(mut entry) = self.principles.get_mut(page_title) {
-            if !entry.is_expired(self.principle_ttl) {
-                return Some(entry.access().clone());
-            } else {
-                drop(entry);
-                self.principles.remove(page_title);
-            }
-        }
-        None
+        self.lookup(&self.principles, "principles", page_title, self.ttls.load().principle_ttl())
+    }
+
+    pub fn remove_principles(&self, page_title: &str) {
+        self.principles.remove(&(current_tenant(), page_title.to_string()));
     }
 
     pub fn cache_principles(&self, page_title: String, principles: Vec<EngineeringPrinciple>) {
-        self.ensure_capacity(&self.principles);
-        self.principles.insert(page_title, CacheEntry::new(principles));
+        self.insert(&self.principles, page_title, principles);
+    }
+
+    // Analysis node caching (for recursive results). Trees are shared rather than copied out of
+    // the cache, which for deep trees would dominate the cost of a hit.
+    pub fn get_analysis_node(&self, cache_key: &str) -> Option<Arc<AnalysisNode>> {
+        self.lookup(&self.analysis_nodes, "analysis_nodes", cache_key, self.ttls.load().principle_ttl())
+    }
+
+    pub fn cache_analysis_node(&self, cache_key: String, node: Arc<AnalysisNode>) {
+        self.insert(&self.analysis_nodes, cache_key, node);
+    }
+
+    // Suggestion caching (keystroke-driven queries repeat often while the user types)
+    pub fn get_suggestions(&self, cache_key: &str) -> Option<Vec<SearchSuggestion>> {
+        self.lookup(&self.suggestions, "suggestions", cache_key, self.ttls.load().suggestion_ttl())
+    }
+
+    pub fn cache_suggestions(&self, cache_key: String, suggestions: Vec<SearchSuggestion>) {
+        self.insert(&self.suggestions, cache_key, suggestions);
+    }
+
+    // Concept decomposition caching
+    pub fn get_decomposition(&self, cache_key: &str) -> Option<ConceptDecomposition> {
+        self.lookup(&self.decompositions, "decompositions", cache_key, self.ttls.load().decomposition_ttl())
+    }
+
+    pub fn cache_decomposition(&self, cache_key: String, decomposition: ConceptDecomposition) {
+        self.insert(&self.decompositions, cache_key, decomposition);
     }
 
-    // Analysis node caching (for recursive results)
-    pub fn get_analysis_node(&self, cache_key: &str) -> Option<AnalysisNode> {
-        if let Some(mut entry) = self.analysis_nodes.get_mut(cache_key) {
-            if !entry.is_expired(self.principle_ttl) {
-                return Some(entry.access().clone());
+    // Keys of entries derived from the knowledge base carry its version, so editing it leaves
+    // the entries built from the old one unused until they expire
+    pub fn generate_decomposition_cache_key(&self, concept: &str, max_depth: u8, kb_version: &str) -> String {
+        format!("decompose:{}:{}:kb={}", concept.trim().to_lowercase(), max_depth, kb_version)
+    }
+
+    pub fn generate_suggestion_cache_key(&self, query: &str, limit: u8, kb_version: &str) -> String {
+        format!("suggest:{}:{}:kb={}", query.trim().to_lowercase(), limit, kb_version)
+    }
+
+    // Generate cache key for analysis with depth and options
+    pub fn generate_analysis_cache_key(
+        &self,
+        term: &str,
+        max_depth: u8,
+        max_results: u8,
+        filters: &str,
+        kb_version: &str,
+    ) -> String {
+        format!("analysis:{}:{}:{}{}:kb={}", term, max_depth, max_results, filters, kb_version)
+    }
+
+    /// The current tenant's unexpired entry under `key`; expired entries are removed
+    fn lookup<T: Clone>(&self, cache: &TenantMap<T>, namespace: &'static str, key: &str, ttl: Duration) -> Option<T> {
+        let tenant = current_tenant();
+        let key = (Arc::clone(&tenant), key.to_string());
+        let mut found = None;
+        if let Some(mut entry) = cache.get_mut(&key) {
+            if !entry.is_expired(ttl) {
+                found = Some(entry.access().clone());
             } else {
+                // Entry expired, remove it
                 drop(entry);
-                self.analysis_nodes.remove(cache_key);
+                cache.remove(&key);
             }
         }
-        None
-    }
 
-    pub fn cache_analysis_node(&self, cache_key: String, node: AnalysisNode) {
-        self.ensure_capacity(&self.analysis_nodes);
-        self.analysis_nodes.insert(cache_key, CacheEntry::new(node));
+        monitoring::record_cache_lookup(namespace, found.is_some());
+        let lookups = match self.lookups.get(&tenant) {
+            Some(lookups) => Some(lookups),
+            None if self.lookups.len() < MAX_TRACKED_TENANTS => Some(self.lookups.entry(tenant).or_default().downgrade()),
+            None => None,
+        };
+        if let Some(lookups) = lookups {
+            let counter = if found.is_some() { &lookups.hits } else { &lookups.misses };
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+        found
     }
 
-    // Generate cache key for analysis with depth and options
-    pub fn generate_analysis_cache_key(&self, term: &str, max_depth: u8, max_results: u8) -> String {
-        format!("analysis:{}:{}:{}", term, max_depth, max_results)
+    fn insert<T>(&self, cache: &TenantMap<T>, key: String, data: T) {
+        let tenant = current_tenant();
+        self.ensure_capacity(cache, &tenant);
+        cache.insert((tenant, key), CacheEntry::new(data));
     }
 
     // Cache management
-    fn ensure_capacity<T>(&self, cache: &Arc<DashMap<String, CacheEntry<T>>>) {
+    fn ensure_capacity<T>(&self, cache: &TenantMap<T>, tenant: &Arc<str>) {
+        if let Some(quota) = self.quotas.quota(tenant) {
+            if cache.tenant_len(tenant) >= quota {
+                self.evict_oldest(cache, Some(tenant));
+                return;
+            }
+        }
         if cache.len() >= self.max_entries {
-            self.evict_oldest(cache);
+            self.evict_oldest(cache, None);
         }
     }
 
-    fn evict_oldest<T>(&self, cache: &Arc<DashMap<String, CacheEntry<T>>>) {
-        let mut oldest_key: Option<String> = None;
+    /// Remove the oldest entry, of `tenant` only when given
+    fn evict_oldest<T>(&self, cache: &TenantMap<T>, tenant: Option<&Arc<str>>) {
+        let mut oldest_key: Option<TenantKey> = None;
         let mut oldest_time = Instant::now();
 
         // Find the oldest entry
-        for entry in cache.iter() {
+        for entry in cache.iter().filter(|entry| tenant.is_none_or(|tenant| &entry.key().0 == tenant)) {
             if entry.timestamp < oldest_time {
                 oldest_time = entry.timestamp;
                 oldest_key = Some(entry.key().clone());
@@ -148,27 +379,34 @@ impl WikiEngineCache {
 
     pub fn cleanup_expired(&self) {
         // Clean up expired Wikipedia pages
-        self.cleanup_expired_entries(&self.wikipedia_pages, self.page_ttl);
+        self.cleanup_expired_entries(&self.wikipedia_pages, self.ttls.load().page_ttl());
         
         // Clean up expired principles
-        self.cleanup_expired_entries(&self.principles, self.principle_ttl);
+        self.cleanup_expired_entries(&self.principles, self.ttls.load().principle_ttl());
         
         // Clean up expired analysis nodes
-        self.cleanup_expired_entries(&self.analysis_nodes, self.principle_ttl);
+        self.cleanup_expired_entries(&self.analysis_nodes, self.ttls.load().principle_ttl());
+
+        // Clean up expired suggestions
+        self.cleanup_expired_entries(&self.suggestions, self.ttls.load().suggestion_ttl());
+
+        // Clean up expired decompositions
+        self.cleanup_expired_entries(&self.decompositions, self.ttls.load().decomposition_ttl());
+
+        // Forget the lookups of tenants with nothing cached, so made-up tenant names don't pile up
+        self.lookups.retain(|tenant, _| self.has_entries(tenant));
     }
 
-    fn cleanup_expired_entries<T>(&self, cache: &Arc<DashMap<String, CacheEntry<T>>>, ttl: Duration) {
-        let mut expired_keys = Vec::new();
-        
-        for entry in cache.iter() {
-            if entry.is_expired(ttl) {
-                expired_keys.push(entry.key().clone());
-            }
-        }
-        
-        for key in expired_keys {
-            cache.remove(&key);
-        }
+    fn has_entries(&self, tenant: &str) -> bool {
+        self.wikipedia_pages.tenant_len(tenant) > 0
+            || self.principles.tenant_len(tenant) > 0
+            || self.analysis_nodes.tenant_len(tenant) > 0
+            || self.suggestions.tenant_len(tenant) > 0
+            || self.decompositions.tenant_len(tenant) > 0
+    }
+
+    fn cleanup_expired_entries<T>(&self, cache: &TenantMap<T>, ttl: Duration) {
+        cache.retain(|_, entry| !entry.is_expired(ttl));
     }
 
     pub fn get_cache_stats(&self) -> CacheStats {
@@ -176,8 +414,36 @@ impl WikiEngineCache {
             wikipedia_pages_count: self.wikipedia_pages.len(),
             principles_count: self.principles.len(),
             analysis_nodes_count: self.analysis_nodes.len(),
+            suggestions_count: self.suggestions.len(),
+            decompositions_count: self.decompositions.len(),
             total_memory_usage: self.estimate_memory_usage(),
+            tenants: self.tenant_stats(),
+        }
+    }
+
+    /// Entries, lookups and quota of every tenant that has cached or looked anything up
+    fn tenant_stats(&self) -> BTreeMap<String, TenantCacheStats> {
+        fn count<T>(cache: &TenantMap<T>, stats: &mut BTreeMap<String, TenantCacheStats>, field: fn(&mut TenantCacheStats) -> &mut usize) {
+            for count in cache.counts.iter() {
+                *field(stats.entry(count.key().to_string()).or_default()) = *count.value();
+            }
         }
+
+        let mut stats = BTreeMap::new();
+        count(&self.wikipedia_pages, &mut stats, |tenant| &mut tenant.wikipedia_pages_count);
+        count(&self.principles, &mut stats, |tenant| &mut tenant.principles_count);
+        count(&self.analysis_nodes, &mut stats, |tenant| &mut tenant.analysis_nodes_count);
+        count(&self.suggestions, &mut stats, |tenant| &mut tenant.suggestions_count);
+        count(&self.decompositions, &mut stats, |tenant| &mut tenant.decompositions_count);
+        for lookups in self.lookups.iter() {
+            let tenant: &mut TenantCacheStats = stats.entry(lookups.key().to_string()).or_default();
+            tenant.hits = lookups.hits.load(Ordering::Relaxed);
+            tenant.misses = lookups.misses.load(Ordering::Relaxed);
+        }
+        for (name, tenant) in &mut stats {
+            tenant.quota = self.quotas.quota(name);
+        }
+        stats
     }
 
     fn estimate_memory_usage(&self) -> usize {
@@ -185,14 +451,95 @@ impl WikiEngineCache {
         let pages_size = self.wikipedia_pages.len() * 1024; // Assume ~1KB per page
         let principles_size = self.principles.len() * 512; // Assume ~512B per principle set
         let nodes_size = self.analysis_nodes.len() * 2048; // Assume ~2KB per analysis node
+        let suggestions_size = self.suggestions.len() * 256; // Assume ~256B per suggestion list
+        let decompositions_size = self.decompositions.len() * 1024; // Assume ~1KB per decomposition
         
-        pages_size + principles_size + nodes_size
+        pages_size + principles_size + nodes_size + suggestions_size + decompositions_size
     }
 
     pub fn clear_all(&self) {
         self.wikipedia_pages.clear();
         self.principles.clear();
         self.analysis_nodes.clear();
+        self.suggestions.clear();
+        self.decompositions.clear();
+        self.lookups.clear();
+    }
+
+    /// Remove every entry of `tenant`, leaving other tenants' untouched
+    pub fn clear_tenant(&self, tenant: &str) {
+        self.wikipedia_pages.retain(|key, _| &*key.0 != tenant);
+        self.principles.retain(|key, _| &*key.0 != tenant);
+        self.analysis_nodes.retain(|key, _| &*key.0 != tenant);
+        self.suggestions.retain(|key, _| &*key.0 != tenant);
+        self.decompositions.retain(|key, _| &*key.0 != tenant);
+        self.lookups.remove(tenant);
+    }
+
+    /// Copy of the long-lived namespaces (pages, principles, analysis nodes, decompositions)
+    pub fn snapshot(&self) -> CacheSnapshot {
+        fn collect<T: Clone>(cache: &TenantMap<T>, snapshot: &mut CacheSnapshot, field: fn(&mut CacheSnapshot) -> &mut HashMap<String, T>) {
+            for entry in cache.iter() {
+                let (tenant, key) = entry.key();
+                let tenant_snapshot = match &**tenant {
+                    DEFAULT_TENANT => &mut *snapshot,
+                    tenant => snapshot.tenants.entry(tenant.to_string()).or_default(),
+                };
+                field(tenant_snapshot).insert(key.clone(), entry.data.clone());
+            }
+        }
+
+        let mut snapshot = CacheSnapshot::default();
+        collect(&self.wikipedia_pages, &mut snapshot, |snapshot| &mut snapshot.wikipedia_pages);
+        collect(&self.principles, &mut snapshot, |snapshot| &mut snapshot.principles);
+        collect(&self.analysis_nodes, &mut snapshot, |snapshot| &mut snapshot.analysis_nodes);
+        collect(&self.decompositions, &mut snapshot, |snapshot| &mut snapshot.decompositions);
+        snapshot
+    }
+
+    /// Load a snapshot; restored entries start a fresh TTL. Returns the number of entries restored.
+    pub fn restore(&self, snapshot: CacheSnapshot) -> usize {
+        let restored = snapshot.len();
+        self.restore_tenant(Arc::from(DEFAULT_TENANT), snapshot);
+        restored
+    }
+
+    fn restore_tenant(&self, tenant: Arc<str>, snapshot: CacheSnapshot) {
+        for (key, page) in snapshot.wikipedia_pages {
+            self.wikipedia_pages.insert((Arc::clone(&tenant), key), CacheEntry::new(page));
+        }
+        for (key, principles) in snapshot.principles {
+            self.principles.insert((Arc::clone(&tenant), key), CacheEntry::new(principles));
+        }
+        for (key, node) in snapshot.analysis_nodes {
+            self.analysis_nodes.insert((Arc::clone(&tenant), key), CacheEntry::new(node));
+        }
+        for (key, decomposition) in snapshot.decompositions {
+            self.decompositions.insert((Arc::clone(&tenant), key), CacheEntry::new(decomposition));
+        }
+        for (name, tenant_snapshot) in snapshot.tenants {
+            self.restore_tenant(Arc::from(name), tenant_snapshot);
+        }
+    }
+
+    /// Persist the cache as JSON so a later process can pick up where this one stopped
+    pub fn save_to_file(&self, path: &Path) -> std::io::Result<()> {
+        let json = serde_json::to_vec(&self.snapshot()).map_err(std::io::Error::other)?;
+        // Write then rename, so an interrupted save never leaves a truncated cache file behind
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(tmp, path)
+    }
+
+    /// Restore a cache file written by `save_to_file`; a missing file restores nothing
+    pub fn load_from_file(&self, path: &Path) -> std::io::Result<usize> {
+        let json = match std::fs::read(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let snapshot: CacheSnapshot = serde_json::from_slice(&json).map_err(std::io::Error::other)?;
+        Ok(self.restore(snapshot))
     }
 
     pub fn warm_up(&self, common_terms: &[&str]) {
@@ -202,12 +549,60 @@ impl WikiEngineCache {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Serializable contents of a cache, see `WikiEngineCache::save_to_file`. The top-level
+/// namespaces hold the default tenant's entries, `tenants` those of every other tenant.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheSnapshot {
+    #[serde(default)]
+    pub wikipedia_pages: HashMap<String, WikipediaPage>,
+    #[serde(default)]
+    pub principles: HashMap<String, Vec<EngineeringPrinciple>>,
+    #[serde(default)]
+    pub analysis_nodes: HashMap<String, Arc<AnalysisNode>>,
+    #[serde(default)]
+    pub decompositions: HashMap<String, ConceptDecomposition>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tenants: BTreeMap<String, CacheSnapshot>,
+}
+
+impl CacheSnapshot {
+    pub fn len(&self) -> usize {
+        self.wikipedia_pages.len()
+            + self.principles.len()
+            + self.analysis_nodes.len()
+            + self.decompositions.len()
+            + self.tenants.values().map(CacheSnapshot::len).sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct CacheStats {
     pub wikipedia_pages_count: usize,
     pub principles_count: usize,
     pub analysis_nodes_count: usize,
+    pub suggestions_count: usize,
+    pub decompositions_count: usize,
     pub total_memory_usage: usize,
+    /// The counts above broken down by tenant, with each tenant's lookups since startup
+    pub tenants: BTreeMap<String, TenantCacheStats>,
+}
+
+/// One tenant's share of the cache
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct TenantCacheStats {
+    pub wikipedia_pages_count: usize,
+    pub principles_count: usize,
+    pub analysis_nodes_count: usize,
+    pub suggestions_count: usize,
+    pub decompositions_count: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries the tenant may keep per namespace, when capped
+    pub quota: Option<usize>,
 }
 
 impl Default for WikiEngineCache {
@@ -233,4 +628,55 @@ pub async fn start_cache_cleanup_task(cache: Arc<WikiEngineCache>) {
             stats.total_memory_usage / 1024
         );
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tenants_have_separate_namespaces_and_quotas() {
+        let cache = WikiEngineCache::new().with_tenant_quotas(TenantQuotas::default().with_quota("team-a", 2));
+        cache.cache_principles("Motor".to_string(), vec![]);
+        with_tenant("team-a", async {
+            assert!(cache.get_principles("Motor").is_none());
+            for title in ["Motor", "Rotor", "Stator"] {
+                cache.cache_principles(title.to_string(), vec![]);
+            }
+            assert!(cache.get_principles("Rotor").is_some());
+        })
+        .await;
+
+        let stats = cache.get_cache_stats();
+        assert_eq!(stats.principles_count, 3);
+        let team_a = &stats.tenants["team-a"];
+        assert_eq!((team_a.principles_count, team_a.hits, team_a.misses, team_a.quota), (2, 1, 1, Some(2)));
+        assert_eq!(stats.tenants[DEFAULT_TENANT].principles_count, 1);
+
+        let restored = WikiEngineCache::new();
+        restored.restore(serde_json::from_value(serde_json::to_value(cache.snapshot()).unwrap()).unwrap());
+        assert_eq!(restored.get_cache_stats().tenants["team-a"].principles_count, 2);
+
+        cache.clear_tenant("team-a");
+        assert!(!cache.get_cache_stats().tenants.contains_key("team-a"));
+        assert!(cache.get_principles("Motor").is_some());
+    }
+
+    #[tokio::test]
+    async fn tenants_without_entries_are_forgotten() {
+        let cache = WikiEngineCache::with_config(Duration::from_secs(3600), Duration::ZERO, 1000);
+        for tenant in ["made-up-1", "made-up-2"] {
+            with_tenant(tenant, async {
+                assert!(cache.get_principles("Motor").is_none());
+                cache.cache_principles("Motor".to_string(), vec![]);
+            })
+            .await;
+        }
+        assert_eq!(cache.get_cache_stats().tenants["made-up-1"].principles_count, 1);
+
+        std::thread::sleep(Duration::from_millis(5));
+        cache.cleanup_expired();
+        let stats = cache.get_cache_stats();
+        assert_eq!(stats.principles_count, 0);
+        assert!(stats.tenants.is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+use crate::types::PrincipleCategory;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+use std::sync::LazyLock;
+use utoipa::ToSchema;
+
+/// Categories shipped with the crate, a JSON array of `CategoryDefinition`s
+const BUILT_IN: &str = include_str!("categories.json");
+
+static REGISTRY: LazyLock<CategoryRegistry> = LazyLock::new(CategoryRegistry::from_env);
+
+/// A principle category: how it is named and drawn, where it sits in the taxonomy and how
+/// sentences are recognized as belonging to it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CategoryDefinition {
+    /// How principles carry the category, e.g. "Mechanical"
+    pub id: String,
+    /// English display name
+    pub name: String,
+    /// Id of the broader category this one refines; filtering on the parent includes it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// CSS color clients draw the category in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Regexes whose matches in a sentence count towards it being in the category
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// A sentence typical of the category, which embedding-based classification compares against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prototype: Option<String>,
+}
+
+/// The category taxonomy, in the order ties between categories are resolved
+#[derive(Debug, Clone, Default)]
+pub struct CategoryRegistry {
+    categories: Vec<(CategoryDefinition, Vec<Regex>)>,
+}
+
+/// The registry loaded at startup: the built-in categories plus those in
+/// `WIKI_ENGINE_CATEGORIES_FILE`
+pub fn registry() -> &'static CategoryRegistry {
+    &REGISTRY
+}
+
+impl CategoryRegistry {
+    /// The built-in categories, extended or overridden by the JSON file at
+    /// `WIKI_ENGINE_CATEGORIES_FILE`. A file that can't be read or parsed is logged and ignored.
+    pub fn from_env() -> Self {
+        let registry = Self::built_in();
+        let Some(path) = std::env::var_os("WIKI_ENGINE_CATEGORIES_FILE").filter(|path| !path.is_empty()) else {
+            return registry;
+        };
+        let path = Path::new(&path);
+        match std::fs::read_to_string(path).and_then(|contents| registry.clone().with_definitions(&contents)) {
+            Ok(loaded) => loaded,
+            Err(error) => {
+                tracing::warn!("Ignoring categories in {}: {}", path.display(), error);
+                registry
+            }
+        }
+    }
+
+    pub fn built_in() -> Self {
+        Self::default().with_definitions(BUILT_IN).expect("built-in categories parse")
+    }
+
+    /// Add the categories of `json`, an array of `CategoryDefinition`s, replacing those with the
+    /// same id
+    pub fn with_definitions(mut self, json: &str) -> io::Result<Self> {
+        let definitions: Vec<CategoryDefinition> =
+            serde_json::from_str(json).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        for definition in definitions {
+            let patterns = definition
+                .patterns
+                .iter()
+                .map(|pattern| Regex::new(pattern))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|error| {
+                    io::Error::new(io::ErrorKind::InvalidData, format!("category {}: {}", definition.id, error))
+                })?;
+            match self.categories.iter_mut().find(|(existing, _)| existing.id == definition.id) {
+                Some(existing) => *existing = (definition, patterns),
+                None => self.categories.push((definition, patterns)),
+            }
+        }
+        Ok(self)
+    }
+
+    pub fn definitions(&self) -> impl Iterator<Item = &CategoryDefinition> {
+        self.categories.iter().map(|(definition, _)| definition)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&CategoryDefinition> {
+        self.definitions().find(|definition| definition.id == id)
+    }
+
+    /// Each category with its compiled patterns
+    pub fn pattern_sets(&self) -> impl Iterator<Item = (&CategoryDefinition, &[Regex])> {
+        self.categories.iter().map(|(definition, patterns)| (definition, patterns.as_slice()))
+    }
+
+    /// The subcategories of `parent`, or with `None` the top-level categories (including those
+    /// whose parent isn't registered)
+    pub fn children<'a>(&'a self, parent: Option<&'a str>) -> impl Iterator<Item = &'a CategoryDefinition> {
+        self.definitions().filter(move |definition| {
+            let own_parent = definition.parent.as_deref().filter(|id| self.get(id).is_some());
+            own_parent == parent
+        })
+    }
+
+    /// Ids of the categories `id` refines, nearest first
+    pub fn ancestors<'a>(&'a self, id: &str) -> Vec<&'a str> {
+        let mut ancestors = Vec::new();
+        let mut current = self.get(id).and_then(|definition| definition.parent.as_deref());
+        // Bounded so a cycle in a configured taxonomy can't loop forever
+        while let Some(parent) = current.filter(|_| ancestors.len() < self.categories.len()) {
+            ancestors.push(parent);
+            current = self.get(parent).and_then(|definition| definition.parent.as_deref());
+        }
+        ancestors
+    }
+
+    /// The most specific category `text` belongs to: starting from the top level, the
+    /// subcategory with the most pattern hits (counting those of its own subcategories) is
+    /// taken for as long as one has any. Of equally scored categories the first wins.
+    pub fn categorize(&self, text: &str) -> Option<PrincipleCategory> {
+        let hits: Vec<(&str, usize)> = self
+            .pattern_sets()
+            .map(|(definition, patterns)| {
+                (definition.id.as_str(), patterns.iter().map(|pattern| pattern.find_iter(text).count()).sum())
+            })
+            .collect();
+        let score = |id: &str| -> usize {
+            hits.iter().filter(|(other, _)| self.is_within(other, id)).map(|(_, hits)| hits).sum()
+        };
+
+        let mut best = None;
+        loop {
+            let mut next: Option<(&str, usize)> = None;
+            for child in self.children(best) {
+                let child_score = score(&child.id);
+                if child_score > next.map_or(0, |(_, best_score)| best_score) {
+                    next = Some((&child.id, child_score));
+                }
+            }
+            match next {
+                Some((id, _)) => best = Some(id),
+                None => return best.map(PrincipleCategory::new),
+            }
+        }
+    }
+
+    /// Compiled patterns of the category `id`, or `None` when it isn't registered
+    pub fn patterns(&self, id: &str) -> Option<&[Regex]> {
+        self.categories
+            .iter()
+            .find(|(definition, _)| definition.id == id)
+            .map(|(_, patterns)| patterns.as_slice())
+    }
+
+    /// Whether `id` is `ancestor` or one of its descendants
+    pub fn is_within(&self, id: &str, ancestor: &str) -> bool {
+        id == ancestor || self.ancestors(id).contains(&ancestor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configured_categories_extend_the_taxonomy() {
+        let registry = CategoryRegistry::built_in()
+            .with_definitions(
+                r##"[
+                    {"id": "Aerodynamics", "name": "Aerodynamics", "parent": "Mechanical", "patterns": ["(?i)\\b(lift|drag)\\b"]},
+                    {"id": "Thermal", "name": "Heat", "color": "#FF0000"}
+                ]"##,
+            )
+            .unwrap();
+
+        assert_eq!(registry.definitions().count(), CategoryRegistry::built_in().definitions().count() + 1);
+        assert_eq!(registry.get("Thermal").unwrap().name, "Heat");
+        assert!(registry.patterns("Thermal").unwrap().is_empty());
+        assert_eq!(registry.patterns("Aerodynamics").unwrap().len(), 1);
+        assert!(registry.is_within("Aerodynamics", "Mechanical"));
+        assert!(registry.is_within("Mechanical", "Mechanical"));
+        assert!(!registry.is_within("Mechanical", "Aerodynamics"));
+        assert!(registry.clone().with_definitions(r#"[{"id": "Bad", "name": "Bad", "patterns": ["("]}]"#).is_err());
+    }
+
+    #[test]
+    fn text_gets_the_most_specific_matching_category() {
+        let registry = CategoryRegistry::built_in();
+        let category = |text: &str| registry.categorize(text).map(|category| category.name().to_string());
+
+        assert_eq!(category("The inverter's IGBT switching frequency sets the motor current").as_deref(), Some("Power Electronics"));
+        assert_eq!(category("A low-pass filter removes noise from the sensor signal").as_deref(), Some("Signal Processing"));
+        assert_eq!(category("Resistance limits the current through the conductor").as_deref(), Some("Electrical"));
+        assert_eq!(category("The gear train multiplies torque").as_deref(), Some("Mechanical"));
+        assert_eq!(category("It was built in 1890"), None);
+        assert_eq!(registry.ancestors("Signal Processing"), ["Electrical"]);
+        assert_eq!(registry.children(Some("Electrical")).count(), 2);
+    }
+}
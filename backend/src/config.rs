@@ -0,0 +1,879 @@
+use crate::types::{ConceptSource, FieldError, Result, SearchRequest, WikiEngineError};
+use crate::MAX_BATCH_TERMS;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use subtle::ConstantTimeEq;
+use utoipa::ToSchema;
+
+/// Upper bounds applied to incoming requests before any work is scheduled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AnalysisLimits {
+    pub max_depth: u8,
+    pub max_results: u8,
+    pub max_term_length: usize,
+    pub max_batch_terms: usize,
+    pub max_suggestions: u8,
+    pub max_principles_per_node: u8,
+    /// Longest an analysis may expand its tree; also the default when a request sets no timeout
+    pub analysis_timeout_secs: u64,
+    /// Page text analyzed per node; longer extracts are cut at a sentence boundary
+    pub max_extract_bytes: usize,
+}
+
+impl Default for AnalysisLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 5,
+            max_results: 20,
+            max_term_length: 200,
+            max_batch_terms: MAX_BATCH_TERMS,
+            max_suggestions: 20,
+            max_principles_per_node: 32,
+            analysis_timeout_secs: 60,
+            max_extract_bytes: 200_000,
+        }
+    }
+}
+
+impl AnalysisLimits {
+    /// Load limits from `WIKI_ENGINE_*` environment variables, falling back to defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            max_depth: env_or("WIKI_ENGINE_MAX_DEPTH", defaults.max_depth),
+            max_results: env_or("WIKI_ENGINE_MAX_RESULTS", defaults.max_results),
+            max_term_length: env_or("WIKI_ENGINE_MAX_TERM_LENGTH", defaults.max_term_length),
+            max_batch_terms: env_or("WIKI_ENGINE_MAX_BATCH_TERMS", defaults.max_batch_terms),
+            max_suggestions: env_or("WIKI_ENGINE_MAX_SUGGESTIONS", defaults.max_suggestions),
+            max_principles_per_node: env_or("WIKI_ENGINE_MAX_PRINCIPLES_PER_NODE", defaults.max_principles_per_node),
+            analysis_timeout_secs: env_or("WIKI_ENGINE_ANALYSIS_TIMEOUT_SECS", defaults.analysis_timeout_secs),
+            max_extract_bytes: env_or("WIKI_ENGINE_MAX_EXTRACT_BYTES", defaults.max_extract_bytes),
+        }
+    }
+}
+
+/// Server-wide caps on concurrent work, shared by every request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ConcurrencyConfig {
+    /// Analyses (single, batch or refresh) running at once
+    pub max_concurrent_analyses: usize,
+    /// Analyses allowed to wait for a slot; further requests get a 429 straight away
+    pub max_queued_analyses: usize,
+    /// How long a queued analysis waits for a slot before giving up with a 429
+    pub queue_timeout_secs: u64,
+    /// Wikipedia requests in flight across all analyses and hosts
+    pub max_concurrent_fetches: usize,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_analyses: 8,
+            max_queued_analyses: 32,
+            queue_timeout_secs: 30,
+            max_concurrent_fetches: 64,
+        }
+    }
+}
+
+impl ConcurrencyConfig {
+    /// Load from `WIKI_ENGINE_MAX_CONCURRENT_ANALYSES`, `WIKI_ENGINE_MAX_QUEUED_ANALYSES`,
+    /// `WIKI_ENGINE_ANALYSIS_QUEUE_TIMEOUT_SECS` and `WIKI_ENGINE_MAX_CONCURRENT_FETCHES`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            max_concurrent_analyses: env_or("WIKI_ENGINE_MAX_CONCURRENT_ANALYSES", defaults.max_concurrent_analyses),
+            max_queued_analyses: env_or("WIKI_ENGINE_MAX_QUEUED_ANALYSES", defaults.max_queued_analyses),
+            queue_timeout_secs: env_or("WIKI_ENGINE_ANALYSIS_QUEUE_TIMEOUT_SECS", defaults.queue_timeout_secs),
+            max_concurrent_fetches: env_or("WIKI_ENGINE_MAX_CONCURRENT_FETCHES", defaults.max_concurrent_fetches),
+        }
+    }
+}
+
+/// Controls which capitalized phrases are allowed to become related-concept candidates
+#[derive(Debug, Clone, Serialize)]
+pub struct ConceptFilterConfig {
+    /// Extra stop-list entries on top of the curated list in the analyzer
+    pub extra_stop_terms: Vec<String>,
+    /// Minimum engineering keyword hits (in the concept and the sentences mentioning it)
+    pub min_relevance: usize,
+    /// Words never taken as terms in any language, on top of the built-in per-language lists
+    pub extra_stop_words: Vec<String>,
+}
+
+impl Default for ConceptFilterConfig {
+    fn default() -> Self {
+        Self {
+            extra_stop_terms: Vec::new(),
+            min_relevance: 2,
+            extra_stop_words: Vec::new(),
+        }
+    }
+}
+
+impl ConceptFilterConfig {
+    /// Load from `WIKI_ENGINE_CONCEPT_STOP_LIST`, `WIKI_ENGINE_STOP_WORDS` (both comma-separated)
+    /// and `WIKI_ENGINE_MIN_CONCEPT_RELEVANCE`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            extra_stop_terms: env_list("WIKI_ENGINE_CONCEPT_STOP_LIST").unwrap_or(defaults.extra_stop_terms),
+            min_relevance: env_or("WIKI_ENGINE_MIN_CONCEPT_RELEVANCE", defaults.min_relevance),
+            extra_stop_words: env_list("WIKI_ENGINE_STOP_WORDS").unwrap_or(defaults.extra_stop_words),
+        }
+    }
+}
+
+/// Tuning for the HTTP client shared by every Wikipedia request
+#[derive(Debug, Clone, Serialize)]
+pub struct HttpClientConfig {
+    pub timeout_secs: u64,
+    /// Idle connections kept open per host for reuse
+    pub pool_max_idle_per_host: usize,
+    pub pool_idle_timeout_secs: u64,
+    pub tcp_keepalive_secs: u64,
+    /// Speak HTTP/2 without ALPN negotiation; only for endpoints known to support it
+    pub http2_prior_knowledge: bool,
+    /// Requests allowed in flight to a single host at once
+    pub max_concurrent_requests_per_host: usize,
+    /// HTTP(S) proxy all requests go through, e.g. `http://proxy.internal:3128`
+    pub proxy: Option<String>,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 30,
+            pool_max_idle_per_host: 32,
+            pool_idle_timeout_secs: 90,
+            tcp_keepalive_secs: 60,
+            http2_prior_knowledge: false,
+            max_concurrent_requests_per_host: 16,
+            proxy: None,
+        }
+    }
+}
+
+impl HttpClientConfig {
+    /// Load from `WIKI_ENGINE_HTTP_*` environment variables, falling back to defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            timeout_secs: env_or("WIKI_ENGINE_HTTP_TIMEOUT_SECS", defaults.timeout_secs),
+            pool_max_idle_per_host: env_or("WIKI_ENGINE_HTTP_POOL_MAX_IDLE_PER_HOST", defaults.pool_max_idle_per_host),
+            pool_idle_timeout_secs: env_or("WIKI_ENGINE_HTTP_POOL_IDLE_TIMEOUT_SECS", defaults.pool_idle_timeout_secs),
+            tcp_keepalive_secs: env_or("WIKI_ENGINE_HTTP_TCP_KEEPALIVE_SECS", defaults.tcp_keepalive_secs),
+            http2_prior_knowledge: env_or("WIKI_ENGINE_HTTP2_PRIOR_KNOWLEDGE", defaults.http2_prior_knowledge),
+            max_concurrent_requests_per_host: env_or(
+                "WIKI_ENGINE_HTTP_MAX_CONCURRENT_PER_HOST",
+                defaults.max_concurrent_requests_per_host,
+            ),
+            proxy: std::env::var("WIKI_ENGINE_HTTP_PROXY").ok().filter(|proxy| !proxy.is_empty()),
+        }
+    }
+}
+
+/// A MediaWiki installation the engine reads from: Wikipedia by default, or a mirror or
+/// an internal engineering wiki
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaWikiSite {
+    /// Short name used in logs, e.g. `enwiki`
+    pub name: String,
+    /// Action API endpoint (`api.php`)
+    pub api_url: String,
+    /// Article URL with `$1` standing for the title, as in MediaWiki's `$wgArticlePath`
+    pub article_url_template: String,
+    /// Project name for the Wikimedia pageviews API; `None` for wikis outside Wikimedia
+    pub pageviews_project: Option<String>,
+}
+
+impl Default for MediaWikiSite {
+    fn default() -> Self {
+        Self::wikipedia("en")
+    }
+}
+
+impl MediaWikiSite {
+    /// The Wikipedia edition for a language code
+    pub fn wikipedia(language: &str) -> Self {
+        Self {
+            name: format!("{}wiki", language),
+            api_url: format!("https://{}.wikipedia.org/w/api.php", language),
+            article_url_template: format!("https://{}.wikipedia.org/wiki/$1", language),
+            pageviews_project: Some(format!("{}.wikipedia", language)),
+        }
+    }
+
+    /// Load from `WIKI_ENGINE_SITE_NAME`, `WIKI_ENGINE_API_URL`, `WIKI_ENGINE_ARTICLE_URL_TEMPLATE` and
+    /// `WIKI_ENGINE_PAGEVIEWS_PROJECT`. Pageviews are only looked up for a custom API URL when a project is set.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let api_url = env_or("WIKI_ENGINE_API_URL", defaults.api_url.clone());
+        let pageviews_project = match std::env::var("WIKI_ENGINE_PAGEVIEWS_PROJECT") {
+            Ok(project) => Some(project).filter(|project| !project.is_empty()),
+            Err(_) if api_url == defaults.api_url => defaults.pageviews_project,
+            Err(_) => None,
+        };
+
+        Self {
+            name: env_or("WIKI_ENGINE_SITE_NAME", defaults.name),
+            api_url,
+            article_url_template: env_or("WIKI_ENGINE_ARTICLE_URL_TEMPLATE", defaults.article_url_template),
+            pageviews_project,
+        }
+    }
+
+    pub fn article_url(&self, title: &str) -> String {
+        self.article_url_template
+            .replace("$1", &urlencoding::encode(&title.replace(' ', "_")))
+    }
+}
+
+/// Settings for the document sources other than MediaWiki
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DocumentSourceConfig {
+    /// Folder of Markdown/plain-text documents; the local folder source is disabled without it
+    pub documents_dir: Option<PathBuf>,
+    /// PatentsView API key; patent search is disabled without it
+    #[serde(skip_serializing)]
+    pub patentsview_api_key: Option<String>,
+    /// Hosts the URL source may fetch (`*.example.org` for subdomains); it is disabled without any
+    pub url_allowed_hosts: Vec<String>,
+}
+
+impl DocumentSourceConfig {
+    /// Load from `WIKI_ENGINE_DOCUMENTS_DIR`, `WIKI_ENGINE_PATENTSVIEW_API_KEY` and
+    /// `WIKI_ENGINE_URL_SOURCE_HOSTS`
+    pub fn from_env() -> Self {
+        Self {
+            documents_dir: std::env::var("WIKI_ENGINE_DOCUMENTS_DIR")
+                .ok()
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+            patentsview_api_key: std::env::var("WIKI_ENGINE_PATENTSVIEW_API_KEY")
+                .ok()
+                .filter(|key| !key.is_empty()),
+            url_allowed_hosts: env_list("WIKI_ENGINE_URL_SOURCE_HOSTS").unwrap_or_default(),
+        }
+    }
+}
+
+/// A named bundle of request settings, picked with `SearchRequest::preset`. Settings the
+/// request gives itself win over the preset's.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AnalysisPreset {
+    #[serde(default)]
+    pub max_depth: Option<u8>,
+    #[serde(default)]
+    pub max_results: Option<u8>,
+    #[serde(default)]
+    pub max_principles_per_node: Option<u8>,
+    #[serde(default)]
+    pub min_confidence: Option<f32>,
+    #[serde(default)]
+    pub prune_below: Option<f32>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Used when the request leaves `concept_source` at its default
+    #[serde(default)]
+    pub concept_source: Option<ConceptSource>,
+}
+
+impl AnalysisPreset {
+    /// Fill the settings `request` leaves unset from the preset
+    pub fn apply(&self, request: &mut SearchRequest) {
+        request.max_depth = request.max_depth.or(self.max_depth);
+        request.max_results = request.max_results.or(self.max_results);
+        request.max_principles_per_node = request.max_principles_per_node.or(self.max_principles_per_node);
+        request.min_confidence = request.min_confidence.or(self.min_confidence);
+        request.prune_below = request.prune_below.or(self.prune_below);
+        request.timeout_ms = request.timeout_ms.or(self.timeout_ms);
+        if request.concept_source == ConceptSource::default() {
+            request.concept_source = self.concept_source.unwrap_or_default();
+        }
+    }
+}
+
+/// The presets requests can name: `quick`, `standard` and `deep`, plus any configured ones
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalysisPresets {
+    presets: BTreeMap<String, AnalysisPreset>,
+}
+
+impl Default for AnalysisPresets {
+    fn default() -> Self {
+        let presets = [
+            (
+                "quick",
+                AnalysisPreset {
+                    max_depth: Some(1),
+                    max_results: Some(5),
+                    max_principles_per_node: Some(4),
+                    min_confidence: Some(0.5),
+                    timeout_ms: Some(10_000),
+                    concept_source: Some(ConceptSource::ExtractRegex),
+                    ..Default::default()
+                },
+            ),
+            (
+                "standard",
+                AnalysisPreset {
+                    max_depth: Some(3),
+                    max_results: Some(10),
+                    max_principles_per_node: Some(crate::DEFAULT_PRINCIPLES_PER_NODE),
+                    concept_source: Some(ConceptSource::Combined),
+                    ..Default::default()
+                },
+            ),
+            (
+                "deep",
+                AnalysisPreset {
+                    max_depth: Some(5),
+                    max_results: Some(20),
+                    max_principles_per_node: Some(16),
+                    prune_below: Some(0.5),
+                    concept_source: Some(ConceptSource::Combined),
+                    ..Default::default()
+                },
+            ),
+        ];
+        Self { presets: presets.into_iter().map(|(name, preset)| (name.to_string(), preset)).collect() }
+    }
+}
+
+impl AnalysisPresets {
+    /// The built-in presets plus those in the JSON file at `WIKI_ENGINE_PRESETS_FILE`, an object
+    /// of presets by name; a configured preset replaces a built-in one of the same name. A file
+    /// that can't be read or parsed is logged and ignored.
+    pub fn from_env() -> Self {
+        let presets = Self::default();
+        let Some(path) = std::env::var_os("WIKI_ENGINE_PRESETS_FILE").filter(|path| !path.is_empty()) else {
+            return presets;
+        };
+        let path = Path::new(&path);
+        let loaded = std::fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|contents| presets.clone().with_presets(&contents).map_err(|error| error.to_string()));
+        loaded.unwrap_or_else(|error| {
+            tracing::warn!("Ignoring presets in {}: {}", path.display(), error);
+            presets
+        })
+    }
+
+    /// Add the presets of `json`, an object of presets by name
+    pub fn with_presets(mut self, json: &str) -> serde_json::Result<Self> {
+        let presets: BTreeMap<String, AnalysisPreset> = serde_json::from_str(json)?;
+        self.presets.extend(presets);
+        Ok(self)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AnalysisPreset> {
+        self.presets.get(name)
+    }
+
+    /// Fill the settings `request` leaves unset from the preset it names, if any
+    pub fn apply(&self, request: &mut SearchRequest) -> Result<()> {
+        let Some(name) = &request.preset else {
+            return Ok(());
+        };
+        let Some(preset) = self.presets.get(name) else {
+            let names: Vec<&str> = self.presets.keys().map(String::as_str).collect();
+            return Err(WikiEngineError::InvalidRequest(vec![FieldError::new(
+                "preset",
+                format!("unknown preset `{}`; expected one of {}", name, names.join(", ")),
+            )]));
+        };
+        preset.apply(request);
+        Ok(())
+    }
+}
+
+/// How long cached entries stay fresh
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct CacheTtlConfig {
+    /// Fetched pages
+    pub page_ttl_secs: u64,
+    /// Extracted principles and analyzed nodes
+    pub principle_ttl_secs: u64,
+    pub suggestion_ttl_secs: u64,
+    /// Concept decompositions, which only change with the knowledge base
+    pub decomposition_ttl_secs: u64,
+}
+
+impl CacheTtlConfig {
+    pub fn page_ttl(&self) -> Duration {
+        Duration::from_secs(self.page_ttl_secs)
+    }
+
+    pub fn principle_ttl(&self) -> Duration {
+        Duration::from_secs(self.principle_ttl_secs)
+    }
+
+    pub fn suggestion_ttl(&self) -> Duration {
+        Duration::from_secs(self.suggestion_ttl_secs)
+    }
+
+    pub fn decomposition_ttl(&self) -> Duration {
+        Duration::from_secs(self.decomposition_ttl_secs)
+    }
+}
+
+impl Default for CacheTtlConfig {
+    fn default() -> Self {
+        Self {
+            page_ttl_secs: 3600,
+            principle_ttl_secs: 7200,
+            suggestion_ttl_secs: 600,
+            decomposition_ttl_secs: 86400,
+        }
+    }
+}
+
+/// How user feedback on principles and concepts changes later analyses of the same page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FeedbackPolicy {
+    /// Confidence a principle loses for each user who flagged it
+    pub penalty_per_flag: f32,
+    /// Users who must flag a principle or child concept before it is left out altogether
+    pub exclude_after: u32,
+    /// Feedback one caller may submit per hour; more is refused until the hour is up
+    pub max_per_caller_per_hour: u32,
+    /// Pages, across every tenant, whose flags are counted; complaints about further pages are
+    /// still recorded but change nothing
+    pub max_flagged_pages: usize,
+}
+
+impl Default for FeedbackPolicy {
+    fn default() -> Self {
+        Self { penalty_per_flag: 0.1, exclude_after: 3, max_per_caller_per_hour: 30, max_flagged_pages: 100_000 }
+    }
+}
+
+impl FeedbackPolicy {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            penalty_per_flag: env_or("WIKI_ENGINE_FEEDBACK_PENALTY", defaults.penalty_per_flag),
+            exclude_after: env_or("WIKI_ENGINE_FEEDBACK_EXCLUDE_AFTER", defaults.exclude_after).max(1),
+            max_per_caller_per_hour: env_or("WIKI_ENGINE_FEEDBACK_MAX_PER_CALLER", defaults.max_per_caller_per_hour).max(1),
+            max_flagged_pages: env_or("WIKI_ENGINE_FEEDBACK_MAX_PAGES", defaults.max_flagged_pages),
+        }
+    }
+}
+
+/// How principle confidence thresholds are tuned from users' feedback
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TuningPolicy {
+    /// Share of the principles kept above a tuned threshold that users should judge correct
+    pub target_precision: f32,
+    /// Labeled principles a category needs before it gets a threshold of its own
+    pub min_labels: usize,
+    /// How often thresholds are refitted
+    pub interval_secs: u64,
+}
+
+impl Default for TuningPolicy {
+    fn default() -> Self {
+        Self { target_precision: 0.8, min_labels: 20, interval_secs: 3600 }
+    }
+}
+
+impl TuningPolicy {
+    /// Load from `WIKI_ENGINE_TUNING_TARGET_PRECISION`, `WIKI_ENGINE_TUNING_MIN_LABELS` and
+    /// `WIKI_ENGINE_TUNING_INTERVAL_SECS`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            target_precision: env_or("WIKI_ENGINE_TUNING_TARGET_PRECISION", defaults.target_precision).clamp(0.0, 1.0),
+            min_labels: env_or("WIKI_ENGINE_TUNING_MIN_LABELS", defaults.min_labels).max(1),
+            interval_secs: env_or("WIKI_ENGINE_TUNING_INTERVAL_SECS", defaults.interval_secs).max(1),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+}
+
+/// Where and how often sentences the analyzers were unsure about are exported for labeling
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelingExportConfig {
+    /// JSON Lines file candidates are appended to; nothing is collected without it
+    pub path: Option<PathBuf>,
+    pub interval_secs: u64,
+    /// How close to the confidence threshold, either side, a sentence's confidence must be to
+    /// be exported
+    pub margin: f32,
+    /// Candidates held between exports; more are dropped until the next export
+    pub max_pending: usize,
+}
+
+impl Default for LabelingExportConfig {
+    fn default() -> Self {
+        Self { path: None, interval_secs: 3600, margin: 0.05, max_pending: 5000 }
+    }
+}
+
+impl LabelingExportConfig {
+    /// Load from `WIKI_ENGINE_LABELING_EXPORT`, `WIKI_ENGINE_LABELING_INTERVAL_SECS` and
+    /// `WIKI_ENGINE_LABELING_MARGIN`
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            path: std::env::var("WIKI_ENGINE_LABELING_EXPORT").ok().filter(|path| !path.is_empty()).map(PathBuf::from),
+            interval_secs: env_or("WIKI_ENGINE_LABELING_INTERVAL_SECS", defaults.interval_secs).max(1),
+            margin: env_or("WIKI_ENGINE_LABELING_MARGIN", defaults.margin),
+            max_pending: defaults.max_pending,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+}
+
+/// Caps on the cache entries each tenant may keep per namespace, so one team's analyses can't
+/// push everyone else's out of the cache
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TenantQuotas {
+    /// Quota of tenants without one of their own
+    default: Option<usize>,
+    tenants: BTreeMap<String, usize>,
+}
+
+impl TenantQuotas {
+    /// Load from `WIKI_ENGINE_TENANT_QUOTAS`, a comma-separated list of `tenant=entries`; the
+    /// tenant `*` sets the quota of those not listed. Malformed items are logged and ignored.
+    pub fn from_env() -> Self {
+        let mut quotas = Self::default();
+        for item in env_list("WIKI_ENGINE_TENANT_QUOTAS").unwrap_or_default() {
+            match item.split_once('=').map(|(tenant, entries)| (tenant.trim(), entries.trim().parse::<usize>())) {
+                Some((tenant, Ok(entries))) if !tenant.is_empty() && entries > 0 => quotas = quotas.with_quota(tenant, entries),
+                _ => tracing::warn!("Ignoring invalid tenant quota '{}' in WIKI_ENGINE_TENANT_QUOTAS", item),
+            }
+        }
+        quotas
+    }
+
+    /// Let `tenant` (`*` for every tenant without a quota of its own) keep `entries` entries per namespace
+    pub fn with_quota(mut self, tenant: &str, entries: usize) -> Self {
+        if tenant == "*" {
+            self.default = Some(entries);
+        } else {
+            self.tenants.insert(tenant.to_string(), entries);
+        }
+        self
+    }
+
+    pub fn quota(&self, tenant: &str) -> Option<usize> {
+        self.tenants.get(tenant).copied().or(self.default)
+    }
+}
+
+/// Bearer tokens tenants present to manage their own cache namespace, since anyone can name a
+/// tenant in `X-Tenant`
+#[derive(Clone, Default, PartialEq)]
+pub struct TenantTokens {
+    tokens: BTreeMap<String, String>,
+}
+
+impl TenantTokens {
+    /// Load from `WIKI_ENGINE_TENANT_TOKENS`, a comma-separated list of `tenant=token`.
+    /// Malformed items are logged and ignored.
+    pub fn from_env() -> Self {
+        let mut tokens = Self::default();
+        for item in env_list("WIKI_ENGINE_TENANT_TOKENS").unwrap_or_default() {
+            match item.split_once('=').map(|(tenant, token)| (tenant.trim(), token.trim())) {
+                Some((tenant, token)) if !tenant.is_empty() && !token.is_empty() => tokens = tokens.with_token(tenant, token),
+                // The item holds a token, so only its tenant is logged
+                _ => tracing::warn!(
+                    "Ignoring invalid tenant token for '{}' in WIKI_ENGINE_TENANT_TOKENS",
+                    item.split('=').next().unwrap_or_default().trim()
+                ),
+            }
+        }
+        tokens
+    }
+
+    pub fn with_token(mut self, tenant: &str, token: &str) -> Self {
+        self.tokens.insert(tenant.to_string(), token.to_string());
+        self
+    }
+
+    /// Whether `token` is the token of `tenant`; tenants without one never authenticate
+    pub fn authenticates(&self, tenant: &str, token: &str) -> bool {
+        self.tokens.get(tenant).is_some_and(|expected| tokens_match(expected, token))
+    }
+}
+
+impl std::fmt::Debug for TenantTokens {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TenantTokens").field("tenants", &self.tokens.keys().collect::<Vec<_>>()).finish()
+    }
+}
+
+/// Where batch completion webhooks may be sent, and how they are signed and retried
+#[derive(Clone, PartialEq)]
+pub struct WebhookConfig {
+    /// Key every delivery is signed with (HMAC-SHA256); webhooks are disabled without it
+    pub secret: Option<String>,
+    /// Hosts webhooks may be sent to (`*.example.org` for subdomains)
+    pub allowed_hosts: Vec<String>,
+    /// Deliveries tried per event before giving up
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled for each one after it
+    pub initial_backoff_ms: u64,
+    pub timeout_secs: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            secret: None,
+            allowed_hosts: Vec::new(),
+            max_attempts: 5,
+            initial_backoff_ms: 1000,
+            timeout_secs: 10,
+        }
+    }
+}
+
+impl WebhookConfig {
+    /// Load from `WIKI_ENGINE_WEBHOOK_*` environment variables, falling back to defaults
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            secret: std::env::var("WIKI_ENGINE_WEBHOOK_SECRET").ok().filter(|secret| !secret.is_empty()),
+            allowed_hosts: env_list("WIKI_ENGINE_WEBHOOK_HOSTS").unwrap_or_default(),
+            max_attempts: env_or("WIKI_ENGINE_WEBHOOK_MAX_ATTEMPTS", defaults.max_attempts).max(1),
+            initial_backoff_ms: env_or("WIKI_ENGINE_WEBHOOK_BACKOFF_MS", defaults.initial_backoff_ms),
+            timeout_secs: env_or("WIKI_ENGINE_WEBHOOK_TIMEOUT_SECS", defaults.timeout_secs),
+        }
+    }
+}
+
+impl std::fmt::Debug for WebhookConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebhookConfig")
+            .field("secret", &self.secret.as_ref().map(|_| "<redacted>"))
+            .field("allowed_hosts", &self.allowed_hosts)
+            .field("max_attempts", &self.max_attempts)
+            .field("initial_backoff_ms", &self.initial_backoff_ms)
+            .field("timeout_secs", &self.timeout_secs)
+            .finish()
+    }
+}
+
+/// Proxies trusted to say who sent a request in `X-Forwarded-For`. The header of any other peer
+/// is ignored, since clients can write whatever they like in it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrustedProxies {
+    addresses: Vec<IpAddr>,
+}
+
+impl TrustedProxies {
+    /// Load from `WIKI_ENGINE_TRUSTED_PROXIES`, a comma-separated list of IP addresses.
+    /// Malformed items are logged and ignored.
+    pub fn from_env() -> Self {
+        let mut proxies = Self::default();
+        for item in env_list("WIKI_ENGINE_TRUSTED_PROXIES").unwrap_or_default() {
+            match item.parse() {
+                Ok(address) => proxies = proxies.with_proxy(address),
+                Err(_) => tracing::warn!("Ignoring invalid proxy address '{}' in WIKI_ENGINE_TRUSTED_PROXIES", item),
+            }
+        }
+        proxies
+    }
+
+    pub fn with_proxy(mut self, address: IpAddr) -> Self {
+        self.addresses.push(address);
+        self
+    }
+
+    pub fn trusts(&self, address: IpAddr) -> bool {
+        self.addresses.contains(&address.to_canonical())
+    }
+}
+
+/// The parameters that can be changed while the server runs, through `PATCH /admin/config`.
+/// Each request reads one snapshot of them, so a change never applies half-way through one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeConfig {
+    pub limits: AnalysisLimits,
+    pub concurrency: ConcurrencyConfig,
+    pub cache_ttls: CacheTtlConfig,
+    /// Minimum similarity to its parent a concept outside the knowledge base needs to be expanded
+    pub similarity_threshold: f32,
+}
+
+impl RuntimeConfig {
+    /// This configuration with `patch`, a JSON merge patch (RFC 7396) of it, applied; patches
+    /// that leave it invalid or change settings only read at startup are rejected
+    pub fn patched(&self, patch: &serde_json::Value) -> Result<Self> {
+        let mut config = serde_json::to_value(self)?;
+        merge_patch(&mut config, patch);
+        let patched: Self = serde_json::from_value(config)
+            .map_err(|error| WikiEngineError::InvalidRequest(vec![FieldError::new("config", error.to_string())]))?;
+
+        let mut errors = Vec::new();
+        let positive = [
+            ("limits.max_depth", u64::from(patched.limits.max_depth)),
+            ("limits.max_results", u64::from(patched.limits.max_results)),
+            ("limits.max_term_length", patched.limits.max_term_length as u64),
+            ("limits.max_batch_terms", patched.limits.max_batch_terms as u64),
+            ("limits.max_principles_per_node", u64::from(patched.limits.max_principles_per_node)),
+            ("limits.analysis_timeout_secs", patched.limits.analysis_timeout_secs),
+            ("concurrency.max_concurrent_analyses", patched.concurrency.max_concurrent_analyses as u64),
+        ];
+        for (field, value) in positive {
+            if value == 0 {
+                errors.push(FieldError::new(field, "must be greater than 0"));
+            }
+        }
+        if !(0.0..=1.0).contains(&patched.similarity_threshold) {
+            errors.push(FieldError::new("similarity_threshold", "must be between 0.0 and 1.0"));
+        }
+        if patched.limits.max_extract_bytes != self.limits.max_extract_bytes {
+            errors.push(FieldError::new("limits.max_extract_bytes", "can only be set at startup"));
+        }
+        if patched.concurrency.max_concurrent_fetches != self.concurrency.max_concurrent_fetches {
+            errors.push(FieldError::new("concurrency.max_concurrent_fetches", "can only be set at startup"));
+        }
+
+        if errors.is_empty() {
+            Ok(patched)
+        } else {
+            Err(WikiEngineError::InvalidRequest(errors))
+        }
+    }
+}
+
+/// Apply an RFC 7396 merge patch: objects merge key by key, `null` removes a key and anything
+/// else replaces the target
+fn merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let serde_json::Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = serde_json::Value::Object(serde_json::Map::new());
+    }
+    let target = target.as_object_mut().expect("target was just made an object");
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(serde_json::Value::Null), value);
+        }
+    }
+}
+
+/// Whether a caller's `given` token is `expected`, taking the same time wherever they differ
+/// so the comparison can't be used to guess a secret token byte by byte
+pub fn tokens_match(expected: &str, given: &str) -> bool {
+    expected.as_bytes().ct_eq(given.as_bytes()).into()
+}
+
+fn env_list(key: &str) -> Option<Vec<String>> {
+    let list = std::env::var(key).ok()?;
+    Some(list.split(',').map(str::trim).filter(|item| !item.is_empty()).map(str::to_string).collect())
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    match std::env::var(key) {
+        Ok(value) => value.parse().unwrap_or_else(|_| {
+            tracing::warn!("Ignoring invalid value '{}' for {}", value, key);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_match_only_exactly() {
+        assert!(tokens_match("s3cret-token", "s3cret-token"));
+        assert!(!tokens_match("s3cret-token", "s3cret-tokem"));
+        assert!(!tokens_match("s3cret-token", "s3cret"));
+        assert!(!tokens_match("s3cret-token", ""));
+    }
+
+    #[test]
+    fn presets_fill_only_what_the_request_leaves_unset() {
+        let presets = AnalysisPresets::default()
+            .with_presets(r#"{ "survey": { "max_depth": 2, "concept_source": "see_also" } }"#)
+            .unwrap();
+        let mut request = SearchRequest {
+            term: "Bridge".to_string(),
+            max_depth: Some(2),
+            preset: Some("quick".to_string()),
+            ..Default::default()
+        };
+        presets.apply(&mut request).unwrap();
+        assert_eq!((request.max_depth, request.max_results), (Some(2), Some(5)));
+        assert_eq!(request.concept_source, ConceptSource::ExtractRegex);
+
+        let mut request = SearchRequest { preset: Some("survey".to_string()), ..Default::default() };
+        presets.apply(&mut request).unwrap();
+        assert_eq!((request.max_depth, request.concept_source), (Some(2), ConceptSource::SeeAlso));
+
+        let mut request = SearchRequest { preset: Some("exhaustive".to_string()), ..Default::default() };
+        let Err(WikiEngineError::InvalidRequest(errors)) = presets.apply(&mut request) else {
+            panic!("expected an unknown preset to be rejected");
+        };
+        assert_eq!(errors[0].field, "preset");
+    }
+
+    #[test]
+    fn runtime_config_patches_are_merged_and_checked() {
+        let config = RuntimeConfig {
+            limits: AnalysisLimits::default(),
+            concurrency: ConcurrencyConfig::default(),
+            cache_ttls: CacheTtlConfig::default(),
+            similarity_threshold: 0.3,
+        };
+
+        let patched = config
+            .patched(&serde_json::json!({ "limits": { "max_depth": 3 }, "cache_ttls": { "page_ttl_secs": 60 } }))
+            .unwrap();
+        assert_eq!(patched.limits.max_depth, 3);
+        assert_eq!(patched.limits.max_results, config.limits.max_results);
+        assert_eq!(patched.cache_ttls.page_ttl_secs, 60);
+
+        let invalid = serde_json::json!({
+            "similarity_threshold": 1.5,
+            "concurrency": { "max_concurrent_analyses": 0, "max_concurrent_fetches": 1 }
+        });
+        let Err(WikiEngineError::InvalidRequest(errors)) = config.patched(&invalid) else {
+            panic!("expected the patch to be rejected");
+        };
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, ["concurrency.max_concurrent_analyses", "similarity_threshold", "concurrency.max_concurrent_fetches"]);
+        assert!(config.patched(&serde_json::json!({ "limits": { "max_depht": 3 } })).is_err());
+        assert!(config.patched(&serde_json::json!({ "limits": null })).is_err());
+    }
+
+    #[test]
+    fn tenants_authenticate_only_with_their_own_token() {
+        let tokens = TenantTokens::default().with_token("team-a", "secret-a").with_token("team-b", "secret-b");
+        assert!(tokens.authenticates("team-a", "secret-a"));
+        assert!(!tokens.authenticates("team-a", "secret-b"));
+        assert!(!tokens.authenticates("team-c", "secret-a"));
+        assert!(!format!("{:?}", tokens).contains("secret"));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::text;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Signatures are split into this many bands for bucketing
+const BANDS: u32 = 8;
+const BAND_BITS: u32 = u64::BITS / BANDS;
+
+/// Signatures at most this many bits apart are near-duplicates. Any such pair agrees exactly
+/// on at least one of the bands, so it always shares a bucket.
+pub const MAX_HAMMING_DISTANCE: u32 = BANDS - 1;
+
+/// 64-bit SimHash of the lowercased words and word pairs of `text`; texts differing in a word
+/// or two get signatures only a few bits apart. Empty text hashes to 0.
+pub fn simhash(text: &str) -> u64 {
+    let words = text::stemmed_words(text);
+    let pairs = words.windows(2).map(|pair| format!("{} {}", pair[0], pair[1]));
+
+    let mut weights = [0i32; u64::BITS as usize];
+    for feature in words.iter().cloned().chain(pairs) {
+        let hash = fnv1a(&feature);
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            *weight += if hash >> bit & 1 == 1 { 1 } else { -1 };
+        }
+    }
+
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |signature, (bit, _)| signature | 1 << bit)
+}
+
+/// FNV-1a, so signatures are stable across runs and builds
+pub fn fnv1a(text: &str) -> u64 {
+    text.bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Signatures bucketed by band (locality-sensitive hashing), so finding a near-duplicate only
+/// compares against signatures sharing a band rather than every one seen so far. Each signature
+/// is stored with a caller-chosen id, e.g. its position in a list.
+#[derive(Debug, Default)]
+pub struct NearDuplicateIndex {
+    buckets: HashMap<(u32, u64), Vec<(u64, usize)>>,
+}
+
+impl NearDuplicateIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a signature within `MAX_HAMMING_DISTANCE` bits of `signature` has been inserted
+    pub fn contains_near(&self, signature: u64) -> bool {
+        self.find_near(signature).is_some()
+    }
+
+    /// Id of an inserted signature within `MAX_HAMMING_DISTANCE` bits of `signature`
+    pub fn find_near(&self, signature: u64) -> Option<usize> {
+        if signature == 0 {
+            return None;
+        }
+        Self::bands(signature).find_map(|band| {
+            self.buckets.get(&band)?.iter().find_map(|&(other, id)| {
+                ((signature ^ other).count_ones() <= MAX_HAMMING_DISTANCE).then_some(id)
+            })
+        })
+    }
+
+    /// Ids of inserted signatures sharing a band with `signature`, the only ones that can be
+    /// near it, each once
+    pub fn candidates(&self, signature: u64) -> impl Iterator<Item = usize> + '_ {
+        let mut seen = HashSet::new();
+        Self::bands(signature)
+            .filter(move |_| signature != 0)
+            .filter_map(|band| self.buckets.get(&band))
+            .flatten()
+            .map(|&(_, id)| id)
+            .filter(move |id| seen.insert(*id))
+    }
+
+    pub fn insert(&mut self, signature: u64, id: usize) {
+        // Empty texts are never duplicates of each other
+        if signature == 0 {
+            return;
+        }
+        for band in Self::bands(signature) {
+            self.buckets.entry(band).or_default().push((signature, id));
+        }
+    }
+
+    fn bands(signature: u64) -> impl Iterator<Item = (u32, u64)> {
+        (0..BANDS).map(move |band| (band, signature >> (band * BAND_BITS) & ((1 << BAND_BITS) - 1)))
+    }
+}
+
+/// Spans of text per document, none overlapping another, so finding one that overlaps a new
+/// span takes a lookup rather than a scan
+#[derive(Debug, Default)]
+pub struct PassageIndex {
+    /// End of each span, keyed by document and start
+    spans: BTreeMap<(String, usize), usize>,
+}
+
+impl PassageIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether an inserted span of `document` overlaps `start..end`; empty spans overlap nothing
+    pub fn overlaps(&self, document: &str, (start, end): (usize, usize)) -> bool {
+        // Inserted spans are disjoint, so only the last one starting before `end` can reach past `start`
+        start < end
+            && self
+                .spans
+                .range((document.to_string(), 0)..(document.to_string(), end))
+                .next_back()
+                .is_some_and(|(_, &other_end)| other_end > start)
+    }
+
+    /// Record a span, which must not overlap any inserted before (check with `overlaps`)
+    pub fn insert(&mut self, document: &str, (start, end): (usize, usize)) {
+        if start < end {
+            self.spans.insert((document.to_string(), start), end);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_near_duplicates_through_buckets() {
+        let original = "The rotor turns because the magnetic field of the stator exerts torque on the rotor windings";
+        let reworded = "The rotor turns because the magnetic field of the stator exerts a torque on the rotor windings";
+        let unrelated = "Bridges carry loads across rivers using trusses, arches or suspension cables";
+
+        let mut index = NearDuplicateIndex::new();
+        index.insert(simhash(original), 7);
+
+        assert_eq!(index.find_near(simhash(original)), Some(7));
+        assert_eq!(index.find_near(simhash(reworded)), Some(7));
+        assert!(!index.contains_near(simhash(unrelated)));
+        assert!(!index.contains_near(simhash("")));
+        assert_eq!(index.candidates(simhash(reworded)).collect::<Vec<_>>(), [7]);
+        assert_eq!(index.candidates(simhash(unrelated)).count(), 0);
+    }
+
+    #[test]
+    fn passages_overlap_within_a_document() {
+        let mut passages = PassageIndex::new();
+        passages.insert("memory://motor", (10, 20));
+        passages.insert("memory://motor", (30, 40));
+
+        assert!(passages.overlaps("memory://motor", (15, 35)));
+        assert!(passages.overlaps("memory://motor", (39, 50)));
+        assert!(!passages.overlaps("memory://motor", (20, 30)));
+        assert!(!passages.overlaps("memory://gear", (10, 20)));
+        assert!(!passages.overlaps("memory://motor", (12, 12)));
+    }
+}
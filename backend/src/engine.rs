@@ -0,0 +1,2603 @@
+
+use crate::analyzer::EngineeringAnalyzer;
+use crate::categories;
+use crate::kb_import::{self, ImportOptions, ImportReport};
+use crate::kb_store::{self, KnowledgeBaseStore};
+use crate::semantic_analyzer::{ComponentExtractor, ComponentSource, ConceptDecomposition, ConceptKnowledgeBase, SemanticAnalyzer, SentenceEmbedder};
+use crate::cache::{self, WikiEngineCache};
+use crate::feedback::FeedbackLog;
+use crate::labeling::{Calibration, LabelingCandidate, LabelingQueue};
+use crate::tuning::ThresholdTuner;
+use crate::dedup::{NearDuplicateIndex, PassageIndex};
+use crate::intern::Term;
+use crate::config::{AnalysisLimits, AnalysisPresets, ConceptFilterConfig, DocumentSourceConfig, TuningPolicy};
+use crate::sources::{ArxivSource, DocumentSource, PatentSource};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::sources::{LocalFolderSource, UrlSource};
+use crate::results::ResultRefresh;
+use crate::types::{
+    AnalysisNode, AnalysisPlan, AnalysisResult, AnalysisWarning, AnalysisWarningKind, BatchAnalysisItem, CategoryClassifier, ConceptSource, DedupeScope, DocumentSourceKind, FieldError,
+    EngineeringPrinciple, NodeProvenance, PrincipleCategory, SearchRequest, popularity_score, SearchSuggestion, SuggestionMatch, Result, WikiEngineError,
+};
+use futures::future::{join_all, FutureExt};
+use futures::stream::{self, Stream, StreamExt};
+use crate::wikipedia::{MediaWikiClient, PageFetch};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use dashmap::DashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use web_time::Instant;
+use tokio::sync::Semaphore;
+use tracing::Instrument;
+use crate::language;
+use crate::templates::DescriptionTemplates;
+use crate::text;
+use crate::monitoring;
+use crate::events::{self, EngineEvent, EventBus};
+use crate::plugins::{self, ExtractorPlugin};
+use crate::progress::{self, ProgressReporter};
+use crate::{
+    ASSUMED_WIKIPEDIA_CALL_SECS, BATCH_CONCURRENCY, DEFAULT_PRINCIPLES_PER_NODE, PAGE_LINKS_LIMIT, PATENT_ABSTRACTS_LIMIT, SEMANTIC_GATING_DEPTH, SIBLING_CONCURRENCY, SUMMARY_SENTENCES,
+};
+
+/// Principles whose embeddings are at least this similar are treated as duplicates
+const EMBEDDING_DUPLICATE_SIMILARITY: f32 = 0.9;
+
+/// Wikipedia calls fetching a page takes: its extract, pageviews and curated links
+const PAGE_FETCH_CALLS: u32 = 3;
+
+/// The message a panic was raised with, when it carried one
+fn panic_message(panic: Box<dyn std::any::Any + Send>) -> String {
+    panic
+        .downcast::<String>()
+        .map(|message| *message)
+        .or_else(|panic| panic.downcast::<&str>().map(|message| message.to_string()))
+        .unwrap_or_else(|_| "unknown panic".to_string())
+}
+
+/// Run CPU-bound work, such as embedding inference or an extractor plugin, on a thread of its
+/// own rather than an async worker. Browsers have no threads to spare, so there it runs in place.
+async fn run_blocking<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> Result<T> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return tokio::task::spawn_blocking(work).await.map_err(|e| match e.try_into_panic() {
+        Ok(panic) => WikiEngineError::Analysis(format!("Analysis panicked: {}", panic_message(panic))),
+        Err(e) => WikiEngineError::Analysis(format!("Analysis was aborted: {}", e)),
+    });
+    #[cfg(target_arch = "wasm32")]
+    Ok(work())
+}
+
+/// What became of one related concept of a node
+enum ChildExpansion {
+    /// The deadline passed before its expansion could start
+    Unstarted,
+    /// It is the node itself or already being expanded further up
+    Skipped,
+    /// Its subtree, or the error or panic expanding it ended in
+    Expanded(Box<std::thread::Result<Result<AnalysisNode>>>),
+}
+
+/// Terms currently being expanded somewhere in the recursion. Claiming a term is a single atomic
+/// insert and no lock is held across an await, so the siblings `analyze_term` expands side by
+/// side can share it.
+#[derive(Clone, Default)]
+struct VisitedTerms(Arc<DashSet<String>>);
+
+impl VisitedTerms {
+    /// Claim `key` until the returned guard is dropped, or `None` if it is already claimed
+    fn enter(&self, key: &str) -> Option<VisitGuard> {
+        self.0.insert(key.to_string()).then(|| VisitGuard {
+            visited: self.clone(),
+            key: key.to_string(),
+        })
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        self.0.contains(key)
+    }
+}
+
+/// Releases a claimed term when its expansion ends, including on early returns and errors
+struct VisitGuard {
+    visited: VisitedTerms,
+    key: String,
+}
+
+impl Drop for VisitGuard {
+    fn drop(&mut self) {
+        self.visited.0.remove(&self.key);
+    }
+}
+
+/// Per-request settings threaded through the recursive analysis
+struct AnalysisOptions {
+    max_depth: u8,
+    max_results: u8,
+    categories: Option<Vec<PrincipleCategory>>,
+    min_confidence: Option<f32>,
+    max_principles: u8,
+    exclude_terms: HashSet<String>,
+    pin_terms: HashSet<String>,
+    concept_source: ConceptSource,
+    document_source: DocumentSourceKind,
+    /// No further nodes are expanded once this has passed
+    deadline: Option<Instant>,
+    deadline_exceeded: AtomicBool,
+}
+
+impl AnalysisOptions {
+    fn from_request(request: &SearchRequest) -> Self {
+        Self {
+            max_depth: request.max_depth.unwrap_or(3),
+            max_results: request.max_results.unwrap_or(10),
+            categories: request.categories.clone(),
+            min_confidence: request.min_confidence,
+            max_principles: request.max_principles_per_node.unwrap_or(DEFAULT_PRINCIPLES_PER_NODE),
+            exclude_terms: request.exclude_terms.iter().map(|term| text::fold_case(term)).collect(),
+            pin_terms: request.pin_terms.iter().map(|term| text::fold_case(term)).collect(),
+            concept_source: request.concept_source,
+            document_source: request.document_source,
+            deadline: request.timeout_ms.map(|ms| Instant::now() + Duration::from_millis(ms)),
+            deadline_exceeded: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether the deadline has passed, remembering it for the result if so
+    fn past_deadline(&self) -> bool {
+        let past = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        if past {
+            self.deadline_exceeded.store(true, Ordering::Relaxed);
+        }
+        past
+    }
+
+    fn is_excluded(&self, concept: &str) -> bool {
+        self.exclude_terms.contains(&text::fold_case(concept))
+    }
+
+    fn is_pinned(&self, concept: &str) -> bool {
+        self.pin_terms.contains(&text::fold_case(concept))
+    }
+
+    fn keeps_principle(&self, principle: &EngineeringPrinciple) -> bool {
+        self.allows_category(&principle.category)
+            && self.min_confidence.is_none_or(|min| principle.confidence >= min)
+    }
+
+    /// Apply the request's principle filters and per-node limit
+    fn select_principles(&self, mut principles: Vec<EngineeringPrinciple>) -> Vec<EngineeringPrinciple> {
+        principles.retain(|principle| self.keeps_principle(principle));
+        principles.truncate(self.max_principles as usize);
+        principles
+    }
+
+    /// Whether `category` is one of the requested categories or a subcategory of one
+    fn allows_category(&self, category: &PrincipleCategory) -> bool {
+        self.categories.as_ref().is_none_or(|categories| {
+            categories.iter().any(|allowed| categories::registry().is_within(category.name(), allowed.name()))
+        })
+    }
+
+    /// Cache-key suffix so filtered and unfiltered trees are cached separately
+    fn filter_key(&self) -> String {
+        let mut key = String::new();
+        if let Some(categories) = &self.categories {
+            let mut names: Vec<&str> = categories.iter().map(PrincipleCategory::name).collect();
+            names.sort_unstable();
+            names.dedup();
+            key.push_str(&format!(":categories={}", names.join(",")));
+        }
+        if let Some(min_confidence) = self.min_confidence {
+            key.push_str(&format!(":min_confidence={}", min_confidence));
+        }
+        if self.max_principles != DEFAULT_PRINCIPLES_PER_NODE {
+            key.push_str(&format!(":max_principles={}", self.max_principles));
+        }
+        for (name, terms) in [("exclude", &self.exclude_terms), ("pin", &self.pin_terms)] {
+            if !terms.is_empty() {
+                let mut terms: Vec<&str> = terms.iter().map(String::as_str).collect();
+                terms.sort_unstable();
+                key.push_str(&format!(":{}={}", name, terms.join(",")));
+            }
+        }
+        if self.concept_source != ConceptSource::default() {
+            key.push_str(&format!(":concepts={}", self.concept_source.name()));
+        }
+        if self.document_source != DocumentSourceKind::default() {
+            key.push_str(&format!(":source={}", self.document_source.name()));
+        }
+        key
+    }
+}
+
+pub struct WikiEngine {
+    wikipedia_client: MediaWikiClient,
+    analyzer: EngineeringAnalyzer,
+    semantic_analyzer: Arc<SemanticAnalyzer>,
+    /// Non-MediaWiki sources a request can pick; Wikipedia itself goes through `wikipedia_client`
+    document_sources: HashMap<DocumentSourceKind, Arc<dyn DocumentSource>>,
+    /// Patent search, when configured, also feeds extra components into decompositions
+    patents: Option<Arc<PatentSource>>,
+    cache: Arc<WikiEngineCache>,
+    /// Analyses run at once by `batch_analyze`
+    max_concurrency: usize,
+    /// Seed for principle ids when results are made reproducible (see `WikiEngineBuilder::deterministic`)
+    deterministic_seed: Option<u64>,
+    /// Page text analyzed per node (see `AnalysisLimits::max_extract_bytes`)
+    max_extract_bytes: usize,
+    /// Settings bundles requests can name with `preset`
+    presets: AnalysisPresets,
+    /// Where edits to the knowledge base are saved as new versions, if anywhere
+    kb_store: Option<Arc<dyn KnowledgeBaseStore>>,
+    /// Held while the knowledge base is edited, so concurrent imports don't drop each other's rows
+    kb_edits: Mutex<()>,
+    /// Users' flags on principles and concepts, applied to every analysis of the flagged pages
+    feedback: Arc<FeedbackLog>,
+    /// Sentences the analyzers were unsure about, collected for labeling
+    labeling: Arc<LabelingQueue>,
+    /// Confidence thresholds fitted to the feedback, for requests that don't set `min_confidence`
+    tuner: Arc<ThresholdTuner>,
+    /// Where the engine announces nodes, pages, principles and finished analyses
+    events: EventBus,
+    /// Extractors run on every page after the built-in analyzers
+    extractor_plugins: Vec<Arc<dyn ExtractorPlugin>>,
+}
+
+/// Composes a [`WikiEngine`] for embedders. Anything left unset falls back to what
+/// `WikiEngine::new` uses: the environment configuration and the built-in extractors.
+pub struct WikiEngineBuilder {
+    cache: Option<Arc<WikiEngineCache>>,
+    wikipedia_client: Option<MediaWikiClient>,
+    sources: Vec<Arc<dyn DocumentSource>>,
+    patents: Option<Arc<PatentSource>>,
+    env_sources: bool,
+    extractors: Option<Vec<ComponentExtractor>>,
+    description_templates: Option<DescriptionTemplates>,
+    knowledge_base: Option<ConceptKnowledgeBase>,
+    kb_store: Option<Arc<dyn KnowledgeBaseStore>>,
+    similarity_threshold: Option<f32>,
+    concept_filter: Option<ConceptFilterConfig>,
+    max_concurrency: usize,
+    deterministic_seed: Option<u64>,
+    max_extract_bytes: Option<usize>,
+    presets: Option<AnalysisPresets>,
+    feedback: Option<Arc<FeedbackLog>>,
+    labeling: Option<Arc<LabelingQueue>>,
+    calibration: Option<Calibration>,
+    embedder: Option<Arc<dyn SentenceEmbedder>>,
+    tuning_policy: Option<TuningPolicy>,
+    events: Option<EventBus>,
+    extractor_plugins: Vec<Arc<dyn ExtractorPlugin>>,
+}
+
+impl Default for WikiEngineBuilder {
+    fn default() -> Self {
+        Self {
+            cache: None,
+            wikipedia_client: None,
+            sources: Vec::new(),
+            patents: None,
+            env_sources: true,
+            extractors: None,
+            description_templates: None,
+            knowledge_base: None,
+            kb_store: None,
+            similarity_threshold: None,
+            concept_filter: None,
+            max_concurrency: BATCH_CONCURRENCY,
+            deterministic_seed: None,
+            max_extract_bytes: None,
+            presets: None,
+            feedback: None,
+            labeling: None,
+            calibration: None,
+            embedder: None,
+            tuning_policy: None,
+            events: None,
+            extractor_plugins: Vec::new(),
+        }
+    }
+}
+
+impl WikiEngineBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Share a cache with other engines or the server; a fresh one is created otherwise
+    pub fn with_cache(mut self, cache: Arc<WikiEngineCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Use an existing MediaWiki client (and its connection pool) for Wikipedia lookups
+    pub fn with_wikipedia_client(mut self, client: MediaWikiClient) -> Self {
+        self.wikipedia_client = Some(client);
+        self
+    }
+
+    /// Register a document source, replacing any default source of the same kind.
+    /// Wikipedia lookups always go through the MediaWiki client, so set that with
+    /// `with_wikipedia_client` instead.
+    pub fn with_source(mut self, source: Arc<dyn DocumentSource>) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Search patents with this source, both as a document source and for decomposition components
+    pub fn with_patent_source(mut self, patents: PatentSource) -> Self {
+        self.patents = Some(Arc::new(patents));
+        self
+    }
+
+    /// Skip the sources configured through the environment (documents folder, PatentsView key,
+    /// URL hosts), keeping only the arXiv source plus those added with `with_source`
+    pub fn without_env_sources(mut self) -> Self {
+        self.env_sources = false;
+        self
+    }
+
+    /// Replace the built-in component extractors used to decompose concepts
+    pub fn with_extractors(mut self, extractors: Vec<ComponentExtractor>) -> Self {
+        self.extractors = Some(extractors);
+        self
+    }
+
+    /// Templates for the titles and descriptions of component principles, instead of those the
+    /// environment configures
+    pub fn with_description_templates(mut self, templates: DescriptionTemplates) -> Self {
+        self.description_templates = Some(templates);
+        self
+    }
+
+    /// Decompose concepts with this knowledge base, instead of the one stored at
+    /// `WIKI_ENGINE_KB_DATABASE` or the built-in one
+    pub fn with_knowledge_base(mut self, knowledge_base: ConceptKnowledgeBase) -> Self {
+        self.knowledge_base = Some(knowledge_base);
+        self
+    }
+
+    /// Load the knowledge base from this store, and save imports into it, instead of using the
+    /// one at `WIKI_ENGINE_KB_DATABASE`
+    pub fn with_knowledge_base_store(mut self, store: Arc<dyn KnowledgeBaseStore>) -> Self {
+        self.kb_store = Some(store);
+        self
+    }
+
+    /// Record feedback in, and apply it from, this log instead of the one `WIKI_ENGINE_FEEDBACK_LOG`
+    /// configures
+    pub fn with_feedback_log(mut self, feedback: Arc<FeedbackLog>) -> Self {
+        self.feedback = Some(feedback);
+        self
+    }
+
+    /// Collect sentences for labeling into this queue instead of the one `WIKI_ENGINE_LABELING_EXPORT`
+    /// configures
+    pub fn with_labeling_queue(mut self, labeling: Arc<LabelingQueue>) -> Self {
+        self.labeling = Some(labeling);
+        self
+    }
+
+    /// Classify with the model and thresholds of this calibration instead of the one at
+    /// `WIKI_ENGINE_CALIBRATION`
+    pub fn with_calibration(mut self, calibration: Calibration) -> Self {
+        self.calibration = Some(calibration);
+        self
+    }
+
+    /// Embed sentences with `embedder` instead of the calibration's ONNX model
+    pub fn with_sentence_embedder(mut self, embedder: Arc<dyn SentenceEmbedder>) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+
+    /// Tune confidence thresholds from the feedback with this policy instead of the one the
+    /// environment configures
+    pub fn with_tuning_policy(mut self, policy: TuningPolicy) -> Self {
+        self.tuning_policy = Some(policy);
+        self
+    }
+
+    /// Run `plugin` on every analyzed page, besides those `WIKI_ENGINE_EXTRACTOR_PLUGINS` lists
+    pub fn with_extractor_plugin(mut self, plugin: Arc<dyn ExtractorPlugin>) -> Self {
+        self.extractor_plugins.push(plugin);
+        self
+    }
+
+    /// Emit events on this bus, e.g. one shared with other engines, instead of a fresh one
+    pub fn with_event_bus(mut self, events: EventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Minimum similarity a concept unknown to the knowledge base needs to its parent to be expanded
+    pub fn similarity_threshold(mut self, threshold: f32) -> Self {
+        self.similarity_threshold = Some(threshold);
+        self
+    }
+
+    /// Stop terms and relevance cut-off for related concepts, instead of the environment's
+    pub fn with_concept_filter(mut self, concept_filter: ConceptFilterConfig) -> Self {
+        self.concept_filter = Some(concept_filter);
+        self
+    }
+
+    /// Analyses run at once by `batch_analyze`
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Page text analyzed per node, instead of the environment's `WIKI_ENGINE_MAX_EXTRACT_BYTES`
+    pub fn max_extract_bytes(mut self, max_extract_bytes: usize) -> Self {
+        self.max_extract_bytes = Some(max_extract_bytes);
+        self
+    }
+
+    /// Presets requests can name, instead of the built-in ones plus `WIKI_ENGINE_PRESETS_FILE`
+    pub fn with_presets(mut self, presets: AnalysisPresets) -> Self {
+        self.presets = Some(presets);
+        self
+    }
+
+    /// Produce byte-identical results for identical inputs: principle ids come from `seed`
+    /// instead of being random, timings are reported as zero, collections are fully sorted and
+    /// request deadlines are ignored, since where one cuts a tree depends on timing
+    pub fn deterministic(mut self, seed: u64) -> Self {
+        self.deterministic_seed = Some(seed);
+        self
+    }
+
+    pub fn build(self) -> Result<WikiEngine> {
+        let wikipedia_client = match self.wikipedia_client {
+            Some(client) => client,
+            None => MediaWikiClient::new()?,
+        };
+        let http_client = wikipedia_client.http_client().clone();
+
+        let mut document_sources: HashMap<DocumentSourceKind, Arc<dyn DocumentSource>> = HashMap::new();
+        document_sources.insert(DocumentSourceKind::Arxiv, Arc::new(ArxivSource::new(http_client.clone())));
+        let mut patents = self.patents;
+        if self.env_sources {
+            let config = DocumentSourceConfig::from_env();
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(dir) = config.documents_dir {
+                document_sources.insert(DocumentSourceKind::LocalFolder, Arc::new(LocalFolderSource::new(dir)));
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            if !config.url_allowed_hosts.is_empty() {
+                document_sources.insert(DocumentSourceKind::Url, Arc::new(UrlSource::new(config.url_allowed_hosts)));
+            }
+            if patents.is_none() {
+                patents = config.patentsview_api_key.map(|key| Arc::new(PatentSource::new(http_client, key)));
+            }
+        }
+        if let Some(patents) = &patents {
+            document_sources.insert(DocumentSourceKind::Patent, Arc::clone(patents) as Arc<dyn DocumentSource>);
+        }
+        for source in self.sources {
+            document_sources.insert(source.kind(), source);
+        }
+
+        let calibration = match self.calibration {
+            Some(calibration) => calibration,
+            None => Calibration::from_env()?.unwrap_or_default(),
+        };
+        let mut analyzer = match self.concept_filter {
+            Some(concept_filter) => EngineeringAnalyzer::with_concept_filter(concept_filter)?,
+            None => EngineeringAnalyzer::new()?,
+        };
+        if let Some(min_confidence) = calibration.min_principle_confidence {
+            analyzer = analyzer.with_min_confidence(min_confidence);
+        }
+        let semantic_analyzer = match (&calibration.model, &calibration.tokenizer) {
+            (Some(model), Some(tokenizer)) => SemanticAnalyzer::with_model_files(model, tokenizer)?,
+            _ => SemanticAnalyzer::new()?,
+        };
+        let mut semantic_analyzer = semantic_analyzer
+            .with_description_templates(self.description_templates.unwrap_or_else(DescriptionTemplates::from_env));
+        if let Some(extractors) = self.extractors {
+            semantic_analyzer = semantic_analyzer.with_component_extractors(extractors);
+        }
+        if let Some(embedder) = self.embedder {
+            semantic_analyzer = semantic_analyzer.with_embedder(embedder);
+        }
+        if let Some(threshold) = self.similarity_threshold {
+            semantic_analyzer = semantic_analyzer.with_similarity_threshold(threshold);
+        }
+        if let Some(min_similarity) = calibration.min_prototype_similarity {
+            semantic_analyzer = semantic_analyzer.with_min_prototype_similarity(min_similarity);
+        }
+        let kb_store = match self.kb_store {
+            Some(kb_store) => Some(kb_store),
+            None if self.knowledge_base.is_none() => kb_store::store_from_env()?,
+            None => None,
+        };
+        let knowledge_base = match (self.knowledge_base, &kb_store) {
+            (Some(knowledge_base), _) => Some(knowledge_base),
+            (None, Some(store)) => Some(kb_store::load_or_seed(store.as_ref(), kb_store::pinned_version_from_env().as_deref())?),
+            (None, None) => None,
+        };
+        if let Some(knowledge_base) = knowledge_base {
+            semantic_analyzer = semantic_analyzer.with_knowledge_base(knowledge_base);
+        }
+
+        let mut extractor_plugins = self.extractor_plugins;
+        extractor_plugins.extend(plugins::plugins_from_env()?);
+
+        let feedback = self.feedback.unwrap_or_else(|| Arc::new(FeedbackLog::from_env()));
+        let tuner = ThresholdTuner::new(Arc::clone(&feedback), self.tuning_policy.unwrap_or_else(TuningPolicy::from_env));
+
+        Ok(WikiEngine {
+            wikipedia_client,
+            analyzer,
+            semantic_analyzer: Arc::new(semantic_analyzer),
+            document_sources,
+            patents,
+            cache: self.cache.unwrap_or_else(|| Arc::new(WikiEngineCache::new())),
+            max_concurrency: self.max_concurrency,
+            deterministic_seed: self.deterministic_seed,
+            max_extract_bytes: self.max_extract_bytes.unwrap_or_else(|| AnalysisLimits::from_env().max_extract_bytes),
+            presets: self.presets.unwrap_or_else(AnalysisPresets::from_env),
+            kb_store,
+            kb_edits: Mutex::new(()),
+            feedback,
+            labeling: self.labeling.unwrap_or_else(|| Arc::new(LabelingQueue::from_env())),
+            tuner: Arc::new(tuner),
+            events: self.events.unwrap_or_default(),
+            extractor_plugins,
+        })
+    }
+}
+
+impl WikiEngine {
+    pub fn new(cache: Arc<WikiEngineCache>) -> Result<Self> {
+        Self::builder().with_cache(cache).build()
+    }
+
+    pub fn builder() -> WikiEngineBuilder {
+        WikiEngineBuilder::new()
+    }
+
+    /// Build an engine around an existing client, sharing its connection pool
+    pub fn with_wikipedia_client(cache: Arc<WikiEngineCache>, wikipedia_client: MediaWikiClient) -> Result<Self> {
+        Self::builder().with_cache(cache).with_wikipedia_client(wikipedia_client).build()
+    }
+
+    /// Run an analysis while reporting its progress to `reporter`'s subscribers
+    pub async fn analyze_with_progress(&self, request: &SearchRequest, reporter: &ProgressReporter) -> Result<AnalysisResult> {
+        reporter.scope(self.analyze_recursive(request)).await
+    }
+
+    /// Run an analysis under a fresh analysis id, announcing its completion on the event bus
+    #[tracing::instrument(skip_all, fields(term = %request.term))]
+    pub async fn analyze_recursive(&self, request: &SearchRequest) -> Result<AnalysisResult> {
+        events::scope(uuid::Uuid::new_v4(), async {
+            let result = self.analyze(request).await;
+            self.events.emit(EngineEvent::analysis_completed(&request.term, &result));
+            result
+        })
+        .await
+    }
+
+    async fn analyze(&self, request: &SearchRequest) -> Result<AnalysisResult> {
+        let start_time = Instant::now();
+        let request = &self.apply_preset(request.clone())?;
+        let _in_flight = monitoring::InFlightGuard::new();
+        let mut options = AnalysisOptions::from_request(request);
+        if self.deterministic_seed.is_some() {
+            options.deadline = None;
+        }
+        progress::record_nodes_discovered(1);
+
+        tracing::info!(
+            "Starting recursive analysis for '{}' (max_depth={}, max_results={})",
+            request.term, options.max_depth, options.max_results
+        );
+
+        // Check cache first
+        let cache_key = self.cache.generate_analysis_cache_key(
+            &self.concept_key(&request.term),
+            options.max_depth,
+            options.max_results,
+            &options.filter_key(),
+            &self.kb_version(),
+        );
+        if let Some(cached_node) = self.cache.get_analysis_node(&cache_key) {
+            tracing::info!("Returning cached analysis for '{}'", request.term);
+            progress::record_node_skipped();
+            let result = AnalysisResult {
+                kb_version: Some(self.kb_version()),
+                ..AnalysisResult::from_tree(request.term.clone(), cached_node, start_time.elapsed().as_millis() as u64)
+            };
+            return Ok(self.apply_result_options(result, request));
+        }
+
+        // Perform recursive analysis
+        let visited = VisitedTerms::default();
+        let (root_node, network_stats) = monitoring::track_network_stats(self.analyze_term_recursive(
+            &request.term,
+            0,
+            &options,
+            visited,
+        )).await;
+        let root_node = Arc::new(root_node?);
+        let deadline_exceeded = options.deadline_exceeded.load(Ordering::Relaxed);
+
+        // Cache the unpruned tree so different prune thresholds can share it; a tree cut short
+        // by the deadline would otherwise be served as complete
+        if deadline_exceeded {
+            tracing::warn!("Deadline reached for '{}', returning partial tree", request.term);
+        } else {
+            self.cache.cache_analysis_node(cache_key, Arc::clone(&root_node));
+        }
+
+        let result = AnalysisResult {
+            network_stats,
+            deadline_exceeded,
+            kb_version: Some(self.kb_version()),
+            ..AnalysisResult::from_tree(request.term.clone(), root_node, start_time.elapsed().as_millis() as u64)
+        };
+
+        tracing::info!(
+            "Completed recursive analysis for '{}': {} principles, {}ms, max_depth={}, warnings={}, wikipedia_requests={}",
+            request.term, result.total_principles, result.total_processing_time_ms,
+            result.max_depth_reached, result.warnings.len(), result.network_stats.requests
+        );
+
+        Ok(self.apply_result_options(result, request))
+    }
+
+    /// Re-analyze only the nodes of a stored result whose cached pages have gone stale, and
+    /// splice their new principles into a copy of the tree. Children are left as they were.
+    #[tracing::instrument(skip_all, fields(term = %result.root_term))]
+    pub async fn refresh_result(&self, request: &SearchRequest, result: &AnalysisResult) -> Result<ResultRefresh> {
+        let options = AnalysisOptions::from_request(request);
+        if options.document_source != DocumentSourceKind::Wikipedia {
+            return Err(WikiEngineError::InvalidRequest(vec![FieldError::new(
+                "document_source",
+                "only results analyzed from Wikipedia can be refreshed",
+            )]));
+        }
+
+        let mut terms = Vec::new();
+        Self::collect_expandable_terms(&result.tree, options.max_depth, &mut terms);
+        let nodes_checked = terms.len();
+
+        // Cached pages that haven't expired may still have been edited on Wikipedia
+        let cached_titles: Vec<String> = terms.iter()
+            .filter_map(|term| self.cache.peek_wikipedia_page(&self.concept_key(term)))
+            .map(|page| page.title)
+            .collect();
+        let live_revisions = match self.wikipedia_client.get_revision_ids(&cached_titles).await {
+            Ok(revisions) => Some(revisions),
+            Err(e) => {
+                tracing::warn!("Could not fetch revision ids, refreshing expired pages only: {}", e);
+                None
+            }
+        };
+
+        let mut refreshed: HashMap<Term, Vec<EngineeringPrinciple>> = HashMap::new();
+        let mut seen = HashSet::new();
+        for term in terms {
+            if !seen.insert(self.concept_key(&term)) || !self.page_needs_refresh(&term, live_revisions.as_ref()) {
+                continue;
+            }
+
+            let key = self.concept_key(&term);
+            let previous = self.cache.take_expired_wikipedia_page(&key)
+                .or_else(|| self.cache.peek_wikipedia_page(&key));
+            match self.fetch_page(&term, previous).await {
+                Ok(Some((_, false))) => tracing::debug!("'{}' not modified since it was last fetched", term),
+                Ok(Some((mut page, true))) => {
+                    self.limit_extract(&mut page);
+                    self.cache.remove_principles(&self.principles_key(&page));
+                    let principles = self.get_or_analyze_principles(&page).await?;
+                    refreshed.insert(term, options.select_principles(principles));
+                }
+                Ok(None) => tracing::debug!("No Wikipedia page found for '{}' during refresh", term),
+                Err(e) => tracing::warn!("Failed to refresh '{}': {}", term, e),
+            }
+        }
+
+        let mut tree = AnalysisNode::clone(&result.tree);
+        Self::splice_principles(&mut tree, &refreshed);
+        // Children aren't re-decomposed, so the tree still reflects the knowledge base it was built with
+        let refreshed_result = AnalysisResult {
+            kb_version: result.kb_version.clone(),
+            ..AnalysisResult::from_tree(result.root_term.clone(), tree, result.total_processing_time_ms)
+        };
+
+        let mut refreshed_terms: Vec<String> = refreshed.into_keys().map(String::from).collect();
+        refreshed_terms.sort();
+        tracing::info!(
+            "Refreshed {} of {} nodes for '{}'",
+            refreshed_terms.len(), nodes_checked, result.root_term
+        );
+
+        Ok(ResultRefresh {
+            result: self.apply_result_options(refreshed_result, request),
+            refreshed_terms,
+            nodes_checked,
+        })
+    }
+
+    /// Whether the page behind a node has to be fetched again: its cache entry expired,
+    /// or Wikipedia reports a newer revision than the one we analyzed
+    fn page_needs_refresh(&self, term: &str, live_revisions: Option<&HashMap<String, u64>>) -> bool {
+        let Some(page) = self.cache.peek_wikipedia_page(&self.concept_key(term)) else {
+            return true;
+        };
+        match (live_revisions, page.revision_id) {
+            (Some(live), Some(revision_id)) => live.get(&page.title).is_some_and(|live_id| *live_id != revision_id),
+            _ => false,
+        }
+    }
+
+    /// Whether a page has been edited (or deleted) on Wikipedia since it was fetched.
+    /// Pages without a recorded revision id are always considered stale.
+    pub async fn is_stale(&self, page: &crate::types::WikipediaPage) -> Result<bool> {
+        let Some(revision_id) = page.revision_id else {
+            return Ok(true);
+        };
+        let live = self.wikipedia_client.get_revision_ids(std::slice::from_ref(&page.title)).await?;
+        Ok(live.get(&page.title) != Some(&revision_id))
+    }
+
+    /// Terms of nodes that were actually analyzed (leaf placeholders at max depth are skipped)
+    fn collect_expandable_terms(node: &AnalysisNode, max_depth: u8, terms: &mut Vec<Term>) {
+        if node.depth < max_depth {
+            terms.push(node.term.clone());
+        }
+        for child in &node.children {
+            Self::collect_expandable_terms(child, max_depth, terms);
+        }
+    }
+
+    fn splice_principles(node: &mut AnalysisNode, refreshed: &HashMap<Term, Vec<EngineeringPrinciple>>) {
+        if let Some(principles) = refreshed.get(&node.term) {
+            node.principles = principles.clone();
+        }
+        for child in &mut node.children {
+            Self::splice_principles(child, refreshed);
+        }
+        node.sort_children();
+    }
+
+    /// Options applied to the finished (possibly cached) tree: the tenant's feedback, the tuned
+    /// confidence thresholds unless the request sets its own, pruning, then cross-node deduplication
+    fn apply_result_options(&self, result: AnalysisResult, request: &SearchRequest) -> AnalysisResult {
+        let result = self.feedback.apply(&cache::current_tenant(), result, request.min_confidence.unwrap_or(0.0));
+        let result = match request.min_confidence {
+            Some(_) => result,
+            None => self.tuner.report().apply(result),
+        };
+        let result = match request.prune_below {
+            Some(threshold) => result.pruned(threshold),
+            None => result,
+        };
+        let mut result = match request.dedupe_scope {
+            DedupeScope::Tree => result.consolidated(),
+            DedupeScope::Node => result,
+        };
+        if let Some(locale) = &request.locale {
+            result.localize(&self.semantic_analyzer.description_templates().for_locale(locale));
+        }
+        if let Some(seed) = self.deterministic_seed {
+            result.make_deterministic(seed);
+        }
+        result
+    }
+
+    /// `request` with the settings it leaves unset taken from the preset it names, if any;
+    /// an unknown preset is an invalid request
+    pub fn apply_preset(&self, mut request: SearchRequest) -> Result<SearchRequest> {
+        self.presets.apply(&mut request)?;
+        Ok(request)
+    }
+
+    /// The locale generated text is rendered in for an `Accept-Language` header, when the
+    /// header names a language there are templates for
+    pub fn negotiate_locale(&self, accept_language: &str) -> Option<String> {
+        self.semantic_analyzer.description_templates().negotiate(accept_language)
+    }
+
+    /// Feedback users gave on analyses
+    pub fn feedback(&self) -> &FeedbackLog {
+        &self.feedback
+    }
+
+    /// The principle of the page for `term` titled (or with the id) `principle`, as last extracted,
+    /// if the page's principles are still cached
+    pub fn principle_on_page(&self, term: &str, principle: &str) -> Option<EngineeringPrinciple> {
+        let principle = text::fold_case(principle);
+        self.cache
+            .get_principles(&self.concept_key(term))?
+            .into_iter()
+            .find(|candidate| text::fold_case(&candidate.title) == principle || candidate.id == principle)
+    }
+
+    /// Fits the confidence thresholds applied to analyses, which the server refits periodically
+    pub fn tuner(&self) -> &Arc<ThresholdTuner> {
+        &self.tuner
+    }
+
+    /// Events of the engine's analyses, for plugins and other cross-cutting features to subscribe to
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    /// Sentences collected for labeling, which the server exports periodically
+    pub fn labeling(&self) -> &Arc<LabelingQueue> {
+        &self.labeling
+    }
+
+    /// Stamp of the knowledge base concepts are decomposed with, see `ConceptKnowledgeBase::version`
+    pub fn kb_version(&self) -> String {
+        self.semantic_analyzer.knowledge_version()
+    }
+
+    /// The knowledge base in use, or with `kb_version` the version with that stamp; `None` when
+    /// it is neither in use nor in the knowledge base store
+    pub fn knowledge_base(&self, kb_version: Option<&str>) -> Result<Option<ConceptKnowledgeBase>> {
+        match kb_version {
+            Some(kb_version) if kb_version != self.kb_version() => match &self.kb_store {
+                Some(store) => Ok(store.load(kb_version)?.map(|(_, knowledge)| knowledge)),
+                None => Ok(None),
+            },
+            _ => Ok(Some(self.semantic_analyzer.knowledge_base())),
+        }
+    }
+
+    /// Merge the rows of a CSV import file (see `kb_import::parse_csv`) into the knowledge base.
+    /// The result is saved as a new version in the knowledge base store, if there is one, and
+    /// used by analyses from then on; as cache keys carry `kb_version`, nothing cached against
+    /// the old knowledge base is served for them.
+    pub fn import_knowledge(&self, csv: &str, options: &ImportOptions) -> Result<ImportReport> {
+        let rows = kb_import::parse_csv(csv)?;
+        let _edit = self.kb_edits.lock().unwrap();
+        let mut knowledge = self.semantic_analyzer.knowledge_base();
+        let mut report = kb_import::merge(&mut knowledge, &rows, options.on_conflict);
+        report.dry_run = options.dry_run;
+        if options.dry_run || report.kb_version == self.kb_version() {
+            return Ok(report);
+        }
+
+        if let Some(store) = &self.kb_store {
+            let note = options.note.clone().unwrap_or_else(|| format!("CSV import of {} rows", report.rows));
+            let revision = store.save(&knowledge, &note)?;
+            tracing::info!("Saved the imported knowledge base as revision {}", revision.revision);
+        }
+        self.semantic_analyzer.set_knowledge_base(knowledge);
+        tracing::info!(
+            kb_version = %report.kb_version,
+            components_added = report.components_added,
+            conflicts = report.conflicts.len(),
+            "Imported {} rows into the knowledge base",
+            report.rows
+        );
+        Ok(report)
+    }
+
+    /// Minimum similarity to its parent a concept outside the knowledge base needs to be expanded
+    pub fn similarity_threshold(&self) -> f32 {
+        self.semantic_analyzer.similarity_threshold()
+    }
+
+    pub fn set_similarity_threshold(&self, threshold: f32) {
+        self.semantic_analyzer.set_similarity_threshold(threshold);
+    }
+
+    /// Estimate how many nodes, Wikipedia calls and seconds `request` would take, without
+    /// fetching anything. The tree is walked as far as cached pages and the knowledge base give
+    /// each node's related concepts; below the nodes they don't cover, the tree is assumed to
+    /// branch like the walked nodes do on average (or fully, to `max_results`, if none were).
+    pub fn plan_analysis(&self, request: &SearchRequest) -> Result<AnalysisPlan> {
+        let request = &self.apply_preset(request.clone())?;
+        let options = AnalysisOptions::from_request(request);
+        let mut plan = AnalysisPlan { term: request.term.clone(), ..Default::default() };
+
+        let cache_key = self.cache.generate_analysis_cache_key(
+            &self.concept_key(&request.term),
+            options.max_depth,
+            options.max_results,
+            &options.filter_key(),
+            &self.kb_version(),
+        );
+        if let Some(cached_node) = self.cache.get_analysis_node(&cache_key) {
+            plan.estimated_nodes = cached_node.node_count();
+            plan.known_nodes = plan.estimated_nodes;
+            plan.cached_result = true;
+            return Ok(plan);
+        }
+
+        // Other document sources cost one fetch per node, which isn't a Wikipedia call
+        let on_wiki = options.document_source == DocumentSourceKind::Wikipedia;
+        let expansion_calls = match options.concept_source {
+            _ if !on_wiki => 0,
+            ConceptSource::Combined => 2,
+            ConceptSource::PageLinks | ConceptSource::Categories => 1,
+            _ => 0,
+        };
+        // Wikipedia calls a node at `depth` takes, with its page already cached or not
+        let node_calls = |depth: u8, cached: bool| {
+            let fetch_calls = if cached || !on_wiki { 0 } else { PAGE_FETCH_CALLS };
+            let expansion_calls = if depth < options.max_depth { expansion_calls } else { 0 };
+            f64::from(fetch_calls + expansion_calls)
+        };
+        let (mut wiki_calls, mut other_fetches) = (0.0f64, 0.0f64);
+
+        let mut visited = HashSet::from([self.concept_key(&request.term)]);
+        let mut queue = VecDeque::from([(request.term.clone(), 0u8)]);
+        // Depths of the nodes whose related concepts can't be known without fetching
+        let mut unexplored = Vec::new();
+        let (mut expanded, mut children) = (0u32, 0u32);
+        while let Some((term, depth)) = queue.pop_front() {
+            plan.known_nodes += 1;
+            let page = on_wiki.then(|| self.cache.peek_wikipedia_page(&self.concept_key(&term))).flatten();
+            wiki_calls += node_calls(depth, page.is_some());
+            match page {
+                Some(_) => plan.cached_pages += 1,
+                None if !on_wiki => other_fetches += 1.0,
+                None => {}
+            }
+            if depth >= options.max_depth {
+                continue;
+            }
+            let Some(related) = self.known_related_concepts(&term, page.as_ref(), options.concept_source) else {
+                unexplored.push(depth);
+                continue;
+            };
+            expanded += 1;
+            for (concept, _) in self.select_child_concepts(&term, depth + 1, related, &options) {
+                if visited.insert(self.concept_key(&concept)) {
+                    children += 1;
+                    queue.push_back((concept, depth + 1));
+                }
+            }
+        }
+
+        let branching = match expanded {
+            0 => f64::from(options.max_results),
+            expanded => f64::from(children) / f64::from(expanded),
+        };
+        let mut extrapolated = 0.0;
+        for depth in unexplored {
+            let mut level = 1.0;
+            for child_depth in depth + 1..=options.max_depth {
+                level *= branching;
+                extrapolated += level;
+                wiki_calls += level * node_calls(child_depth, false);
+                if !on_wiki {
+                    other_fetches += level;
+                }
+            }
+        }
+
+        let call_secs = monitoring::average_wikipedia_call_secs().unwrap_or(ASSUMED_WIKIPEDIA_CALL_SECS);
+        plan.estimated_nodes = (f64::from(plan.known_nodes) + extrapolated).round().min(f64::from(u32::MAX)) as u32;
+        plan.estimated_wikipedia_calls = wiki_calls.round().min(f64::from(u32::MAX)) as u32;
+        plan.estimated_seconds = ((wiki_calls + other_fetches) * f64::from(call_secs)) as f32;
+        plan.exceeds_timeout = request
+            .timeout_ms
+            .is_some_and(|timeout_ms| f64::from(plan.estimated_seconds) * 1000.0 > timeout_ms as f64);
+        Ok(plan)
+    }
+
+    /// Related concepts of `term` known without fetching anything, from its cached page and the
+    /// knowledge base; `None` when they depend on something not cached (the page itself, or its
+    /// links and categories, which are never cached)
+    fn known_related_concepts(
+        &self,
+        term: &str,
+        page: Option<&crate::types::WikipediaPage>,
+        source: ConceptSource,
+    ) -> Option<Vec<(String, Vec<ConceptSource>)>> {
+        let known = |source: ConceptSource| match source {
+            ConceptSource::ExtractRegex => page.map(|page| self.analyzer.extract_related_concepts(page)),
+            ConceptSource::SeeAlso => page.map(|page| page.curated_links.clone()),
+            ConceptSource::KnowledgeBase if !self.semantic_analyzer.has_knowledge_of(term) => Some(vec![]),
+            ConceptSource::KnowledgeBase => self.semantic_analyzer.decompose_concept(term, None, 1).ok().map(
+                |decomposition| decomposition.components.into_iter().map(|component| component.name).collect(),
+            ),
+            ConceptSource::PageLinks
+            | ConceptSource::Categories
+            | ConceptSource::SeeAlsoThenExtract
+            | ConceptSource::Combined => None,
+        };
+        if source == ConceptSource::SeeAlsoThenExtract {
+            return page.map(|page| Self::curated_then_extracted(&page.curated_links, self.analyzer.extract_related_concepts(page)));
+        }
+        if source != ConceptSource::Combined {
+            return known(source).map(|concepts| concepts.into_iter().map(|concept| (concept, vec![source])).collect());
+        }
+        if page.is_none() && !self.semantic_analyzer.has_knowledge_of(term) {
+            return None;
+        }
+        // Links and categories rank below the other sources, so leaving them out mostly just
+        // leaves the budget to them
+        let sources = ConceptSource::COMBINED.into_iter().filter_map(|source| Some((source, known(source)?))).collect();
+        Some(Self::combine_concept_sources(sources))
+    }
+
+    fn analyze_term_recursive<'a>(
+        &'a self,
+        term: &'a str,
+        current_depth: u8,
+        options: &'a AnalysisOptions,
+        visited: VisitedTerms,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<AnalysisNode>> + Send + 'a>> {
+        Box::pin(async move {
+            self.events.emit(EngineEvent::node_started(term, current_depth));
+            let node = self.analyze_term(term, current_depth, options, visited).await?;
+            self.events.emit(EngineEvent::node_completed(&node));
+            Ok(node)
+        })
+    }
+
+    /// One node of the tree, expanding its children through `analyze_term_recursive`
+    fn analyze_term<'a>(
+        &'a self,
+        term: &'a str,
+        current_depth: u8,
+        options: &'a AnalysisOptions,
+        visited: VisitedTerms,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<AnalysisNode>> + Send + 'a>> {
+        let span = tracing::info_span!("analyze_term", term = %term, depth = current_depth);
+        Box::pin(async move {
+        let term_start = Instant::now();
+        let _progress = progress::NodeGuard::new(term);
+        let max_depth = options.max_depth;
+        
+        // Prevent infinite recursion: a term already being expanded further up becomes a leaf
+        let term_key = self.concept_key(term);
+        let visit = if current_depth < max_depth { visited.enter(&term_key) } else { None };
+        let Some(_visit) = visit else {
+            return Ok(AnalysisNode {
+                processing_time_ms: term_start.elapsed().as_millis() as u64,
+                ..AnalysisNode::leaf(term, current_depth)
+            });
+        };
+
+        tracing::debug!("Analyzing term '{}' at depth {}", term, current_depth);
+
+        // Get the page (or document) for the term
+        let mut page = match self.get_or_fetch_document(term, options.document_source).await? {
+            Some(page) => page,
+            None if current_depth == 0 => {
+                return Err(WikiEngineError::PageNotFound(term.to_string()));
+            }
+            None => {
+                tracing::warn!("No Wikipedia page found for '{}'", term);
+                return Ok(AnalysisNode {
+                    processing_time_ms: term_start.elapsed().as_millis() as u64,
+                    warnings: vec![AnalysisWarning {
+                        term: term.to_string(),
+                        depth: current_depth,
+                        kind: AnalysisWarningKind::PageNotFound,
+                        message: format!("No Wikipedia page found for '{}'", term),
+                    }],
+                    ..AnalysisNode::leaf(term, current_depth)
+                });
+            }
+        };
+
+        self.events.emit(EngineEvent::page_fetched(term, &page));
+        let truncated_source = self.limit_extract(&mut page);
+
+        // Analyze the page for engineering principles
+        let principles = options.select_principles(self.get_or_analyze_principles(&page).await?);
+
+        // Extract related concepts for recursive analysis
+        let related_concepts = if current_depth < max_depth {
+            self.related_concepts(term, &page, options.concept_source).await
+        } else {
+            vec![]
+        };
+
+        // Recursively analyze related concepts
+        let mut children = Vec::new();
+        let mut warnings = Vec::new();
+        let concepts_to_analyze = self.select_child_concepts(term, current_depth + 1, related_concepts, options);
+        progress::record_nodes_discovered(concepts_to_analyze.len());
+
+        // Siblings are expanded a few at a time, in one task so the progress and tenant scopes
+        // carry over; deterministic engines take them one by one, since which of two siblings
+        // reaching the same term expands it would otherwise depend on timing
+        let concurrency = if self.deterministic_seed.is_some() { 1 } else { SIBLING_CONCURRENCY };
+        let term_key = &term_key;
+        let expansions: Vec<_> = stream::iter(concepts_to_analyze)
+            .map(|(concept, sources)| {
+                let visited = visited.clone();
+                async move {
+                    if options.past_deadline() {
+                        return (concept, sources, ChildExpansion::Unstarted);
+                    }
+                    let concept_key = self.concept_key(&concept);
+                    if visited.contains(&concept_key) || concept_key == *term_key {
+                        progress::record_node_skipped();
+                        return (concept, sources, ChildExpansion::Skipped);
+                    }
+                    // A panic while expanding one branch costs that branch only; the visit and
+                    // progress guards it held are released as it unwinds
+                    let child = AssertUnwindSafe(self.analyze_term_recursive(&concept, current_depth + 1, options, visited))
+                        .catch_unwind()
+                        .await;
+                    (concept, sources, ChildExpansion::Expanded(Box::new(child)))
+                }
+            })
+            .buffered(concurrency)
+            .collect()
+            .await;
+
+        let mut unstarted = 0;
+        for (concept, sources, expansion) in expansions {
+            let child = match expansion {
+                ChildExpansion::Unstarted => {
+                    unstarted += 1;
+                    progress::record_node_skipped();
+                    continue;
+                }
+                ChildExpansion::Skipped => continue,
+                ChildExpansion::Expanded(child) => *child,
+            };
+            match child {
+                Ok(Ok(mut child_node)) => {
+                    child_node.provenance = Some(Self::provenance(term, &page, &concept, sources, options));
+                    let is_empty = child_node.principles.is_empty() && child_node.children.is_empty();
+                    if options.min_confidence.is_some() && is_empty {
+                        // Nothing left above the threshold; keep its warnings but drop the node
+                        warnings.extend(child_node.warnings);
+                    } else {
+                        children.push(child_node);
+                    }
+                }
+                Ok(Err(e)) => {
+                    tracing::warn!("Failed to analyze related concept '{}': {}", concept, e);
+                    warnings.push(AnalysisWarning {
+                        term: concept.clone(),
+                        depth: current_depth + 1,
+                        kind: AnalysisWarningKind::from(&e),
+                        message: e.to_string(),
+                    });
+                }
+                Err(panic) => {
+                    let message = panic_message(panic);
+                    tracing::error!("Analysis of related concept '{}' panicked: {}", concept, message);
+                    warnings.push(AnalysisWarning {
+                        term: concept.clone(),
+                        depth: current_depth + 1,
+                        kind: AnalysisWarningKind::Panicked,
+                        message: format!("Analysis panicked: {}", message),
+                    });
+                }
+            }
+        }
+        if unstarted > 0 {
+            warnings.push(AnalysisWarning {
+                term: term.to_string(),
+                depth: current_depth,
+                kind: AnalysisWarningKind::Timeout,
+                message: format!("Deadline reached, {} related concepts left unexpanded", unstarted),
+            });
+        }
+
+        let language = language::detect_language(&page.extract);
+        let mut node = AnalysisNode {
+            term: Term::new(term),
+            principles,
+            children,
+            depth: current_depth,
+            processing_time_ms: term_start.elapsed().as_millis() as u64,
+            thumbnail_url: page.thumbnail_url.clone(),
+            popularity: page.monthly_views.map(popularity_score),
+            warnings,
+            summary: Some(self.analyzer.summarize(&page.extract, SUMMARY_SENTENCES)).filter(|summary| !summary.is_empty()),
+            complexity: Some(self.analyzer.complexity_score(&page)),
+            english_term: self.english_term(&page, language).await,
+            language: language.map(str::to_string),
+            romanized_term: language::romanize(term),
+            definitions: self.analyzer.extract_definitions(&page),
+            provenance: None,
+            truncated_source,
+        };
+        node.sort_children();
+
+        Ok(node)
+        }.instrument(span))
+    }
+
+    /// English title for a page from another-language wiki, so trees built from different wikis
+    /// can be matched up
+    async fn english_term(&self, page: &crate::types::WikipediaPage, language: Option<&str>) -> Option<String> {
+        if page.source != DocumentSourceKind::Wikipedia || language.is_none_or(|language| language == "en") {
+            return None;
+        }
+        self.wikipedia_client.get_english_title(&page.title).await.unwrap_or_else(|e| {
+            tracing::debug!("No English title for '{}': {}", page.title, e);
+            None
+        })
+    }
+
+    /// Related concepts of a page drawn from the requested source, each with the sources that proposed it
+    async fn related_concepts(
+        &self,
+        term: &str,
+        page: &crate::types::WikipediaPage,
+        source: ConceptSource,
+    ) -> Vec<(String, Vec<ConceptSource>)> {
+        if source == ConceptSource::SeeAlsoThenExtract {
+            return Self::curated_then_extracted(&page.curated_links, self.analyzer.extract_related_concepts(page));
+        }
+        if source != ConceptSource::Combined {
+            let concepts = self.concepts_from_source(term, page, source).await;
+            return concepts.into_iter().map(|concept| (concept, vec![source])).collect();
+        }
+
+        let mut sources = Vec::new();
+        for source in ConceptSource::COMBINED {
+            sources.push((source, self.concepts_from_source(term, page, source).await));
+        }
+        Self::combine_concept_sources(sources)
+    }
+
+    async fn concepts_from_source(&self, term: &str, page: &crate::types::WikipediaPage, source: ConceptSource) -> Vec<String> {
+        let fetched = match source {
+            ConceptSource::ExtractRegex => return self.analyzer.extract_related_concepts(page),
+            ConceptSource::SeeAlso => return page.curated_links.clone(),
+            ConceptSource::KnowledgeBase => {
+                if !self.semantic_analyzer.has_knowledge_of(term) {
+                    return vec![];
+                }
+                self.get_or_decompose_concept(term, 1).await.map(|decomposition| {
+                    decomposition.components.into_iter().map(|component| component.name).collect()
+                })
+            }
+            // Links and categories only exist for pages on the wiki
+            ConceptSource::PageLinks | ConceptSource::Categories if page.source != DocumentSourceKind::Wikipedia => {
+                return vec![]
+            }
+            ConceptSource::PageLinks => self.wikipedia_client.get_page_links(&page.title, PAGE_LINKS_LIMIT).await,
+            ConceptSource::Categories => self.wikipedia_client.get_page_categories(&page.title).await,
+            // These are expanded into the individual sources by `related_concepts`
+            ConceptSource::SeeAlsoThenExtract | ConceptSource::Combined => return vec![],
+        };
+
+        // A missing source just contributes nothing; the other sources still apply
+        fetched.unwrap_or_else(|e| {
+            tracing::debug!("No {} concepts for '{}': {}", source.name(), page.title, e);
+            vec![]
+        })
+    }
+
+    /// Curated See also/infobox links take precedence over concepts pulled from the extract
+    fn curated_then_extracted(curated_links: &[String], extracted: Vec<String>) -> Vec<(String, Vec<ConceptSource>)> {
+        let mut seen = HashSet::new();
+        curated_links
+            .iter()
+            .map(|concept| (concept.clone(), vec![ConceptSource::SeeAlso]))
+            .chain(extracted.into_iter().map(|concept| (concept, vec![ConceptSource::ExtractRegex])))
+            .filter(|(concept, _)| seen.insert(concept.to_lowercase()))
+            .collect()
+    }
+
+    /// Merge concept lists from several sources, ranking each concept by the summed weight of the
+    /// sources that produced it; ties keep first-seen order
+    fn combine_concept_sources(sources: Vec<(ConceptSource, Vec<String>)>) -> Vec<(String, Vec<ConceptSource>)> {
+        let mut ranked: Vec<(String, f32, Vec<ConceptSource>)> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+
+        for (source, concepts) in sources {
+            let mut counted = HashSet::new();
+            for concept in concepts {
+                let key = concept.to_lowercase();
+                if !counted.insert(key.clone()) {
+                    continue;
+                }
+                match positions.get(&key) {
+                    Some(&i) => {
+                        ranked[i].1 += source.weight();
+                        ranked[i].2.push(source);
+                    }
+                    None => {
+                        positions.insert(key, ranked.len());
+                        ranked.push((concept, source.weight(), vec![source]));
+                    }
+                }
+            }
+        }
+
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranked.into_iter().map(|(concept, _, sources)| (concept, sources)).collect()
+    }
+
+    /// Record how `concept` was reached from `parent` and where the parent's text mentions it
+    fn provenance(
+        parent: &str,
+        page: &crate::types::WikipediaPage,
+        concept: &str,
+        sources: Vec<ConceptSource>,
+        options: &AnalysisOptions,
+    ) -> NodeProvenance {
+        let sentence_span = EngineeringAnalyzer::sentence_mentioning(&page.extract, concept);
+        NodeProvenance {
+            parent_term: Term::new(parent),
+            sources,
+            pinned: options.is_pinned(concept),
+            sentence: sentence_span.map(|(start, end)| page.extract[start..end].to_string()),
+            sentence_span,
+        }
+    }
+
+    /// Pick which related concepts get expanded, applying the request's filters and budget.
+    /// Concepts the sources rank alike go by the pageviews of their cached pages, so the budget
+    /// favours commonly studied ones.
+    fn select_child_concepts(
+        &self,
+        parent: &str,
+        child_depth: u8,
+        related_concepts: Vec<(String, Vec<ConceptSource>)>,
+        options: &AnalysisOptions,
+    ) -> Vec<(String, Vec<ConceptSource>)> {
+        let source_rank = |sources: &[ConceptSource]| sources.iter().map(ConceptSource::weight).sum::<f32>();
+        let mut ranked: Vec<_> = related_concepts
+            .into_iter()
+            .map(|(concept, sources)| {
+                let views = self.cache.cached_monthly_views(&self.concept_key(&concept)).unwrap_or(0);
+                (source_rank(&sources), views, concept, sources)
+            })
+            .collect();
+        // Stable, so sources' own order still decides among concepts with equal rank and views
+        ranked.sort_by(|a, b| b.0.total_cmp(&a.0).then(b.1.cmp(&a.1)));
+        let related_concepts = ranked.into_iter().map(|(_, _, concept, sources)| (concept, sources));
+
+        let mut selected = Vec::new();
+        let mut budgeted = 0;
+
+        for (concept, sources) in related_concepts {
+            if options.is_excluded(&concept) {
+                continue;
+            }
+            if self.feedback.excludes_concept(&cache::current_tenant(), parent, &concept) {
+                tracing::debug!("Skipping '{}' under '{}': flagged as off-topic", concept, parent);
+                continue;
+            }
+            // Pinned concepts bypass the filters and don't count against max_results
+            if options.is_pinned(&concept) {
+                selected.push((concept, sources));
+                continue;
+            }
+            if budgeted >= options.max_results as usize {
+                continue;
+            }
+            // Concepts with no recognizable category are kept rather than guessed away
+            let category_allowed = self.analyzer
+                .infer_concept_category(&concept)
+                .is_none_or(|category| options.allows_category(&category));
+            if !category_allowed {
+                continue;
+            }
+            // Deeper levels only follow concepts that look related to their parent
+            if child_depth >= SEMANTIC_GATING_DEPTH && !self.semantic_analyzer.is_related_concept(parent, &concept) {
+                tracing::debug!("Skipping '{}' under '{}': not semantically related", concept, parent);
+                continue;
+            }
+            budgeted += 1;
+            selected.push((concept, sources));
+        }
+
+        selected
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_or_fetch_page(&self, term: &str) -> Result<Option<crate::types::WikipediaPage>> {
+        // Check cache first
+        let key = self.concept_key(term);
+        let expired = self.cache.take_expired_wikipedia_page(&key);
+        if let Some(cached_page) = self.cache.get_wikipedia_page(&key) {
+            tracing::debug!("Using cached Wikipedia page for '{}'", term);
+            return Ok(Some(cached_page));
+        }
+
+        Ok(self.fetch_page(term, expired).await?.map(|(page, _)| page))
+    }
+
+    /// Fetch a page from Wikipedia and cache it. A previously cached copy carrying HTTP validators
+    /// is revalidated instead, so an unchanged article only costs a 304.
+    /// Returns the page and whether its content changed.
+    async fn fetch_page(
+        &self,
+        term: &str,
+        previous: Option<crate::types::WikipediaPage>,
+    ) -> Result<Option<(crate::types::WikipediaPage, bool)>> {
+        if let Some(previous) = previous.filter(|page| page.has_validators()) {
+            tracing::debug!("Revalidating Wikipedia page for '{}'", term);
+            return match self.wikipedia_client.revalidate_page(&previous).await? {
+                PageFetch::NotModified => {
+                    let page = crate::types::WikipediaPage { fetched_at: chrono::Utc::now(), ..previous };
+                    self.cache_page(term, &page);
+                    Ok(Some((page, false)))
+                }
+                PageFetch::Modified(page) => Ok(Some((self.enrich_and_cache_page(term, *page).await, true))),
+                PageFetch::Missing => Ok(None),
+            };
+        }
+
+        tracing::debug!("Fetching Wikipedia page for '{}'", term);
+        match self.wikipedia_client.get_page_extract(term).await? {
+            Some(page) => Ok(Some((self.enrich_and_cache_page(term, page).await, true))),
+            None => Ok(None),
+        }
+    }
+
+    async fn enrich_and_cache_page(&self, term: &str, mut page: crate::types::WikipediaPage) -> crate::types::WikipediaPage {
+        // Both are nice-to-haves, so they're fetched side by side and a failure of either
+        // shouldn't fail the analysis
+        let (views, links) = futures::join!(
+            self.wikipedia_client.get_monthly_pageviews(&page.title),
+            self.wikipedia_client.get_curated_links(&page.title)
+        );
+        match views {
+            Ok(views) => page.monthly_views = Some(views),
+            Err(e) => tracing::debug!("No pageviews for '{}': {}", page.title, e),
+        }
+        match links {
+            Ok(links) => page.curated_links = links,
+            Err(e) => tracing::debug!("No curated links for '{}': {}", page.title, e),
+        }
+        self.cache_page(term, &page);
+        page
+    }
+
+    fn cache_page(&self, term: &str, page: &crate::types::WikipediaPage) {
+        // Also cache under the resolved title so redirects share one entry
+        let key = self.concept_key(term);
+        let title_key = self.concept_key(&page.title);
+        if title_key != key {
+            self.cache.cache_wikipedia_page(title_key, page.clone());
+        }
+        self.cache.cache_wikipedia_page(key, page.clone());
+    }
+
+    /// Fetch a term's text from the requested source. Documents from sources other than
+    /// MediaWiki are cached under a per-source key so they never shadow Wikipedia pages.
+    async fn get_or_fetch_document(
+        &self,
+        term: &str,
+        kind: DocumentSourceKind,
+    ) -> Result<Option<crate::types::WikipediaPage>> {
+        if kind == DocumentSourceKind::Wikipedia {
+            return self.get_or_fetch_page(term).await;
+        }
+        let source = self.document_sources.get(&kind).ok_or_else(|| {
+            WikiEngineError::InvalidRequest(vec![FieldError::new(
+                "document_source",
+                format!("{} is not configured on this server", kind.name()),
+            )])
+        })?;
+
+        let key = format!("{}:{}", kind.name(), self.concept_key(term));
+        if let Some(cached_page) = self.cache.get_wikipedia_page(&key) {
+            tracing::debug!("Using cached {} document for '{}'", kind.name(), term);
+            return Ok(Some(cached_page));
+        }
+
+        tracing::debug!("Fetching {} document for '{}'", kind.name(), term);
+        let Some(document) = source.fetch(term).await? else {
+            return Ok(None);
+        };
+        let page = document.into_page(kind);
+        self.cache.cache_wikipedia_page(key, page.clone());
+        Ok(Some(page))
+    }
+
+    /// Cut the page's text to the engine's extract limit at a sentence boundary, so giant pages
+    /// don't dominate the analyzers' CPU and memory; returns whether anything was cut
+    fn limit_extract(&self, page: &mut crate::types::WikipediaPage) -> bool {
+        let kept = EngineeringAnalyzer::truncate_at_sentence(&page.extract, self.max_extract_bytes).len();
+        if kept == page.extract.len() {
+            return false;
+        }
+        tracing::debug!("Analyzing only the first {} of {} bytes of '{}'", kept, page.extract.len(), page.title);
+        page.extract.truncate(kept);
+        true
+    }
+
+    /// Queue the page's sentences worth labeling: those the regex analyzer categorized differently
+    /// from the embedding model (already in `unsure`) or the semantic analyzer, and those whose
+    /// confidence was near the threshold
+    fn collect_for_labeling(
+        &self,
+        page: &crate::types::WikipediaPage,
+        regex_principles: &[EngineeringPrinciple],
+        semantic_principles: &[EngineeringPrinciple],
+        mut unsure: Vec<LabelingCandidate>,
+    ) {
+        for principle in regex_principles {
+            let other = semantic_principles
+                .iter()
+                .find(|semantic| semantic.source_span == principle.source_span && semantic.category != principle.category);
+            if let Some(other) = other {
+                unsure.push(LabelingCandidate::disagreement(page, principle, &other.category));
+            }
+        }
+        let threshold = self.analyzer.min_confidence();
+        unsure.extend(
+            self.analyzer
+                .borderline_sentences(page, self.labeling.config().margin)
+                .iter()
+                .map(|scored| LabelingCandidate::near_threshold(page, scored, threshold)),
+        );
+        self.labeling.push(unsure);
+    }
+
+    /// Principles are cached per title, namespaced by source for non-Wikipedia documents
+    fn principles_key(&self, page: &crate::types::WikipediaPage) -> String {
+        match page.source {
+            DocumentSourceKind::Wikipedia => self.concept_key(&page.title),
+            source => format!("{}:{}", source.name(), self.concept_key(&page.title)),
+        }
+    }
+
+    /// Canonical key for a concept (casing and knowledge-base synonyms), used for caches and visited tracking
+    fn concept_key(&self, term: &str) -> String {
+        self.semantic_analyzer.normalize_concept(term)
+    }
+
+    #[tracing::instrument(skip_all, fields(page = %page.title))]
+    async fn get_or_analyze_principles(
+        &self,
+        page: &crate::types::WikipediaPage,
+    ) -> Result<Vec<EngineeringPrinciple>> {
+        // Check cache first
+        let principles_key = self.principles_key(page);
+        if let Some(cached_principles) = self.cache.get_principles(&principles_key) {
+            tracing::debug!("Using cached principles for '{}'", page.title);
+            return Ok(cached_principles);
+        }
+
+        // Analyze the page using both traditional and semantic approaches
+        tracing::debug!("Analyzing principles for '{}'", page.title);
+        
+        // Get results from traditional regex-based analyzer
+        let mut regex_principles = self.analyzer.analyze_page(page)?;
+        tracing::debug!("Regex analyzer found {} principles", regex_principles.len());
+        
+        // Get results from semantic analyzer (focused on foundational building blocks); it may
+        // run the embedding model, so it gets a thread of its own
+        let semantic_principles = {
+            let (semantic_analyzer, page) = (Arc::clone(&self.semantic_analyzer), page.clone());
+            run_blocking(move || semantic_analyzer.analyze_page_semantically(&page)).await??
+        };
+        tracing::debug!("Semantic analyzer found {} principles", semantic_principles.len());
+        let plugin_principles = self.extract_with_plugins(page).await;
+        let embeddings = self
+            .embed_descriptions(semantic_principles.iter().chain(&regex_principles).chain(&plugin_principles))
+            .await?;
+        
+        // The embedding model, when loaded, has the final say on the category of each sentence
+        let mut unsure = Vec::new();
+        for principle in &mut regex_principles {
+            let category = embeddings
+                .get(&principle.description)
+                .and_then(|embedding| self.semantic_analyzer.classify_embedding(embedding));
+            if let Some(category) = category {
+                if self.labeling.is_enabled() && category != principle.category {
+                    unsure.push(LabelingCandidate::disagreement(page, principle, &category));
+                }
+                principle.category = category;
+                principle.classified_by = Some(CategoryClassifier::Embeddings);
+            }
+        }
+        if self.labeling.is_enabled() {
+            self.collect_for_labeling(page, &regex_principles, &semantic_principles, unsure);
+        }
+        
+        // Combine and deduplicate results, prioritizing semantic results and keeping the
+        // plugins' only where the built-in analyzers found nothing like them
+        let acronyms = text::acronyms(&page.extract);
+        let mut combined_principles = self.deduplicate_principles(
+            semantic_principles.into_iter().chain(regex_principles).chain(plugin_principles),
+            &acronyms,
+            &embeddings,
+        );
+        
+        // Principles that name no law may still state one in other words
+        for principle in combined_principles.iter_mut().filter(|principle| principle.named_law.is_none()) {
+            principle.named_law = embeddings
+                .get(&principle.description)
+                .and_then(|embedding| self.semantic_analyzer.named_law_of_embedding(embedding))
+                .map(str::to_string);
+        }
+        
+        // Sort by confidence; callers truncate to their per-node limit so the full set is cached
+        combined_principles.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap());
+        
+        tracing::info!("Combined analysis found {} principles for '{}'", combined_principles.len(), page.title);
+        for principle in &combined_principles {
+            self.events.emit(EngineEvent::principle_extracted(page, principle));
+        }
+        
+        // Cache the results
+        self.cache.cache_principles(principles_key, combined_principles.clone());
+        
+        Ok(combined_principles)
+    }
+
+    /// Principles the extractor plugins find on the page. A failing plugin costs only its own
+    /// principles.
+    async fn extract_with_plugins(&self, page: &crate::types::WikipediaPage) -> Vec<EngineeringPrinciple> {
+        if self.extractor_plugins.is_empty() {
+            return Vec::new();
+        }
+        let text: Arc<str> = Arc::from(page.extract.as_str());
+        let mut principles = Vec::new();
+        for plugin in &self.extractor_plugins {
+            // A WASM plugin may run until its fuel is spent
+            let outcome = {
+                let (plugin, text) = (Arc::clone(plugin), Arc::clone(&text));
+                run_blocking(move || plugin.extract(&text)).await.and_then(|outcome| outcome)
+            };
+            match outcome {
+                Ok(extracted) => {
+                    tracing::debug!("Extractor plugin '{}' found {} principles", plugin.name(), extracted.len());
+                    principles.extend(extracted.into_iter().filter_map(|principle| principle.into_principle(page)));
+                }
+                Err(e) => tracing::warn!("Skipping extractor plugin '{}' on '{}': {}", plugin.name(), page.title, e),
+            }
+        }
+        principles
+    }
+
+    /// Sentence embedding of each distinct principle description, computed once for the
+    /// category, duplicate and law checks alike; empty without the embedding model
+    async fn embed_descriptions<'a>(
+        &self,
+        principles: impl IntoIterator<Item = &'a EngineeringPrinciple>,
+    ) -> Result<HashMap<String, Vec<f32>>> {
+        if !self.semantic_analyzer.has_embedding_model() {
+            return Ok(HashMap::new());
+        }
+        let descriptions: HashSet<String> = principles.into_iter().map(|principle| principle.description.clone()).collect();
+        let semantic_analyzer = Arc::clone(&self.semantic_analyzer);
+        run_blocking(move || {
+            descriptions
+                .into_iter()
+                .filter_map(|description| {
+                    let embedding = semantic_analyzer.sentence_embedding(&description)?;
+                    Some((description, embedding))
+                })
+                .collect()
+        })
+        .await
+    }
+
+    /// Keep the first of each group of near-duplicate principles: drawn from the same passage,
+    /// with close SimHash signatures or close `embeddings` (from `embed_descriptions`).
+    /// Paraphrases rarely share a SimHash band, so embeddings are compared with every kept one.
+    fn deduplicate_principles(
+        &self,
+        principles: impl IntoIterator<Item = EngineeringPrinciple>,
+        acronyms: &HashMap<String, String>,
+        embeddings: &HashMap<String, Vec<f32>>,
+    ) -> Vec<EngineeringPrinciple> {
+        let mut index = NearDuplicateIndex::new();
+        let mut passages = PassageIndex::new();
+        let mut unique: Vec<EngineeringPrinciple> = Vec::new();
+        let mut kept_embeddings: Vec<&[f32]> = Vec::new();
+
+        for principle in principles {
+            let signature = principle.signature_expanding(acronyms);
+            if index.contains_near(signature) || passages.overlaps(&principle.source_url, principle.source_span) {
+                continue;
+            }
+            let embedding = embeddings.get(&principle.description).map(Vec::as_slice);
+            let close_in_meaning = embedding.is_some_and(|embedding| {
+                kept_embeddings
+                    .iter()
+                    .any(|other| text::cosine_similarity(embedding, other) >= EMBEDDING_DUPLICATE_SIMILARITY)
+            });
+
+            if !close_in_meaning {
+                index.insert(signature, unique.len());
+                passages.insert(&principle.source_url, principle.source_span);
+                kept_embeddings.extend(embedding);
+                unique.push(principle);
+            }
+        }
+
+        unique
+    }
+
+    pub async fn suggest_terms(&self, query: &str, limit: u8) -> Result<Vec<SearchSuggestion>> {
+        let cache_key = self.cache.generate_suggestion_cache_key(query, limit, &self.kb_version());
+        if let Some(cached) = self.cache.get_suggestions(&cache_key) {
+            tracing::debug!("Using cached suggestions for '{}'", query);
+            return Ok(cached);
+        }
+
+        let query_lower = text::fold_case(query.trim());
+        let mut search_results = self.wikipedia_client.search_pages(query, limit).await?;
+        let mut fuzzy_search = false;
+        if search_results.is_empty() {
+            // Nothing matched literally, fall back to Wikipedia's typo-tolerant profile
+            search_results = self.wikipedia_client.search_pages_fuzzy(query, limit).await?;
+            fuzzy_search = true;
+        }
+        
+        let mut suggestions = Vec::new();
+        let mut seen = HashSet::new();
+        for title in search_results {
+            // Simple heuristic for engineering relevance
+            let confidence = self.calculate_engineering_relevance(&title);
+            let category = self.infer_category_from_title(&title);
+            let matched_via = if text::fold_case(&title).starts_with(&query_lower) {
+                SuggestionMatch::Exact
+            } else if fuzzy_search {
+                SuggestionMatch::Fuzzy
+            } else {
+                SuggestionMatch::Search
+            };
+            
+            seen.insert(text::fold_case(&title));
+            suggestions.push(SearchSuggestion {
+                term: title,
+                confidence,
+                category,
+                matched_via,
+            });
+        }
+
+        // Typo-tolerant matching against titles we've already seen
+        for (title, similarity) in self.fuzzy_title_matches(&query_lower) {
+            if !seen.insert(text::fold_case(&title)) {
+                continue;
+            }
+            let category = self.infer_category_from_title(&title);
+            suggestions.push(SearchSuggestion {
+                confidence: self.calculate_engineering_relevance(&title).max(similarity as f32 * 0.9),
+                term: title,
+                category,
+                matched_via: SuggestionMatch::Fuzzy,
+            });
+        }
+
+        // Sort by confidence
+        suggestions.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap());
+        suggestions.truncate(limit as usize);
+        self.cache.cache_suggestions(cache_key, suggestions.clone());
+        
+        Ok(suggestions)
+    }
+
+    /// Cached page titles and knowledge-base concepts that are within a small edit distance of the query
+    fn fuzzy_title_matches(&self, query_lower: &str) -> Vec<(String, f64)> {
+        const MIN_SIMILARITY: f64 = 0.85;
+
+        let mut candidates = self.cache.cached_page_titles();
+        candidates.extend(self.semantic_analyzer.known_concepts());
+
+        let mut matches: Vec<(String, f64)> = candidates
+            .into_iter()
+            .filter_map(|title| {
+                let similarity = text::spelling_similarity(query_lower, &title);
+                (similarity >= MIN_SIMILARITY && text::fold_case(&title) != query_lower).then_some((title, similarity))
+            })
+            .collect();
+
+        matches.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then_with(|| a.0.cmp(&b.0)));
+        matches
+    }
+
+    fn calculate_engineering_relevance(&self, title: &str) -> f32 {
+        let engineering_keywords = [
+            "engine", "motor", "system", "design", "structure", "material", "process",
+            "machine", "device", "technology", "mechanism", "circuit", "bridge", "building",
+            "manufacturing", "engineering", "mechanical", "electrical", "chemical", "civil",
+        ];
+
+        let title_lower = title.to_lowercase();
+        let mut score: f32 = 0.0;
+
+        for keyword in &engineering_keywords {
+            if title_lower.contains(keyword) {
+                score += 0.2;
+            }
+        }
+
+        // Bonus for longer, more specific titles
+        if title.len() > 10 {
+            score += 0.1;
+        }
+
+        score.min(1.0)
+    }
+
+    fn infer_category_from_title(&self, title: &str) -> String {
+        let title_lower = title.to_lowercase();
+        
+        if title_lower.contains("bridge") || title_lower.contains("building") || title_lower.contains("structure") {
+            "Structural".to_string()
+        } else if title_lower.contains("engine") || title_lower.contains("motor") || title_lower.contains("gear") {
+            "Mechanical".to_string()
+        } else if title_lower.contains("circuit") || title_lower.contains("electronic") || title_lower.contains("electrical") {
+            "Electrical".to_string()
+        } else if title_lower.contains("material") || title_lower.contains("steel") || title_lower.contains("composite") {
+            "Material".to_string()
+        } else if title_lower.contains("process") || title_lower.contains("manufacturing") {
+            "Process".to_string()
+        } else {
+            "General".to_string()
+        }
+    }
+
+    /// Run many analyses with at most `concurrency` in flight, yielding each result as soon as
+    /// it finishes (in completion order) so callers can persist progress as they go
+    pub fn analyze_each(
+        &self,
+        requests: Vec<SearchRequest>,
+        concurrency: usize,
+    ) -> impl Stream<Item = (SearchRequest, Result<AnalysisResult>)> + '_ {
+        stream::iter(requests)
+            .map(move |request| async move {
+                let result = self.analyze_recursive(&request).await;
+                (request, result)
+            })
+            .buffer_unordered(concurrency.max(1))
+    }
+
+    /// Analyze several terms concurrently, reporting the outcome of each term individually
+    pub async fn batch_analyze(
+        &self,
+        terms: &[String],
+        max_depth: u8,
+        max_results: u8,
+        timeout_ms: Option<u64>,
+    ) -> Vec<BatchAnalysisItem> {
+        let semaphore = Semaphore::new(self.max_concurrency);
+        // One deadline for the whole batch, so terms waiting for a permit get what is left of it
+        let deadline = timeout_ms.map(|ms| Instant::now() + Duration::from_millis(ms));
+
+        let analyses = terms.iter().map(|term| {
+            let semaphore = &semaphore;
+            async move {
+                let _permit = semaphore.acquire().await.expect("batch semaphore closed");
+                let request = SearchRequest {
+                    term: term.clone(),
+                    max_depth: Some(max_depth),
+                    max_results: Some(max_results),
+                    timeout_ms: deadline.map(|deadline| {
+                        deadline.saturating_duration_since(Instant::now()).as_millis() as u64
+                    }),
+                    ..Default::default()
+                };
+
+                match self.analyze_recursive(&request).await {
+                    Ok(result) => BatchAnalysisItem {
+                        term: term.clone(),
+                        result: Some(result),
+                        error: None,
+                    },
+                    Err(e) => {
+                        tracing::error!("Batch analysis failed for '{}': {}", term, e);
+                        BatchAnalysisItem {
+                            term: term.clone(),
+                            result: None,
+                            error: Some(e.to_string()),
+                        }
+                    }
+                }
+            }
+        });
+
+        join_all(analyses).await
+    }
+
+    /// Check that the Wikipedia API is reachable
+    pub async fn probe_wikipedia(&self) -> Result<()> {
+        self.wikipedia_client.probe().await
+    }
+
+    pub fn embedding_model_loaded(&self) -> bool {
+        self.semantic_analyzer.has_embedding_model()
+    }
+
+    pub fn get_cache_reference(&self) -> Arc<WikiEngineCache> {
+        Arc::clone(&self.cache)
+    }
+
+    /// New method for hierarchical engineering concept analysis
+    /// This is the main public interface for decomposing concepts like "UAV" into foundational blocks
+    pub async fn analyze_engineering_concept(
+        &self,
+        concept: &str,
+        max_depth: u8,
+    ) -> Result<Vec<EngineeringPrinciple>> {
+        tracing::info!("Analyzing engineering concept: {} with max_depth: {}", concept, max_depth);
+        
+        // Use the new semantic analyzer for direct concept decomposition
+        let decomposition = self.get_or_decompose_concept(concept, max_depth).await?;
+        Ok(self.semantic_analyzer.principles_from_decomposition(concept, &decomposition, None))
+    }
+
+    /// Get hierarchical breakdown of a concept as a structured tree
+    pub async fn get_engineering_concept_hierarchy(
+        &self,
+        concept: &str,
+        max_depth: u8,
+    ) -> Result<ConceptDecomposition> {
+        tracing::info!("Getting concept hierarchy for: {} with max_depth: {}", concept, max_depth);
+        
+        self.get_or_decompose_concept(concept, max_depth).await
+    }
+
+    /// Decompose a concept from the knowledge base alone, or from caller-supplied `text` for
+    /// concepts it doesn't know. Never fetches anything, so it answers immediately.
+    pub fn decompose_offline(&self, concept: &str, max_depth: u8, text: Option<&str>) -> Result<ConceptDecomposition> {
+        self.semantic_analyzer.decompose_concept(concept, text, max_depth)
+    }
+
+    /// Add components mined from related patent abstracts; patents are an optional enrichment,
+    /// so any failure just leaves the decomposition as it was
+    async fn merge_patent_components(&self, decomposition: &mut ConceptDecomposition) {
+        let Some(patents) = &self.patents else {
+            return;
+        };
+        let abstracts = match patents.search_abstracts(&decomposition.concept, PATENT_ABSTRACTS_LIMIT).await {
+            Ok(abstracts) => abstracts,
+            Err(e) => {
+                tracing::debug!("No patents for '{}': {}", decomposition.concept, e);
+                return;
+            }
+        };
+
+        for document in abstracts {
+            match self.semantic_analyzer.merge_components_from_text(decomposition, &document.text, ComponentSource::Patent) {
+                Ok(added) if added > 0 => {
+                    tracing::debug!("Added {} components from patent '{}'", added, document.title)
+                }
+                Ok(_) => {}
+                Err(e) => tracing::debug!("Failed to extract components from patent '{}': {}", document.title, e),
+            }
+        }
+    }
+
+    async fn get_or_decompose_concept(&self, concept: &str, max_depth: u8) -> Result<ConceptDecomposition> {
+        let cache_key = self.cache.generate_decomposition_cache_key(&self.concept_key(concept), max_depth, &self.kb_version());
+        if let Some(cached) = self.cache.get_decomposition(&cache_key) {
+            tracing::debug!("Using cached decomposition for '{}'", concept);
+            return Ok(cached);
+        }
+
+        // Concepts outside the knowledge base are decomposed from their Wikipedia article
+        let page = if self.semantic_analyzer.has_knowledge_of(concept) {
+            None
+        } else {
+            let mut page = self.get_or_fetch_page(concept).await?
+                .ok_or_else(|| WikiEngineError::PageNotFound(concept.to_string()))?;
+            self.limit_extract(&mut page);
+            Some(page)
+        };
+
+        let content = page.as_ref().map(|page| page.extract.as_str());
+        let mut decomposition = self.semantic_analyzer.decompose_concept(concept, content, max_depth)?;
+        self.merge_patent_components(&mut decomposition).await;
+        self.cache.cache_decomposition(cache_key, decomposition.clone());
+        Ok(decomposition)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::CacheSnapshot;
+    use crate::sources::{Document, SourceFuture};
+    use proptest::prelude::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[tokio::test]
+    async fn sibling_expansions_claim_a_term_one_at_a_time() {
+        let visited = VisitedTerms::default();
+        let holders = Arc::new(AtomicUsize::new(0));
+
+        // Siblings racing for the same term interleave at every await, as child expansions do
+        let claims = join_all((0..32).map(|_| {
+            let visited = visited.clone();
+            let holders = Arc::clone(&holders);
+            async move {
+                let Some(_guard) = visited.enter("stator") else { return false };
+                assert_eq!(holders.fetch_add(1, Ordering::SeqCst), 0);
+                tokio::task::yield_now().await;
+                assert!(visited.contains("stator"));
+                holders.fetch_sub(1, Ordering::SeqCst);
+                true
+            }
+        }))
+        .await;
+
+        assert_eq!(claims.iter().filter(|claimed| **claimed).count(), 1);
+        assert!(!visited.contains("stator"));
+        assert!(visited.enter("stator").is_some());
+    }
+
+    const PARTS: [&str; 6] = ["Armature", "Commutator", "Flywheel", "Governor", "Impeller", "Sprocket"];
+
+    /// In-memory pages standing in for the documents folder, so whole analyses run offline
+    #[derive(Debug)]
+    struct PageGraph(HashMap<String, String>);
+
+    impl DocumentSource for PageGraph {
+        fn kind(&self) -> DocumentSourceKind {
+            DocumentSourceKind::LocalFolder
+        }
+
+        fn fetch<'a>(&'a self, term: &'a str) -> SourceFuture<'a, Result<Option<Document>>> {
+            Box::pin(async move {
+                Ok(self.0.get(term).map(|text| Document {
+                    title: term.to_string(),
+                    text: text.clone(),
+                    url: format!("memory://{}", term),
+                }))
+            })
+        }
+    }
+
+    /// A page per part, each linking to some of the parts (itself included), so the graph
+    /// usually has cycles
+    fn page_graph() -> impl Strategy<Value = PageGraph> {
+        prop::collection::vec(prop::collection::vec(prop::sample::select(&PARTS[..]), 0..4), PARTS.len()).prop_map(|links| {
+            let pages = PARTS.iter().zip(links).map(|(part, links)| {
+                let mut text = format!("{} is a part of the machine that transmits torque.", part);
+                for link in links {
+                    text.push_str(&format!(" It couples to {}, which carries the load on the shaft and bearing.", link));
+                }
+                (part.to_string(), text)
+            });
+            PageGraph(pages.collect())
+        })
+    }
+
+    fn offline_engine(cache: Arc<WikiEngineCache>, pages: Option<PageGraph>) -> WikiEngine {
+        let mut builder = WikiEngine::builder()
+            .with_cache(cache)
+            .without_env_sources()
+            .similarity_threshold(0.0)
+            .with_concept_filter(ConceptFilterConfig { min_relevance: 1, ..Default::default() });
+        if let Some(pages) = pages {
+            builder = builder.with_source(Arc::new(pages));
+        }
+        builder.build().unwrap()
+    }
+
+    /// Checks depths follow the nesting, stay within `max_depth` and that no term repeats on a
+    /// path from the root; returns the number of principles in the tree
+    fn walk(node: &AnalysisNode, depth: u8, max_depth: u8, path: &mut Vec<String>) -> u32 {
+        assert_eq!(node.depth, depth, "depth of '{}' doesn't match its nesting", node.term);
+        assert!(depth <= max_depth, "'{}' is deeper than max_depth", node.term);
+        let key = node.term.to_lowercase();
+        assert!(!path.contains(&key), "'{}' repeats on its own path {:?}", node.term, path);
+
+        path.push(key);
+        let principles = node.principles.len() as u32
+            + node.children.iter().map(|child| walk(child, depth + 1, max_depth, path)).sum::<u32>();
+        path.pop();
+        principles
+    }
+
+    /// Panics when asked for `Commutator`, otherwise serves the pages it wraps
+    #[derive(Debug)]
+    struct PanickingSource(PageGraph);
+
+    impl DocumentSource for PanickingSource {
+        fn kind(&self) -> DocumentSourceKind {
+            DocumentSourceKind::LocalFolder
+        }
+
+        fn fetch<'a>(&'a self, term: &'a str) -> SourceFuture<'a, Result<Option<Document>>> {
+            assert_ne!(term, "Commutator", "fixture source refuses Commutator");
+            self.0.fetch(term)
+        }
+    }
+
+    #[tokio::test]
+    async fn panicking_branch_becomes_a_warning() {
+        let page = |link: &str| format!("Armature is a part of the machine that transmits torque. It couples to {}, which carries the load on the shaft and bearing.", link);
+        let pages = PageGraph(HashMap::from([
+            ("Armature".to_string(), format!("{} {}", page("Commutator"), page("Flywheel"))),
+            ("Flywheel".to_string(), "Flywheel is a part of the machine that stores energy.".to_string()),
+        ]));
+        let engine = WikiEngine::builder()
+            .without_env_sources()
+            .with_source(Arc::new(PanickingSource(pages)))
+            .similarity_threshold(0.0)
+            .with_concept_filter(ConceptFilterConfig { min_relevance: 1, ..Default::default() })
+            .build()
+            .unwrap();
+        let request = SearchRequest {
+            term: "Armature".to_string(),
+            max_depth: Some(2),
+            document_source: DocumentSourceKind::LocalFolder,
+            concept_source: ConceptSource::ExtractRegex,
+            ..Default::default()
+        };
+
+        let result = engine.analyze_recursive(&request).await.unwrap();
+        let panicked: Vec<_> = result.warnings.iter().filter(|w| w.kind == AnalysisWarningKind::Panicked).collect();
+        assert_eq!(panicked.len(), 1);
+        assert_eq!(panicked[0].term, "Commutator");
+        assert!(panicked[0].message.contains("fixture source refuses Commutator"));
+        assert!(result.tree.children.iter().any(|child| child.term == "Flywheel"));
+    }
+
+    /// Serves the pages it wraps slowly, tracking how many fetches overlap at most
+    #[derive(Debug)]
+    struct SlowSource(PageGraph, AtomicUsize, AtomicUsize);
+
+    impl DocumentSource for SlowSource {
+        fn kind(&self) -> DocumentSourceKind {
+            DocumentSourceKind::LocalFolder
+        }
+
+        fn fetch<'a>(&'a self, term: &'a str) -> SourceFuture<'a, Result<Option<Document>>> {
+            Box::pin(async move {
+                let in_flight = self.1.fetch_add(1, Ordering::SeqCst) + 1;
+                self.2.fetch_max(in_flight, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                self.1.fetch_sub(1, Ordering::SeqCst);
+                self.0.fetch(term).await
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn siblings_are_expanded_concurrently() {
+        let links = ["Commutator", "Flywheel", "Governor"]
+            .map(|link| format!("It couples to {}, which carries the load on the shaft and bearing.", link))
+            .join(" ");
+        let pages = PageGraph(HashMap::from([(
+            "Armature".to_string(),
+            format!("Armature is a part of the machine that transmits torque. {}", links),
+        )]));
+        let source = Arc::new(SlowSource(pages, AtomicUsize::new(0), AtomicUsize::new(0)));
+        let engine = WikiEngine::builder()
+            .without_env_sources()
+            .with_source(source.clone())
+            .similarity_threshold(0.0)
+            .with_concept_filter(ConceptFilterConfig { min_relevance: 1, ..Default::default() })
+            .build()
+            .unwrap();
+        let request = SearchRequest {
+            term: "Armature".to_string(),
+            max_depth: Some(2),
+            document_source: DocumentSourceKind::LocalFolder,
+            concept_source: ConceptSource::ExtractRegex,
+            ..Default::default()
+        };
+
+        engine.analyze_recursive(&request).await.unwrap();
+        assert!(source.2.load(Ordering::SeqCst) > 1);
+        assert!(source.2.load(Ordering::SeqCst) <= SIBLING_CONCURRENCY);
+    }
+
+    /// Counts how often each text is embedded
+    struct CountingEmbedder(crate::semantic_analyzer::WordGroupEmbedder, std::sync::Mutex<HashMap<String, usize>>);
+
+    impl SentenceEmbedder for CountingEmbedder {
+        fn embed(&self, text: &str) -> Result<Vec<f32>> {
+            *self.1.lock().unwrap().entry(text.to_string()).or_default() += 1;
+            self.0.embed(text)
+        }
+    }
+
+    #[tokio::test]
+    async fn each_principle_description_is_embedded_once() {
+        let sentence = "The power stage follows the fundamental principle that it runs hot under load. \
+            The heat sink follows the principle that cooling depends on temperature.";
+        let pages = PageGraph(HashMap::from([("Power stage".to_string(), sentence.to_string())]));
+        let embedder = Arc::new(CountingEmbedder(
+            crate::semantic_analyzer::WordGroupEmbedder(vec![&["hot", "heat", "temperature", "cooling"]]),
+            Default::default(),
+        ));
+        let engine = WikiEngine::builder()
+            .without_env_sources()
+            .with_source(Arc::new(pages))
+            .with_sentence_embedder(Arc::clone(&embedder) as Arc<dyn SentenceEmbedder>)
+            .build()
+            .unwrap();
+        embedder.1.lock().unwrap().clear();
+        let request = SearchRequest {
+            term: "Power stage".to_string(),
+            max_depth: Some(1),
+            document_source: DocumentSourceKind::LocalFolder,
+            concept_source: ConceptSource::ExtractRegex,
+            ..Default::default()
+        };
+
+        let result = engine.analyze_recursive(&request).await.unwrap();
+        assert!(!result.tree.principles.is_empty());
+        let embedded = embedder.1.lock().unwrap();
+        for principle in &result.tree.principles {
+            assert_eq!(embedded.get(&principle.description), Some(&1), "{}", principle.description);
+        }
+    }
+
+    #[tokio::test]
+    async fn sentences_the_model_and_patterns_disagree_on_are_exported() {
+        let path = std::env::temp_dir().join(format!("labeling-{}.jsonl", uuid::Uuid::new_v4()));
+        let labeling = Arc::new(LabelingQueue::new(crate::config::LabelingExportConfig { path: Some(path.clone()), ..Default::default() }));
+        // "load" makes the patterns call it structural, while the model reads it as thermal
+        let sentence = "The power stage follows the fundamental principle that it runs hot under load.";
+        let pages = PageGraph(HashMap::from([("Power stage".to_string(), sentence.to_string())]));
+        let embedder = crate::semantic_analyzer::WordGroupEmbedder(vec![&["hot", "heat", "temperature", "cooling"]]);
+        let engine = WikiEngine::builder()
+            .without_env_sources()
+            .with_source(Arc::new(pages))
+            .with_labeling_queue(Arc::clone(&labeling))
+            .with_sentence_embedder(Arc::new(embedder))
+            .build()
+            .unwrap();
+        let request = SearchRequest {
+            term: "Power stage".to_string(),
+            max_depth: Some(1),
+            document_source: DocumentSourceKind::LocalFolder,
+            concept_source: ConceptSource::ExtractRegex,
+            ..Default::default()
+        };
+
+        let result = engine.analyze_recursive(&request).await.unwrap();
+        let principle = result.tree.principles.iter().find(|p| p.description.contains("runs hot")).unwrap();
+        assert_eq!(principle.category, PrincipleCategory::new("Thermal"));
+        assert_eq!(principle.classified_by, Some(CategoryClassifier::Embeddings));
+
+        assert!(labeling.export().unwrap() >= 1);
+        let exported = std::fs::read_to_string(&path).unwrap();
+        let line: serde_json::Value = exported
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .find(|line: &serde_json::Value| line["reason"] == "category_disagreement")
+            .unwrap();
+        assert_eq!(line["text"], sentence);
+        assert_eq!(line["options"], serde_json::json!(["Structural", "Thermal"]));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    fn dedup_principle(description: &str, span: (usize, usize)) -> EngineeringPrinciple {
+        EngineeringPrinciple {
+            id: String::new(),
+            title: String::new(),
+            description: description.to_string(),
+            category: PrincipleCategory::new("Mechanical"),
+            confidence: 0.8,
+            source_url: "memory://machine".to_string(),
+            related_terms: vec![],
+            source_span: span,
+            source_section: None,
+            appears_in: vec![],
+            classified_by: None,
+            named_law: None,
+            component: None,
+        }
+    }
+
+    #[test]
+    fn thousands_of_principles_are_deduplicated_through_indexes() {
+        let engine = WikiEngine::builder().without_env_sources().build().unwrap();
+        // Every principle twice: reworded from the same passage, and repeated word for word
+        let principles = (0..3000).flat_map(|i| {
+            let description = (0..8).map(|word| format!("part{}w{}", i, word)).collect::<Vec<_>>().join(" ");
+            let span = (i * 100, i * 100 + 50);
+            [
+                dedup_principle(&description, span),
+                dedup_principle(&format!("{} in other words", i), span),
+                dedup_principle(&description, (0, 0)),
+            ]
+        });
+
+        let unique = engine.deduplicate_principles(principles, &HashMap::new(), &HashMap::new());
+        assert_eq!(unique.len(), 3000);
+        assert!(unique.iter().all(|principle| principle.description.starts_with("part")));
+    }
+
+    #[tokio::test]
+    async fn paraphrases_are_merged_by_their_embeddings() {
+        let engine = WikiEngine::builder()
+            .without_env_sources()
+            .with_sentence_embedder(Arc::new(crate::semantic_analyzer::WordGroupEmbedder(vec![
+                &["rotor", "armature"],
+                &["spins", "revolves"],
+                &["stator", "housing"],
+                &["inside", "within"],
+                &["battery", "charge"],
+            ])))
+            .build()
+            .unwrap();
+        let original = dedup_principle("The rotor spins inside the stator of the motor", (0, 40));
+        let paraphrase = dedup_principle("An armature revolves within a fixed housing while current flows", (100, 160));
+        let unrelated = dedup_principle("A battery stores charge for later use", (200, 240));
+
+        // Worded too differently to share a SimHash band, so only the embeddings can tell
+        let mut index = NearDuplicateIndex::new();
+        index.insert(original.signature_expanding(&HashMap::new()), 0);
+        assert_eq!(index.candidates(paraphrase.signature_expanding(&HashMap::new())).count(), 0);
+
+        let principles = [original, paraphrase, unrelated];
+        let embeddings = engine.embed_descriptions(&principles).await.unwrap();
+        let unique = engine.deduplicate_principles(principles, &HashMap::new(), &embeddings);
+        let descriptions: Vec<_> = unique.iter().map(|principle| principle.description.as_str()).collect();
+        assert_eq!(
+            descriptions,
+            ["The rotor spins inside the stator of the motor", "A battery stores charge for later use"]
+        );
+    }
+
+    #[test]
+    fn popular_concepts_win_expansion_ties() {
+        let engine = WikiEngine::builder().without_env_sources().build().unwrap();
+        for (title, views) in [("Commutator", 100), ("Brush", 50_000), ("Bearing", 10)] {
+            let mut page = Document { title: title.to_string(), text: String::new(), url: String::new() }
+                .into_page(DocumentSourceKind::Wikipedia);
+            page.monthly_views = Some(views);
+            engine.cache.cache_wikipedia_page(engine.concept_key(title), page);
+        }
+        let related = vec![
+            ("Commutator".to_string(), vec![ConceptSource::ExtractRegex]),
+            ("Winding".to_string(), vec![ConceptSource::ExtractRegex]),
+            ("Brush".to_string(), vec![ConceptSource::ExtractRegex]),
+            ("Bearing".to_string(), vec![ConceptSource::SeeAlso]),
+        ];
+        let options = AnalysisOptions::from_request(&SearchRequest { max_results: Some(3), ..Default::default() });
+
+        let selected: Vec<_> = engine
+            .select_child_concepts("Electric motor", 1, related, &options)
+            .into_iter()
+            .map(|(concept, _)| concept)
+            .collect();
+        // The curated link still comes first however few read it; the budget then goes to the
+        // most viewed of the extracted concepts, and uncached ones come last
+        assert_eq!(selected, ["Bearing", "Brush", "Commutator"]);
+    }
+
+    #[test]
+    fn curated_links_come_before_extracted_concepts_by_default() {
+        assert_eq!(SearchRequest::default().concept_source, ConceptSource::SeeAlsoThenExtract);
+        let concepts = WikiEngine::curated_then_extracted(
+            &["Commutator".to_string(), "Brush".to_string()],
+            vec!["brush".to_string(), "Stator".to_string()],
+        );
+        assert_eq!(
+            concepts,
+            [
+                ("Commutator".to_string(), vec![ConceptSource::SeeAlso]),
+                ("Brush".to_string(), vec![ConceptSource::SeeAlso]),
+                ("Stator".to_string(), vec![ConceptSource::ExtractRegex]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn long_pages_are_analyzed_up_to_the_extract_limit() {
+        let sentence = "The armature is a winding that transmits torque to the shaft. ";
+        let pages = PageGraph(HashMap::from([("Armature".to_string(), sentence.repeat(20))]));
+        let engine = WikiEngine::builder()
+            .without_env_sources()
+            .with_source(Arc::new(pages))
+            .max_extract_bytes(sentence.len() * 3 + 10)
+            .build()
+            .unwrap();
+        let request = SearchRequest {
+            term: "Armature".to_string(),
+            max_depth: Some(1),
+            document_source: DocumentSourceKind::LocalFolder,
+            ..Default::default()
+        };
+
+        let result = engine.analyze_recursive(&request).await.unwrap();
+        assert!(result.tree.truncated_source);
+        assert!(result.tree.principles.iter().all(|p| p.source_span.1 <= sentence.len() * 3));
+        let json = serde_json::to_value(&*result.tree).unwrap();
+        assert_eq!(json["truncated_source"], true);
+    }
+
+    #[tokio::test]
+    async fn analyses_announce_their_progress_on_the_event_bus() {
+        let text = "Armature is a part of the machine that transmits torque. It couples to Flywheel, which carries the load on the shaft and bearing.";
+        let pages = PageGraph(HashMap::from([
+            ("Armature".to_string(), text.to_string()),
+            ("Flywheel".to_string(), "Flywheel is a part of the machine that stores energy.".to_string()),
+        ]));
+        let engine = offline_engine(Arc::new(WikiEngineCache::new()), Some(pages));
+        let mut events = engine.events().subscribe();
+        let request = SearchRequest {
+            term: "Armature".to_string(),
+            max_depth: Some(1),
+            document_source: DocumentSourceKind::LocalFolder,
+            concept_source: ConceptSource::ExtractRegex,
+            ..Default::default()
+        };
+
+        let result = engine.analyze_recursive(&request).await.unwrap();
+        let mut received = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            received.push(event);
+        }
+        let names: Vec<_> = received.iter().map(EngineEvent::name).filter(|name| *name != "principle_extracted").collect();
+        assert_eq!(
+            names,
+            // Flywheel sits at max_depth, so it is a leaf whose page isn't fetched
+            ["node_started", "page_fetched", "node_started", "node_completed", "node_completed", "analysis_completed"]
+        );
+        let extracted = received.iter().filter(|event| event.name() == "principle_extracted").count();
+        assert_eq!(extracted as u32, result.total_principles);
+
+        let id = received[0].analysis_id().unwrap();
+        assert!(received.iter().all(|event| event.analysis_id() == Some(id)));
+        let EngineEvent::AnalysisCompleted { total_principles, error: None, .. } = received.last().unwrap() else {
+            panic!("expected the analysis to complete");
+        };
+        assert_eq!(*total_principles, result.total_principles);
+    }
+
+    /// Finds a principle wherever "flux" appears, and fails on pages without it
+    struct FluxPlugin;
+
+    impl ExtractorPlugin for FluxPlugin {
+        fn name(&self) -> &str {
+            "flux"
+        }
+
+        fn extract(&self, text: &str) -> Result<Vec<plugins::ExtractedPrinciple>> {
+            let start = text.find("flux").ok_or_else(|| WikiEngineError::Analysis("no flux".to_string()))?;
+            Ok(vec![plugins::ExtractedPrinciple {
+                title: "Flux linkage".to_string(),
+                description: "Torque follows the flux linking the windings.".to_string(),
+                category: "Electrical".to_string(),
+                confidence: 0.9,
+                span: Some((start, start + 4)),
+            }])
+        }
+    }
+
+    #[tokio::test]
+    async fn extractor_plugins_add_principles() {
+        let pages = PageGraph(HashMap::from([
+            ("Armature".to_string(), "The armature carries the magnetic flux of the machine.".to_string()),
+            ("Flywheel".to_string(), "Flywheel is a part of the machine that stores energy.".to_string()),
+        ]));
+        let engine = WikiEngine::builder()
+            .without_env_sources()
+            .with_source(Arc::new(pages))
+            .with_extractor_plugin(Arc::new(FluxPlugin))
+            .build()
+            .unwrap();
+        let analyze = |term: &str| {
+            let request = SearchRequest {
+                term: term.to_string(),
+                max_depth: Some(1),
+                document_source: DocumentSourceKind::LocalFolder,
+                ..Default::default()
+            };
+            let engine = &engine;
+            async move { engine.analyze_recursive(&request).await.unwrap() }
+        };
+
+        let armature = analyze("Armature").await;
+        let flux = armature.tree.principles.iter().find(|p| p.title == "Flux linkage").unwrap();
+        assert_eq!(flux.category, PrincipleCategory::new("Electrical"));
+        assert_eq!(&"The armature carries the magnetic flux of the machine."[flux.source_span.0..flux.source_span.1], "flux");
+
+        // The plugin's failure leaves the built-in analyzers' principles
+        let flywheel = analyze("Flywheel").await;
+        assert!(flywheel.tree.principles.iter().all(|p| p.title != "Flux linkage"));
+    }
+
+    #[tokio::test]
+    async fn plans_estimate_without_fetching() {
+        let engine = WikiEngine::builder()
+            .with_cache(Arc::new(WikiEngineCache::new()))
+            .without_env_sources()
+            .build()
+            .unwrap();
+        let request = SearchRequest {
+            term: "uav".to_string(),
+            max_depth: Some(2),
+            concept_source: ConceptSource::KnowledgeBase,
+            ..Default::default()
+        };
+
+        let plan = engine.plan_analysis(&request).unwrap();
+        assert!(!plan.cached_result);
+        assert!(plan.known_nodes > 1);
+        assert_eq!(plan.cached_pages, 0);
+        assert_eq!(plan.estimated_nodes, plan.known_nodes);
+        assert_eq!(plan.estimated_wikipedia_calls, plan.known_nodes * PAGE_FETCH_CALLS);
+        assert!(plan.estimated_seconds > 0.0);
+        assert!(!plan.exceeds_timeout);
+        assert_eq!(engine.cache.get_cache_stats().wikipedia_pages_count, 0);
+
+        let plan = engine.plan_analysis(&SearchRequest { timeout_ms: Some(1), ..request.clone() }).unwrap();
+        assert!(plan.exceeds_timeout);
+        let unknown_preset = SearchRequest { preset: Some("exhaustive".to_string()), ..request };
+        assert!(matches!(engine.plan_analysis(&unknown_preset), Err(WikiEngineError::InvalidRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn deterministic_engines_produce_identical_results() {
+        let pages = || {
+            let text = |part: &str, link: &str| {
+                format!("{} is a part of the machine that transmits torque. It couples to {}, which carries the load on the shaft and bearing.", part, link)
+            };
+            PageGraph(HashMap::from([
+                ("Armature".to_string(), text("Armature", "Commutator")),
+                ("Commutator".to_string(), text("Commutator", "Armature")),
+            ]))
+        };
+        let request = SearchRequest {
+            term: "Armature".to_string(),
+            max_depth: Some(2),
+            document_source: DocumentSourceKind::LocalFolder,
+            concept_source: ConceptSource::ExtractRegex,
+            timeout_ms: Some(0),
+            ..Default::default()
+        };
+        let analyze = |seed: u64| {
+            let engine = WikiEngine::builder()
+                .with_cache(Arc::new(WikiEngineCache::new()))
+                .without_env_sources()
+                .with_source(Arc::new(pages()))
+                .with_concept_filter(ConceptFilterConfig { min_relevance: 1, ..Default::default() })
+                .deterministic(seed)
+                .build()
+                .unwrap();
+            let request = request.clone();
+            async move { serde_json::to_string(&engine.analyze_recursive(&request).await.unwrap()).unwrap() }
+        };
+
+        let first = analyze(7).await;
+        assert_eq!(first, analyze(7).await);
+        assert_ne!(first, analyze(8).await);
+
+        let result: AnalysisResult = serde_json::from_str(&first).unwrap();
+        assert_eq!(result.total_processing_time_ms, 0);
+        assert!(!result.deadline_exceeded);
+        assert!(!result.tree.children.is_empty());
+        assert!(result.tree.principles.iter().all(|p| uuid::Uuid::parse_str(&p.id).is_ok()));
+    }
+
+    proptest! {
+        // Every case builds two engines, which is slow in debug builds
+        #![proptest_config(ProptestConfig::with_cases(16))]
+
+        #[test]
+        fn recursion_respects_tree_invariants(pages in page_graph(), root in prop::sample::select(&PARTS[..]), max_depth in 1u8..4) {
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+            let request = SearchRequest {
+                term: root.to_string(),
+                max_depth: Some(max_depth),
+                max_results: Some(5),
+                document_source: DocumentSourceKind::LocalFolder,
+                concept_source: ConceptSource::ExtractRegex,
+                ..Default::default()
+            };
+            let cache = Arc::new(WikiEngineCache::new());
+            let engine = offline_engine(Arc::clone(&cache), Some(pages));
+
+            let result = runtime.block_on(engine.analyze_recursive(&request)).unwrap();
+            let principles = walk(&result.tree, 0, max_depth, &mut Vec::new());
+            prop_assert_eq!(result.total_principles, principles);
+            prop_assert!(result.max_depth_reached <= max_depth);
+            let tree = serde_json::to_value(&*result.tree).unwrap();
+
+            let cached = runtime.block_on(engine.analyze_recursive(&request)).unwrap();
+            prop_assert_eq!(serde_json::to_value(&*cached.tree).unwrap(), tree.clone());
+
+            // A persisted cache answers the same request without any documents to read
+            let snapshot: CacheSnapshot = serde_json::from_slice(&serde_json::to_vec(&cache.snapshot()).unwrap()).unwrap();
+            let restored_cache = Arc::new(WikiEngineCache::new());
+            restored_cache.restore(snapshot);
+            let restored = runtime.block_on(offline_engine(restored_cache, None).analyze_recursive(&request)).unwrap();
+            prop_assert_eq!(serde_json::to_value(&*restored.tree).unwrap(), tree);
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use crate::types::{AnalysisNode, AnalysisResult, DocumentSourceKind, EngineeringPrinciple, Result, WikipediaPage};
+use serde::Serialize;
+use std::future::Future;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+tokio::task_local! {
+    static ANALYSIS: Uuid;
+}
+
+/// Events a subscriber can fall behind by before it misses the oldest
+pub const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
+/// Something the engine did. Every event carries the id of the analysis it belongs to; work done
+/// outside one, such as refreshing stored results, has none.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EngineEvent {
+    /// A term's node began expanding
+    NodeStarted {
+        analysis_id: Option<Uuid>,
+        term: String,
+        depth: u8,
+    },
+    /// The page or document for a term was found, from the cache or its source
+    PageFetched {
+        analysis_id: Option<Uuid>,
+        term: String,
+        title: String,
+        url: String,
+        source: DocumentSourceKind,
+    },
+    /// The analyzers found a principle on a page; principles served from the cache aren't repeated
+    PrincipleExtracted {
+        analysis_id: Option<Uuid>,
+        page: String,
+        title: String,
+        category: String,
+        confidence: f32,
+    },
+    /// A term's node and everything below it finished
+    NodeCompleted {
+        analysis_id: Option<Uuid>,
+        term: String,
+        depth: u8,
+        principles: usize,
+        children: usize,
+        processing_time_ms: u64,
+    },
+    /// An analysis returned, from the cache or not; `error` is set when it failed and the
+    /// totals are then zero
+    AnalysisCompleted {
+        analysis_id: Option<Uuid>,
+        root_term: String,
+        total_principles: u32,
+        max_depth_reached: u8,
+        total_processing_time_ms: u64,
+        deadline_exceeded: bool,
+        error: Option<String>,
+    },
+}
+
+impl EngineEvent {
+    pub fn node_started(term: &str, depth: u8) -> Self {
+        Self::NodeStarted { analysis_id: current_analysis(), term: term.to_string(), depth }
+    }
+
+    pub fn page_fetched(term: &str, page: &WikipediaPage) -> Self {
+        Self::PageFetched {
+            analysis_id: current_analysis(),
+            term: term.to_string(),
+            title: page.title.clone(),
+            url: page.url.clone(),
+            source: page.source,
+        }
+    }
+
+    pub fn principle_extracted(page: &WikipediaPage, principle: &EngineeringPrinciple) -> Self {
+        Self::PrincipleExtracted {
+            analysis_id: current_analysis(),
+            page: page.title.clone(),
+            title: principle.title.clone(),
+            category: principle.category.name().to_string(),
+            confidence: principle.confidence,
+        }
+    }
+
+    pub fn node_completed(node: &AnalysisNode) -> Self {
+        Self::NodeCompleted {
+            analysis_id: current_analysis(),
+            term: node.term.to_string(),
+            depth: node.depth,
+            principles: node.principles.len(),
+            children: node.children.len(),
+            processing_time_ms: node.processing_time_ms,
+        }
+    }
+
+    pub fn analysis_completed(root_term: &str, result: &Result<AnalysisResult>) -> Self {
+        let analysis_id = current_analysis();
+        match result {
+            Ok(result) => Self::AnalysisCompleted {
+                analysis_id,
+                root_term: root_term.to_string(),
+                total_principles: result.total_principles,
+                max_depth_reached: result.max_depth_reached,
+                total_processing_time_ms: result.total_processing_time_ms,
+                deadline_exceeded: result.deadline_exceeded,
+                error: None,
+            },
+            Err(error) => Self::AnalysisCompleted {
+                analysis_id,
+                root_term: root_term.to_string(),
+                total_principles: 0,
+                max_depth_reached: 0,
+                total_processing_time_ms: 0,
+                deadline_exceeded: false,
+                error: Some(error.to_string()),
+            },
+        }
+    }
+
+    /// Name of the event, as in its `event` field
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::NodeStarted { .. } => "node_started",
+            Self::PageFetched { .. } => "page_fetched",
+            Self::PrincipleExtracted { .. } => "principle_extracted",
+            Self::NodeCompleted { .. } => "node_completed",
+            Self::AnalysisCompleted { .. } => "analysis_completed",
+        }
+    }
+
+    pub fn analysis_id(&self) -> Option<Uuid> {
+        match self {
+            Self::NodeStarted { analysis_id, .. }
+            | Self::PageFetched { analysis_id, .. }
+            | Self::PrincipleExtracted { analysis_id, .. }
+            | Self::NodeCompleted { analysis_id, .. }
+            | Self::AnalysisCompleted { analysis_id, .. } => *analysis_id,
+        }
+    }
+}
+
+/// Broadcasts the engine's events to any number of subscribers (plugins, SSE streams, metrics).
+/// Emitting never waits: with nobody subscribed an event is dropped, and a subscriber more than
+/// the capacity behind misses the oldest events instead of holding the engine up.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<EngineEvent>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    /// Receiver of every event emitted from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<EngineEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn subscribers(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    pub fn emit(&self, event: EngineEvent) {
+        let _ = self.sender.send(event);
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_CAPACITY)
+    }
+}
+
+/// Run `future` as the analysis `id`, tagging the events it causes with it
+pub async fn scope<F: Future>(id: Uuid, future: F) -> F::Output {
+    ANALYSIS.scope(id, future).await
+}
+
+/// Id of the analysis the current task is running, if any
+pub fn current_analysis() -> Option<Uuid> {
+    ANALYSIS.try_with(|id| *id).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn events_are_tagged_with_their_analysis() {
+        let bus = EventBus::new(4);
+        bus.emit(EngineEvent::node_started("Rotor", 1));
+        let mut events = bus.subscribe();
+
+        let id = Uuid::new_v4();
+        scope(id, async { bus.emit(EngineEvent::node_started("Stator", 1)) }).await;
+        bus.emit(EngineEvent::node_started("Bearing", 2));
+
+        let tagged = events.recv().await.unwrap();
+        assert_eq!(tagged, EngineEvent::NodeStarted { analysis_id: Some(id), term: "Stator".to_string(), depth: 1 });
+        assert_eq!(events.recv().await.unwrap().analysis_id(), None);
+
+        let json = serde_json::to_value(&tagged).unwrap();
+        assert_eq!(json["event"], "node_started");
+        assert_eq!(json["analysis_id"], id.to_string());
+    }
+}
@@ -0,0 +1,219 @@
+use crate::types::{AnalysisNode, AnalysisResult, EngineeringPrinciple};
+use serde::Serialize;
+
+/// Graphviz DOT digraph of the tree, one node per term labelled with its principle count
+pub fn to_dot(result: &AnalysisResult) -> String {
+    to_dot_combined(std::slice::from_ref(result))
+}
+
+/// Several trees in one digraph, each in its own cluster
+pub fn to_dot_combined(results: &[AnalysisResult]) -> String {
+    let mut dot = String::from("digraph tech_tree {\n    rankdir=LR;\n    node [shape=box];\n");
+    let mut next_id = 0;
+    for (i, result) in results.iter().enumerate() {
+        if results.len() > 1 {
+            dot.push_str(&format!("    subgraph cluster_{} {{\n    label=\"{}\";\n", i, escape_dot(&result.root_term)));
+        }
+        write_dot_node(&result.tree, None, &mut next_id, &mut dot);
+        if results.len() > 1 {
+            dot.push_str("    }\n");
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+fn write_dot_node(node: &AnalysisNode, parent: Option<usize>, next_id: &mut usize, dot: &mut String) {
+    // Terms can repeat across branches, so nodes are keyed by position rather than name
+    let id = *next_id;
+    *next_id += 1;
+
+    dot.push_str(&format!(
+        "    n{} [label=\"{}\\n{} principles\"];\n",
+        id,
+        escape_dot(&node.term),
+        node.principles.len()
+    ));
+    if let Some(parent) = parent {
+        dot.push_str(&format!("    n{} -> n{};\n", parent, id));
+    }
+    for child in &node.children {
+        write_dot_node(child, Some(id), next_id, dot);
+    }
+}
+
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// One CSV row per principle (or per term without principles), with the term's parent and depth
+pub fn to_csv(result: &AnalysisResult) -> String {
+    to_csv_combined(std::slice::from_ref(result))
+}
+
+/// Rows of several trees under a single header
+pub fn to_csv_combined(results: &[AnalysisResult]) -> String {
+    let mut csv = String::from("depth,parent,term,principle,category,confidence,source_url\n");
+    for result in results {
+        write_csv_node(&result.tree, "", &mut csv);
+    }
+    csv
+}
+
+fn write_csv_node(node: &AnalysisNode, parent: &str, csv: &mut String) {
+    let prefix = format!("{},{},{}", node.depth, escape_csv(parent), escape_csv(&node.term));
+    if node.principles.is_empty() {
+        csv.push_str(&format!("{},,,,\n", prefix));
+    }
+    for principle in &node.principles {
+        csv.push_str(&format!(
+            "{},{},{},{:.3},{}\n",
+            prefix,
+            escape_csv(&principle.title),
+            principle.category.name(),
+            principle.confidence,
+            escape_csv(&principle.source_url)
+        ));
+    }
+    for child in &node.children {
+        write_csv_node(child, &node.term, csv);
+    }
+}
+
+/// A principle with where in its tree it was found, as written on each NDJSON line
+#[derive(Debug, Serialize)]
+pub struct FlatPrinciple<'a> {
+    pub root_term: &'a str,
+    /// Node the principle belongs to
+    pub term: &'a str,
+    pub parent: Option<&'a str>,
+    pub depth: u8,
+    #[serde(flatten)]
+    pub principle: &'a EngineeringPrinciple,
+}
+
+/// Every principle of the tree, parents before their children
+pub fn flatten_principles(result: &AnalysisResult) -> Vec<FlatPrinciple<'_>> {
+    let mut principles = Vec::new();
+    collect_flat_principles(&result.root_term, &result.tree, None, &mut principles);
+    principles
+}
+
+fn collect_flat_principles<'a>(
+    root_term: &'a str,
+    node: &'a AnalysisNode,
+    parent: Option<&'a str>,
+    principles: &mut Vec<FlatPrinciple<'a>>,
+) {
+    principles.extend(node.principles.iter().map(|principle| FlatPrinciple {
+        root_term,
+        term: &node.term,
+        parent,
+        depth: node.depth,
+        principle,
+    }));
+    for child in &node.children {
+        collect_flat_principles(root_term, child, Some(&node.term), principles);
+    }
+}
+
+/// One JSON object per line for each principle of the tree (see `FlatPrinciple`)
+pub fn to_ndjson(result: &AnalysisResult) -> String {
+    to_ndjson_combined(std::slice::from_ref(result))
+}
+
+/// Lines of several trees, each tagged with its `root_term`
+pub fn to_ndjson_combined(results: &[AnalysisResult]) -> String {
+    let mut ndjson = String::new();
+    for principle in results.iter().flat_map(flatten_principles) {
+        ndjson.push_str(&serde_json::to_string(&principle).expect("principles serialize to JSON"));
+        ndjson.push('\n');
+    }
+    ndjson
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{EngineeringPrinciple, PrincipleCategory};
+
+    fn node(term: &str, depth: u8, children: Vec<AnalysisNode>) -> AnalysisNode {
+        AnalysisNode { children, ..AnalysisNode::leaf(term, depth) }
+    }
+
+    #[test]
+    fn exports_tree_as_dot_and_csv() {
+        let mut root = node("Electric motor", 0, vec![node("Stator", 1, vec![])]);
+        root.principles.push(EngineeringPrinciple {
+            id: "1".to_string(),
+            title: "Torque, from \"Lorentz force\"".to_string(),
+            description: String::new(),
+            category: PrincipleCategory::new("Electrical"),
+            confidence: 0.9,
+            source_url: "https://en.wikipedia.org/wiki/Electric_motor".to_string(),
+            related_terms: vec![],
+            source_span: (0, 0),
+            source_section: None,
+            appears_in: vec![],
+            classified_by: None,
+            named_law: None,
+            component: None,
+        });
+        let result = AnalysisResult::from_tree("Electric motor".to_string(), root, 0);
+
+        let dot = to_dot(&result);
+        assert!(dot.contains("n0 [label=\"Electric motor\\n1 principles\"]"));
+        assert!(dot.contains("n0 -> n1;"));
+
+        let csv = to_csv(&result);
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            rows[1],
+            "0,,Electric motor,\"Torque, from \"\"Lorentz force\"\"\",Electrical,0.900,https://en.wikipedia.org/wiki/Electric_motor"
+        );
+        assert_eq!(rows[2], "1,Electric motor,Stator,,,,");
+    }
+
+    #[test]
+    fn ndjson_has_a_line_per_principle() {
+        let principle = |title: &str| EngineeringPrinciple {
+            id: title.to_string(),
+            title: title.to_string(),
+            description: String::new(),
+            category: PrincipleCategory::new("Mechanical"),
+            confidence: 0.7,
+            source_url: String::new(),
+            related_terms: vec![],
+            source_span: (0, 0),
+            source_section: None,
+            appears_in: vec![],
+            classified_by: None,
+            named_law: None,
+            component: None,
+        };
+        let mut stator = node("Stator", 1, vec![]);
+        stator.principles = vec![principle("Flux"), principle("Cooling")];
+        let mut root = node("Electric motor", 0, vec![stator]);
+        root.principles.push(principle("Torque"));
+        let result = AnalysisResult::from_tree("Electric motor".to_string(), root, 0);
+
+        let lines: Vec<serde_json::Value> =
+            to_ndjson(&result).lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["title"], "Torque");
+        assert_eq!(lines[0]["parent"], serde_json::Value::Null);
+        assert_eq!(lines[2]["root_term"], "Electric motor");
+        assert_eq!(lines[2]["term"], "Stator");
+        assert_eq!(lines[2]["parent"], "Electric motor");
+        assert_eq!(lines[2]["depth"], 1);
+        assert_eq!(lines[2]["category"], "Mechanical");
+    }
+}
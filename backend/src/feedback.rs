@@ -0,0 +1,440 @@
+use crate::config::FeedbackPolicy;
+use crate::text;
+use crate::types::{AnalysisNode, AnalysisResult, EngineeringPrinciple};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use utoipa::{IntoParams, ToSchema};
+
+/// Entries kept in memory when the log isn't backed by a file; flags are counted from every entry
+const MAX_RECENT_ENTRIES: usize = 1000;
+
+/// Entries returned by a query unless it asks for fewer
+const DEFAULT_QUERY_LIMIT: usize = 100;
+
+/// Window `FeedbackPolicy::max_per_caller_per_hour` is counted over
+const SUBMISSION_WINDOW: Duration = Duration::from_secs(3600);
+
+/// What a user says is wrong with part of an analysis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedbackKind {
+    /// The principle misstates its page
+    WrongPrinciple,
+    /// The principle may be true but is beside the point of its page
+    IrrelevantPrinciple,
+    /// The child concept has nothing to do with its parent
+    OffTopicConcept,
+    /// The principle is right; tells threshold tuning what users accept, and changes nothing else
+    CorrectPrinciple,
+}
+
+impl FeedbackKind {
+    /// Whether the feedback is about a principle rather than a child concept
+    pub fn flags_principle(self) -> bool {
+        !matches!(self, FeedbackKind::OffTopicConcept)
+    }
+
+    /// Whether the feedback says something is wrong, counting towards penalties and exclusions
+    pub fn is_complaint(self) -> bool {
+        !matches!(self, FeedbackKind::CorrectPrinciple)
+    }
+}
+
+/// A flag on a principle or child concept of an analysis, as users submit it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Feedback {
+    pub kind: FeedbackKind,
+    /// Term of the node the principle or concept was found under, i.e. the page it came from
+    pub term: String,
+    /// Title (or id) of the principle, for `wrong_principle`, `irrelevant_principle` and `correct_principle`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub principle: Option<String>,
+    /// Term of the flagged child node, for `off_topic_concept`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub concept: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// Feedback as recorded, with who gave it and when
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FeedbackEntry {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    /// Tenant whose analyses the feedback applies to
+    pub tenant: String,
+    /// Client address; each caller counts once towards a principle's or concept's flags
+    pub caller: String,
+    /// Category of the principle when the feedback was given, if it was still cached
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Confidence of the principle when the feedback was given, if it was still cached
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f32>,
+    #[serde(flatten)]
+    pub feedback: Feedback,
+}
+
+impl FeedbackEntry {
+    pub fn new(feedback: Feedback, tenant: &str, caller: &str) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            tenant: tenant.to_string(),
+            caller: caller.to_string(),
+            category: None,
+            confidence: None,
+            feedback,
+        }
+    }
+
+    /// The entry with the category and confidence `principle`, the one the feedback is about, had
+    pub fn with_principle(mut self, principle: &EngineeringPrinciple) -> Self {
+        self.category = Some(principle.category.name().to_string());
+        self.confidence = Some(principle.confidence);
+        self
+    }
+}
+
+/// Which feedback entries to return, newest first
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+pub struct FeedbackQuery {
+    /// Only feedback on this page (case-insensitive)
+    pub term: Option<String>,
+    pub kind: Option<FeedbackKind>,
+    pub tenant: Option<String>,
+    /// Most entries to return (defaults to 100)
+    pub limit: Option<usize>,
+}
+
+impl FeedbackQuery {
+    fn matches(&self, entry: &FeedbackEntry) -> bool {
+        self.term.as_ref().is_none_or(|term| entry.feedback.term.eq_ignore_ascii_case(term))
+            && self.kind.is_none_or(|kind| entry.feedback.kind == kind)
+            && self.tenant.as_ref().is_none_or(|tenant| &entry.tenant == tenant)
+    }
+}
+
+/// Callers who flagged each principle (by case-folded title or id) and child concept of a page
+#[derive(Debug, Default)]
+struct PageFlags {
+    principles: HashMap<String, HashSet<String>>,
+    concepts: HashMap<String, HashSet<String>>,
+}
+
+/// Feedback each caller submitted in the current window
+#[derive(Debug)]
+struct Submissions {
+    started: Instant,
+    counts: HashMap<String, u32>,
+}
+
+impl Default for Submissions {
+    fn default() -> Self {
+        Self { started: Instant::now(), counts: HashMap::new() }
+    }
+}
+
+/// Feedback users gave on analyses, and what it does to later ones: a flagged principle loses
+/// confidence for each caller who flagged it, and principles and child concepts flagged by
+/// enough callers are left out (see `FeedbackPolicy`). Backed by a JSON Lines file the feedback
+/// survives restarts; otherwise only the latest entries can be listed, though all still count.
+#[derive(Debug, Default)]
+pub struct FeedbackLog {
+    recent: Mutex<VecDeque<FeedbackEntry>>,
+    file: Option<(PathBuf, Mutex<File>)>,
+    /// Flags by tenant and case-folded page term
+    flags: RwLock<HashMap<String, HashMap<String, PageFlags>>>,
+    /// Pages in `flags`, across every tenant; only changed under its write lock
+    flagged_pages: AtomicUsize,
+    submissions: Mutex<Submissions>,
+    policy: FeedbackPolicy,
+}
+
+impl FeedbackLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Log appending to the file at `WIKI_ENGINE_FEEDBACK_LOG`, if set, applying the policy the
+    /// environment configures. A file that can't be opened is logged and the log is kept in
+    /// memory instead.
+    pub fn from_env() -> Self {
+        let policy = FeedbackPolicy::from_env();
+        let Some(path) = std::env::var_os("WIKI_ENGINE_FEEDBACK_LOG").filter(|path| !path.is_empty()) else {
+            return Self::new().with_policy(policy);
+        };
+        let path = Path::new(&path);
+        let log = Self::with_file(path).unwrap_or_else(|error| {
+            tracing::warn!("Keeping feedback in memory, can't open {}: {}", path.display(), error);
+            Self::new()
+        });
+        log.with_policy(policy)
+    }
+
+    /// Log appending to `path`, creating it if needed; the feedback already in it counts
+    pub fn with_file(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let log = Self { file: Some((path.to_path_buf(), Mutex::new(file))), ..Self::default() };
+        for entry in Self::read(path)? {
+            log.count(&entry);
+        }
+        Ok(log)
+    }
+
+    pub fn with_policy(mut self, policy: FeedbackPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn policy(&self) -> &FeedbackPolicy {
+        &self.policy
+    }
+
+    /// Count a submission from `caller` against its hourly allowance, or how long until the
+    /// allowance renews when it is used up
+    pub fn throttle(&self, caller: &str) -> Result<(), Duration> {
+        let mut submissions = self.submissions.lock().unwrap();
+        let elapsed = submissions.started.elapsed();
+        if elapsed >= SUBMISSION_WINDOW {
+            *submissions = Submissions::default();
+        }
+        if let Some(count) = submissions.counts.get_mut(caller) {
+            if *count >= self.policy.max_per_caller_per_hour {
+                return Err(SUBMISSION_WINDOW.saturating_sub(elapsed));
+            }
+            *count += 1;
+        } else {
+            submissions.counts.insert(caller.to_string(), 1);
+        }
+        Ok(())
+    }
+
+    pub fn record(&self, entry: FeedbackEntry) {
+        self.count(&entry);
+        if let Some((path, file)) = &self.file {
+            let mut line = serde_json::to_vec(&entry).expect("feedback entries serialize to JSON");
+            line.push(b'\n');
+            if let Err(error) = file.lock().unwrap().write_all(&line) {
+                tracing::warn!("Failed to append to the feedback log {}: {}", path.display(), error);
+            }
+            return;
+        }
+
+        let mut recent = self.recent.lock().unwrap();
+        if recent.len() >= MAX_RECENT_ENTRIES {
+            recent.pop_front();
+        }
+        recent.push_back(entry);
+    }
+
+    /// Entries matching `query`, newest first
+    pub fn query(&self, query: &FeedbackQuery) -> io::Result<Vec<FeedbackEntry>> {
+        let limit = query.limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+        Ok(self.entries()?.into_iter().rev().filter(|entry| query.matches(entry)).take(limit).collect())
+    }
+
+    /// Every entry that can be listed, oldest first
+    pub fn entries(&self) -> io::Result<Vec<FeedbackEntry>> {
+        match &self.file {
+            Some((path, _)) => Self::read(path),
+            None => Ok(self.recent.lock().unwrap().iter().cloned().collect()),
+        }
+    }
+
+    /// Whether enough of `tenant`'s callers flagged `concept` as off-topic under `parent` for it
+    /// not to be expanded there
+    pub fn excludes_concept(&self, tenant: &str, parent: &str, concept: &str) -> bool {
+        let flags = self.flags.read().unwrap();
+        flags
+            .get(tenant)
+            .and_then(|pages| pages.get(&text::fold_case(parent)))
+            .and_then(|page| page.concepts.get(&text::fold_case(concept)))
+            .is_some_and(|callers| self.is_excluded(callers))
+    }
+
+    /// `result` with `tenant`'s feedback applied to every node whose page was flagged:
+    /// principles lose confidence per flag, and are dropped once below `min_confidence` or
+    /// flagged by enough callers, as are child nodes flagged as off-topic
+    pub fn apply(&self, tenant: &str, mut result: AnalysisResult, min_confidence: f32) -> AnalysisResult {
+        let flags = self.flags.read().unwrap();
+        let Some(pages) = flags.get(tenant).filter(|pages| Self::touches(pages, &result.tree)) else {
+            return result;
+        };
+        self.adjust(pages, Arc::make_mut(&mut result.tree), min_confidence);
+        result.recounted()
+    }
+
+    fn touches(pages: &HashMap<String, PageFlags>, node: &AnalysisNode) -> bool {
+        pages.contains_key(&text::fold_case(&node.term)) || node.children.iter().any(|child| Self::touches(pages, child))
+    }
+
+    fn adjust(&self, pages: &HashMap<String, PageFlags>, node: &mut AnalysisNode, min_confidence: f32) {
+        if let Some(page) = pages.get(&text::fold_case(&node.term)) {
+            node.principles.retain_mut(|principle| {
+                let callers = [&principle.title, &principle.id]
+                    .into_iter()
+                    .filter_map(|key| page.principles.get(&text::fold_case(key)))
+                    .max_by_key(|callers| callers.len());
+                let Some(callers) = callers else { return true };
+                if self.is_excluded(callers) {
+                    return false;
+                }
+                principle.confidence = (principle.confidence - self.policy.penalty_per_flag * callers.len() as f32).max(0.0);
+                principle.confidence > 0.0 && principle.confidence >= min_confidence
+            });
+            node.principles.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+            node.children.retain(|child| {
+                page.concepts.get(&text::fold_case(&child.term)).is_none_or(|callers| !self.is_excluded(callers))
+            });
+        }
+        for child in &mut node.children {
+            self.adjust(pages, child, min_confidence);
+        }
+    }
+
+    fn is_excluded(&self, callers: &HashSet<String>) -> bool {
+        callers.len() as u32 >= self.policy.exclude_after
+    }
+
+    fn count(&self, entry: &FeedbackEntry) {
+        let feedback = &entry.feedback;
+        if !feedback.kind.is_complaint() {
+            return;
+        }
+        let target = match feedback.kind.flags_principle() {
+            true => feedback.principle.as_deref(),
+            false => feedback.concept.as_deref(),
+        };
+        let Some(target) = target else { return };
+
+        let term = text::fold_case(&feedback.term);
+        let mut flags = self.flags.write().unwrap();
+        if !flags.get(&entry.tenant).is_some_and(|pages| pages.contains_key(&term)) {
+            if self.flagged_pages.load(Ordering::Relaxed) >= self.policy.max_flagged_pages {
+                tracing::warn!("Not counting feedback on '{}': flags are kept for {} pages at most", feedback.term, self.policy.max_flagged_pages);
+                return;
+            }
+            self.flagged_pages.fetch_add(1, Ordering::Relaxed);
+        }
+        let page = flags.entry(entry.tenant.clone()).or_default().entry(term).or_default();
+        let targets = match feedback.kind.flags_principle() {
+            true => &mut page.principles,
+            false => &mut page.concepts,
+        };
+        targets.entry(text::fold_case(target)).or_default().insert(entry.caller.clone());
+    }
+
+    /// Every entry in the file at `path`, oldest first; lines that don't parse are skipped
+    fn read(path: &Path) -> io::Result<Vec<FeedbackEntry>> {
+        let mut entries = Vec::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            if let Ok(entry) = serde_json::from_str(&line?) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{EngineeringPrinciple, PrincipleCategory};
+
+    fn node(term: &str, principles: &[(&str, f32)], children: Vec<AnalysisNode>) -> AnalysisNode {
+        let principles = principles
+            .iter()
+            .map(|(title, confidence)| EngineeringPrinciple {
+                id: uuid::Uuid::new_v4().to_string(),
+                title: title.to_string(),
+                description: format!("{} carries the load of the deck", title),
+                category: PrincipleCategory::new("Mechanical"),
+                confidence: *confidence,
+                source_url: String::new(),
+                related_terms: vec![],
+                source_span: (0, 0),
+                source_section: None,
+                appears_in: vec![],
+                classified_by: None,
+                named_law: None,
+                component: None,
+            })
+            .collect();
+        AnalysisNode { principles, children, ..AnalysisNode::leaf(term, 0) }
+    }
+
+    fn flag(log: &FeedbackLog, kind: FeedbackKind, target: &str, caller: &str) {
+        let (principle, concept) = match kind.flags_principle() {
+            true => (Some(target.to_string()), None),
+            false => (None, Some(target.to_string())),
+        };
+        let feedback = Feedback { kind, term: "Bridge".to_string(), principle, concept, comment: None };
+        log.record(FeedbackEntry::new(feedback, "default", caller));
+    }
+
+    #[test]
+    fn flags_penalize_then_exclude() {
+        let path = std::env::temp_dir().join(format!("feedback-{}.jsonl", uuid::Uuid::new_v4()));
+        let policy = FeedbackPolicy { penalty_per_flag: 0.2, exclude_after: 2, ..FeedbackPolicy::default() };
+        let log = FeedbackLog::with_file(&path).unwrap().with_policy(policy.clone());
+        flag(&log, FeedbackKind::WrongPrinciple, "load paths", "10.0.0.1");
+        flag(&log, FeedbackKind::WrongPrinciple, "load paths", "10.0.0.1");
+        flag(&log, FeedbackKind::IrrelevantPrinciple, "Toll Plazas", "10.0.0.1");
+        flag(&log, FeedbackKind::IrrelevantPrinciple, "toll plazas", "10.0.0.2");
+        flag(&log, FeedbackKind::OffTopicConcept, "river", "10.0.0.1");
+        flag(&log, FeedbackKind::OffTopicConcept, "River", "10.0.0.3");
+        drop(log);
+
+        let log = FeedbackLog::with_file(&path).unwrap().with_policy(policy);
+        let tree = node(
+            "bridge",
+            &[("Load paths", 0.9), ("Toll plazas", 0.8), ("Truss", 0.75)],
+            vec![node("river", &[], vec![]), node("truss", &[], vec![])],
+        );
+        let result = log.apply("default", AnalysisResult::from_tree("bridge".to_string(), tree, 0), 0.0);
+        let titles: Vec<_> = result.tree.principles.iter().map(|principle| principle.title.as_str()).collect();
+        assert_eq!(titles, ["Truss", "Load paths"]);
+        assert!((result.tree.principles[1].confidence - 0.7).abs() < 1e-6);
+        assert_eq!(result.total_principles, 2);
+        assert_eq!(result.tree.children.len(), 1);
+        assert!(log.excludes_concept("default", "BRIDGE", "river"));
+        assert!(!log.excludes_concept("team-a", "bridge", "river"));
+
+        let entries = log.query(&FeedbackQuery { kind: Some(FeedbackKind::OffTopicConcept), ..Default::default() }).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].caller, "10.0.0.3");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn feedback_is_throttled_per_caller_and_flags_bounded() {
+        let policy = FeedbackPolicy { exclude_after: 1, max_per_caller_per_hour: 2, max_flagged_pages: 1, ..FeedbackPolicy::default() };
+        let log = FeedbackLog::new().with_policy(policy);
+        assert!(log.throttle("10.0.0.1").is_ok());
+        assert!(log.throttle("10.0.0.1").is_ok());
+        let retry_after = log.throttle("10.0.0.1").unwrap_err();
+        assert!(retry_after > Duration::ZERO && retry_after <= SUBMISSION_WINDOW);
+        assert!(log.throttle("10.0.0.2").is_ok());
+
+        flag(&log, FeedbackKind::OffTopicConcept, "river", "10.0.0.1");
+        let feedback = Feedback {
+            kind: FeedbackKind::OffTopicConcept,
+            term: "Viaduct".to_string(),
+            principle: None,
+            concept: Some("river".to_string()),
+            comment: None,
+        };
+        log.record(FeedbackEntry::new(feedback, "default", "10.0.0.1"));
+        assert!(log.excludes_concept("default", "bridge", "river"));
+        assert!(!log.excludes_concept("default", "viaduct", "river"));
+        assert_eq!(log.entries().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::types::{FieldError, Result, WikiEngineError};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Field of `AnalysisResult` holding the tree
+const TREE_FIELD: &str = "tree";
+
+/// Field of `AnalysisNode` holding its child nodes
+const CHILDREN_FIELD: &str = "children";
+
+/// Fields of a serialized `AnalysisResult`; paths starting with anything else are taken to be
+/// relative to the tree's nodes
+const RESULT_FIELDS: &[&str] = &[
+    "root_term",
+    TREE_FIELD,
+    "total_processing_time_ms",
+    "total_principles",
+    "max_depth_reached",
+    "warnings",
+    "result_id",
+    "kb_version",
+    "network_stats",
+    "deadline_exceeded",
+    "category_summary",
+    "glossary",
+];
+
+/// The parts of an analysis result a client asked for, parsed from a comma-separated list of
+/// dot-separated paths such as `tree.term,tree.children,principles.title`.
+///
+/// Arrays are transparent: a path applies to each of their elements. `children` without
+/// subfields keeps the child nodes with the same fields as their parent, so `tree.term,tree.children`
+/// is the skeleton of the whole tree.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldSelection {
+    /// Keep the value as it is
+    all: bool,
+    fields: BTreeMap<String, FieldSelection>,
+}
+
+impl FieldSelection {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut selection = Self::default();
+        let mut errors = Vec::new();
+        for path in spec.split(',').map(str::trim).filter(|path| !path.is_empty()) {
+            let mut segments: Vec<&str> = path.split('.').map(str::trim).collect();
+            if segments.iter().any(|segment| segment.is_empty()) {
+                errors.push(FieldError::new("fields", format!("'{}' has an empty path segment", path)));
+                continue;
+            }
+            if !RESULT_FIELDS.contains(&segments[0]) {
+                segments.insert(0, TREE_FIELD);
+            }
+
+            let mut current = &mut selection;
+            for segment in segments {
+                if current.all {
+                    break;
+                }
+                current = current.fields.entry(segment.to_string()).or_default();
+            }
+            // A whole field covers any of its subfields listed alongside it
+            current.all = true;
+            current.fields.clear();
+        }
+
+        if errors.is_empty() && selection.fields.is_empty() {
+            errors.push(FieldError::new("fields", "must name at least one field"));
+        }
+        if errors.is_empty() {
+            Ok(selection)
+        } else {
+            Err(WikiEngineError::InvalidRequest(errors))
+        }
+    }
+
+    /// `result`, a serialized `AnalysisResult`, with only the selected fields
+    pub fn apply(&self, result: Value) -> Value {
+        self.filter(result, false)
+    }
+
+    fn filter(&self, value: Value, is_node: bool) -> Value {
+        if self.all {
+            return value;
+        }
+        match value {
+            Value::Array(items) => Value::Array(items.into_iter().map(|item| self.filter(item, is_node)).collect()),
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .filter_map(|(key, value)| {
+                        let selection = self.fields.get(&key)?;
+                        let value = match key.as_str() {
+                            CHILDREN_FIELD if is_node && selection.all => self.filter(value, true),
+                            CHILDREN_FIELD if is_node => selection.filter(value, true),
+                            TREE_FIELD if !is_node => selection.filter(value, true),
+                            _ => selection.filter(value, false),
+                        };
+                        Some((key, value))
+                    })
+                    .collect(),
+            ),
+            value => value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn skeleton_selection_recurses_through_children() {
+        let result = json!({
+            "root_term": "motor",
+            "total_principles": 3,
+            "tree": {
+                "term": "motor",
+                "depth": 0,
+                "principles": [{"title": "Torque", "description": "Long text"}],
+                "children": [{
+                    "term": "rotor",
+                    "depth": 1,
+                    "principles": [{"title": "Inertia", "description": "More text"}],
+                    "children": [{"term": "shaft", "depth": 2, "principles": [], "children": []}]
+                }]
+            }
+        });
+
+        let selection = FieldSelection::parse("tree.term, tree.children,principles.title,total_principles").unwrap();
+        assert_eq!(
+            selection.apply(result.clone()),
+            json!({
+                "total_principles": 3,
+                "tree": {
+                    "term": "motor",
+                    "principles": [{"title": "Torque"}],
+                    "children": [{
+                        "term": "rotor",
+                        "principles": [{"title": "Inertia"}],
+                        "children": [{"term": "shaft", "principles": [], "children": []}]
+                    }]
+                }
+            })
+        );
+
+        let selection = FieldSelection::parse("tree.children.term,tree,tree.term").unwrap();
+        assert_eq!(selection.apply(result.clone())["tree"], result["tree"]);
+        let selection = FieldSelection::parse("tree.term,tree.children.depth").unwrap();
+        assert_eq!(selection.apply(result.clone())["tree"]["children"], json!([{"depth": 1}]));
+    }
+
+    #[test]
+    fn malformed_selections_are_rejected() {
+        assert!(matches!(FieldSelection::parse("tree..term"), Err(WikiEngineError::InvalidRequest(_))));
+        assert!(matches!(FieldSelection::parse(" , "), Err(WikiEngineError::InvalidRequest(_))));
+    }
+}
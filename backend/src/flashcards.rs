@@ -0,0 +1,127 @@
+use crate::intern::Term;
+use crate::types::{AnalysisNode, AnalysisResult, EngineeringPrinciple, PrincipleCategory};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+const CLOZE_BLANK: &str = "_____";
+
+/// A cloze-deletion card: a principle's description with one key term blanked out
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Flashcard {
+    pub question: String,
+    /// The term that was blanked out
+    pub answer: String,
+    /// Node the principle belongs to
+    pub term: String,
+    pub category: PrincipleCategory,
+    pub source_url: String,
+}
+
+/// How flashcards are returned
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FlashcardFormat {
+    #[default]
+    Json,
+    /// Tab-separated front/back/tags rows that Anki imports directly
+    Tsv,
+}
+
+/// One card per principle of the tree that mentions one of its related terms (or its node's
+/// term) in its description
+pub fn flashcards(result: &AnalysisResult) -> Vec<Flashcard> {
+    let mut cards = Vec::new();
+    collect_cards(&result.tree, &mut cards);
+    cards
+}
+
+fn collect_cards(node: &AnalysisNode, cards: &mut Vec<Flashcard>) {
+    cards.extend(node.principles.iter().filter_map(|principle| cloze_card(&node.term, principle)));
+    for child in &node.children {
+        collect_cards(child, cards);
+    }
+}
+
+/// Blank out the first related term (falling back to the node's term) found in the description
+fn cloze_card(term: &str, principle: &EngineeringPrinciple) -> Option<Flashcard> {
+    principle
+        .related_terms
+        .iter()
+        .map(Term::as_str)
+        .chain(std::iter::once(term))
+        .filter(|answer| answer.chars().count() > 2)
+        .find_map(|answer| {
+            let pattern = whole_word(answer)?;
+            pattern.is_match(&principle.description).then(|| Flashcard {
+                question: pattern.replace_all(&principle.description, CLOZE_BLANK).into_owned(),
+                answer: answer.to_string(),
+                term: term.to_string(),
+                category: principle.category.clone(),
+                source_url: principle.source_url.clone(),
+            })
+        })
+}
+
+fn whole_word(text: &str) -> Option<Regex> {
+    RegexBuilder::new(&format!(r"\b{}\b", regex::escape(text)))
+        .case_insensitive(true)
+        .build()
+        .ok()
+}
+
+/// Anki-compatible TSV: question, answer with a link to the source, and tags (node term and
+/// category, with spaces replaced since Anki separates tags by spaces)
+pub fn to_anki_tsv(cards: &[Flashcard]) -> String {
+    let mut tsv = String::from("#separator:tab\n#html:true\n#tags column:3\n");
+    for card in cards {
+        let tags = [card.term.as_str(), card.category.name()]
+            .map(|tag| tag.replace(char::is_whitespace, "_"))
+            .join(" ");
+        tsv.push_str(&format!(
+            "{}\t{}<br><a href=\"{}\">source</a>\t{}\n",
+            escape_tsv(&card.question),
+            escape_tsv(&card.answer),
+            escape_tsv(&card.source_url),
+            escape_tsv(&tags)
+        ));
+    }
+    tsv
+}
+
+fn escape_tsv(field: &str) -> String {
+    field.replace(['\t', '\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blanks_out_a_related_term() {
+        let principle = EngineeringPrinciple {
+            id: "1".to_string(),
+            title: "Lorentz force".to_string(),
+            description: "The Stator field exerts torque on the rotor.\tIt follows the Lorentz force law".to_string(),
+            category: PrincipleCategory::new("Electrical"),
+            confidence: 0.9,
+            source_url: "https://en.wikipedia.org/wiki/Electric_motor".to_string(),
+            related_terms: vec!["XY".into(), "stator".into()],
+            source_span: (0, 0),
+            source_section: None,
+            appears_in: vec![],
+            classified_by: None,
+            named_law: None,
+            component: None,
+        };
+
+        let card = cloze_card("Electric motor", &principle).unwrap();
+        assert_eq!(card.answer, "stator");
+        assert!(card.question.starts_with("The _____ field exerts torque"));
+
+        let tsv = to_anki_tsv(&[card]);
+        let row = tsv.lines().last().unwrap();
+        assert_eq!(row.split('\t').count(), 3);
+        assert!(row.ends_with("\tElectric_motor Electrical"));
+    }
+}
@@ -0,0 +1,137 @@
+use crate::WikiEngine;
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use utoipa::ToSchema;
+
+/// Status of a single dependency or of the service as a whole
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Ok,
+    Degraded,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ComponentHealth {
+    pub name: String,
+    pub status: HealthStatus,
+    pub detail: Option<String>,
+    pub latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ReadinessReport {
+    pub status: HealthStatus,
+    pub components: Vec<ComponentHealth>,
+    pub checked_at: String,
+}
+
+/// Runs readiness checks, caching the Wikipedia probe so frequent polling
+/// doesn't translate into a request to Wikipedia every time
+pub struct HealthChecker {
+    wikipedia_probe: Mutex<Option<(Instant, ComponentHealth)>>,
+    probe_ttl: Duration,
+    slow_probe_threshold: Duration,
+}
+
+impl HealthChecker {
+    pub fn new() -> Self {
+        Self::with_probe_ttl(Duration::from_secs(30))
+    }
+
+    pub fn with_probe_ttl(probe_ttl: Duration) -> Self {
+        Self {
+            wikipedia_probe: Mutex::new(None),
+            probe_ttl,
+            slow_probe_threshold: Duration::from_secs(2),
+        }
+    }
+
+    pub async fn readiness(&self, engine: &WikiEngine) -> ReadinessReport {
+        let components = vec![
+            self.check_wikipedia(engine).await,
+            Self::check_embedding_model(engine),
+            Self::check_cache(engine),
+        ];
+
+        let status = components
+            .iter()
+            .map(|component| component.status)
+            .max()
+            .unwrap_or(HealthStatus::Ok);
+
+        ReadinessReport {
+            status,
+            components,
+            checked_at: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    async fn check_wikipedia(&self, engine: &WikiEngine) -> ComponentHealth {
+        let mut cached = self.wikipedia_probe.lock().await;
+        if let Some((checked_at, health)) = cached.as_ref() {
+            if checked_at.elapsed() < self.probe_ttl {
+                return health.clone();
+            }
+        }
+
+        let start = Instant::now();
+        let result = engine.probe_wikipedia().await;
+        let latency = start.elapsed();
+
+        let (status, detail) = match result {
+            Ok(()) if latency > self.slow_probe_threshold => {
+                (HealthStatus::Degraded, Some("Wikipedia API responding slowly".to_string()))
+            }
+            Ok(()) => (HealthStatus::Ok, None),
+            Err(e) => (HealthStatus::Down, Some(e.to_string())),
+        };
+
+        let health = ComponentHealth {
+            name: "wikipedia_api".to_string(),
+            status,
+            detail,
+            latency_ms: Some(latency.as_millis() as u64),
+        };
+        *cached = Some((Instant::now(), health.clone()));
+        health
+    }
+
+    fn check_embedding_model(engine: &WikiEngine) -> ComponentHealth {
+        // Without the model the semantic analyzer falls back to its knowledge base
+        let (status, detail) = if engine.embedding_model_loaded() {
+            (HealthStatus::Ok, None)
+        } else {
+            (HealthStatus::Degraded, Some("ONNX model not loaded, using knowledge-based fallback".to_string()))
+        };
+
+        ComponentHealth {
+            name: "embedding_model".to_string(),
+            status,
+            detail,
+            latency_ms: None,
+        }
+    }
+
+    fn check_cache(engine: &WikiEngine) -> ComponentHealth {
+        let stats = engine.get_cache_reference().get_cache_stats();
+
+        ComponentHealth {
+            name: "cache".to_string(),
+            status: HealthStatus::Ok,
+            detail: Some(format!(
+                "{} pages, {} principle sets, {} analysis nodes",
+                stats.wikipedia_pages_count, stats.principles_count, stats.analysis_nodes_count
+            )),
+            latency_ms: None,
+        }
+    }
+}
+
+impl Default for HealthChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
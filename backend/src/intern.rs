@@ -0,0 +1,185 @@
+use dashmap::DashSet;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, LazyLock};
+
+/// Interned terms are swept of unused entries once the table grows past this size, and after
+/// that whenever it has doubled since the last sweep
+const MIN_SWEEP_SIZE: usize = 1024;
+
+static INTERNER: LazyLock<Interner> = LazyLock::new(|| Interner {
+    terms: DashSet::new(),
+    sweep_at: AtomicUsize::new(MIN_SWEEP_SIZE),
+});
+
+struct Interner {
+    terms: DashSet<Arc<str>>,
+    sweep_at: AtomicUsize,
+}
+
+impl Interner {
+    fn intern(&self, text: &str) -> Arc<str> {
+        if let Some(term) = self.terms.get(text) {
+            return Arc::clone(&term);
+        }
+        let term: Arc<str> = Arc::from(text);
+        if !self.terms.insert(Arc::clone(&term)) {
+            // Another thread interned it in the meantime
+            if let Some(existing) = self.terms.get(text) {
+                return Arc::clone(&existing);
+            }
+        }
+
+        if self.terms.len() > self.sweep_at.load(Ordering::Relaxed) {
+            // Terms only the table still holds are no longer part of any tree
+            self.terms.retain(|term| Arc::strong_count(term) > 1);
+            self.sweep_at.store((self.terms.len() * 2).max(MIN_SWEEP_SIZE), Ordering::Relaxed);
+        }
+        term
+    }
+}
+
+/// A concept or page title shared by every place it occurs in the trees held in memory: the same
+/// titles recur across nodes, principles and provenance, and cloning a `Term` is a reference
+/// count bump rather than a string copy. Serializes as a plain string.
+#[derive(Clone)]
+pub struct Term(Arc<str>);
+
+impl Term {
+    pub fn new(text: &str) -> Self {
+        Self(INTERNER.intern(text))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Term {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Term {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Term {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Term {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
+impl From<String> for Term {
+    fn from(text: String) -> Self {
+        Self::new(&text)
+    }
+}
+
+impl From<Term> for String {
+    fn from(term: Term) -> Self {
+        term.0.to_string()
+    }
+}
+
+impl PartialEq for Term {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+
+impl Eq for Term {}
+
+impl PartialEq<str> for Term {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Term {
+    fn eq(&self, other: &&str) -> bool {
+        &*self.0 == *other
+    }
+}
+
+impl PartialEq<String> for Term {
+    fn eq(&self, other: &String) -> bool {
+        *self.0 == **other
+    }
+}
+
+impl Hash for Term {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl PartialOrd for Term {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Term {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl fmt::Debug for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for Term {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Term {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        Ok(Self::new(&text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equal_terms_share_one_allocation() {
+        let first = Term::new("Electric motor");
+        let second = Term::from("Electric motor".to_string());
+        assert!(Arc::ptr_eq(&first.0, &second.0));
+        assert_eq!(first, "Electric motor");
+
+        let json = serde_json::to_string(&first).unwrap();
+        assert_eq!(json, "\"Electric motor\"");
+        let restored: Term = serde_json::from_str(&json).unwrap();
+        assert!(Arc::ptr_eq(&first.0, &restored.0));
+    }
+}
@@ -0,0 +1,229 @@
+use crate::semantic_analyzer::{ComponentRelation, ConceptKnowledgeBase};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use utoipa::ToSchema;
+
+/// Names added to and removed from one concept's list, its components or its synonyms
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ListChange {
+    pub concept: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// A relation between two components listed under `concept` that was added (no `before`),
+/// removed (no `after`) or changed in type, direction or confidence
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct RelationChange {
+    pub concept: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<ComponentRelation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<ComponentRelation>,
+}
+
+/// A concept whose category mapping was added, removed or changed
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct CategoryChange {
+    pub concept: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// How one knowledge base snapshot differs from another, for reviewing an edit before it is
+/// deployed. Every list is sorted by concept. `Display` renders it as one line per change.
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct KnowledgeBaseDiff {
+    /// `kb_version` of the older snapshot
+    pub from_version: String,
+    /// `kb_version` of the newer snapshot
+    pub to_version: String,
+    /// Concepts only the newer snapshot decomposes
+    pub added_concepts: Vec<String>,
+    /// Concepts only the older snapshot decomposes
+    pub removed_concepts: Vec<String>,
+    /// Concepts both decompose, into different components
+    pub components: Vec<ListChange>,
+    pub relations: Vec<RelationChange>,
+    pub categories: Vec<CategoryChange>,
+    pub synonyms: Vec<ListChange>,
+}
+
+impl KnowledgeBaseDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_concepts.is_empty()
+            && self.removed_concepts.is_empty()
+            && self.components.is_empty()
+            && self.relations.is_empty()
+            && self.categories.is_empty()
+            && self.synonyms.is_empty()
+    }
+}
+
+/// How `after` differs from `before`
+pub fn diff(before: &ConceptKnowledgeBase, after: &ConceptKnowledgeBase) -> KnowledgeBaseDiff {
+    let before_concepts: BTreeSet<&String> = before.concept_hierarchies.keys().collect();
+    let after_concepts: BTreeSet<&String> = after.concept_hierarchies.keys().collect();
+    let components = before_concepts
+        .intersection(&after_concepts)
+        .filter_map(|concept| list_change(concept, &before.concept_hierarchies[*concept], &after.concept_hierarchies[*concept]))
+        .collect();
+
+    let relations = sorted_union(&before.component_relationships, &after.component_relationships)
+        .flat_map(|concept| {
+            let before = relations_by_pair(before.component_relationships.get(concept));
+            let after = relations_by_pair(after.component_relationships.get(concept));
+            let pairs: BTreeSet<_> = before.keys().chain(after.keys()).cloned().collect();
+            pairs
+                .into_iter()
+                .filter_map(|pair| {
+                    let (before, after) = (before.get(&pair).copied(), after.get(&pair).copied());
+                    let unchanged = match (before, after) {
+                        (Some(before), Some(after)) => {
+                            before.subject == after.subject
+                                && before.relation_type == after.relation_type
+                                && before.confidence == after.confidence
+                        }
+                        _ => false,
+                    };
+                    (!unchanged).then(|| RelationChange { concept: concept.clone(), before: before.cloned(), after: after.cloned() })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let categories = sorted_union(&before.category_mappings, &after.category_mappings)
+        .filter_map(|concept| {
+            let before = before.category_mappings.get(concept).map(|category| category.name().to_string());
+            let after = after.category_mappings.get(concept).map(|category| category.name().to_string());
+            (before != after).then(|| CategoryChange { concept: concept.clone(), before, after })
+        })
+        .collect();
+
+    let no_synonyms = Vec::new();
+    let synonyms = sorted_union(&before.synonyms, &after.synonyms)
+        .filter_map(|concept| {
+            let before = before.synonyms.get(concept).unwrap_or(&no_synonyms);
+            list_change(concept, before, after.synonyms.get(concept).unwrap_or(&no_synonyms))
+        })
+        .collect();
+
+    KnowledgeBaseDiff {
+        from_version: before.version(),
+        to_version: after.version(),
+        added_concepts: after_concepts.difference(&before_concepts).map(|concept| concept.to_string()).collect(),
+        removed_concepts: before_concepts.difference(&after_concepts).map(|concept| concept.to_string()).collect(),
+        components,
+        relations,
+        categories,
+        synonyms,
+    }
+}
+
+/// The keys of both maps, sorted and without repeats
+fn sorted_union<'a, V>(before: &'a HashMap<String, V>, after: &'a HashMap<String, V>) -> impl Iterator<Item = &'a String> {
+    before.keys().chain(after.keys()).collect::<BTreeSet<_>>().into_iter()
+}
+
+/// Relations keyed by the two components they connect, whichever way round
+fn relations_by_pair(relations: Option<&Vec<ComponentRelation>>) -> BTreeMap<(String, String), &ComponentRelation> {
+    relations
+        .into_iter()
+        .flatten()
+        .map(|relation| {
+            let mut pair = [relation.subject.clone(), relation.component.clone()];
+            pair.sort();
+            let [first, second] = pair;
+            ((first, second), relation)
+        })
+        .collect()
+}
+
+fn list_change(concept: &str, before: &[String], after: &[String]) -> Option<ListChange> {
+    let added: Vec<String> = after.iter().filter(|name| !before.contains(name)).cloned().collect();
+    let removed: Vec<String> = before.iter().filter(|name| !after.contains(name)).cloned().collect();
+    (!added.is_empty() || !removed.is_empty()).then(|| ListChange { concept: concept.to_string(), added, removed })
+}
+
+fn describe(relation: &ComponentRelation) -> String {
+    let relation_type = relation.relation_type.name().replace('_', " ");
+    format!("{} {} {} ({:.2})", relation.subject, relation_type, relation.component, relation.confidence)
+}
+
+fn describe_list(change: &ListChange) -> String {
+    let added = change.added.iter().map(|name| format!("+{}", name));
+    let removed = change.removed.iter().map(|name| format!("-{}", name));
+    added.chain(removed).collect::<Vec<_>>().join(" ")
+}
+
+impl fmt::Display for KnowledgeBaseDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Knowledge base {} -> {}", self.from_version, self.to_version)?;
+        if self.is_empty() {
+            return writeln!(f, "No differences");
+        }
+        for concept in &self.added_concepts {
+            writeln!(f, "+ concept {}", concept)?;
+        }
+        for concept in &self.removed_concepts {
+            writeln!(f, "- concept {}", concept)?;
+        }
+        for change in &self.components {
+            writeln!(f, "~ components of {}: {}", change.concept, describe_list(change))?;
+        }
+        for change in &self.relations {
+            match (&change.before, &change.after) {
+                (None, Some(after)) => writeln!(f, "+ relation under {}: {}", change.concept, describe(after))?,
+                (Some(before), None) => writeln!(f, "- relation under {}: {}", change.concept, describe(before))?,
+                (Some(before), Some(after)) => {
+                    writeln!(f, "~ relation under {}: {} => {}", change.concept, describe(before), describe(after))?
+                }
+                (None, None) => {}
+            }
+        }
+        for change in &self.categories {
+            let before = change.before.as_deref().unwrap_or("(none)");
+            let after = change.after.as_deref().unwrap_or("(none)");
+            writeln!(f, "~ category of {}: {} => {}", change.concept, before, after)?;
+        }
+        for change in &self.synonyms {
+            writeln!(f, "~ synonyms of {}: {}", change.concept, describe_list(change))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::semantic_analyzer::RelationType;
+    use crate::types::PrincipleCategory;
+
+    #[test]
+    fn edits_show_up_in_the_diff() {
+        let before = ConceptKnowledgeBase::built_in();
+        assert!(diff(&before, &before).is_empty());
+
+        let mut after = before.clone();
+        after.concept_hierarchies.insert("heat pump".to_string(), vec!["compressor".to_string()]);
+        after.concept_hierarchies.get_mut("uav").unwrap().retain(|component| component != "payload system");
+        let battery = after.component_relationships.get_mut("uav").unwrap().iter_mut().find(|relation| relation.involves("battery"));
+        let battery = battery.unwrap();
+        (battery.subject, battery.component, battery.relation_type) = ("battery".to_string(), "uav".to_string(), RelationType::PartOf);
+        after.category_mappings.insert("compressor".to_string(), PrincipleCategory::new("Mechanical"));
+
+        let changes = diff(&before, &after);
+        assert_eq!(changes.added_concepts, ["heat pump"]);
+        assert!(changes.removed_concepts.is_empty());
+        assert_eq!(changes.components[0].removed, ["payload system"]);
+        assert_eq!(changes.relations.len(), 1);
+        assert_eq!(changes.categories[0].after.as_deref(), Some("Mechanical"));
+        assert_eq!(changes.to_version, after.version());
+
+        let rendered = changes.to_string();
+        assert!(rendered.contains("~ relation under uav: uav requires battery (0.98) => battery part of uav (0.98)"));
+        assert!(rendered.contains("~ components of uav: -payload system"));
+        assert_eq!(diff(&after, &before).removed_concepts, ["heat pump"]);
+    }
+}
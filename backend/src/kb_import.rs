@@ -0,0 +1,366 @@
+use crate::categories;
+use crate::semantic_analyzer::{ComponentRelation, ConceptKnowledgeBase, RelationType};
+use crate::text;
+use crate::types::{FieldError, PrincipleCategory, Result, WikiEngineError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::{IntoParams, ToSchema};
+
+/// Columns of an import file, in any order. Only `concept` and `component` are required, and
+/// only they need a value in every row.
+pub const COLUMNS: &[&str] = &["concept", "component", "relation", "confidence", "category"];
+
+/// Confidence of imported relations whose row leaves it empty
+const DEFAULT_CONFIDENCE: f32 = 0.9;
+
+/// Confidences closer than this to the knowledge base's don't count as a conflict, so values
+/// rounded by a spreadsheet still match
+const CONFIDENCE_TOLERANCE: f32 = 0.005;
+
+/// One row of an import file: `component` is a part of `concept`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportRow {
+    /// Line of the file the row is on
+    pub line: u64,
+    pub concept: String,
+    pub component: String,
+    /// How the component relates to the concept. `requires` reads "concept requires component",
+    /// every other relation "component <relation> concept", as in the built-in knowledge base.
+    /// Rows without a relation or confidence only add the component to the concept's hierarchy.
+    pub relation: Option<RelationType>,
+    pub confidence: Option<f32>,
+    /// Category of the component's principles
+    pub category: Option<PrincipleCategory>,
+}
+
+impl ImportRow {
+    fn edge(&self, relation_type: RelationType, confidence: f32) -> ComponentRelation {
+        let (subject, component) = match relation_type {
+            RelationType::Requires => (&self.concept, &self.component),
+            _ => (&self.component, &self.concept),
+        };
+        ComponentRelation { subject: subject.clone(), component: component.clone(), relation_type, confidence }
+    }
+
+    fn conflict(&self, field: &str, existing: String, imported: String, replaced: bool) -> ImportConflict {
+        ImportConflict {
+            line: self.line,
+            concept: self.concept.clone(),
+            component: self.component.clone(),
+            field: field.to_string(),
+            existing,
+            imported,
+            replaced,
+        }
+    }
+}
+
+/// What an import does with rows that disagree with the knowledge base
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// Leave the knowledge base's entry as it is
+    #[default]
+    Keep,
+    /// Take the imported value
+    Replace,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, IntoParams)]
+pub struct ImportOptions {
+    /// `keep` (default) leaves entries the file disagrees with as they are, `replace` takes the file's values
+    #[serde(default)]
+    pub on_conflict: ConflictResolution,
+    /// Report what the import would change without changing anything
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Kept with the new version in the knowledge base's history
+    pub note: Option<String>,
+}
+
+/// An imported value that disagreed with the knowledge base
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ImportConflict {
+    /// Line of the file the row is on
+    pub line: u64,
+    pub concept: String,
+    pub component: String,
+    /// `relation`, `confidence` or `category`
+    pub field: String,
+    pub existing: String,
+    pub imported: String,
+    /// Whether the imported value replaced the existing one
+    pub replaced: bool,
+}
+
+/// What an import changed, or with `dry_run` would change
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct ImportReport {
+    /// Rows read, not counting the header or blank rows
+    pub rows: usize,
+    pub components_added: usize,
+    pub relations_added: usize,
+    pub categories_added: usize,
+    /// Rows the knowledge base already held exactly
+    pub unchanged: usize,
+    pub conflicts: Vec<ImportConflict>,
+    /// Stamp of the knowledge base after the import, see `ConceptKnowledgeBase::version`
+    pub kb_version: String,
+    pub dry_run: bool,
+}
+
+/// Parse an import file: CSV with a header naming the `COLUMNS` it has. Concept and component
+/// names are case-folded, and relations may be written as words ("part of"). Every invalid
+/// value, and every row contradicting an earlier one, is reported at once.
+pub fn parse_csv(csv: &str) -> Result<Vec<ImportRow>> {
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(csv.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|error| WikiEngineError::InvalidRequest(vec![FieldError::new("header", error.to_string())]))?
+        .clone();
+
+    let mut errors = Vec::new();
+    let mut columns = HashMap::new();
+    for (index, name) in headers.iter().enumerate() {
+        let name = name.to_lowercase();
+        if !COLUMNS.contains(&name.as_str()) {
+            errors.push(FieldError::new("header", format!("unknown column `{}`, expected {}", name, COLUMNS.join(", "))));
+        } else if columns.insert(name.clone(), index).is_some() {
+            errors.push(FieldError::new("header", format!("column `{}` appears twice", name)));
+        }
+    }
+    for required in ["concept", "component"] {
+        if !columns.contains_key(required) {
+            errors.push(FieldError::new("header", format!("missing column `{}`", required)));
+        }
+    }
+    if !errors.is_empty() {
+        return Err(WikiEngineError::InvalidRequest(errors));
+    }
+
+    let mut rows: Vec<ImportRow> = Vec::new();
+    // Earlier row relating each concept/component pair and categorizing each component
+    let mut relations: HashMap<(String, String), usize> = HashMap::new();
+    let mut component_categories: HashMap<String, usize> = HashMap::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(error) => {
+                let line = error.position().map_or(0, |position| position.line());
+                errors.push(FieldError::new(format!("row (line {})", line), error.to_string()));
+                continue;
+            }
+        };
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+        let line = record.position().map_or(0, |position| position.line());
+        let value = |column: &str| columns.get(column).and_then(|&index| record.get(index)).unwrap_or("");
+        let field = |column: &str| format!("{} (line {})", column, line);
+
+        let concept = text::fold_case(value("concept"));
+        let component = text::fold_case(value("component"));
+        for (column, name) in [("concept", &concept), ("component", &component)] {
+            if name.is_empty() {
+                errors.push(FieldError::new(field(column), "must not be empty"));
+            }
+        }
+        if !concept.is_empty() && concept == component {
+            errors.push(FieldError::new(field("component"), "must differ from the concept"));
+        }
+
+        let relation = match value("relation") {
+            "" => None,
+            name => {
+                let normalized = name.to_lowercase().replace([' ', '-'], "_");
+                let relation = RelationType::ALL.into_iter().find(|relation| relation.name() == normalized);
+                if relation.is_none() {
+                    let known: Vec<&str> = RelationType::ALL.iter().map(RelationType::name).collect();
+                    errors.push(FieldError::new(field("relation"), format!("unknown relation `{}`, expected one of {}", name, known.join(", "))));
+                }
+                relation
+            }
+        };
+        let confidence = match value("confidence") {
+            "" => None,
+            number => match number.parse::<f32>() {
+                Ok(confidence) if (0.0..=1.0).contains(&confidence) => Some(confidence),
+                _ => {
+                    errors.push(FieldError::new(field("confidence"), "must be a number between 0.0 and 1.0"));
+                    None
+                }
+            },
+        };
+        let category = match value("category") {
+            "" => None,
+            id => {
+                let definition = categories::registry().definitions().find(|definition| definition.id.eq_ignore_ascii_case(id));
+                if definition.is_none() {
+                    errors.push(FieldError::new(field("category"), format!("unknown category `{}`", id)));
+                }
+                definition.map(|definition| PrincipleCategory::new(&definition.id))
+            }
+        };
+
+        if relation.is_some() || confidence.is_some() {
+            if let Some(earlier) = relations
+                .insert((concept.clone(), component.clone()), rows.len())
+                .map(|index| &rows[index])
+                .filter(|earlier| (&earlier.relation, earlier.confidence) != (&relation, confidence))
+            {
+                errors.push(FieldError::new(field("relation"), format!("contradicts line {}", earlier.line)));
+            }
+        }
+        if let Some(category) = &category {
+            if let Some(earlier) = component_categories
+                .insert(component.clone(), rows.len())
+                .map(|index| &rows[index])
+                .filter(|earlier| earlier.category.as_ref() != Some(category))
+            {
+                errors.push(FieldError::new(field("category"), format!("contradicts line {}", earlier.line)));
+            }
+        }
+
+        rows.push(ImportRow { line, concept, component, relation, confidence, category });
+    }
+
+    if errors.is_empty() && rows.is_empty() {
+        errors.push(FieldError::new("rows", "the file has no rows to import"));
+    }
+    if errors.is_empty() {
+        Ok(rows)
+    } else {
+        Err(WikiEngineError::InvalidRequest(errors))
+    }
+}
+
+/// Merge `rows` into `knowledge`. Components are appended to their concept's hierarchy, and
+/// relations and categories the knowledge base lacks are added; where it already holds a
+/// different value the disagreement is reported, and resolved as `on_conflict` says.
+pub fn merge(knowledge: &mut ConceptKnowledgeBase, rows: &[ImportRow], on_conflict: ConflictResolution) -> ImportReport {
+    let replace = on_conflict == ConflictResolution::Replace;
+    let mut report = ImportReport { rows: rows.len(), ..Default::default() };
+    for row in rows {
+        let conflicts_before = report.conflicts.len();
+        let mut changed = false;
+
+        let components = knowledge.concept_hierarchies.entry(row.concept.clone()).or_default();
+        if !components.contains(&row.component) {
+            components.push(row.component.clone());
+            report.components_added += 1;
+            changed = true;
+        }
+
+        if row.relation.is_some() || row.confidence.is_some() {
+            let relations = knowledge.component_relationships.entry(row.concept.clone()).or_default();
+            match relations.iter_mut().find(|relation| relation.involves(&row.concept) && relation.involves(&row.component)) {
+                None => {
+                    let relation_type = row.relation.clone().unwrap_or(RelationType::PartOf);
+                    relations.push(row.edge(relation_type, row.confidence.unwrap_or(DEFAULT_CONFIDENCE)));
+                    report.relations_added += 1;
+                    changed = true;
+                }
+                Some(existing) => {
+                    if let Some(relation_type) = row.relation.clone().filter(|relation| *relation != existing.relation_type) {
+                        let imported = relation_type.name().to_string();
+                        report.conflicts.push(row.conflict("relation", existing.relation_type.name().to_string(), imported, replace));
+                        if replace {
+                            *existing = row.edge(relation_type, row.confidence.unwrap_or(existing.confidence));
+                        }
+                    }
+                    if let Some(confidence) =
+                        row.confidence.filter(|confidence| (confidence - existing.confidence).abs() > CONFIDENCE_TOLERANCE)
+                    {
+                        let (existing_confidence, imported) = (format!("{:.2}", existing.confidence), format!("{:.2}", confidence));
+                        report.conflicts.push(row.conflict("confidence", existing_confidence, imported, replace));
+                        if replace {
+                            existing.confidence = confidence;
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(category) = &row.category {
+            match knowledge.category_mappings.get(&row.component) {
+                None => {
+                    knowledge.category_mappings.insert(row.component.clone(), category.clone());
+                    report.categories_added += 1;
+                    changed = true;
+                }
+                Some(existing) if existing != category => {
+                    report.conflicts.push(row.conflict("category", existing.name().to_string(), category.name().to_string(), replace));
+                    if replace {
+                        knowledge.category_mappings.insert(row.component.clone(), category.clone());
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+
+        if !changed && report.conflicts.len() == conflicts_before {
+            report.unchanged += 1;
+        }
+    }
+    report.kb_version = knowledge.version();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_merge_and_conflicts_are_reported() {
+        let csv = "\u{feff}Concept,Component,Relation,Confidence,Category\n\
+                   UAV,Motor,part of,0.95,Mechanical\n\
+                   uav,battery,controls,,electrical\n\
+                   uav,Parachute,,,Mechanical\n\
+                   uav,Flight Controller,controls,0.92,\n\
+                   \"heat pump\",compressor,requires,0.9,\n\
+                   ,,,,\n";
+        let rows = parse_csv(csv).unwrap();
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[0].concept, "uav");
+        assert_eq!(rows[0].relation, Some(RelationType::PartOf));
+        assert_eq!(rows[4].line, 6);
+
+        let built_in = ConceptKnowledgeBase::built_in();
+        let mut kept = built_in.clone();
+        let report = merge(&mut kept, &rows, ConflictResolution::Keep);
+        assert_eq!((report.components_added, report.relations_added, report.categories_added), (4, 1, 1));
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!((report.conflicts[0].field.as_str(), report.conflicts[0].existing.as_str()), ("relation", "requires"));
+        assert_eq!(report.kb_version, kept.version());
+        assert_ne!(report.kb_version, built_in.version());
+        assert!(kept.concept_hierarchies["uav"].contains(&"parachute".to_string()));
+        assert_eq!(kept.component_relationships["heat pump"][0].subject, "heat pump");
+
+        let mut replaced = built_in.clone();
+        let report = merge(&mut replaced, &rows, ConflictResolution::Replace);
+        assert!(report.conflicts[0].replaced);
+        let battery = replaced.component_relationships["uav"].iter().find(|relation| relation.involves("battery")).unwrap();
+        assert_eq!((battery.relation_type.clone(), battery.subject.as_str()), (RelationType::Controls, "battery"));
+        assert_eq!(battery.confidence, 0.98);
+    }
+
+    #[test]
+    fn invalid_files_report_every_problem() {
+        let Err(WikiEngineError::InvalidRequest(errors)) = parse_csv("concept,part\nuav,motor\n") else { panic!() };
+        assert_eq!(errors.len(), 2);
+
+        let csv = "concept,component,relation,confidence,category\n\
+                   uav,uav,,,\n\
+                   uav,motor,powers,1.5,Magic\n\
+                   uav,frame,supports,,Mechanical\n\
+                   uav,frame,part_of,,Electrical\n";
+        let Err(WikiEngineError::InvalidRequest(errors)) = parse_csv(csv) else { panic!() };
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            ["component (line 2)", "relation (line 3)", "confidence (line 3)", "category (line 3)", "relation (line 5)", "category (line 5)"]
+        );
+        assert!(parse_csv("concept,component\n").is_err());
+    }
+}
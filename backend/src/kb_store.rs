@@ -0,0 +1,377 @@
+use crate::semantic_analyzer::ConceptKnowledgeBase;
+use crate::types::{Result, WikiEngineError};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// One saved version of the knowledge base
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct KnowledgeBaseRevision {
+    /// Position in the store's history, starting at 1
+    pub revision: u64,
+    /// `ConceptKnowledgeBase::version` of the contents, as results carry it in `kb_version`
+    pub kb_version: String,
+    pub created_at: DateTime<Utc>,
+    pub note: String,
+}
+
+/// Versioned storage for the knowledge base. Saving never overwrites earlier versions, so an
+/// analysis can be reproduced against the knowledge base its result's `kb_version` names.
+pub trait KnowledgeBaseStore: Send + Sync {
+    /// Save `knowledge` as the latest version; contents equal to the latest version's return it
+    /// instead of adding a new one
+    fn save(&self, knowledge: &ConceptKnowledgeBase, note: &str) -> Result<KnowledgeBaseRevision>;
+
+    /// The latest version, or `None` before anything was saved
+    fn latest(&self) -> Result<Option<(KnowledgeBaseRevision, ConceptKnowledgeBase)>>;
+
+    /// The latest version with the stamp `kb_version`
+    fn load(&self, kb_version: &str) -> Result<Option<(KnowledgeBaseRevision, ConceptKnowledgeBase)>>;
+
+    /// Every version, oldest first
+    fn history(&self) -> Result<Vec<KnowledgeBaseRevision>>;
+}
+
+/// The store at `WIKI_ENGINE_KB_DATABASE`, if set
+pub fn store_from_env() -> Result<Option<Arc<dyn KnowledgeBaseStore>>> {
+    let Some(path) = std::env::var_os("WIKI_ENGINE_KB_DATABASE").filter(|path| !path.is_empty()) else {
+        return Ok(None);
+    };
+
+    #[cfg(feature = "sqlite")]
+    {
+        let store = SqliteKnowledgeBaseStore::open(std::path::Path::new(&path))?;
+        Ok(Some(Arc::new(store)))
+    }
+    #[cfg(not(feature = "sqlite"))]
+    {
+        tracing::warn!(
+            "Ignoring WIKI_ENGINE_KB_DATABASE={}: built without the `sqlite` feature",
+            std::path::Path::new(&path).display()
+        );
+        Ok(None)
+    }
+}
+
+/// The knowledge base version `WIKI_ENGINE_KB_VERSION` pins the store's analyses to, if set
+pub fn pinned_version_from_env() -> Option<String> {
+    std::env::var("WIKI_ENGINE_KB_VERSION").ok().filter(|version| !version.is_empty())
+}
+
+/// The version of `store` stamped `pinned`, or its latest; an empty store is seeded with the
+/// built-in knowledge base first. A pinned version missing from the store is an error, so
+/// analyses never silently run against a different knowledge base than asked for.
+pub fn load_or_seed(store: &dyn KnowledgeBaseStore, pinned: Option<&str>) -> Result<ConceptKnowledgeBase> {
+    if let Some(kb_version) = pinned {
+        let (revision, knowledge) = store.load(kb_version)?.ok_or_else(|| {
+            WikiEngineError::Analysis(format!("Knowledge base version {} is not in the store", kb_version))
+        })?;
+        tracing::info!("Using knowledge base revision {} ({})", revision.revision, revision.kb_version);
+        return Ok(knowledge);
+    }
+    match store.latest()? {
+        Some((revision, knowledge)) => {
+            tracing::info!("Using knowledge base revision {} ({})", revision.revision, revision.kb_version);
+            Ok(knowledge)
+        }
+        None => {
+            let knowledge = ConceptKnowledgeBase::built_in();
+            let revision = store.save(&knowledge, "built-in knowledge base")?;
+            tracing::info!("Seeded the knowledge base store with the built-in knowledge base ({})", revision.kb_version);
+            Ok(knowledge)
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteKnowledgeBaseStore;
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::{KnowledgeBaseRevision, KnowledgeBaseStore};
+    use crate::semantic_analyzer::{ComponentRelation, ConceptKnowledgeBase, RelationType};
+    use crate::types::{PrincipleCategory, Result, WikiEngineError};
+    use chrono::{DateTime, Utc};
+    use rusqlite::{params, Connection, OptionalExtension, Row};
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    /// Every version is stored in full, one row per hierarchy entry, relation, category mapping
+    /// and synonym. Concepts mapped to an empty list get a single row with a NULL value, so they
+    /// survive a round trip.
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS kb_revisions (
+            revision INTEGER PRIMARY KEY AUTOINCREMENT,
+            kb_version TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            note TEXT NOT NULL DEFAULT ''
+        );
+        CREATE INDEX IF NOT EXISTS kb_revisions_by_version ON kb_revisions (kb_version);
+        CREATE TABLE IF NOT EXISTS kb_hierarchies (
+            revision INTEGER NOT NULL REFERENCES kb_revisions (revision),
+            concept TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            component TEXT,
+            PRIMARY KEY (revision, concept, position)
+        );
+        CREATE TABLE IF NOT EXISTS kb_relations (
+            revision INTEGER NOT NULL REFERENCES kb_revisions (revision),
+            concept TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            subject TEXT,
+            component TEXT,
+            relation_type TEXT,
+            confidence REAL,
+            PRIMARY KEY (revision, concept, position)
+        );
+        CREATE TABLE IF NOT EXISTS kb_categories (
+            revision INTEGER NOT NULL REFERENCES kb_revisions (revision),
+            concept TEXT NOT NULL,
+            category TEXT NOT NULL,
+            PRIMARY KEY (revision, concept)
+        );
+        CREATE TABLE IF NOT EXISTS kb_synonyms (
+            revision INTEGER NOT NULL REFERENCES kb_revisions (revision),
+            concept TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            synonym TEXT,
+            PRIMARY KEY (revision, concept, position)
+        );
+    ";
+
+    /// Knowledge base versions in a SQLite database
+    pub struct SqliteKnowledgeBaseStore {
+        connection: Mutex<Connection>,
+    }
+
+    impl SqliteKnowledgeBaseStore {
+        /// Open the database at `path`, creating it and its tables if needed
+        pub fn open(path: &Path) -> Result<Self> {
+            Self::with_connection(Connection::open(path)?)
+        }
+
+        /// A store that lives only as long as it does, for tests and experiments
+        pub fn open_in_memory() -> Result<Self> {
+            Self::with_connection(Connection::open_in_memory()?)
+        }
+
+        fn with_connection(connection: Connection) -> Result<Self> {
+            connection.execute_batch(SCHEMA)?;
+            Ok(Self { connection: Mutex::new(connection) })
+        }
+
+        fn revision_from_row(row: &Row) -> rusqlite::Result<KnowledgeBaseRevision> {
+            let created_at: String = row.get(2)?;
+            let created_at = DateTime::parse_from_rfc3339(&created_at)
+                .map(|created_at| created_at.with_timezone(&Utc))
+                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?;
+            Ok(KnowledgeBaseRevision {
+                revision: row.get(0)?,
+                kb_version: row.get(1)?,
+                created_at,
+                note: row.get(3)?,
+            })
+        }
+
+        /// The latest revision matching `filter` (a condition on `kb_revisions`)
+        fn find(
+            connection: &Connection,
+            filter: &str,
+            params: impl rusqlite::Params,
+        ) -> Result<Option<(KnowledgeBaseRevision, ConceptKnowledgeBase)>> {
+            let sql = format!(
+                "SELECT revision, kb_version, created_at, note FROM kb_revisions WHERE {} ORDER BY revision DESC LIMIT 1",
+                filter
+            );
+            let Some(revision) = connection.query_row(&sql, params, Self::revision_from_row).optional()? else {
+                return Ok(None);
+            };
+            let knowledge = Self::read(connection, revision.revision)?;
+            Ok(Some((revision, knowledge)))
+        }
+
+        fn read(connection: &Connection, revision: u64) -> Result<ConceptKnowledgeBase> {
+            let mut knowledge = ConceptKnowledgeBase::default();
+
+            let mut statement = connection
+                .prepare("SELECT concept, component FROM kb_hierarchies WHERE revision = ?1 ORDER BY concept, position")?;
+            let mut rows = statement.query(params![revision])?;
+            while let Some(row) = rows.next()? {
+                let components = knowledge.concept_hierarchies.entry(row.get(0)?).or_default();
+                components.extend(row.get::<_, Option<String>>(1)?);
+            }
+
+            let mut statement = connection.prepare(
+                "SELECT concept, subject, component, relation_type, confidence FROM kb_relations
+                 WHERE revision = ?1 ORDER BY concept, position",
+            )?;
+            let mut rows = statement.query(params![revision])?;
+            while let Some(row) = rows.next()? {
+                let relations = knowledge.component_relationships.entry(row.get(0)?).or_default();
+                let (Some(subject), Some(component), Some(relation_type), Some(confidence)) = (
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<f64>>(4)?,
+                ) else {
+                    continue;
+                };
+                let relation_type: RelationType = serde_json::from_value(serde_json::Value::String(relation_type))?;
+                relations.push(ComponentRelation { subject, component, relation_type, confidence: confidence as f32 });
+            }
+
+            let mut statement = connection.prepare("SELECT concept, category FROM kb_categories WHERE revision = ?1")?;
+            let mut rows = statement.query(params![revision])?;
+            while let Some(row) = rows.next()? {
+                let category: String = row.get(1)?;
+                knowledge.category_mappings.insert(row.get(0)?, PrincipleCategory::new(&category));
+            }
+
+            let mut statement = connection
+                .prepare("SELECT concept, synonym FROM kb_synonyms WHERE revision = ?1 ORDER BY concept, position")?;
+            let mut rows = statement.query(params![revision])?;
+            while let Some(row) = rows.next()? {
+                let synonyms = knowledge.synonyms.entry(row.get(0)?).or_default();
+                synonyms.extend(row.get::<_, Option<String>>(1)?);
+            }
+
+            Ok(knowledge)
+        }
+
+        fn write(connection: &Connection, revision: u64, knowledge: &ConceptKnowledgeBase) -> Result<()> {
+            /// `(position, item)` for each item, or a single `(0, None)` for an empty list
+            fn positioned<T>(items: &[T]) -> Vec<(usize, Option<&T>)> {
+                if items.is_empty() {
+                    return vec![(0, None)];
+                }
+                items.iter().enumerate().map(|(position, item)| (position, Some(item))).collect()
+            }
+
+            let mut statement = connection
+                .prepare("INSERT INTO kb_hierarchies (revision, concept, position, component) VALUES (?1, ?2, ?3, ?4)")?;
+            for (concept, components) in &knowledge.concept_hierarchies {
+                for (position, component) in positioned(components) {
+                    statement.execute(params![revision, concept, position, component])?;
+                }
+            }
+
+            let mut statement = connection.prepare(
+                "INSERT INTO kb_relations (revision, concept, position, subject, component, relation_type, confidence)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for (concept, relations) in &knowledge.component_relationships {
+                for (position, relation) in positioned(relations) {
+                    let relation_type = relation
+                        .map(|relation| serde_json::to_value(&relation.relation_type))
+                        .transpose()?
+                        .and_then(|value| value.as_str().map(str::to_string));
+                    statement.execute(params![
+                        revision,
+                        concept,
+                        position,
+                        relation.map(|relation| &relation.subject),
+                        relation.map(|relation| &relation.component),
+                        relation_type,
+                        relation.map(|relation| f64::from(relation.confidence)),
+                    ])?;
+                }
+            }
+
+            let mut statement =
+                connection.prepare("INSERT INTO kb_categories (revision, concept, category) VALUES (?1, ?2, ?3)")?;
+            for (concept, category) in &knowledge.category_mappings {
+                statement.execute(params![revision, concept, category.name()])?;
+            }
+
+            let mut statement = connection
+                .prepare("INSERT INTO kb_synonyms (revision, concept, position, synonym) VALUES (?1, ?2, ?3, ?4)")?;
+            for (concept, synonyms) in &knowledge.synonyms {
+                for (position, synonym) in positioned(synonyms) {
+                    statement.execute(params![revision, concept, position, synonym])?;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl KnowledgeBaseStore for SqliteKnowledgeBaseStore {
+        fn save(&self, knowledge: &ConceptKnowledgeBase, note: &str) -> Result<KnowledgeBaseRevision> {
+            let kb_version = knowledge.version();
+            let mut connection = self.connection.lock().unwrap();
+            let transaction = connection.transaction()?;
+            if let Some((latest, _)) = Self::find(&transaction, "1 = 1", [])? {
+                if latest.kb_version == kb_version {
+                    return Ok(latest);
+                }
+            }
+
+            let created_at = Utc::now();
+            transaction.execute(
+                "INSERT INTO kb_revisions (kb_version, created_at, note) VALUES (?1, ?2, ?3)",
+                params![kb_version, created_at.to_rfc3339(), note],
+            )?;
+            let revision = transaction.last_insert_rowid() as u64;
+            Self::write(&transaction, revision, knowledge)?;
+            transaction.commit()?;
+            Ok(KnowledgeBaseRevision { revision, kb_version, created_at, note: note.to_string() })
+        }
+
+        fn latest(&self) -> Result<Option<(KnowledgeBaseRevision, ConceptKnowledgeBase)>> {
+            Self::find(&self.connection.lock().unwrap(), "1 = 1", [])
+        }
+
+        fn load(&self, kb_version: &str) -> Result<Option<(KnowledgeBaseRevision, ConceptKnowledgeBase)>> {
+            Self::find(&self.connection.lock().unwrap(), "kb_version = ?1", [kb_version])
+        }
+
+        fn history(&self) -> Result<Vec<KnowledgeBaseRevision>> {
+            let connection = self.connection.lock().unwrap();
+            let mut statement =
+                connection.prepare("SELECT revision, kb_version, created_at, note FROM kb_revisions ORDER BY revision")?;
+            let revisions = statement.query_map([], Self::revision_from_row)?.collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(revisions)
+        }
+    }
+
+    impl From<rusqlite::Error> for WikiEngineError {
+        fn from(error: rusqlite::Error) -> Self {
+            WikiEngineError::Analysis(format!("Knowledge base storage error: {}", error))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::kb_store::load_or_seed;
+
+        #[test]
+        fn versions_round_trip_and_are_kept() {
+            let store = SqliteKnowledgeBaseStore::open_in_memory().unwrap();
+            let built_in = load_or_seed(&store, None).unwrap();
+            let first = store.latest().unwrap().unwrap().0;
+            assert_eq!(first.kb_version, ConceptKnowledgeBase::built_in().version());
+            assert_eq!(built_in.version(), first.kb_version);
+
+            let mut edited = built_in.clone();
+            edited.concept_hierarchies.insert("gearbox".to_string(), vec!["gear".to_string(), "shaft".to_string()]);
+            edited.synonyms.insert("gearbox".to_string(), vec![]);
+            let second = store.save(&edited, "add gearbox").unwrap();
+            assert_eq!(second.revision, first.revision + 1);
+            assert_eq!(store.save(&edited, "unchanged").unwrap(), second);
+
+            let (latest, loaded) = store.latest().unwrap().unwrap();
+            assert_eq!(latest, second);
+            assert_eq!(loaded.version(), edited.version());
+            assert_eq!(loaded.concept_hierarchies["gearbox"], ["gear", "shaft"]);
+            assert!(loaded.synonyms["gearbox"].is_empty());
+
+            // The earlier version is still there to reproduce older results with
+            let (_, pinned) = store.load(&first.kb_version).unwrap().unwrap();
+            assert!(!pinned.concept_hierarchies.contains_key("gearbox"));
+            assert_eq!(load_or_seed(&store, Some(&first.kb_version)).unwrap().version(), first.kb_version);
+            assert!(load_or_seed(&store, Some("0000000000000000")).is_err());
+            assert_eq!(store.history().unwrap().len(), 2);
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use crate::analyzer::ScoredSentence;
+use crate::config::LabelingExportConfig;
+use crate::dedup;
+use crate::types::{EngineeringPrinciple, FieldError, PrincipleCategory, Result, WikiEngineError, WikipediaPage};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Why a sentence was exported for labeling
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelingReason {
+    /// The keyword patterns and the embedding model or knowledge base put it in different categories
+    CategoryDisagreement,
+    /// Its confidence was close to the threshold for keeping it as a principle
+    NearThreshold,
+}
+
+/// One line of the labeling export: a sentence, the categories the analyzers proposed for it and
+/// an empty `label` for an annotator to fill in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelingCandidate {
+    /// SimHash of the sentence in hex, the same wherever the sentence turns up
+    pub id: String,
+    pub text: String,
+    /// Category the annotator picked; always `null` in exports
+    pub label: Option<String>,
+    /// Categories the analyzers proposed, the keyword patterns' first
+    pub options: Vec<String>,
+    pub reason: LabelingReason,
+    /// Confidence the keyword patterns gave the sentence
+    pub confidence: f32,
+    /// Threshold the confidence was close to, for `near_threshold`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f32>,
+    pub page: String,
+    pub source_url: String,
+    pub collected_at: DateTime<Utc>,
+}
+
+impl LabelingCandidate {
+    /// A sentence the patterns turned into `principle` but another classifier put in `other`
+    pub fn disagreement(page: &WikipediaPage, principle: &EngineeringPrinciple, other: &PrincipleCategory) -> Self {
+        let options = vec![principle.category.name().to_string(), other.name().to_string()];
+        Self::new(page, principle.source_span, LabelingReason::CategoryDisagreement, options, principle.confidence, None)
+    }
+
+    /// A sentence whose confidence fell close to `threshold`, kept or not
+    pub fn near_threshold(page: &WikipediaPage, scored: &ScoredSentence, threshold: f32) -> Self {
+        let options = vec![scored.category.name().to_string()];
+        Self::new(page, scored.span, LabelingReason::NearThreshold, options, scored.confidence, Some(threshold))
+    }
+
+    fn new(
+        page: &WikipediaPage,
+        (start, end): (usize, usize),
+        reason: LabelingReason,
+        options: Vec<String>,
+        confidence: f32,
+        threshold: Option<f32>,
+    ) -> Self {
+        let text = page.extract.get(start..end).unwrap_or_default().trim().to_string();
+        Self {
+            id: format!("{:016x}", dedup::simhash(&text)),
+            text,
+            label: None,
+            options,
+            reason,
+            confidence,
+            threshold,
+            page: page.title.clone(),
+            source_url: page.url.clone(),
+            collected_at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PendingCandidates {
+    candidates: Vec<LabelingCandidate>,
+    /// Ids of every sentence collected so far, exported or not, so each is labeled once
+    seen: HashSet<String>,
+}
+
+/// Sentences the analyzers were unsure about, held until they are appended to the labeling
+/// export. Collects nothing unless an export file is configured.
+#[derive(Debug, Default)]
+pub struct LabelingQueue {
+    config: LabelingExportConfig,
+    pending: Mutex<PendingCandidates>,
+}
+
+impl LabelingQueue {
+    pub fn new(config: LabelingExportConfig) -> Self {
+        Self { config, pending: Mutex::default() }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(LabelingExportConfig::from_env())
+    }
+
+    pub fn config(&self) -> &LabelingExportConfig {
+        &self.config
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.path.is_some()
+    }
+
+    /// Candidates waiting for the next export
+    pub fn pending(&self) -> usize {
+        self.pending.lock().unwrap().candidates.len()
+    }
+
+    /// Hold `candidates` for the next export, skipping sentences already collected and any
+    /// beyond `max_pending`
+    pub fn push(&self, candidates: impl IntoIterator<Item = LabelingCandidate>) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut pending = self.pending.lock().unwrap();
+        for candidate in candidates {
+            if candidate.text.is_empty() || pending.candidates.len() >= self.config.max_pending {
+                continue;
+            }
+            if pending.seen.insert(candidate.id.clone()) {
+                pending.candidates.push(candidate);
+            }
+        }
+    }
+
+    /// Append the pending candidates to the export file, one JSON object per line, returning how
+    /// many were written. Candidates that couldn't be written are kept for the next export.
+    pub fn export(&self) -> io::Result<usize> {
+        let Some(path) = &self.config.path else { return Ok(0) };
+        let candidates = std::mem::take(&mut self.pending.lock().unwrap().candidates);
+        if candidates.is_empty() {
+            return Ok(0);
+        }
+
+        let mut lines = Vec::new();
+        for candidate in &candidates {
+            serde_json::to_writer(&mut lines, candidate).expect("labeling candidates serialize to JSON");
+            lines.push(b'\n');
+        }
+        let written = OpenOptions::new().create(true).append(true).open(path).and_then(|mut file| file.write_all(&lines));
+        if let Err(error) = written {
+            let mut pending = self.pending.lock().unwrap();
+            pending.candidates.splice(0..0, candidates);
+            return Err(error);
+        }
+        Ok(candidates.len())
+    }
+}
+
+/// Export the queue's candidates every `WIKI_ENGINE_LABELING_INTERVAL_SECS`
+pub async fn start_labeling_export_task(queue: Arc<LabelingQueue>) {
+    let mut interval = tokio::time::interval(queue.config().interval());
+    loop {
+        interval.tick().await;
+        match queue.export() {
+            Ok(0) => {}
+            Ok(exported) => tracing::info!("Exported {} sentences for labeling", exported),
+            Err(error) => tracing::warn!("Failed to export sentences for labeling: {}", error),
+        }
+    }
+}
+
+/// A classifier fine-tuned on labeled sentences: the embedding model to load and the thresholds
+/// to apply, read from the JSON file at `WIKI_ENGINE_CALIBRATION`. Settings left out keep
+/// their defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Calibration {
+    /// ONNX sentence embedding model; relative paths are taken from the calibration file's folder
+    pub model: Option<PathBuf>,
+    /// Tokenizer of `model`, required with it
+    pub tokenizer: Option<PathBuf>,
+    /// Confidence a sentence needs to be kept as a principle
+    pub min_principle_confidence: Option<f32>,
+    /// Similarity to a category prototype a sentence needs to be classified by embedding
+    pub min_prototype_similarity: Option<f32>,
+}
+
+impl Calibration {
+    /// The calibration in the file at `WIKI_ENGINE_CALIBRATION`, if set
+    pub fn from_env() -> Result<Option<Self>> {
+        match std::env::var_os("WIKI_ENGINE_CALIBRATION").filter(|path| !path.is_empty()) {
+            Some(path) => Self::load(Path::new(&path)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Read a calibration file, checking its thresholds and that its model files exist
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| WikiEngineError::Analysis(format!("Failed to read calibration {}: {}", path.display(), e)))?;
+        let mut calibration: Self = serde_json::from_str(&contents)?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for file in [&mut calibration.model, &mut calibration.tokenizer].into_iter().flatten() {
+            if file.is_relative() {
+                *file = dir.join(&*file);
+            }
+        }
+        calibration.validate()
+    }
+
+    fn validate(self) -> Result<Self> {
+        let mut errors = Vec::new();
+        let thresholds = [
+            ("min_principle_confidence", self.min_principle_confidence),
+            ("min_prototype_similarity", self.min_prototype_similarity),
+        ];
+        for (field, threshold) in thresholds {
+            if threshold.is_some_and(|threshold| !(0.0..=1.0).contains(&threshold)) {
+                errors.push(FieldError::new(field, "must be between 0 and 1"));
+            }
+        }
+        match (&self.model, &self.tokenizer) {
+            (Some(_), None) => errors.push(FieldError::new("tokenizer", "is required with a model")),
+            (None, Some(_)) => errors.push(FieldError::new("model", "is required with a tokenizer")),
+            _ => {}
+        }
+        for (field, file) in [("model", &self.model), ("tokenizer", &self.tokenizer)] {
+            if let Some(file) = file.as_ref().filter(|file| !file.is_file()) {
+                errors.push(FieldError::new(field, format!("{} does not exist", file.display())));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(self)
+        } else {
+            Err(WikiEngineError::InvalidRequest(errors))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::EngineeringAnalyzer;
+
+    fn page(extract: &str) -> WikipediaPage {
+        WikipediaPage {
+            title: "Electric motor".to_string(),
+            extract: extract.to_string(),
+            url: "https://en.wikipedia.org/wiki/Electric_motor".to_string(),
+            page_id: 1,
+            revision_id: None,
+            fetched_at: Utc::now(),
+            thumbnail_url: None,
+            monthly_views: None,
+            curated_links: vec![],
+            etag: None,
+            last_modified: None,
+            source: Default::default(),
+        }
+    }
+
+    #[test]
+    fn borderline_sentences_are_exported_once() {
+        let path = std::env::temp_dir().join(format!("labeling-{}.jsonl", uuid::Uuid::new_v4()));
+        let queue = LabelingQueue::new(LabelingExportConfig { path: Some(path.clone()), ..Default::default() });
+        let analyzer = EngineeringAnalyzer::new().unwrap();
+        let page = page("The process is important. The torque follows the fundamental principle of the Lorentz force on the rotor current.");
+
+        let threshold = analyzer.min_confidence();
+        let candidates: Vec<_> = analyzer
+            .borderline_sentences(&page, 0.05)
+            .iter()
+            .map(|scored| LabelingCandidate::near_threshold(&page, scored, threshold))
+            .collect();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].text, "The process is important.");
+        queue.push(candidates.clone());
+        queue.push(candidates);
+        assert_eq!(queue.pending(), 1);
+
+        assert_eq!(queue.export().unwrap(), 1);
+        assert_eq!(queue.export().unwrap(), 0);
+        let exported = std::fs::read_to_string(&path).unwrap();
+        let line: serde_json::Value = serde_json::from_str(exported.lines().next().unwrap()).unwrap();
+        assert_eq!(line["reason"], "near_threshold");
+        assert!(line["label"].is_null());
+        std::fs::remove_file(&path).unwrap();
+
+        let disabled = LabelingQueue::default();
+        disabled.push(vec![LabelingCandidate::near_threshold(&page, &analyzer.borderline_sentences(&page, 0.05)[0], threshold)]);
+        assert_eq!(disabled.pending(), 0);
+    }
+
+    #[test]
+    fn calibration_resolves_model_paths_and_checks_thresholds() {
+        let dir = std::env::temp_dir().join(format!("calibration-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("model.onnx"), b"").unwrap();
+        std::fs::write(dir.join("tokenizer.json"), b"{}").unwrap();
+        let write = |contents: &str| {
+            std::fs::write(dir.join("calibration.json"), contents).unwrap();
+            Calibration::load(&dir.join("calibration.json"))
+        };
+
+        let calibration =
+            write(r#"{"model": "model.onnx", "tokenizer": "tokenizer.json", "min_principle_confidence": 0.42}"#).unwrap();
+        assert_eq!(calibration.model, Some(dir.join("model.onnx")));
+        assert_eq!(calibration.min_principle_confidence, Some(0.42));
+
+        let Err(WikiEngineError::InvalidRequest(errors)) = write(r#"{"model": "missing.onnx", "min_prototype_similarity": 1.5}"#) else {
+            panic!("expected the calibration to be rejected");
+        };
+        let fields: Vec<_> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, ["min_prototype_similarity", "tokenizer", "model"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
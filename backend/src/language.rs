@@ -0,0 +1,139 @@
+/// Common function words of the Latin-script languages told apart by `detect_language`
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "and", "of", "is", "which", "with", "that", "from"]),
+    ("de", &["der", "die", "das", "und", "ist", "ein", "eine", "mit", "von"]),
+    ("fr", &["le", "la", "les", "et", "est", "une", "des", "du", "avec"]),
+    ("es", &["el", "los", "las", "es", "una", "del", "por", "con", "que"]),
+    ("it", &["il", "della", "che", "una", "sono", "per", "gli", "con", "delle"]),
+    ("pt", &["o", "os", "da", "do", "uma", "em", "que", "com", "são"]),
+    ("nl", &["het", "een", "van", "en", "is", "de", "met", "wordt", "zijn"]),
+];
+
+/// Function-word hits needed before a Latin-script language is guessed
+const MIN_STOPWORD_HITS: usize = 2;
+
+
+/// Best-guess ISO 639-1 code for the language of `text`. Non-Latin scripts are identified by
+/// their script (Cyrillic is assumed to be Russian); Latin-script text by its function words.
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let mut latin = 0;
+    let mut scripts: Vec<(&'static str, usize)> = Vec::new();
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        let language = match c as u32 {
+            0x0400..=0x04FF => "ru",
+            0x0370..=0x03FF => "el",
+            0x3040..=0x30FF => "ja",
+            0x4E00..=0x9FFF => "zh",
+            0xAC00..=0xD7AF | 0x1100..=0x11FF => "ko",
+            0x0600..=0x06FF => "ar",
+            0x0590..=0x05FF => "he",
+            0x0900..=0x097F => "hi",
+            0x0E00..=0x0E7F => "th",
+            _ => {
+                latin += 1;
+                continue;
+            }
+        };
+        match scripts.iter_mut().find(|(known, _)| *known == language) {
+            Some((_, count)) => *count += 1,
+            None => scripts.push((language, 1)),
+        }
+    }
+
+    // Kana alongside Han characters is Japanese
+    let kana = scripts.iter().any(|(language, _)| *language == "ja");
+    if let Some(&(language, count)) = scripts.iter().max_by_key(|(_, count)| *count) {
+        if count > latin {
+            return Some(if language == "zh" && kana { "ja" } else { language });
+        }
+    }
+
+    let words: Vec<String> = text.split(|c: char| !c.is_alphabetic()).map(str::to_lowercase).collect();
+    STOPWORDS
+        .iter()
+        .map(|(language, stopwords)| {
+            (*language, words.iter().filter(|word| stopwords.contains(&word.as_str())).count())
+        })
+        .filter(|(_, hits)| *hits >= MIN_STOPWORD_HITS)
+        .max_by_key(|(_, hits)| *hits)
+        .map(|(language, _)| language)
+}
+
+/// ASCII rendering of a name in Latin (with diacritics), Cyrillic or Greek script, or `None`
+/// when it is already ASCII or uses a script without a transliteration table here
+pub fn romanize(text: &str) -> Option<String> {
+    if text.is_ascii() {
+        return None;
+    }
+
+    let mut romanized = String::with_capacity(text.len());
+    for c in text.chars() {
+        match transliterate(c.to_lowercase().next().unwrap_or(c)) {
+            Some(latin) if c.is_uppercase() => {
+                let mut letters = latin.chars();
+                if let Some(first) = letters.next() {
+                    romanized.extend(first.to_uppercase());
+                    romanized.push_str(letters.as_str());
+                }
+            }
+            Some(latin) => romanized.push_str(latin),
+            None => romanized.push(c),
+        }
+    }
+    romanized.is_ascii().then_some(romanized)
+}
+
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        // Latin letters with diacritics
+        'á' | 'à' | 'â' | 'ä' | 'ã' | 'å' | 'ā' | 'ą' => "a",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' => "d",
+        'é' | 'è' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' => "e",
+        'í' | 'ì' | 'î' | 'ï' | 'ī' => "i",
+        'ł' | 'ľ' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ó' | 'ò' | 'ô' | 'ö' | 'õ' | 'ø' | 'ō' | 'ő' => "o",
+        'ř' => "r",
+        'ś' | 'š' | 'ş' => "s",
+        'ť' | 'ţ' => "t",
+        'ú' | 'ù' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => "u",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        'ß' => "ss",
+        'æ' => "ae",
+        'œ' => "oe",
+        // Cyrillic (Russian)
+        'а' => "a", 'б' => "b", 'в' => "v", 'г' => "g", 'д' => "d", 'е' => "e", 'ё' => "yo",
+        'ж' => "zh", 'з' => "z", 'и' => "i", 'й' => "y", 'к' => "k", 'л' => "l", 'м' => "m",
+        'н' => "n", 'о' => "o", 'п' => "p", 'р' => "r", 'с' => "s", 'т' => "t", 'у' => "u",
+        'ф' => "f", 'х' => "kh", 'ц' => "ts", 'ч' => "ch", 'ш' => "sh", 'щ' => "shch",
+        'ъ' | 'ь' => "", 'ы' => "y", 'э' => "e", 'ю' => "yu", 'я' => "ya",
+        // Greek
+        'α' | 'ά' => "a", 'β' => "v", 'γ' => "g", 'δ' => "d", 'ε' | 'έ' => "e", 'ζ' => "z",
+        'η' | 'ή' => "i", 'θ' => "th", 'ι' | 'ί' | 'ϊ' => "i", 'κ' => "k", 'λ' => "l", 'μ' => "m",
+        'ν' => "n", 'ξ' => "x", 'ο' | 'ό' => "o", 'π' => "p", 'ρ' => "r", 'σ' | 'ς' => "s",
+        'τ' => "t", 'υ' | 'ύ' | 'ϋ' => "y", 'φ' => "f", 'χ' => "ch", 'ψ' => "ps", 'ω' | 'ώ' => "o",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_language_and_romanizes_names() {
+        assert_eq!(detect_language("Der Elektromotor ist eine Maschine, die mit Strom arbeitet"), Some("de"));
+        assert_eq!(detect_language("An electric motor is a machine that converts electrical energy"), Some("en"));
+        assert_eq!(detect_language("Электродвигатель — электрическая машина"), Some("ru"));
+        assert_eq!(detect_language("電動機"), Some("zh"));
+        assert_eq!(detect_language("Motor"), None);
+
+        assert_eq!(romanize("Электродвигатель").as_deref(), Some("Elektrodvigatel"));
+        assert_eq!(romanize("Straßenbahn Zürich").as_deref(), Some("Strassenbahn Zurich"));
+        assert_eq!(romanize("Motor"), None);
+        assert_eq!(romanize("電動機"), None);
+    }
+
+}
@@ -0,0 +1,182 @@
+use crate::types::PrincipleCategory;
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Named laws and equations: name, category id, a one-sentence statement and a pattern for
+/// mentions of the law or its equation
+const REGISTRY: &[(&str, &str, &str, &str)] = &[
+    (
+        "Ohm's law",
+        "Electrical",
+        "The current through a conductor is proportional to the voltage across it and inversely proportional to its resistance",
+        r"(?i)\bohm['’]?s\s+law\b|\bV\s*=\s*I\s*R\b",
+    ),
+    (
+        "Kirchhoff's circuit laws",
+        "Electrical",
+        "The currents into a circuit node sum to zero, as do the voltages around any closed loop",
+        r"(?i)\bkirchhoff['’]?s?\s+(?:current\s+|voltage\s+|circuit\s+)?laws?\b",
+    ),
+    (
+        "Faraday's law of induction",
+        "Electrical",
+        "A changing magnetic flux through a circuit induces an electromotive force in it",
+        r"(?i)\bfaraday['’]?s\s+law\b|\belectromagnetic\s+induction\b",
+    ),
+    (
+        "Lenz's law",
+        "Electrical",
+        "An induced current flows in the direction that opposes the change in magnetic flux producing it",
+        r"(?i)\blenz['’]?s\s+law\b",
+    ),
+    (
+        "Lorentz force law",
+        "Electrical",
+        "A charge moving through electric and magnetic fields feels a force perpendicular to the magnetic field",
+        r"(?i)\blorentz\s+force\b",
+    ),
+    (
+        "Coulomb's law",
+        "Electrical",
+        "The force between two charges is proportional to their product and inversely proportional to the square of their distance",
+        r"(?i)\bcoulomb['’]?s\s+law\b",
+    ),
+    (
+        "Newton's laws of motion",
+        "Mechanical",
+        "A body's acceleration is proportional to the net force on it, and every action has an equal and opposite reaction",
+        r"(?i)\bnewton['’]?s\s+(?:first\s+|second\s+|third\s+)?laws?(?:\s+of\s+motion)?\b|\bF\s*=\s*m\s*a\b",
+    ),
+    (
+        "Newton's law of cooling",
+        "Thermal",
+        "A body loses heat at a rate proportional to the temperature difference between it and its surroundings",
+        r"(?i)\bnewton['’]?s\s+law\s+of\s+cooling\b",
+    ),
+    (
+        "Hooke's law",
+        "Material",
+        "The deformation of an elastic material is proportional to the load applied to it",
+        r"(?i)\bhooke['’]?s\s+law\b|\byoung['’]?s\s+modulus\b",
+    ),
+    (
+        "Euler buckling",
+        "Structural",
+        "A slender column buckles at a critical load proportional to its stiffness and inversely proportional to the square of its length",
+        r"(?i)\beuler(?:['’]s)?\s+(?:buckling|critical\s+load|column\s+formula)\b|\bbuckling\s+load\b",
+    ),
+    (
+        "Bernoulli's principle",
+        "Mechanical",
+        "An increase in the speed of a flowing fluid comes with a decrease in its pressure",
+        r"(?i)\bbernoulli['’]?s?\s+(?:principle|equation|law)\b",
+    ),
+    (
+        "Navier-Stokes equations",
+        "Mechanical",
+        "The motion of a viscous fluid follows from the balance of momentum, pressure and viscous forces",
+        r"(?i)\bnavier\s*[-–]?\s*stokes\b",
+    ),
+    (
+        "Pascal's law",
+        "Mechanical",
+        "Pressure applied to a confined fluid is transmitted equally in all directions",
+        r"(?i)\bpascal['’]?s\s+(?:law|principle)\b",
+    ),
+    (
+        "Archimedes' principle",
+        "Mechanical",
+        "A body immersed in a fluid is buoyed up by a force equal to the weight of the fluid it displaces",
+        r"(?i)\barchimedes['’]?\s+principle\b",
+    ),
+    (
+        "Fourier's law",
+        "Thermal",
+        "Heat conducts through a material at a rate proportional to the temperature gradient across it",
+        r"(?i)\bfourier['’]?s\s+law\b|\b(?:heat|thermal)\s+conduction\b",
+    ),
+    (
+        "Stefan-Boltzmann law",
+        "Thermal",
+        "The power a body radiates is proportional to the fourth power of its absolute temperature",
+        r"(?i)\bstefan\s*[-–]?\s*boltzmann\b",
+    ),
+    (
+        "Laws of thermodynamics",
+        "Thermal",
+        "Energy is conserved, and the entropy of an isolated system never decreases",
+        r"(?i)\b(?:zeroth|first|second|third)\s+law\s+of\s+thermodynamics\b|\blaws\s+of\s+thermodynamics\b",
+    ),
+    (
+        "Ideal gas law",
+        "Thermal",
+        "The pressure times the volume of a gas is proportional to its amount and its absolute temperature",
+        r"(?i)\bideal\s+gas\s+law\b|\bP\s*V\s*=\s*n\s*R\s*T\b",
+    ),
+    (
+        "Joule heating",
+        "Electrical",
+        "A current through a resistance dissipates heat in proportion to the square of the current",
+        r"(?i)\bjoule\s+heating\b|\bjoule['’]?s\s+(?:first\s+)?law\b|\bohmic\s+heating\b",
+    ),
+    (
+        "Conservation of energy",
+        "System",
+        "Energy can be converted from one form to another but is neither created nor destroyed",
+        r"(?i)\bconservation\s+of\s+energy\b",
+    ),
+];
+
+static NAMED_LAWS: LazyLock<Vec<NamedLaw>> = LazyLock::new(|| {
+    REGISTRY
+        .iter()
+        .map(|(name, category, statement, pattern)| NamedLaw {
+            name,
+            category: PrincipleCategory::new(category),
+            statement,
+            pattern: Regex::new(pattern).expect("named law patterns compile"),
+        })
+        .collect()
+});
+
+/// A law or equation that principles can be grouped under
+#[derive(Debug)]
+pub struct NamedLaw {
+    pub name: &'static str,
+    pub category: PrincipleCategory,
+    /// What the law says in one sentence, which embedding-based detection compares text against
+    pub statement: &'static str,
+    pattern: Regex,
+}
+
+/// Every law in the registry
+pub fn named_laws() -> &'static [NamedLaw] {
+    &NAMED_LAWS
+}
+
+/// The law `text` names or gives the equation of. With several, the earliest mention wins, and
+/// of mentions starting together the longest ("Newton's law of cooling" over "Newton's law").
+pub fn detect(text: &str) -> Option<&'static str> {
+    NAMED_LAWS
+        .iter()
+        .filter_map(|law| law.pattern.find(text).map(|m| (m.start(), std::cmp::Reverse(m.end()), law.name)))
+        .min()
+        .map(|(_, _, name)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn laws_are_detected_by_name_and_equation() {
+        assert_eq!(detect("The winding obeys Ohm’s law, so heat grows with current"), Some("Ohm's law"));
+        assert_eq!(detect("From V = IR the drop across the coil follows"), Some("Ohm's law"));
+        assert_eq!(detect("By Newton's law of cooling the casing sheds heat"), Some("Newton's law of cooling"));
+        assert_eq!(detect("Newton's second law gives the thrust"), Some("Newton's laws of motion"));
+        assert_eq!(detect("Tall columns fail at the Euler critical load before Hooke's law stops holding"), Some("Euler buckling"));
+        assert_eq!(detect("The Navier–Stokes equations describe the airflow"), Some("Navier-Stokes equations"));
+        assert_eq!(detect("The rotor spins in bearings"), None);
+        assert!(named_laws().iter().all(|law| !law.statement.is_empty()));
+    }
+}
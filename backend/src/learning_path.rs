@@ -0,0 +1,158 @@
+use crate::intern::Term;
+use crate::types::{AnalysisNode, AnalysisResult};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use utoipa::ToSchema;
+
+/// Minutes assumed for a topic before accounting for its principles and complexity
+const BASE_TOPIC_MINUTES: f32 = 15.0;
+const MINUTES_PER_PRINCIPLE: f32 = 5.0;
+
+/// An ordered curriculum for a concept: each stage only builds on topics from earlier stages
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LearningPath {
+    pub concept: String,
+    pub stages: Vec<LearningStage>,
+    pub total_effort_minutes: u32,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LearningStage {
+    /// 1 for the foundations, counting up towards the concept itself
+    pub stage: u32,
+    /// Topics of the stage, simplest first
+    pub topics: Vec<LearningTopic>,
+    pub effort_minutes: u32,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LearningTopic {
+    pub term: String,
+    /// Sub-concepts in the tree, which the path puts in earlier stages
+    pub prerequisites: Vec<String>,
+    pub complexity: Option<f32>,
+    pub principles: usize,
+    pub effort_minutes: u32,
+}
+
+/// Per-term facts gathered from every place the term occurs in the tree
+#[derive(Default)]
+struct Topic {
+    prerequisites: Vec<Term>,
+    complexity: Option<f32>,
+    principles: usize,
+}
+
+/// Turn an analysis tree into a learning path. A node's children are its prerequisites, so each
+/// topic goes one stage after the latest of its prerequisites; within a stage topics are ordered
+/// by complexity.
+pub fn learning_path(result: &AnalysisResult) -> LearningPath {
+    let mut topics: HashMap<Term, Topic> = HashMap::new();
+    collect_topics(&result.tree, &mut topics);
+
+    let mut stages: HashMap<String, u32> = HashMap::new();
+    let mut by_stage: BTreeMap<u32, Vec<LearningTopic>> = BTreeMap::new();
+    for (term, topic) in &topics {
+        let stage = stage_of(term, &topics, &mut stages, &mut HashSet::new());
+        by_stage.entry(stage).or_default().push(LearningTopic {
+            term: term.to_string(),
+            prerequisites: topic.prerequisites.iter().map(Term::to_string).collect(),
+            complexity: topic.complexity,
+            principles: topic.principles,
+            effort_minutes: effort_minutes(topic),
+        });
+    }
+
+    let stages: Vec<LearningStage> = by_stage
+        .into_iter()
+        .map(|(stage, mut topics)| {
+            topics.sort_by(|a, b| {
+                a.complexity.unwrap_or(0.0).total_cmp(&b.complexity.unwrap_or(0.0)).then_with(|| a.term.cmp(&b.term))
+            });
+            LearningStage {
+                stage,
+                effort_minutes: topics.iter().map(|topic| topic.effort_minutes).sum(),
+                topics,
+            }
+        })
+        .collect();
+
+    LearningPath {
+        concept: result.root_term.clone(),
+        total_effort_minutes: stages.iter().map(|stage| stage.effort_minutes).sum(),
+        stages,
+    }
+}
+
+fn collect_topics(node: &AnalysisNode, topics: &mut HashMap<Term, Topic>) {
+    let topic = topics.entry(node.term.clone()).or_default();
+    for child in &node.children {
+        if !topic.prerequisites.contains(&child.term) {
+            topic.prerequisites.push(child.term.clone());
+        }
+    }
+    topic.complexity = topic.complexity.or(node.complexity);
+    topic.principles = topic.principles.max(node.principles.len());
+
+    for child in &node.children {
+        collect_topics(child, topics);
+    }
+}
+
+/// One more than the latest stage among the term's prerequisites. A term can reach itself
+/// through different branches of the tree; such cycles are broken where they close.
+fn stage_of(
+    term: &str,
+    topics: &HashMap<Term, Topic>,
+    stages: &mut HashMap<String, u32>,
+    in_progress: &mut HashSet<String>,
+) -> u32 {
+    if let Some(&stage) = stages.get(term) {
+        return stage;
+    }
+    in_progress.insert(term.to_string());
+
+    let mut stage = 1;
+    for prerequisite in topics.get(term).map(|topic| topic.prerequisites.as_slice()).unwrap_or_default() {
+        if !in_progress.contains(prerequisite.as_str()) {
+            stage = stage.max(stage_of(prerequisite, topics, stages, in_progress) + 1);
+        }
+    }
+
+    in_progress.remove(term);
+    stages.insert(term.to_string(), stage);
+    stage
+}
+
+fn effort_minutes(topic: &Topic) -> u32 {
+    let minutes = BASE_TOPIC_MINUTES + MINUTES_PER_PRINCIPLE * topic.principles as f32;
+    (minutes * (1.0 + topic.complexity.unwrap_or(0.5))).round() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(term: &str, complexity: f32, children: Vec<AnalysisNode>) -> AnalysisNode {
+        AnalysisNode { children, complexity: Some(complexity), ..AnalysisNode::leaf(term, 0) }
+    }
+
+    #[test]
+    fn orders_prerequisites_before_the_topics_needing_them() {
+        let motor = node(
+            "Electric motor",
+            0.6,
+            vec![node("Stator", 0.5, vec![node("Magnetism", 0.4, vec![])]), node("Bearing", 0.2, vec![])],
+        );
+        let path = learning_path(&AnalysisResult::from_tree("Electric motor".to_string(), motor, 0));
+
+        let stages: Vec<Vec<&str>> = path
+            .stages
+            .iter()
+            .map(|stage| stage.topics.iter().map(|topic| topic.term.as_str()).collect())
+            .collect();
+        assert_eq!(stages, [vec!["Bearing", "Magnetism"], vec!["Stator"], vec!["Electric motor"]]);
+        assert_eq!(path.stages[2].topics[0].prerequisites, ["Stator", "Bearing"]);
+        assert_eq!(path.total_effort_minutes, path.stages.iter().map(|stage| stage.effort_minutes).sum::<u32>());
+    }
+}
@@ -1,28 +1,57 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tracing::{info, error};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use wiki_engine::api::{create_router_with_state, WikiEngineState};
-use wiki_engine::cache::{start_cache_cleanup_task, WikiEngineCache};
+use wiki_engine::cache::start_cache_cleanup_task;
+use wiki_engine::labeling::start_labeling_export_task;
+use wiki_engine::monitoring::record_engine_events;
+use wiki_engine::tuning::start_tuning_task;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize tracing
-    tracing_subscriber::registry()
+    // LOG_FORMAT=json switches to one JSON object per log line
+    let json_logs = std::env::var("LOG_FORMAT").is_ok_and(|format| format.eq_ignore_ascii_case("json"));
+    let subscriber = tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "wiki_engine_backend=debug,tower_http=debug".into()),
+                .unwrap_or_else(|_| "wiki_engine_backend=debug,wiki_engine=debug,tower_http=debug".into()),
         )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+        .with(json_logs.then(|| tracing_subscriber::fmt::layer().json()))
+        .with((!json_logs).then(tracing_subscriber::fmt::layer));
+
+    // Export spans over OTLP when OTEL_EXPORTER_OTLP_ENDPOINT is set
+    #[cfg(feature = "otel")]
+    let subscriber = subscriber.with(wiki_engine::telemetry::otlp_layer());
+
+    subscriber.init();
 
     info!("Starting Wiki Engine Backend Server");
 
+    // Load the category taxonomy now so a bad WIKI_ENGINE_CATEGORIES_FILE is reported at startup
+    let categories = wiki_engine::categories::registry().definitions().count();
+    info!("Loaded {} principle categories", categories);
+
     // Create WikiEngine state with cache
     let state = Arc::new(WikiEngineState::new().expect("Failed to create WikiEngine state"));
     
     // Start cache cleanup task
     tokio::spawn(start_cache_cleanup_task(Arc::clone(&state.cache)));
 
+    // Count extracted principles from the engine's events
+    tokio::spawn(record_engine_events(state.engine.events().subscribe()));
+
+    // Refit the confidence thresholds from users' feedback
+    tokio::spawn(start_tuning_task(Arc::clone(state.engine.tuner())));
+
+    // Export sentences the analyzers were unsure about for labeling, when WIKI_ENGINE_LABELING_EXPORT is set
+    let labeling = state.engine.labeling();
+    if let Some(path) = &labeling.config().path {
+        info!("Exporting sentences for labeling to {} every {}s", path.display(), labeling.config().interval_secs);
+        tokio::spawn(start_labeling_export_task(Arc::clone(labeling)));
+    }
+
     // Warm up cache with common engineering terms
     let common_terms = [
         "bridge", "engine", "motor", "gear", "lever", "pulley", "circuit", "transistor",
@@ -51,30 +80,85 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("Endpoints available:");
     info!("  POST /analyze - Analyze engineering principles (JSON body)");
     info!("  GET  /analyze?term=<term>&max_depth=<depth> - Analyze via query params");
-    info!("  GET  /health - Health check");
+    info!("  POST /analyze/batch - Analyze multiple terms concurrently (JSON body)");
+    info!("  POST /analyze/batch/principles - Stream a batch's principles as NDJSON, one per line");
+    info!("  POST /analyze/plan - Estimate an analysis's nodes, Wikipedia calls and time without running it");
+    info!("  GET  /health, /health/live - Liveness check");
+    info!("  GET  /health/ready - Readiness check with dependency status");
+    #[cfg(feature = "metrics")]
+    info!("  GET  /metrics - Prometheus metrics");
+    info!("  GET  /categories - Principle categories with display names and colors");
     info!("  GET  /cache/stats - Cache statistics");
     info!("  POST /cache/clear - Clear cache");
+    info!("  GET  /admin/audit - Audit log of analyses (needs WIKI_ENGINE_ADMIN_TOKEN)");
+    info!("  POST /feedback - Flag a principle as wrong or irrelevant, or a child concept as off-topic");
+    info!("  GET  /admin/feedback - Feedback users gave on analyses (needs WIKI_ENGINE_ADMIN_TOKEN)");
+    info!("  GET  /admin/tuning - Confidence thresholds tuned from feedback, with precision and recall per category (POST to refit; needs WIKI_ENGINE_ADMIN_TOKEN)");
+    info!("  GET  /admin/events - Live stream of engine events as server-sent events (needs WIKI_ENGINE_ADMIN_TOKEN)");
+    info!("  GET  /admin/config - Runtime-tunable settings (PATCH to change them; needs WIKI_ENGINE_ADMIN_TOKEN)");
+    info!("  GET  /kb/export - Download the knowledge base as JSON or YAML (needs WIKI_ENGINE_ADMIN_TOKEN)");
+    info!("  POST /kb/import - Merge a CSV of concept components into the knowledge base (needs WIKI_ENGINE_ADMIN_TOKEN)");
+    info!("  GET  /openapi.json - OpenAPI specification (Swagger UI at /docs)");
 
     // Run the server
-    axum::serve(listener, app)
+    // Connection info lets the audit log name callers that don't come through a proxy
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+        .with_graceful_shutdown(shutdown_signal())
         .await
         .unwrap_or_else(|e| {
             error!("Server error: {}", e);
             std::process::exit(1);
         });
 
+    #[cfg(feature = "otel")]
+    wiki_engine::telemetry::shutdown();
+
     Ok(())
 }
 
+// Graceful shutdown handling
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install signal handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    info!("Shutdown signal received, starting graceful shutdown");
+}
+
 #[cfg(test)]
 mod tests {
-    use super::*;
     use axum::{
         body::Body,
+        extract::ConnectInfo,
         http::{Request, StatusCode},
     };
     use tower::ServiceExt;
-    use wiki_engine::types::SearchRequest;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use wiki_engine::api::{create_router, create_router_with_state, WikiEngineState};
+    use wiki_engine::config::{TenantTokens, TrustedProxies, WebhookConfig};
+    use wiki_engine::events::{self, EngineEvent};
+    use wiki_engine::semantic_analyzer::ConceptKnowledgeBase;
+    use wiki_engine::types::{EngineeringPrinciple, PrincipleCategory, SearchRequest};
+    use wiki_engine::webhooks::{self, WebhookEvent, WebhookNotifier};
 
     #[tokio::test]
     async fn test_health_endpoint() {
@@ -88,6 +172,39 @@ mod tests {
         assert_eq!(response.status(), StatusCode::OK);
     }
 
+    #[tokio::test]
+    async fn test_network_stats_endpoint() {
+        let app = create_router().unwrap();
+
+        let response = app
+            .oneshot(Request::builder().uri("/stats/network").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(json["data"]["requests"].is_u64());
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_propagated() {
+        let app = create_router().unwrap();
+        
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/health")
+                    .header("x-request-id", "test-request-id")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.headers()["x-request-id"], "test-request-id");
+    }
+
     #[tokio::test]
     async fn test_analyze_endpoint() {
         let app = create_router().unwrap();
@@ -96,6 +213,7 @@ mod tests {
             term: "bridge".to_string(),
             max_depth: Some(2),
             max_results: Some(5),
+            ..Default::default()
         };
         
         let request = Request::builder()
@@ -111,6 +229,29 @@ mod tests {
         assert!(response.status().is_success() || response.status().is_server_error());
     }
 
+    #[tokio::test]
+    async fn test_analyze_endpoint_rejects_invalid_request() {
+        let app = create_router().unwrap();
+        
+        let request_body = SearchRequest {
+            term: "   ".to_string(),
+            max_depth: Some(255),
+            max_results: Some(5),
+            ..Default::default()
+        };
+        
+        let request = Request::builder()
+            .uri("/analyze")
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+            .unwrap();
+
+        let response = app.oneshot(request).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
     #[tokio::test]
     async fn test_cache_stats_endpoint() {
         let app = create_router().unwrap();
@@ -122,53 +263,521 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::OK);
     }
-}
 
-// Performance monitoring
-#[cfg(feature = "metrics")]
-mod metrics {
-    use std::time::Instant;
-    
-    pub struct PerformanceMonitor {
-        start_time: Instant,
+    #[tokio::test]
+    async fn test_refresh_unknown_result_returns_not_found() {
+        let app = create_router().unwrap();
+        
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/results/does-not-exist/refresh")
+                    .method("POST")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
-    
-    impl PerformanceMonitor {
-        pub fn new() -> Self {
-            Self {
-                start_time: Instant::now(),
-            }
+
+    #[tokio::test]
+    async fn test_results_are_encoded_as_the_client_accepts() {
+        let state = WikiEngineState::new().unwrap();
+        let result = serde_json::from_value(serde_json::json!({
+            "root_term": "bridge",
+            "tree": { "term": "bridge", "principles": [], "children": [], "depth": 0, "processing_time_ms": 0 },
+            "total_processing_time_ms": 0,
+            "total_principles": 0,
+            "max_depth_reached": 0
+        }))
+        .unwrap();
+        let id = state.results.insert(SearchRequest::default(), result);
+        let app = create_router_with_state(Arc::new(state)).unwrap();
+
+        let get = |accept: &str| {
+            Request::builder().uri(format!("/results/{}", id)).header("accept", accept).body(Body::empty()).unwrap()
+        };
+        let response = app.clone().oneshot(get("application/cbor;q=0.5, application/msgpack")).await.unwrap();
+        assert_eq!(response.headers()["content-type"], "application/msgpack");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let decoded: serde_json::Value = rmp_serde::from_slice(&body).unwrap();
+        assert_eq!(decoded["data"]["tree"]["term"], "bridge");
+
+        let response = app.clone().oneshot(get("application/cbor")).await.unwrap();
+        assert_eq!(response.headers()["content-type"], "application/cbor");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let decoded: serde_json::Value = ciborium::from_reader(&body[..]).unwrap();
+        assert_eq!(decoded["data"]["root_term"], "bridge");
+
+        let response = app.oneshot(get("text/html, */*;q=0.8")).await.unwrap();
+        assert_eq!(response.headers()["content-type"], "application/json");
+    }
+
+    #[tokio::test]
+    async fn test_analyses_are_audited_for_admins() {
+        let mut state = WikiEngineState::new().unwrap();
+        state.admin_token = Some("secret".to_string());
+        state.trusted_proxies = TrustedProxies::default().with_proxy([10, 0, 0, 1].into()).with_proxy([10, 0, 0, 2].into());
+        let app = create_router_with_state(Arc::new(state)).unwrap();
+
+        let analyze = |peer: [u8; 4]| {
+            let request_body = SearchRequest { term: "   ".to_string(), ..Default::default() };
+            Request::builder()
+                .uri("/analyze")
+                .method("POST")
+                .header("content-type", "application/json")
+                .header("x-forwarded-for", "198.51.100.1, 203.0.113.7, 10.0.0.1")
+                .extension(ConnectInfo(SocketAddr::from((peer, 443))))
+                .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+                .unwrap()
+        };
+        let response = app.clone().oneshot(analyze([10, 0, 0, 2])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        // Only trusted proxies are believed about who they forward for
+        let response = app.clone().oneshot(analyze([192, 0, 2, 5])).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let audit = |token: Option<&str>| {
+            let request = Request::builder().uri("/admin/audit?errors_only=true");
+            let request = match token {
+                Some(token) => request.header("authorization", format!("Bearer {}", token)),
+                None => request,
+            };
+            request.body(Body::empty()).unwrap()
+        };
+        let response = app.clone().oneshot(audit(Some("wrong"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app.oneshot(audit(Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let entries = json["data"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["endpoint"], "/analyze");
+        assert_eq!(entries[0]["caller"], "192.0.2.5");
+        assert_eq!(entries[1]["caller"], "203.0.113.7");
+        assert!(entries[0]["error"].as_str().unwrap().starts_with("Invalid request"));
+    }
+
+    #[tokio::test]
+    async fn test_feedback_is_recorded_and_excludes_flagged_concepts() {
+        let mut state = WikiEngineState::new().unwrap();
+        state.admin_token = Some("secret".to_string());
+        let state = Arc::new(state);
+        let app = create_router_with_state(Arc::clone(&state)).unwrap();
+
+        let flag = |caller: &str, body: serde_json::Value| {
+            Request::builder()
+                .uri("/feedback")
+                .method("POST")
+                .header("content-type", "application/json")
+                .extension(ConnectInfo(SocketAddr::new(caller.parse().unwrap(), 50000)))
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let off_topic = serde_json::json!({ "kind": "off_topic_concept", "term": "Bridge", "concept": "river" });
+        for caller in ["203.0.113.1", "203.0.113.2", "203.0.113.3"] {
+            let response = app.clone().oneshot(flag(caller, off_topic.clone())).await.unwrap();
+            assert_eq!(response.status(), StatusCode::CREATED);
         }
-        
-        pub fn uptime(&self) -> std::time::Duration {
-            self.start_time.elapsed()
+        assert!(state.engine.feedback().excludes_concept("default", "bridge", "River"));
+
+        // A client can't pass for several callers by forging `X-Forwarded-For`
+        let off_topic = serde_json::json!({ "kind": "off_topic_concept", "term": "Bridge", "concept": "toll" });
+        for forged in ["198.51.100.1", "198.51.100.2", "198.51.100.3"] {
+            let mut request = flag("192.0.2.5", off_topic.clone());
+            request.headers_mut().insert("x-forwarded-for", forged.parse().unwrap());
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::CREATED);
         }
+        assert!(!state.engine.feedback().excludes_concept("default", "bridge", "toll"));
+
+        let invalid = serde_json::json!({ "kind": "wrong_principle", "term": "bridge", "concept": "river" });
+        let response = app.clone().oneshot(flag("203.0.113.4", invalid)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let request = Request::builder()
+            .uri("/admin/feedback?term=bridge&limit=2")
+            .header("authorization", "Bearer secret")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let entries = json["data"].as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["caller"], "192.0.2.5");
+        assert_eq!(entries[0]["concept"], "toll");
     }
-}
 
-// Graceful shutdown handling
-async fn shutdown_signal() {
-    let ctrl_c = async {
-        tokio::signal::ctrl_c()
+    #[tokio::test]
+    async fn test_thresholds_are_tuned_from_principle_feedback() {
+        let mut state = WikiEngineState::new().unwrap();
+        state.admin_token = Some("secret".to_string());
+        let state = Arc::new(state);
+        let app = create_router_with_state(Arc::clone(&state)).unwrap();
+        let truss = EngineeringPrinciple {
+            id: "truss".to_string(),
+            title: "Truss action".to_string(),
+            description: "A truss carries load through members in tension and compression".to_string(),
+            category: PrincipleCategory::new("Structural"),
+            confidence: 0.55,
+            source_url: String::new(),
+            related_terms: vec![],
+            source_span: (0, 0),
+            source_section: None,
+            appears_in: vec![],
+            classified_by: None,
+            named_law: None,
+            component: None,
+        };
+        state.cache.cache_principles("bridge".to_string(), vec![truss]);
+
+        for (caller, kind) in [("203.0.113.1", "correct_principle"), ("203.0.113.2", "wrong_principle")] {
+            let body = serde_json::json!({ "kind": kind, "term": "bridge", "principle": "truss action" });
+            let request = Request::builder()
+                .uri("/feedback")
+                .method("POST")
+                .header("content-type", "application/json")
+                .extension(ConnectInfo(SocketAddr::new(caller.parse().unwrap(), 50000)))
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["data"]["category"], "Structural");
+        }
+        assert!(!state.engine.feedback().excludes_concept("default", "bridge", "truss action"));
+
+        let request = Request::builder()
+            .uri("/admin/tuning")
+            .method("POST")
+            .header("authorization", "Bearer secret")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let structural = &json["data"]["categories"][0];
+        assert_eq!(structural["category"], "Structural");
+        assert_eq!(structural["labels"], 2);
+        assert_eq!(structural["precision"], 0.5);
+        assert!(structural["threshold"].is_null());
+        assert_eq!(state.engine.tuner().report().overall.labels, 2);
+
+        let response = app
+            .oneshot(Request::builder().uri("/admin/tuning").body(Body::empty()).unwrap())
             .await
-            .expect("failed to install Ctrl+C handler");
-    };
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
 
-    #[cfg(unix)]
-    let terminate = async {
-        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
-            .expect("failed to install signal handler")
-            .recv()
+    #[tokio::test]
+    async fn test_engine_events_stream_to_admins() {
+        use futures::StreamExt;
+
+        let mut state = WikiEngineState::new().unwrap();
+        state.admin_token = Some("secret".to_string());
+        let state = Arc::new(state);
+        let app = create_router_with_state(Arc::clone(&state)).unwrap();
+
+        let request = Request::builder()
+            .uri("/admin/events?events=analysis_completed")
+            .header("authorization", "Bearer secret")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        let id = uuid::Uuid::new_v4();
+        let bus = state.engine.events();
+        events::scope(id, async {
+            bus.emit(EngineEvent::node_started("bridge", 0));
+            bus.emit(EngineEvent::analysis_completed("bridge", &Err(wiki_engine::types::WikiEngineError::PageNotFound("bridge".to_string()))));
+        })
+        .await;
+
+        let frame = response.into_body().into_data_stream().next().await.unwrap().unwrap();
+        let frame = String::from_utf8(frame.to_vec()).unwrap();
+        assert!(frame.starts_with("event: analysis_completed\n"), "{}", frame);
+        let data: serde_json::Value = serde_json::from_str(frame.lines().nth(1).unwrap().strip_prefix("data: ").unwrap()).unwrap();
+        assert_eq!(data["analysis_id"], id.to_string());
+        assert!(data["error"].as_str().unwrap().contains("bridge"));
+
+        let response = app
+            .oneshot(Request::builder().uri("/admin/events").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_runtime_config_can_be_patched_by_admins() {
+        let mut state = WikiEngineState::new().unwrap();
+        state.admin_token = Some("secret".to_string());
+        let state = Arc::new(state);
+        let app = create_router_with_state(Arc::clone(&state)).unwrap();
+
+        let patch = |body: serde_json::Value| {
+            Request::builder()
+                .uri("/admin/config")
+                .method("PATCH")
+                .header("authorization", "Bearer secret")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let response = app.clone().oneshot(patch(serde_json::json!({ "limits": { "max_depth": 1 } }))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["data"]["limits"]["max_depth"], 1);
+        assert_eq!(state.limits().max_depth, 1);
+
+        // The new ceiling applies to the next request
+        let request_body = SearchRequest { term: "bridge".to_string(), max_depth: Some(2), ..Default::default() };
+        let request = Request::builder()
+            .uri("/analyze/plan")
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(Body::from(serde_json::to_string(&request_body).unwrap()))
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = app.clone().oneshot(patch(serde_json::json!({ "similarity_threshold": 2.0 }))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(state.config().similarity_threshold, state.engine.similarity_threshold());
+
+        let response = app
+            .oneshot(Request::builder().uri("/admin/config").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_knowledge_base_import_takes_effect() {
+        let mut state = WikiEngineState::new().unwrap();
+        state.admin_token = Some("secret".to_string());
+        let state = Arc::new(state);
+        let app = create_router_with_state(Arc::clone(&state)).unwrap();
+        let original_version = state.engine.kb_version();
+
+        let import = |query: &str, csv: &str| {
+            Request::builder()
+                .uri(format!("/kb/import{}", query))
+                .method("POST")
+                .header("authorization", "Bearer secret")
+                .header("content-type", "text/csv")
+                .body(Body::from(csv.to_string()))
+                .unwrap()
+        };
+        let csv = "concept,component,relation,confidence,category\nuav,parachute,part of,0.8,Mechanical\nuav,battery,controls,,\n";
+        let response = app.clone().oneshot(import("?dry_run=true", csv)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["data"]["conflicts"][0]["existing"], "requires");
+        assert_eq!(state.engine.kb_version(), original_version);
+
+        let response = app.clone().oneshot(import("", csv)).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["data"]["components_added"], 2);
+        assert_eq!(json["data"]["kb_version"], state.engine.kb_version());
+        assert_ne!(state.engine.kb_version(), original_version);
+        let decomposition = state.engine.decompose_offline("uav", 1, None).unwrap();
+        assert!(decomposition.components.iter().any(|component| component.name == "parachute"));
+
+        let response = app.oneshot(import("", "concept,component\nuav,\n")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_knowledge_base_exports_as_yaml() {
+        let mut state = WikiEngineState::new().unwrap();
+        state.admin_token = Some("secret".to_string());
+        let state = Arc::new(state);
+        let app = create_router_with_state(Arc::clone(&state)).unwrap();
+
+        let export = |query: &str| {
+            Request::builder()
+                .uri(format!("/kb/export{}", query))
+                .header("authorization", "Bearer secret")
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = app.clone().oneshot(export("?format=yaml")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-kb-version"], state.engine.kb_version().as_str());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let exported: ConceptKnowledgeBase = serde_yaml::from_slice(&body).unwrap();
+        assert_eq!(exported.version(), state.engine.kb_version());
+
+        let response = app.oneshot(export("?version=0000000000000000")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_tenants_are_named_by_header() {
+        let mut state = WikiEngineState::new().unwrap();
+        state.tenant_tokens = TenantTokens::default().with_token("team-a", "secret-a").with_token("team-b", "secret-b");
+        let app = create_router_with_state(Arc::new(state)).unwrap();
+        let clear = |tenant: &str, token: &str| {
+            Request::builder()
+                .uri("/cache/clear")
+                .method("POST")
+                .header("x-tenant", tenant)
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(clear("team-a", "secret-a")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["data"], "Cache of tenant 'team-a' cleared");
+
+        // Naming a tenant isn't enough to clear its cache
+        let response = app.clone().oneshot(clear("team-a", "secret-b")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = app.oneshot(clear("team a/../b", "secret-a")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_only_admins_clear_every_tenants_cache() {
+        let mut state = WikiEngineState::new().unwrap();
+        state.admin_token = Some("secret".to_string());
+        let state = Arc::new(state);
+        let app = create_router_with_state(Arc::clone(&state)).unwrap();
+        state.cache.cache_principles("bridge".to_string(), vec![]);
+        wiki_engine::cache::with_tenant("team-a", async { state.cache.cache_principles("bridge".to_string(), vec![]) }).await;
+
+        let request = |uri: &str, method: &str, token: Option<&str>| {
+            let request = Request::builder().uri(uri).method(method);
+            let request = match token {
+                Some(token) => request.header("authorization", format!("Bearer {}", token)),
+                None => request,
+            };
+            request.body(Body::empty()).unwrap()
+        };
+        let tenants = |response: axum::response::Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            json["data"]["tenants"].as_object().unwrap().keys().cloned().collect::<Vec<_>>()
+        };
+
+        // Only the admin sees who else uses the cache
+        let response = app.clone().oneshot(request("/cache/stats", "GET", None)).await.unwrap();
+        assert!(tenants(response).await.is_empty());
+        let response = app.clone().oneshot(request("/cache/stats", "GET", Some("secret"))).await.unwrap();
+        assert_eq!(tenants(response).await, ["default", "team-a"]);
+
+        let response = app.clone().oneshot(request("/cache/clear", "POST", None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(state.cache.get_cache_stats().principles_count, 2);
+
+        let response = app.oneshot(request("/cache/clear", "POST", Some("secret"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(state.cache.get_cache_stats().principles_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_batch_results_go_to_allowed_webhooks() {
+        let receiver = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .respond_with(wiremock::ResponseTemplate::new(204))
+            .mount(&receiver)
             .await;
-    };
+        let batch = |webhook_url: &str| {
+            let body = serde_json::json!({ "terms": ["Gearbox"], "max_depth": 1, "max_results": 1, "webhook_url": webhook_url });
+            Request::builder()
+                .uri("/analyze/batch")
+                .method("POST")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
 
-    #[cfg(not(unix))]
-    let terminate = std::future::pending::<()>();
+        // Without a secret there is nothing to sign deliveries with
+        let app = create_router().unwrap();
+        let response = app.oneshot(batch(&format!("{}/hook", receiver.uri()))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
 
-    tokio::select! {
-        _ = ctrl_c => {},
-        _ = terminate => {},
+        let mut state = WikiEngineState::new().unwrap();
+        let config = WebhookConfig {
+            secret: Some("hook-secret".to_string()),
+            allowed_hosts: vec!["127.0.0.1".to_string()],
+            ..WebhookConfig::default()
+        };
+        state.webhooks = WebhookNotifier::new(config).unwrap();
+        let app = create_router_with_state(Arc::new(state)).unwrap();
+
+        let response = app.clone().oneshot(batch("https://hooks.example.org/hook")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = app.oneshot(batch(&format!("{}/hook", receiver.uri()))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let id = json["data"]["id"].as_str().unwrap().to_string();
+
+        let delivery = tokio::time::timeout(std::time::Duration::from_secs(60), async {
+            loop {
+                if let Some(delivery) = receiver.received_requests().await.unwrap().pop() {
+                    return delivery;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let event: WebhookEvent = serde_json::from_slice(&delivery.body).unwrap();
+        assert_eq!(event.id, id);
+        assert_eq!(event.items.len(), 1);
+        assert_eq!(event.items[0].term, "Gearbox");
+        let timestamp: i64 = delivery.headers[webhooks::TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+        assert_eq!(
+            delivery.headers[webhooks::SIGNATURE_HEADER].to_str().unwrap(),
+            webhooks::sign(b"hook-secret", timestamp, &delivery.body)
+        );
     }
 
-    info!("Shutdown signal received, starting graceful shutdown");
-}
\ No newline at end of file
+    #[tokio::test]
+    async fn test_openapi_endpoint() {
+        let app = create_router().unwrap();
+        
+        let response = app
+            .oneshot(Request::builder().uri("/openapi.json").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let spec: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(spec["paths"]["/analyze"].is_object());
+    }
+
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        let app = create_router().unwrap();
+        
+        let response = app
+            .oneshot(Request::builder().uri("/metrics").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
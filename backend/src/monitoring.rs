@@ -0,0 +1,207 @@
+use crate::events::EngineEvent;
+use crate::progress;
+use crate::types::NetworkStats;
+use metrics::{counter, gauge, histogram};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+pub const HTTP_REQUESTS_TOTAL: &str = "http_requests_total";
+pub const HTTP_REQUEST_DURATION_SECONDS: &str = "http_request_duration_seconds";
+pub const WIKIPEDIA_API_CALLS_TOTAL: &str = "wikipedia_api_calls_total";
+pub const WIKIPEDIA_API_CALL_DURATION_SECONDS: &str = "wikipedia_api_call_duration_seconds";
+pub const WIKIPEDIA_API_BYTES_TOTAL: &str = "wikipedia_api_bytes_total";
+pub const WIKIPEDIA_API_ERRORS_TOTAL: &str = "wikipedia_api_errors_total";
+pub const CACHE_LOOKUPS_TOTAL: &str = "cache_lookups_total";
+pub const ANALYSES_IN_FLIGHT: &str = "analyses_in_flight";
+pub const PRINCIPLES_EXTRACTED_TOTAL: &str = "principles_extracted_total";
+
+#[cfg(feature = "metrics")]
+static PROMETHEUS_HANDLE: std::sync::OnceLock<metrics_exporter_prometheus::PrometheusHandle> =
+    std::sync::OnceLock::new();
+
+/// Install the Prometheus recorder (once per process) and return its handle
+#[cfg(feature = "metrics")]
+pub fn prometheus_handle() -> &'static metrics_exporter_prometheus::PrometheusHandle {
+    PROMETHEUS_HANDLE.get_or_init(|| {
+        let builder = metrics_exporter_prometheus::PrometheusBuilder::new();
+        let handle = match builder.install_recorder() {
+            Ok(handle) => handle,
+            Err(e) => {
+                tracing::warn!("Failed to install Prometheus recorder: {}", e);
+                metrics_exporter_prometheus::PrometheusBuilder::new().build_recorder().handle()
+            }
+        };
+        describe_metrics();
+        handle
+    })
+}
+
+#[cfg(feature = "metrics")]
+fn describe_metrics() {
+    use metrics::{describe_counter, describe_gauge, describe_histogram, Unit};
+
+    describe_counter!(HTTP_REQUESTS_TOTAL, "HTTP requests handled, by method, route and status");
+    describe_histogram!(HTTP_REQUEST_DURATION_SECONDS, Unit::Seconds, "HTTP request latency, by method and route");
+    describe_counter!(WIKIPEDIA_API_CALLS_TOTAL, "Requests sent to the Wikipedia API, by outcome");
+    describe_histogram!(WIKIPEDIA_API_CALL_DURATION_SECONDS, Unit::Seconds, "Wikipedia API request latency");
+    describe_counter!(WIKIPEDIA_API_BYTES_TOTAL, Unit::Bytes, "Response body bytes received from the Wikipedia API");
+    describe_counter!(WIKIPEDIA_API_ERRORS_TOTAL, "Error responses returned inside successful Wikipedia API calls");
+    describe_counter!(CACHE_LOOKUPS_TOTAL, "Cache lookups, by cache namespace and hit/miss");
+    describe_gauge!(ANALYSES_IN_FLIGHT, "Recursive analyses currently running");
+    describe_counter!(PRINCIPLES_EXTRACTED_TOTAL, "Principles extracted, by category");
+}
+
+pub fn record_http_request(method: &str, route: &str, status: u16, elapsed: Duration) {
+    counter!(
+        HTTP_REQUESTS_TOTAL,
+        "method" => method.to_string(),
+        "route" => route.to_string(),
+        "status" => status.to_string()
+    )
+    .increment(1);
+    histogram!(
+        HTTP_REQUEST_DURATION_SECONDS,
+        "method" => method.to_string(),
+        "route" => route.to_string()
+    )
+    .record(elapsed.as_secs_f64());
+}
+
+/// Wikipedia traffic counters, kept for the whole process and for each tracked analysis
+#[derive(Default)]
+struct NetworkCounters {
+    requests: AtomicU64,
+    bytes: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl NetworkCounters {
+    const fn new() -> Self {
+        Self {
+            requests: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+        }
+    }
+
+    fn snapshot(&self) -> NetworkStats {
+        NetworkStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            bytes: self.bytes.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+}
+
+static GLOBAL_NETWORK: NetworkCounters = NetworkCounters::new();
+
+/// Time spent in Wikipedia calls since the process started, for the average call latency
+static WIKIPEDIA_CALL_MICROS: AtomicU64 = AtomicU64::new(0);
+
+tokio::task_local! {
+    static TRACKED_NETWORK: Arc<NetworkCounters>;
+}
+
+/// Apply `record` to the global counters and to those of the analysis being tracked, if any
+fn record_network(record: impl Fn(&NetworkCounters)) {
+    record(&GLOBAL_NETWORK);
+    let _ = TRACKED_NETWORK.try_with(|counters| record(counters));
+}
+
+/// Run `future`, returning the Wikipedia traffic it caused alongside its output
+pub async fn track_network_stats<F: Future>(future: F) -> (F::Output, NetworkStats) {
+    let counters = Arc::new(NetworkCounters::default());
+    let output = TRACKED_NETWORK.scope(Arc::clone(&counters), future).await;
+    (output, counters.snapshot())
+}
+
+/// Wikipedia traffic since the process started
+pub fn global_network_stats() -> NetworkStats {
+    GLOBAL_NETWORK.snapshot()
+}
+
+/// Average latency of the Wikipedia calls made so far, or `None` before the first
+pub fn average_wikipedia_call_secs() -> Option<f32> {
+    let calls = GLOBAL_NETWORK.requests.load(Ordering::Relaxed);
+    (calls > 0).then(|| WIKIPEDIA_CALL_MICROS.load(Ordering::Relaxed) as f32 / calls as f32 / 1_000_000.0)
+}
+
+pub fn record_wikipedia_call(outcome: &'static str, elapsed: Duration) {
+    progress::record_wikipedia_call();
+    let failed = matches!(outcome, "network_error" | "http_error" | "rate_limited");
+    record_network(|counters| {
+        counters.requests.fetch_add(1, Ordering::Relaxed);
+        if failed {
+            counters.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    });
+    WIKIPEDIA_CALL_MICROS.fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    counter!(WIKIPEDIA_API_CALLS_TOTAL, "outcome" => outcome).increment(1);
+    histogram!(WIKIPEDIA_API_CALL_DURATION_SECONDS).record(elapsed.as_secs_f64());
+}
+
+pub fn record_wikipedia_bytes(bytes: usize) {
+    record_network(|counters| {
+        counters.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    });
+    counter!(WIKIPEDIA_API_BYTES_TOTAL).increment(bytes as u64);
+}
+
+/// An error reported in the body of an otherwise successful API response (e.g. maxlag)
+pub fn record_wikipedia_api_error() {
+    record_network(|counters| {
+        counters.errors.fetch_add(1, Ordering::Relaxed);
+    });
+    counter!(WIKIPEDIA_API_ERRORS_TOTAL).increment(1);
+}
+
+pub fn record_cache_lookup(cache: &'static str, hit: bool) {
+    if hit {
+        progress::record_cache_hit();
+    }
+    let result = if hit { "hit" } else { "miss" };
+    counter!(CACHE_LOOKUPS_TOTAL, "cache" => cache, "result" => result).increment(1);
+}
+
+pub fn record_principle_extracted(category: &str) {
+    counter!(PRINCIPLES_EXTRACTED_TOTAL, "category" => category.to_string()).increment(1);
+}
+
+/// Record the metrics derived from the engine's events until the bus is dropped
+pub async fn record_engine_events(mut events: broadcast::Receiver<EngineEvent>) {
+    loop {
+        match events.recv().await {
+            Ok(EngineEvent::PrincipleExtracted { category, .. }) => record_principle_extracted(&category),
+            Ok(_) => {}
+            Err(broadcast::error::RecvError::Lagged(missed)) => {
+                tracing::warn!("Metrics fell behind the engine's events, {} not recorded", missed)
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Tracks a running analysis in the in-flight gauge until dropped
+pub struct InFlightGuard;
+
+impl InFlightGuard {
+    pub fn new() -> Self {
+        gauge!(ANALYSES_IN_FLIGHT).increment(1.0);
+        Self
+    }
+}
+
+impl Default for InFlightGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        gauge!(ANALYSES_IN_FLIGHT).decrement(1.0);
+    }
+}
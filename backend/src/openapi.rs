@@ -0,0 +1,44 @@
+use crate::api;
+use utoipa::OpenApi;
+
+/// OpenAPI description of the HTTP API, served at `/openapi.json` (Swagger UI at `/docs`)
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Wiki Engine API", description = "Recursive engineering-principle analysis of Wikipedia concepts"),
+    paths(
+        api::health_check,
+        api::readiness_check,
+        api::analyze_term,
+        api::analyze_term_query,
+        api::analyze_batch,
+        api::stream_batch_principles,
+        api::plan_analysis,
+        api::get_result,
+        api::result_flashcards,
+        api::refresh_result,
+        api::learning_path,
+        api::suggest_terms,
+        api::list_categories,
+        api::cache_stats,
+        api::network_stats,
+        api::clear_cache,
+        api::audit_log,
+        api::get_config,
+        api::patch_config,
+        api::submit_feedback,
+        api::list_feedback,
+        api::tuning_report,
+        api::refit_thresholds,
+        api::stream_events,
+        api::export_knowledge_base,
+        api::import_knowledge_base,
+    ),
+    tags(
+        (name = "analysis", description = "Concept analysis and suggestions"),
+        (name = "cache", description = "Cache inspection and management"),
+        (name = "system", description = "Health and monitoring"),
+        (name = "knowledge_base", description = "Editing the concept knowledge base analyses decompose concepts with"),
+        (name = "admin", description = "Operator endpoints, enabled by setting WIKI_ENGINE_ADMIN_TOKEN")
+    )
+)]
+pub struct ApiDoc;
@@ -0,0 +1,446 @@
+use crate::analyzer::EngineeringAnalyzer;
+use crate::laws;
+use crate::types::{EngineeringPrinciple, PrincipleCategory, Result, WikiEngineError, WikipediaPage};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Version of the plugin interface; plugins report the version they were built against from
+/// `wiki_engine_plugin_abi` and are refused when it differs
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// Largest output, in bytes, a WASM extraction may return; a module returning more fails
+pub const MAX_PLUGIN_OUTPUT_BYTES: usize = 4 << 20;
+
+/// A principle as an extractor plugin reports it, one element of the JSON array it returns
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtractedPrinciple {
+    pub title: String,
+    pub description: String,
+    /// Category id, e.g. `"Mechanical"`; ids the category registry doesn't know are kept as is
+    pub category: String,
+    /// Clamped to 0-1
+    pub confidence: f32,
+    /// Byte range of the passage of the text the principle came from, if it came from one
+    #[serde(default)]
+    pub span: Option<(usize, usize)>,
+}
+
+impl ExtractedPrinciple {
+    /// The principle as found on `page`, or `None` when it has no title or description. A span
+    /// that isn't a passage of the page's text is dropped.
+    pub fn into_principle(self, page: &WikipediaPage) -> Option<EngineeringPrinciple> {
+        if self.title.trim().is_empty() || self.description.trim().is_empty() {
+            return None;
+        }
+        let source_span = self
+            .span
+            .filter(|&(start, end)| start < end && page.extract.get(start..end).is_some())
+            .unwrap_or((0, 0));
+        Some(EngineeringPrinciple {
+            id: uuid::Uuid::new_v4().to_string(),
+            named_law: laws::detect(&self.description).map(str::to_string),
+            title: self.title.trim().to_string(),
+            description: self.description.trim().to_string(),
+            category: PrincipleCategory::new(&self.category),
+            confidence: self.confidence.clamp(0.0, 1.0),
+            source_url: page.url.clone(),
+            related_terms: vec![],
+            source_span,
+            source_section: (source_span != (0, 0))
+                .then(|| EngineeringAnalyzer::section_at(&page.extract, source_span.0))
+                .flatten(),
+            appears_in: vec![],
+            classified_by: None,
+            component: None,
+        })
+    }
+}
+
+/// An extractor run on every analyzed page alongside the built-in analyzers, so organizations
+/// can add proprietary extraction without forking the crate. Implement it in-process and pass
+/// it to `WikiEngineBuilder::with_extractor_plugin`, or build a WASM module or shared library
+/// and list it in `WIKI_ENGINE_EXTRACTOR_PLUGINS`.
+pub trait ExtractorPlugin: Send + Sync {
+    /// Name the plugin is reported under in logs
+    fn name(&self) -> &str;
+
+    /// Principles stated in `text`, the page's plain-text extract
+    fn extract(&self, text: &str) -> Result<Vec<ExtractedPrinciple>>;
+}
+
+/// The plugins listed in `WIKI_ENGINE_EXTRACTOR_PLUGINS`, comma-separated paths to `.wasm`
+/// modules and shared libraries. A plugin that can't be loaded is an error, so a server never
+/// starts without an extractor it was configured with.
+pub fn plugins_from_env() -> Result<Vec<Arc<dyn ExtractorPlugin>>> {
+    let Ok(paths) = std::env::var("WIKI_ENGINE_EXTRACTOR_PLUGINS") else {
+        return Ok(vec![]);
+    };
+    paths
+        .split(',')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(|path| load(Path::new(path)))
+        .collect()
+}
+
+/// Load the plugin at `path`: a WebAssembly module when it ends in `.wasm`, otherwise a shared
+/// library
+pub fn load(path: &Path) -> Result<Arc<dyn ExtractorPlugin>> {
+    #[cfg(feature = "plugins")]
+    {
+        let plugin: Arc<dyn ExtractorPlugin> = if path.extension().is_some_and(|extension| extension == "wasm") {
+            Arc::new(WasmExtractorPlugin::load(path)?)
+        } else {
+            Arc::new(NativeExtractorPlugin::load(path)?)
+        };
+        tracing::info!("Loaded extractor plugin '{}' from {}", plugin.name(), path.display());
+        Ok(plugin)
+    }
+    #[cfg(not(feature = "plugins"))]
+    {
+        Err(WikiEngineError::Analysis(format!(
+            "Cannot load extractor plugin {}: built without the `plugins` feature",
+            path.display()
+        )))
+    }
+}
+
+#[cfg(feature = "plugins")]
+fn plugin_name(path: &Path) -> String {
+    path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_else(|| path.display().to_string())
+}
+
+#[cfg(feature = "plugins")]
+fn plugin_error(name: &str, error: impl std::fmt::Display) -> WikiEngineError {
+    WikiEngineError::Analysis(format!("Extractor plugin '{}' failed: {}", name, error))
+}
+
+#[cfg(feature = "plugins")]
+fn parse_output(name: &str, json: &[u8]) -> Result<Vec<ExtractedPrinciple>> {
+    serde_json::from_slice(json).map_err(|e| plugin_error(name, format!("invalid output: {}", e)))
+}
+
+#[cfg(feature = "plugins")]
+pub use native::NativeExtractorPlugin;
+#[cfg(feature = "plugins")]
+pub use wasm::{WasmExtractorPlugin, WASM_PLUGIN_FUEL, WASM_PLUGIN_MAX_MEMORY_BYTES};
+
+#[cfg(feature = "plugins")]
+mod wasm {
+    use super::{parse_output, plugin_error, plugin_name, ExtractedPrinciple, ExtractorPlugin, MAX_PLUGIN_OUTPUT_BYTES, PLUGIN_ABI_VERSION};
+    use crate::types::Result;
+    use std::path::Path;
+    use wasmi::{Config, Engine, Instance, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+    /// Instructions (roughly) a single extraction may execute before it is stopped
+    pub const WASM_PLUGIN_FUEL: u64 = 2_000_000_000;
+
+    /// Linear memory a single extraction may grow to
+    pub const WASM_PLUGIN_MAX_MEMORY_BYTES: usize = 64 << 20;
+
+    /// Elements a module's table may grow to
+    const WASM_PLUGIN_MAX_TABLE_ELEMENTS: u32 = 10_000;
+
+    /// A WebAssembly module run in a sandbox with no imports. It exports its `memory` and:
+    ///
+    /// - `wiki_engine_plugin_abi() -> i32`, returning [`PLUGIN_ABI_VERSION`]
+    /// - `alloc(len: i32) -> i32`, reserving `len` bytes for the input text
+    /// - `extract(ptr: i32, len: i32) -> i64`, given the UTF-8 text, returning the location of
+    ///   its output, a JSON array of [`ExtractedPrinciple`]s, as `ptr << 32 | len`
+    ///
+    /// Every extraction runs in a fresh instance, so the module needn't free anything and calls
+    /// can't see each other's state, and is stopped once it runs out of fuel ([`WASM_PLUGIN_FUEL`]
+    /// unless set with `with_fuel`) or grows its memory past [`WASM_PLUGIN_MAX_MEMORY_BYTES`]. A
+    /// module gets one memory, one table and no more instances than itself.
+    #[derive(Debug)]
+    pub struct WasmExtractorPlugin {
+        name: String,
+        engine: Engine,
+        module: Module,
+        fuel: u64,
+    }
+
+    impl WasmExtractorPlugin {
+        pub fn load(path: &Path) -> Result<Self> {
+            let name = plugin_name(path);
+            let bytes = std::fs::read(path).map_err(|e| plugin_error(&name, format!("cannot read {}: {}", path.display(), e)))?;
+            Self::from_bytes(name, &bytes)
+        }
+
+        pub fn from_bytes(name: String, wasm: &[u8]) -> Result<Self> {
+            let mut config = Config::default();
+            config.consume_fuel(true);
+            let engine = Engine::new(&config);
+            let module = Module::new(&engine, wasm).map_err(|e| plugin_error(&name, e))?;
+            let plugin = Self { name, engine, module, fuel: WASM_PLUGIN_FUEL };
+
+            let (mut store, instance) = plugin.instantiate()?;
+            let abi = instance
+                .get_typed_func::<(), i32>(&store, "wiki_engine_plugin_abi")
+                .and_then(|abi| abi.call(&mut store, ()))
+                .map_err(|e| plugin_error(&plugin.name, e))?;
+            if abi as u32 != PLUGIN_ABI_VERSION {
+                return Err(plugin_error(&plugin.name, format!("built for plugin ABI {}, expected {}", abi, PLUGIN_ABI_VERSION)));
+            }
+            Ok(plugin)
+        }
+
+        /// Stop each extraction after about `fuel` instructions
+        pub fn with_fuel(mut self, fuel: u64) -> Self {
+            self.fuel = fuel;
+            self
+        }
+
+        fn instantiate(&self) -> Result<(Store<StoreLimits>, Instance)> {
+            let limits = StoreLimitsBuilder::new()
+                .memory_size(WASM_PLUGIN_MAX_MEMORY_BYTES)
+                .table_elements(WASM_PLUGIN_MAX_TABLE_ELEMENTS)
+                .memories(1)
+                .tables(1)
+                .instances(1)
+                // Growing past the limits stops the extraction rather than handing the module a
+                // failure it may ignore
+                .trap_on_grow_failure(true)
+                .build();
+            let mut store = Store::new(&self.engine, limits);
+            store.limiter(|limits| limits);
+            store.set_fuel(self.fuel).map_err(|e| plugin_error(&self.name, e))?;
+            let instance = Linker::<StoreLimits>::new(&self.engine)
+                .instantiate(&mut store, &self.module)
+                .and_then(|instance| instance.start(&mut store))
+                .map_err(|e| plugin_error(&self.name, e))?;
+            Ok((store, instance))
+        }
+    }
+
+    impl ExtractorPlugin for WasmExtractorPlugin {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn extract(&self, text: &str) -> Result<Vec<ExtractedPrinciple>> {
+            let fail = |error: &dyn std::fmt::Display| plugin_error(&self.name, error);
+            let (mut store, instance) = self.instantiate()?;
+            let memory = instance.get_memory(&store, "memory").ok_or_else(|| fail(&"no exported memory"))?;
+            let alloc = instance.get_typed_func::<i32, i32>(&store, "alloc").map_err(|e| fail(&e))?;
+            let extract = instance.get_typed_func::<(i32, i32), i64>(&store, "extract").map_err(|e| fail(&e))?;
+
+            let len = i32::try_from(text.len()).map_err(|_| fail(&"text too long"))?;
+            let input = alloc.call(&mut store, len).map_err(|e| fail(&e))?;
+            memory.write(&mut store, input as u32 as usize, text.as_bytes()).map_err(|e| fail(&e))?;
+            let output = extract.call(&mut store, (input, len)).map_err(|e| fail(&e))? as u64;
+
+            let (ptr, len) = ((output >> 32) as usize, (output & 0xffff_ffff) as usize);
+            if len > MAX_PLUGIN_OUTPUT_BYTES {
+                return Err(fail(&format!("output of {} bytes exceeds the limit of {}", len, MAX_PLUGIN_OUTPUT_BYTES)));
+            }
+            // Parsed in place, so a bogus location can't make us allocate anything
+            let json = ptr
+                .checked_add(len)
+                .and_then(|end| memory.data(&store).get(ptr..end))
+                .ok_or_else(|| fail(&"output lies outside the module's memory"))?;
+            parse_output(&self.name, json)
+        }
+    }
+}
+
+#[cfg(feature = "plugins")]
+mod native {
+    use super::{parse_output, plugin_error, plugin_name, ExtractedPrinciple, ExtractorPlugin, PLUGIN_ABI_VERSION};
+    use crate::types::Result;
+    use libloading::Library;
+    use std::ffi::{c_char, CStr};
+    use std::path::Path;
+
+    type AbiFn = unsafe extern "C" fn() -> u32;
+    type ExtractFn = unsafe extern "C" fn(text: *const u8, len: usize) -> *mut c_char;
+    type FreeFn = unsafe extern "C" fn(output: *mut c_char);
+
+    /// A shared library (`cdylib`) exporting, with C linkage:
+    ///
+    /// - `uint32_t wiki_engine_plugin_abi(void)`, returning [`PLUGIN_ABI_VERSION`]
+    /// - `char *wiki_engine_extract(const uint8_t *text, size_t len)`, given the UTF-8 text,
+    ///   returning a NUL-terminated JSON array of [`ExtractedPrinciple`]s, or NULL on failure
+    /// - `void wiki_engine_free(char *output)`, releasing what `wiki_engine_extract` returned
+    ///
+    /// It runs in the server's process with its privileges, unsandboxed, and is called from
+    /// several threads at once, so it must be trusted and thread-safe.
+    #[derive(Debug)]
+    pub struct NativeExtractorPlugin {
+        name: String,
+        extract: ExtractFn,
+        free: FreeFn,
+        /// Keeps the functions above loaded
+        _library: Library,
+    }
+
+    impl NativeExtractorPlugin {
+        pub fn load(path: &Path) -> Result<Self> {
+            let name = plugin_name(path);
+            let fail = |error: &dyn std::fmt::Display| plugin_error(&name, error);
+            // SAFETY: loading runs the library's initializers; plugins are trusted code the
+            // operator configured
+            let library = unsafe { Library::new(path) }.map_err(|e| fail(&e))?;
+            // SAFETY: the symbols are declared with the signatures the plugin interface specifies
+            let (abi, extract, free) = unsafe {
+                let abi = *library.get::<AbiFn>(b"wiki_engine_plugin_abi\0").map_err(|e| fail(&e))?;
+                let extract = *library.get::<ExtractFn>(b"wiki_engine_extract\0").map_err(|e| fail(&e))?;
+                let free = *library.get::<FreeFn>(b"wiki_engine_free\0").map_err(|e| fail(&e))?;
+                (abi, extract, free)
+            };
+            // SAFETY: takes no arguments and returns a plain integer
+            let version = unsafe { abi() };
+            if version != PLUGIN_ABI_VERSION {
+                return Err(fail(&format!("built for plugin ABI {}, expected {}", version, PLUGIN_ABI_VERSION)));
+            }
+            Ok(Self { name, extract, free, _library: library })
+        }
+    }
+
+    impl ExtractorPlugin for NativeExtractorPlugin {
+        fn name(&self) -> &str {
+            &self.name
+        }
+
+        fn extract(&self, text: &str) -> Result<Vec<ExtractedPrinciple>> {
+            // SAFETY: the text outlives the call, and the plugin returns NULL or a NUL-terminated
+            // string it owns until it is passed back to `wiki_engine_free`
+            unsafe {
+                let output = (self.extract)(text.as_ptr(), text.len());
+                if output.is_null() {
+                    return Err(plugin_error(&self.name, "extraction failed"));
+                }
+                let principles = parse_output(&self.name, CStr::from_ptr(output).to_bytes());
+                (self.free)(output);
+                principles
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "plugins"))]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    /// Returns the same principle whatever the text, then loops forever on texts over 100 bytes
+    const MODULE: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (data (i32.const 0) "[{\"title\": \"Lorentz force\", \"description\": \"The rotor turns by the Lorentz force.\", \"category\": \"Electrical\", \"confidence\": 1.5, \"span\": [0, 12]}]")
+          (func (export "wiki_engine_plugin_abi") (result i32) i32.const 1)
+          (func (export "alloc") (param i32) (result i32) i32.const 1024)
+          (func (export "extract") (param i32 i32) (result i64)
+            (if (i32.gt_u (local.get 1) (i32.const 100)) (then (loop (br 0))))
+            i64.const 146))
+    "#;
+
+    #[test]
+    fn wasm_plugins_extract_in_a_sandbox() {
+        let plugin = WasmExtractorPlugin::from_bytes("lorentz".to_string(), &wat::parse_str(MODULE).unwrap()).unwrap().with_fuel(100_000);
+        let page = WikipediaPage {
+            title: "Electric motor".to_string(),
+            extract: "Electric motors convert electrical energy into mechanical work.".to_string(),
+            url: "https://en.wikipedia.org/wiki/Electric_motor".to_string(),
+            page_id: 1,
+            revision_id: None,
+            fetched_at: Utc::now(),
+            thumbnail_url: None,
+            monthly_views: None,
+            curated_links: vec![],
+            etag: None,
+            last_modified: None,
+            source: Default::default(),
+        };
+
+        let extracted = plugin.extract(&page.extract).unwrap();
+        assert_eq!(extracted[0].span, Some((0, 12)));
+        let principle = extracted[0].clone().into_principle(&page).unwrap();
+        assert_eq!(principle.category, PrincipleCategory::new("Electrical"));
+        assert_eq!(principle.confidence, 1.0);
+        assert_eq!(principle.source_span, (0, 12));
+
+        let runaway = plugin.extract(&"rotor ".repeat(20)).unwrap_err();
+        assert!(runaway.to_string().contains("Extractor plugin 'lorentz' failed"), "{}", runaway);
+
+        let outdated = MODULE.replace("(result i32) i32.const 1)", "(result i32) i32.const 0)");
+        assert!(WasmExtractorPlugin::from_bytes("old".to_string(), &wat::parse_str(outdated).unwrap()).is_err());
+
+        // Output lengths and locations come from the module, and are checked before use
+        for (output, error) in [("0xffff_ffff", "exceeds the limit"), ("0x1_0000_0000_0092", "outside the module's memory")] {
+            let hostile = MODULE.replace("i64.const 146", &format!("i64.const {}", output));
+            let plugin = WasmExtractorPlugin::from_bytes("hostile".to_string(), &wat::parse_str(hostile).unwrap()).unwrap();
+            let failure = plugin.extract("rotor").unwrap_err();
+            assert!(failure.to_string().contains(error), "{}", failure);
+        }
+
+        // A module growing its memory a megabyte at a time, as long as that succeeds, is
+        // stopped at the limit instead of taking the host's memory
+        let greedy = MODULE.replace("(loop (br 0))", "(loop (br_if 0 (i32.ne (memory.grow (i32.const 16)) (i32.const -1))))");
+        let plugin = WasmExtractorPlugin::from_bytes("greedy".to_string(), &wat::parse_str(greedy).unwrap()).unwrap();
+        let failure = plugin.extract(&"rotor ".repeat(20)).unwrap_err();
+        assert!(failure.to_string().contains("growth operation limited"), "{}", failure);
+    }
+
+    /// Source of a shared library plugin; `--cfg outdated` builds it for ABI 0 and
+    /// `--cfg leaky` without `wiki_engine_free`
+    const LIBRARY: &str = r##"
+        use std::ffi::{c_char, CString};
+
+        #[no_mangle]
+        pub extern "C" fn wiki_engine_plugin_abi() -> u32 {
+            if cfg!(outdated) { 0 } else { 1 }
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn wiki_engine_extract(text: *const u8, len: usize) -> *mut c_char {
+            let text = std::str::from_utf8(std::slice::from_raw_parts(text, len)).unwrap_or_default();
+            if !text.contains("rotor") {
+                return std::ptr::null_mut();
+            }
+            let json = r#"[{"title": "Lorentz force", "description": "The rotor turns by the Lorentz force.", "category": "Electrical", "confidence": 0.9}]"#;
+            CString::new(json).unwrap().into_raw()
+        }
+
+        #[cfg(not(leaky))]
+        #[no_mangle]
+        pub unsafe extern "C" fn wiki_engine_free(output: *mut c_char) {
+            drop(CString::from_raw(output));
+        }
+    "##;
+
+    /// Compile `LIBRARY`, with `cfg` set, into a shared library in `dir`
+    fn build_library(dir: &Path, name: &str, cfg: Option<&str>) -> std::path::PathBuf {
+        let source = dir.join(format!("{}.rs", name));
+        std::fs::write(&source, LIBRARY).unwrap();
+        let library = dir.join(format!("{}{}{}", std::env::consts::DLL_PREFIX, name, std::env::consts::DLL_SUFFIX));
+        let mut rustc = std::process::Command::new(std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()));
+        rustc.args(["--crate-type", "cdylib", "--edition", "2021", "-o"]).arg(&library).arg(&source);
+        if let Some(cfg) = cfg {
+            rustc.args(["--cfg", cfg]);
+        }
+        let output = rustc.output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        library
+    }
+
+    #[test]
+    fn native_plugins_are_checked_when_loaded() {
+        let dir = std::env::temp_dir().join(format!("plugins-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+
+        let plugin = NativeExtractorPlugin::load(&build_library(&dir, "lorentz", None)).unwrap();
+        assert_eq!(plugin.name(), format!("{}lorentz", std::env::consts::DLL_PREFIX));
+        let extracted = plugin.extract("The rotor of an electric motor").unwrap();
+        assert_eq!(extracted[0].title, "Lorentz force");
+        assert!(plugin.extract("A stator").unwrap_err().to_string().contains("extraction failed"));
+
+        let outdated = NativeExtractorPlugin::load(&build_library(&dir, "outdated", Some("outdated"))).unwrap_err();
+        assert!(outdated.to_string().contains("built for plugin ABI 0, expected 1"), "{}", outdated);
+        let leaky = NativeExtractorPlugin::load(&build_library(&dir, "leaky", Some("leaky"))).unwrap_err();
+        assert!(leaky.to_string().contains("wiki_engine_free"), "{}", leaky);
+        assert!(NativeExtractorPlugin::load(&dir.join("missing.so")).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::sync::watch;
+use utoipa::ToSchema;
+
+tokio::task_local! {
+    static CURRENT: ProgressReporter;
+}
+
+/// Where a running analysis has got to
+#[derive(Debug, Clone, Default, PartialEq, Serialize, ToSchema)]
+pub struct AnalysisProgress {
+    pub nodes_completed: usize,
+    /// Grows as each node picks its children, so it only settles once the tree is fully expanded
+    pub estimated_total_nodes: usize,
+    pub current_term: Option<String>,
+    pub wikipedia_calls: usize,
+    pub cache_hits: usize,
+}
+
+/// Collects progress from the engine while a future runs inside [`ProgressReporter::scope`].
+/// Any number of subscribers (jobs, SSE streams, the CLI) can watch it.
+#[derive(Clone)]
+pub struct ProgressReporter {
+    sender: Arc<watch::Sender<AnalysisProgress>>,
+}
+
+impl ProgressReporter {
+    pub fn new() -> Self {
+        let (sender, _) = watch::channel(AnalysisProgress::default());
+        Self { sender: Arc::new(sender) }
+    }
+
+    /// Receiver notified on every update; always holds the latest snapshot
+    pub fn subscribe(&self) -> watch::Receiver<AnalysisProgress> {
+        self.sender.subscribe()
+    }
+
+    pub fn snapshot(&self) -> AnalysisProgress {
+        self.sender.borrow().clone()
+    }
+
+    /// Run `future`, reporting the progress of any engine work it does to this reporter
+    pub async fn scope<F: Future>(&self, future: F) -> F::Output {
+        CURRENT.scope(self.clone(), future).await
+    }
+
+    fn update(&self, change: impl FnOnce(&mut AnalysisProgress)) {
+        self.sender.send_modify(change);
+    }
+}
+
+impl Default for ProgressReporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Apply a change to the reporter of the current scope, if any
+fn report(change: impl FnOnce(&mut AnalysisProgress)) {
+    let _ = CURRENT.try_with(|reporter| reporter.update(change));
+}
+
+pub fn record_nodes_discovered(count: usize) {
+    report(|progress| progress.estimated_total_nodes += count);
+}
+
+/// A discovered node that turned out not to need analyzing (already in the tree)
+pub fn record_node_skipped() {
+    report(|progress| progress.nodes_completed += 1);
+}
+
+pub fn record_wikipedia_call() {
+    report(|progress| progress.wikipedia_calls += 1);
+}
+
+pub fn record_cache_hit() {
+    report(|progress| progress.cache_hits += 1);
+}
+
+/// Marks a node as the current term until dropped, then counts it as completed
+pub struct NodeGuard;
+
+impl NodeGuard {
+    pub fn new(term: &str) -> Self {
+        report(|progress| progress.current_term = Some(term.to_string()));
+        Self
+    }
+}
+
+impl Drop for NodeGuard {
+    fn drop(&mut self) {
+        report(|progress| progress.nodes_completed += 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reports_only_inside_scope() {
+        let reporter = ProgressReporter::new();
+        let mut updates = reporter.subscribe();
+
+        record_wikipedia_call();
+        reporter
+            .scope(async {
+                record_nodes_discovered(3);
+                let _node = NodeGuard::new("Stator");
+                record_wikipedia_call();
+                record_cache_hit();
+                record_node_skipped();
+            })
+            .await;
+
+        assert!(updates.has_changed().unwrap());
+        assert_eq!(
+            *updates.borrow_and_update(),
+            AnalysisProgress {
+                nodes_completed: 2,
+                estimated_total_nodes: 3,
+                current_term: Some("Stator".to_string()),
+                wikipedia_calls: 1,
+                cache_hits: 1,
+            }
+        );
+    }
+}
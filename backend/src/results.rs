@@ -0,0 +1,103 @@
+use crate::types::{AnalysisResult, SearchRequest};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// An analysis kept around so it can be fetched or refreshed later by id
+#[derive(Debug, Clone)]
+pub struct StoredResult {
+    pub id: String,
+    pub request: SearchRequest,
+    pub result: AnalysisResult,
+    pub created_at: DateTime<Utc>,
+    pub refreshed_at: Option<DateTime<Utc>>,
+}
+
+/// Outcome of refreshing a stored result
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ResultRefresh {
+    pub result: AnalysisResult,
+    /// Terms whose pages were re-fetched and re-analyzed
+    pub refreshed_terms: Vec<String>,
+    pub nodes_checked: usize,
+}
+
+/// In-memory store of recent analysis results, evicting the oldest once full
+pub struct ResultStore {
+    results: DashMap<String, StoredResult>,
+    max_entries: usize,
+}
+
+impl ResultStore {
+    pub fn new() -> Self {
+        Self::with_capacity(500)
+    }
+
+    pub fn with_capacity(max_entries: usize) -> Self {
+        Self {
+            results: DashMap::new(),
+            max_entries,
+        }
+    }
+
+    /// Store a result under a fresh id, returning the id
+    pub fn insert(&self, request: SearchRequest, mut result: AnalysisResult) -> String {
+        if self.results.len() >= self.max_entries {
+            self.evict_oldest();
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        result.result_id = Some(id.clone());
+        self.results.insert(
+            id.clone(),
+            StoredResult {
+                id: id.clone(),
+                request,
+                result,
+                created_at: Utc::now(),
+                refreshed_at: None,
+            },
+        );
+        id
+    }
+
+    pub fn get(&self, id: &str) -> Option<StoredResult> {
+        self.results.get(id).map(|entry| entry.clone())
+    }
+
+    /// Replace the tree of an existing result after a refresh
+    pub fn update(&self, id: &str, mut result: AnalysisResult) {
+        if let Some(mut entry) = self.results.get_mut(id) {
+            result.result_id = Some(id.to_string());
+            entry.result = result;
+            entry.refreshed_at = Some(Utc::now());
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.results.is_empty()
+    }
+
+    fn evict_oldest(&self) {
+        let oldest = self
+            .results
+            .iter()
+            .min_by_key(|entry| entry.created_at)
+            .map(|entry| entry.key().clone());
+
+        if let Some(id) = oldest {
+            self.results.remove(&id);
+        }
+    }
+}
+
+impl Default for ResultStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
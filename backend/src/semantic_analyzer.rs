@@ -1,29 +1,172 @@
-use crate::types::{EngineeringPrinciple, PrincipleCategory, Result, WikipediaPage};
-use std::collections::{HashMap, HashSet};
+use crate::analyzer::EngineeringAnalyzer;
+use crate::categories;
+use crate::dedup;
+use crate::intern::Term;
+use crate::laws;
+use crate::templates::DescriptionTemplates;
+use crate::text;
+use aho_corasick::AhoCorasick;
+use arc_swap::ArcSwap;
+use crate::types::{DocumentSourceKind, EngineeringPrinciple, PrincipleCategory, Result, WikiEngineError, WikipediaPage};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, LazyLock};
+#[cfg(feature = "embeddings")]
+use std::sync::Mutex;
+#[cfg(feature = "embeddings")]
 use tokenizers::Tokenizer;
-use ort::{session::Session, value::Value};
-use ndarray::Array2;
+#[cfg(feature = "embeddings")]
+use ort::{session::Session, value::Tensor};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-/// Knowledge base for engineering concepts and hierarchical relationships
-#[derive(Debug, Clone)]
+/// Below this cosine similarity to every prototype, a sentence is left to the keyword patterns,
+/// unless a calibration sets another threshold
+pub const MIN_PROTOTYPE_SIMILARITY: f32 = 0.35;
+
+/// Sentence embedding model loaded when no other is configured
+pub const DEFAULT_EMBEDDING_MODEL: &str = "models/sentence-transformer.onnx";
+
+/// Tokenizer of the default embedding model
+pub const DEFAULT_TOKENIZER: &str = "models/tokenizer.json";
+
+/// "<whole> consists of <parts>" and its variants, splitting a sentence into the whole and the list of parts
+static COMPOSITION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:(?:an?|the)\s+)?(.+?)\s+(?:consists of|comprises|is made up of|is composed of|contains)\s+(.+)$").unwrap()
+});
+
+/// Below this cosine similarity to every law's statement, a principle is left without a named law
+const MIN_LAW_SIMILARITY: f32 = 0.7;
+
+/// Phrasings of each relation between two components, "X" standing for the one the sentence
+/// mentions first and "Y" for the other; the flag is whether X is the relation's subject
+const RELATION_PROTOTYPES: &[(RelationType, bool, &str)] = &[
+    (RelationType::PartOf, true, "X is a component of Y"),
+    (RelationType::PartOf, true, "the X is mounted inside the Y"),
+    (RelationType::PartOf, false, "the X consists of Y"),
+    (RelationType::PartOf, false, "the X is made up of Y and other parts"),
+    (RelationType::Requires, true, "the X needs a Y to operate"),
+    (RelationType::Requires, true, "the X draws its power from the Y"),
+    (RelationType::Requires, false, "the X powers the Y"),
+    (RelationType::Requires, false, "the X supplies energy to the Y"),
+    (RelationType::Controls, true, "the X adjusts the speed of the Y"),
+    (RelationType::Controls, true, "the X sends commands to the Y"),
+    (RelationType::Controls, false, "the X is driven by signals from the Y"),
+    (RelationType::Connects, true, "the X is wired to the Y"),
+    (RelationType::Connects, true, "the X is linked to the Y"),
+    (RelationType::Supports, true, "the X bears the weight of the Y"),
+    (RelationType::Supports, false, "the X rests on the Y"),
+    (RelationType::Converts, true, "the X turns stored energy into Y"),
+];
+
+/// Below this cosine similarity to every relation prototype, a sentence yields no relation
+const MIN_RELATION_SIMILARITY: f32 = 0.6;
+
+/// Knowledge base for engineering concepts and hierarchical relationships. Serialized with its
+/// concepts sorted, so exports of equal knowledge bases are identical and diff line by line.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 pub struct ConceptKnowledgeBase {
+    #[serde(default, serialize_with = "serialize_sorted")]
     pub concept_hierarchies: HashMap<String, Vec<String>>,
+    #[serde(default, serialize_with = "serialize_sorted")]
     pub component_relationships: HashMap<String, Vec<ComponentRelation>>,
+    #[serde(default, serialize_with = "serialize_sorted")]
     pub category_mappings: HashMap<String, PrincipleCategory>,
+    #[serde(default, serialize_with = "serialize_sorted")]
     pub synonyms: HashMap<String, Vec<String>>,
 }
 
-/// Represents a relationship between engineering components
-#[derive(Debug, Clone)]
+fn serialize_sorted<S: serde::Serializer, V: Serialize>(map: &HashMap<String, V>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_map(map.iter().collect::<BTreeMap<_, _>>())
+}
+
+impl ConceptKnowledgeBase {
+    /// The knowledge base shipped with the crate
+    pub fn built_in() -> Self {
+        SemanticAnalyzer::build_knowledge_base()
+    }
+
+    /// Stamp identifying the contents, carried by results as `kb_version`: equal knowledge bases
+    /// get the same stamp in every process, and any edit gets a different one
+    pub fn version(&self) -> String {
+        // Sorted, so the stamp doesn't depend on hash map iteration order
+        fn sorted<V>(map: &HashMap<String, V>) -> BTreeMap<&String, &V> {
+            map.iter().collect()
+        }
+        let canonical = serde_json::json!([
+            sorted(&self.concept_hierarchies),
+            sorted(&self.component_relationships),
+            sorted(&self.category_mappings),
+            sorted(&self.synonyms),
+        ]);
+        format!("{:016x}", dedup::fnv1a(&canonical.to_string()))
+    }
+}
+
+/// A knowledge base with the lookups derived from it, replaced as a whole when it is edited
+struct KnowledgeSnapshot {
+    knowledge: ConceptKnowledgeBase,
+    /// `ConceptKnowledgeBase::version` of it
+    version: String,
+    /// Knowledge-base concept for the stemmed form of each concept name and synonym
+    stems: HashMap<String, String>,
+}
+
+impl KnowledgeSnapshot {
+    fn new(knowledge: ConceptKnowledgeBase) -> Self {
+        Self { version: knowledge.version(), stems: Self::index_stems(&knowledge), knowledge }
+    }
+
+    /// Map the stemmed form of every knowledge-base concept and synonym to its concept.
+    /// Synonyms win over other entries, so "engine" still resolves to "motor".
+    fn index_stems(knowledge: &ConceptKnowledgeBase) -> HashMap<String, String> {
+        let mut stems = HashMap::new();
+        let mut synonyms: Vec<_> = knowledge.synonyms.iter().collect();
+        synonyms.sort();
+        for (key, names) in synonyms {
+            for name in std::iter::once(key).chain(names) {
+                stems.entry(text::stem_phrase(name)).or_insert_with(|| key.clone());
+            }
+        }
+        let mut others: Vec<&String> = knowledge.concept_hierarchies.keys().chain(knowledge.category_mappings.keys()).collect();
+        others.sort();
+        for key in others {
+            stems.entry(text::stem_phrase(key)).or_insert_with(|| key.clone());
+        }
+        stems
+    }
+
+    fn normalize(&self, concept: &str) -> String {
+        match self.stems.get(&text::stem_phrase(concept)) {
+            Some(known) => known.clone(),
+            None => text::fold_case(concept.trim()),
+        }
+    }
+}
+
+/// Represents a directed relationship between engineering components: `subject <relation> component`
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ComponentRelation {
+    /// Source of the edge (e.g. "motor" in "motor is part of uav")
+    pub subject: String,
+    /// Target of the edge
     pub component: String,
     pub relation_type: RelationType,
     pub confidence: f32,
 }
 
+impl ComponentRelation {
+    /// Whether `name` is either end of the relation
+    pub fn involves(&self, name: &str) -> bool {
+        self.subject == name || self.component == name
+    }
+}
+
 /// Types of relationships between components
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
 pub enum RelationType {
     PartOf,        // Motor is part of UAV
     Requires,      // UAV requires power source
@@ -33,15 +176,89 @@ pub enum RelationType {
     Converts,      // Battery converts chemical to electrical energy
 }
 
+impl RelationType {
+    pub const ALL: [RelationType; 6] = [
+        RelationType::PartOf,
+        RelationType::Requires,
+        RelationType::Controls,
+        RelationType::Connects,
+        RelationType::Supports,
+        RelationType::Converts,
+    ];
+
+    /// Name as serialized, e.g. `part_of`
+    pub fn name(&self) -> &'static str {
+        match self {
+            RelationType::PartOf => "part_of",
+            RelationType::Requires => "requires",
+            RelationType::Controls => "controls",
+            RelationType::Connects => "connects",
+            RelationType::Supports => "supports",
+            RelationType::Converts => "converts",
+        }
+    }
+}
+
 /// Component extractor for identifying engineering parts
 #[derive(Debug, Clone)]
 pub struct ComponentExtractor {
     pub name: String,
+    /// Part names matched as whole words, case-insensitively
+    pub keywords: Vec<String>,
+    /// Patterns for names a keyword list can't express; capture group 1 is the component name
     pub patterns: Vec<Regex>,
     pub category: PrincipleCategory,
     pub weight: f32,
 }
 
+/// The keywords of every extractor in a single Aho-Corasick automaton, so text is scanned once
+/// no matter how many extractors and keywords there are
+#[derive(Debug, Clone)]
+struct KeywordMatcher {
+    automaton: AhoCorasick,
+    /// Extractor each automaton pattern belongs to
+    extractor_of: Vec<usize>,
+}
+
+impl KeywordMatcher {
+    fn new(extractors: &[ComponentExtractor]) -> Self {
+        let (extractor_of, keywords): (Vec<usize>, Vec<String>) = extractors
+            .iter()
+            .enumerate()
+            .flat_map(|(index, extractor)| extractor.keywords.iter().map(move |keyword| (index, text::fold_case(keyword))))
+            .unzip();
+        let automaton = AhoCorasick::builder()
+            .ascii_case_insensitive(true)
+            .build(&keywords)
+            .expect("component keywords fit the automaton size limits");
+        Self { automaton, extractor_of }
+    }
+
+    /// Whole-word keyword matches in `text`, grouped by extractor. Within an extractor they are
+    /// in text order and don't overlap, the longest keyword winning where several start together,
+    /// as a `\b(a|b|...)\b` regex would find them.
+    fn find<'t>(&self, text: &'t str, extractors: usize) -> Vec<Vec<&'t str>> {
+        let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+        let mut matches: Vec<(usize, usize, usize)> = self
+            .automaton
+            .find_overlapping_iter(text)
+            .filter(|m| !is_word(text[..m.start()].chars().next_back()) && !is_word(text[m.end()..].chars().next()))
+            .map(|m| (self.extractor_of[m.pattern().as_usize()], m.start(), m.end()))
+            .collect();
+        matches.sort_by_key(|&(extractor, start, end)| (extractor, start, std::cmp::Reverse(end)));
+
+        let mut found = vec![Vec::new(); extractors];
+        let mut taken_until = vec![0; extractors];
+        for (extractor, start, end) in matches {
+            if start >= taken_until[extractor] {
+                taken_until[extractor] = end;
+                found[extractor].push(&text[start..end]);
+            }
+        }
+        found
+    }
+}
+
 /// Pattern for detecting relationships between components
 #[derive(Debug, Clone)]
 pub struct RelationshipPattern {
@@ -51,7 +268,7 @@ pub struct RelationshipPattern {
 }
 
 /// Result of concept decomposition
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ConceptDecomposition {
     pub concept: String,
     pub components: Vec<FoundationalComponent>,
@@ -60,71 +277,268 @@ pub struct ConceptDecomposition {
 }
 
 /// A foundational engineering component
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct FoundationalComponent {
     pub name: String,
     pub category: PrincipleCategory,
     pub description: String,
     pub importance: f32,
+    /// Names of the immediate sub-components
     pub sub_components: Vec<String>,
+    /// Sub-components decomposed further, down to the requested depth
+    #[serde(default)]
+    #[schema(no_recursion)]
+    pub children: Vec<FoundationalComponent>,
+    /// Where the component was discovered
+    #[serde(default)]
+    pub source: ComponentSource,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ComponentSource {
+    #[default]
+    KnowledgeBase,
+    /// Extracted from the concept's article text
+    Article,
+    /// Extracted from patent abstracts related to the concept
+    Patent,
+}
+
+/// Turns a sentence into a fixed-length vector, sentences alike in meaning getting vectors
+/// with a high cosine similarity
+pub trait SentenceEmbedder: Send + Sync {
+    fn embed(&self, text: &str) -> Result<Vec<f32>>;
+}
+
+/// ONNX sentence-transformer session and its tokenizer
+#[cfg(feature = "embeddings")]
+struct EmbeddingModel {
+    // Running a session needs it mutably
+    session: Mutex<Session>,
+    tokenizer: Tokenizer,
+}
+
+#[cfg(feature = "embeddings")]
+impl SentenceEmbedder for EmbeddingModel {
+    /// Tokenize `text`, run the model on it and mean-pool the token embeddings over the
+    /// attention mask (models that pool themselves return the sentence embedding as is), then
+    /// scale the result to unit length
+    fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        let model_error = |error: &dyn std::fmt::Display| WikiEngineError::ModelUnavailable(format!("embedding model: {}", error));
+        let encoding = self.tokenizer.encode(text, true).map_err(|e| model_error(&e))?;
+        let attention_mask: Vec<i64> = encoding.get_attention_mask().iter().map(|&m| i64::from(m)).collect();
+        let tokens = attention_mask.len();
+
+        let mut session = self.session.lock().unwrap();
+        let inputs = session
+            .inputs
+            .iter()
+            .map(|input| {
+                let values: Vec<i64> = match input.name.as_str() {
+                    "input_ids" => encoding.get_ids().iter().map(|&id| i64::from(id)).collect(),
+                    "attention_mask" => attention_mask.clone(),
+                    "token_type_ids" => encoding.get_type_ids().iter().map(|&id| i64::from(id)).collect(),
+                    other => return Err(model_error(&format!("unsupported input '{}'", other))),
+                };
+                let tensor = Tensor::from_array(([1usize, tokens], values)).map_err(|e| model_error(&e))?;
+                Ok((input.name.clone(), tensor))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let outputs = session.run(inputs).map_err(|e| model_error(&e))?;
+        let (shape, values) = outputs[0].try_extract_tensor::<f32>().map_err(|e| model_error(&e))?;
+
+        let mut embedding = match **shape {
+            [1, output_tokens, dimensions] if output_tokens as usize == tokens => {
+                text::mean_pool(values, &attention_mask, dimensions as usize)
+            }
+            [1, _] => values.to_vec(),
+            _ => return Err(model_error(&format!("unexpected output shape {:?}", &**shape))),
+        };
+        text::normalize(&mut embedding);
+        Ok(embedding)
+    }
+}
+
+/// Embeds a text as how many words of each group it contains: a stand-in for a sentence model,
+/// whose idea of what texts mean alike tests can predict
+#[cfg(test)]
+pub(crate) struct WordGroupEmbedder(pub Vec<&'static [&'static str]>);
+
+#[cfg(test)]
+impl SentenceEmbedder for WordGroupEmbedder {
+    fn embed(&self, sentence: &str) -> Result<Vec<f32>> {
+        let words = text::stemmed_words(sentence);
+        let mut embedding: Vec<f32> = self
+            .0
+            .iter()
+            .map(|group| group.iter().map(|word| words.iter().filter(|w| **w == text::stem_phrase(word)).count() as f32).sum())
+            .collect();
+        text::normalize(&mut embedding);
+        Ok(embedding)
+    }
 }
 
 /// Advanced semantic analyzer using ML techniques for hierarchical concept decomposition
 pub struct SemanticAnalyzer {
-    // ONNX Runtime session for sentence embeddings
-    embedding_session: Option<Session>,
-    tokenizer: Option<Tokenizer>,
+    // Sentence embeddings, when the model files are present or an embedder is given
+    embedding_model: Option<Arc<dyn SentenceEmbedder>>,
     
-    // Knowledge base for engineering concepts and their relationships
-    concept_knowledge: ConceptKnowledgeBase,
+    // Knowledge base for engineering concepts and their relationships; swapped, so it can be
+    // edited while analyses run
+    knowledge: ArcSwap<KnowledgeSnapshot>,
     
-    // Pre-computed embeddings for engineering concepts
-    concept_embeddings: HashMap<String, Vec<f32>>,
+    // Embedding of each category's prototype sentence; empty without the embedding model
+    category_prototypes: Vec<(PrincipleCategory, Vec<f32>)>,
+    // Similarity to a category prototype a sentence needs to be classified by embedding
+    min_prototype_similarity: f32,
+    // Embedding of each relation prototype, keyed by relation and whether X is the subject
+    relation_prototypes: Vec<((RelationType, bool), Vec<f32>)>,
+    // Embedding of each named law's statement, keyed by law name
+    law_prototypes: Vec<(&'static str, Vec<f32>)>,
     
     // NLP components
     component_extractors: Vec<ComponentExtractor>,
+    keyword_matcher: KeywordMatcher,
     relationship_patterns: Vec<RelationshipPattern>,
+    description_templates: DescriptionTemplates,
     
-    // Model parameters
-    similarity_threshold: f32,
-    confidence_threshold: f32,
+    // Model parameters; f32 bits, so the threshold can be tuned while analyses run
+    recursion_similarity_threshold: AtomicU32,
 }
 
 impl SemanticAnalyzer {
     pub fn new() -> Result<Self> {
-        // Initialize embedding model
-        let (embedding_session, tokenizer) = Self::try_load_embedding_model();
-        
+        Self::with_model_files(Path::new(DEFAULT_EMBEDDING_MODEL), Path::new(DEFAULT_TOKENIZER))
+    }
+
+    /// Analyzer embedding sentences with the ONNX model and tokenizer at these paths, such as a
+    /// model fine-tuned on labeled sentences; without them it falls back to the knowledge base
+    pub fn with_model_files(model: &Path, tokenizer: &Path) -> Result<Self> {
         // Build comprehensive knowledge base
         let concept_knowledge = Self::build_knowledge_base();
         
         // Initialize component extractors with ML-driven patterns
-        let component_extractors = Self::build_component_extractors();
+        let component_extractors = Self::default_component_extractors();
         
         // Build relationship detection patterns
         let relationship_patterns = Self::build_relationship_patterns();
         
-        // Pre-compute embeddings for key engineering concepts
-        let concept_embeddings = Self::precompute_concept_embeddings(&embedding_session, &tokenizer);
-        
-        if embedding_session.is_some() {
-            tracing::info!("Advanced ML-based semantic analyzer initialized with embeddings");
-        } else {
-            tracing::info!("Semantic analyzer initialized with knowledge-based fallback");
-        }
-
-        Ok(Self {
-            embedding_session,
-            tokenizer,
-            concept_knowledge,
-            concept_embeddings,
+        let analyzer = Self {
+            embedding_model: None,
+            knowledge: ArcSwap::from_pointee(KnowledgeSnapshot::new(concept_knowledge)),
+            category_prototypes: Vec::new(),
+            min_prototype_similarity: MIN_PROTOTYPE_SIMILARITY,
+            relation_prototypes: Vec::new(),
+            law_prototypes: Vec::new(),
+            keyword_matcher: KeywordMatcher::new(&component_extractors),
             component_extractors,
             relationship_patterns,
-            similarity_threshold: 0.6,
-            confidence_threshold: 0.4,
+            description_templates: DescriptionTemplates::default(),
+            recursion_similarity_threshold: AtomicU32::new(0.3f32.to_bits()),
+        };
+        Ok(match Self::try_load_embedding_model(model, tokenizer) {
+            Some(embedder) => {
+                tracing::info!("Advanced ML-based semantic analyzer initialized with embeddings");
+                analyzer.with_embedder(embedder)
+            }
+            None => {
+                tracing::info!("Semantic analyzer initialized with knowledge-based fallback");
+                analyzer
+            }
         })
     }
 
+    /// Embed sentences with `embedder` instead of the ONNX model, e.g. a remote or smaller model
+    pub fn with_embedder(mut self, embedder: Arc<dyn SentenceEmbedder>) -> Self {
+        self.embedding_model = Some(embedder);
+        // Sentences are classified by the category prototype their embedding is closest to
+        self.category_prototypes = categories::registry()
+            .definitions()
+            .filter_map(|definition| {
+                let embedding = self.compute_embedding(definition.prototype.as_deref()?)?;
+                Some((PrincipleCategory::new(&definition.id), embedding))
+            })
+            .collect();
+        self.relation_prototypes = RELATION_PROTOTYPES
+            .iter()
+            .filter_map(|(relation_type, subject_first, prototype)| {
+                Some(((relation_type.clone(), *subject_first), self.compute_embedding(prototype)?))
+            })
+            .collect();
+        self.law_prototypes = laws::named_laws()
+            .iter()
+            .filter_map(|law| Some((law.name, self.compute_embedding(law.statement)?)))
+            .collect();
+        self
+    }
+
+    /// Replace the component extractors, e.g. to add domain-specific part names
+    pub fn with_component_extractors(mut self, component_extractors: Vec<ComponentExtractor>) -> Self {
+        self.keyword_matcher = KeywordMatcher::new(&component_extractors);
+        self.component_extractors = component_extractors;
+        self
+    }
+
+    /// Replace the knowledge base concepts are decomposed and matched against
+    pub fn with_knowledge_base(self, knowledge: ConceptKnowledgeBase) -> Self {
+        self.set_knowledge_base(knowledge);
+        self
+    }
+
+    /// Decompose and match concepts against `knowledge` from now on; decompositions already
+    /// running finish with the knowledge base they started with
+    pub fn set_knowledge_base(&self, knowledge: ConceptKnowledgeBase) {
+        self.knowledge.store(Arc::new(KnowledgeSnapshot::new(knowledge)));
+    }
+
+    /// Copy of the knowledge base in use
+    pub fn knowledge_base(&self) -> ConceptKnowledgeBase {
+        self.knowledge.load().knowledge.clone()
+    }
+
+    /// Stamp of the knowledge base in use, see `ConceptKnowledgeBase::version`
+    pub fn knowledge_version(&self) -> String {
+        self.knowledge.load().version.clone()
+    }
+
+    /// Templates component descriptions and principle titles are generated from
+    pub fn description_templates(&self) -> &DescriptionTemplates {
+        &self.description_templates
+    }
+
+    /// Replace the templates component descriptions and principle titles are generated from
+    pub fn with_description_templates(mut self, description_templates: DescriptionTemplates) -> Self {
+        self.description_templates = description_templates;
+        self
+    }
+
+    /// Similarity to a category prototype a sentence needs for the embedding model to classify it
+    pub fn with_min_prototype_similarity(mut self, min_similarity: f32) -> Self {
+        self.min_prototype_similarity = min_similarity;
+        self
+    }
+
+    /// Minimum similarity to its parent a concept outside the knowledge base needs to be expanded
+    pub fn with_similarity_threshold(mut self, threshold: f32) -> Self {
+        *self.recursion_similarity_threshold.get_mut() = threshold.to_bits();
+        self
+    }
+
+    pub fn similarity_threshold(&self) -> f32 {
+        f32::from_bits(self.recursion_similarity_threshold.load(Ordering::Relaxed))
+    }
+
+    /// Change the threshold for the analyses that start expanding concepts from now on
+    pub fn set_similarity_threshold(&self, threshold: f32) {
+        self.recursion_similarity_threshold.store(threshold.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Whether sentences are embedded, by the ONNX model or an embedder given to `with_embedder`
+    pub fn has_embedding_model(&self) -> bool {
+        self.embedding_model.is_some()
+    }
+
     /// Build comprehensive engineering knowledge base
     fn build_knowledge_base() -> ConceptKnowledgeBase {
         let mut concept_hierarchies = HashMap::new();
@@ -186,18 +600,18 @@ impl SemanticAnalyzer {
 
         // Component relationships for UAV
         component_relationships.insert("uav".to_string(), vec![
-            ComponentRelation { component: "motor".to_string(), relation_type: RelationType::PartOf, confidence: 0.95 },
-            ComponentRelation { component: "battery".to_string(), relation_type: RelationType::Requires, confidence: 0.98 },
-            ComponentRelation { component: "propeller".to_string(), relation_type: RelationType::PartOf, confidence: 0.90 },
-            ComponentRelation { component: "flight controller".to_string(), relation_type: RelationType::Controls, confidence: 0.92 },
+            ComponentRelation { subject: "motor".to_string(), component: "uav".to_string(), relation_type: RelationType::PartOf, confidence: 0.95 },
+            ComponentRelation { subject: "uav".to_string(), component: "battery".to_string(), relation_type: RelationType::Requires, confidence: 0.98 },
+            ComponentRelation { subject: "propeller".to_string(), component: "uav".to_string(), relation_type: RelationType::PartOf, confidence: 0.90 },
+            ComponentRelation { subject: "flight controller".to_string(), component: "uav".to_string(), relation_type: RelationType::Controls, confidence: 0.92 },
         ]);
 
         // Category mappings
-        category_mappings.insert("motor".to_string(), PrincipleCategory::Mechanical);
-        category_mappings.insert("battery".to_string(), PrincipleCategory::Electrical);
-        category_mappings.insert("propeller".to_string(), PrincipleCategory::Mechanical);
-        category_mappings.insert("flight controller".to_string(), PrincipleCategory::System);
-        category_mappings.insert("frame".to_string(), PrincipleCategory::Structural);
+        category_mappings.insert("motor".to_string(), PrincipleCategory::new("Mechanical"));
+        category_mappings.insert("battery".to_string(), PrincipleCategory::new("Electrical"));
+        category_mappings.insert("propeller".to_string(), PrincipleCategory::new("Mechanical"));
+        category_mappings.insert("flight controller".to_string(), PrincipleCategory::new("System"));
+        category_mappings.insert("frame".to_string(), PrincipleCategory::new("Structural"));
 
         // Synonyms for better matching
         synonyms.insert("uav".to_string(), vec!["drone".to_string(), "unmanned aerial vehicle".to_string(), "quadcopter".to_string()]);
@@ -212,52 +626,69 @@ impl SemanticAnalyzer {
         }
     }
 
-    /// Build ML-driven component extractors
-    fn build_component_extractors() -> Vec<ComponentExtractor> {
+    /// The built-in ML-driven component extractors
+    pub fn default_component_extractors() -> Vec<ComponentExtractor> {
         vec![
             ComponentExtractor {
                 name: "mechanical_components".to_string(),
-                patterns: vec![
-                    Regex::new(r"\b(motor|engine|gear|bearing|shaft|piston|turbine|pump|compressor|fan|propeller)\b").unwrap(),
-                    Regex::new(r"\b(actuator|servo|stepper|valve|clutch|brake|transmission|coupling)\b").unwrap(),
-                ],
-                category: PrincipleCategory::Mechanical,
+                keywords: [
+                    "motor", "engine", "gear", "bearing", "shaft", "piston", "turbine", "pump", "compressor",
+                    "fan", "propeller", "actuator", "servo", "stepper", "valve", "clutch", "brake",
+                    "transmission", "coupling",
+                ]
+                .map(String::from)
+                .to_vec(),
+                patterns: vec![],
+                category: PrincipleCategory::new("Mechanical"),
                 weight: 0.8,
             },
             ComponentExtractor {
                 name: "electrical_components".to_string(),
-                patterns: vec![
-                    Regex::new(r"\b(battery|capacitor|resistor|transistor|diode|circuit|sensor|microcontroller)\b").unwrap(),
-                    Regex::new(r"\b(power supply|transformer|inverter|converter|relay|switch|connector)\b").unwrap(),
-                ],
-                category: PrincipleCategory::Electrical,
+                keywords: [
+                    "battery", "capacitor", "resistor", "transistor", "diode", "circuit", "sensor",
+                    "microcontroller", "power supply", "transformer", "inverter", "converter", "relay",
+                    "switch", "connector",
+                ]
+                .map(String::from)
+                .to_vec(),
+                patterns: vec![],
+                category: PrincipleCategory::new("Electrical"),
                 weight: 0.85,
             },
             ComponentExtractor {
                 name: "structural_components".to_string(),
-                patterns: vec![
-                    Regex::new(r"\b(frame|chassis|beam|column|foundation|support|bracket|mount|housing)\b").unwrap(),
-                    Regex::new(r"\b(panel|plate|shell|casing|structure|framework|skeleton)\b").unwrap(),
-                ],
-                category: PrincipleCategory::Structural,
+                keywords: [
+                    "frame", "chassis", "beam", "column", "foundation", "support", "bracket", "mount",
+                    "housing", "panel", "plate", "shell", "casing", "structure", "framework", "skeleton",
+                ]
+                .map(String::from)
+                .to_vec(),
+                patterns: vec![],
+                category: PrincipleCategory::new("Structural"),
                 weight: 0.75,
             },
             ComponentExtractor {
                 name: "control_components".to_string(),
-                patterns: vec![
-                    Regex::new(r"\b(controller|processor|computer|ECU|flight controller|autopilot)\b").unwrap(),
-                    Regex::new(r"\b(sensor|gyroscope|accelerometer|GPS|IMU|barometer|compass)\b").unwrap(),
-                ],
-                category: PrincipleCategory::System,
+                keywords: [
+                    "controller", "processor", "computer", "ECU", "flight controller", "autopilot", "sensor",
+                    "gyroscope", "accelerometer", "GPS", "IMU", "barometer", "compass",
+                ]
+                .map(String::from)
+                .to_vec(),
+                patterns: vec![],
+                category: PrincipleCategory::new("System"),
                 weight: 0.9,
             },
             ComponentExtractor {
                 name: "thermal_components".to_string(),
-                patterns: vec![
-                    Regex::new(r"\b(radiator|heat sink|cooling fan|thermal pad|heat exchanger)\b").unwrap(),
-                    Regex::new(r"\b(insulation|thermal barrier|coolant|refrigeration)\b").unwrap(),
-                ],
-                category: PrincipleCategory::Thermal,
+                keywords: [
+                    "radiator", "heat sink", "cooling fan", "thermal pad", "heat exchanger", "insulation",
+                    "thermal barrier", "coolant", "refrigeration",
+                ]
+                .map(String::from)
+                .to_vec(),
+                patterns: vec![],
+                category: PrincipleCategory::new("Thermal"),
                 weight: 0.7,
             },
         ]
@@ -267,79 +698,53 @@ impl SemanticAnalyzer {
     fn build_relationship_patterns() -> Vec<RelationshipPattern> {
         vec![
             RelationshipPattern {
-                pattern: Regex::new(r"(\w+)\s+(?:is|are)\s+(?:part of|component of|element of)\s+(\w+)").unwrap(),
+                pattern: Regex::new(r"(\w+)\s+(?:is|are)\s+(?:part of|component of|element of)\s+(?:(?:an?|the)\s+)?(\w+)").unwrap(),
                 relation_type: RelationType::PartOf,
                 confidence: 0.9,
             },
             RelationshipPattern {
-                pattern: Regex::new(r"(\w+)\s+(?:requires|needs|depends on)\s+(\w+)").unwrap(),
+                pattern: Regex::new(r"(\w+)\s+(?:requires|needs|depends on)\s+(?:(?:an?|the)\s+)?(\w+)").unwrap(),
                 relation_type: RelationType::Requires,
                 confidence: 0.85,
             },
             RelationshipPattern {
-                pattern: Regex::new(r"(\w+)\s+(?:controls|manages|regulates)\s+(\w+)").unwrap(),
+                pattern: Regex::new(r"(\w+)\s+(?:controls|manages|regulates)\s+(?:(?:an?|the)\s+)?(\w+)").unwrap(),
                 relation_type: RelationType::Controls,
                 confidence: 0.8,
             },
             RelationshipPattern {
-                pattern: Regex::new(r"(\w+)\s+(?:connects to|links to|attached to)\s+(\w+)").unwrap(),
+                pattern: Regex::new(r"(\w+)\s+(?:connects to|links to|attached to)\s+(?:(?:an?|the)\s+)?(\w+)").unwrap(),
                 relation_type: RelationType::Connects,
                 confidence: 0.75,
             },
             RelationshipPattern {
-                pattern: Regex::new(r"(\w+)\s+(?:supports|holds|carries)\s+(\w+)").unwrap(),
+                pattern: Regex::new(r"(\w+)\s+(?:supports|holds|carries)\s+(?:(?:an?|the)\s+)?(\w+)").unwrap(),
                 relation_type: RelationType::Supports,
                 confidence: 0.8,
             },
             RelationshipPattern {
-                pattern: Regex::new(r"(\w+)\s+(?:converts|transforms|changes)\s+.*(?:into|to)\s+(\w+)").unwrap(),
+                pattern: Regex::new(r"(\w+)\s+(?:converts|transforms|changes)\s+.*(?:into|to)\s+(?:(?:an?|the)\s+)?(\w+)").unwrap(),
                 relation_type: RelationType::Converts,
                 confidence: 0.7,
             },
         ]
     }
 
-    /// Pre-compute embeddings for engineering concepts
-    fn precompute_concept_embeddings(
-        embedding_session: &Option<Session>,
-        tokenizer: &Option<Tokenizer>,
-    ) -> HashMap<String, Vec<f32>> {
-        let mut embeddings = HashMap::new();
-        
-        // In a real implementation, this would compute embeddings for key concepts
-        // For now, return empty map - the system will use knowledge-based fallback
-        if embedding_session.is_some() && tokenizer.is_some() {
-            // Pre-compute embeddings for foundational engineering concepts
-            let key_concepts = vec![
-                "motor", "battery", "controller", "sensor", "frame", "propeller",
-                "engine", "transmission", "brake", "suspension", "steering",
-                "circuit", "capacitor", "resistor", "transformer", "switch",
-            ];
-            
-            for concept in key_concepts {
-                // In real implementation: embeddings.insert(concept.to_string(), compute_embedding(concept));
-                // For now, use placeholder
-                embeddings.insert(concept.to_string(), vec![0.0; 384]); // 384 is common embedding size
-            }
-        }
-        
-        embeddings
-    }
-
     /// Try to load embedding model (sentence transformer via ONNX)
-    fn try_load_embedding_model() -> (Option<Session>, Option<Tokenizer>) {
+    #[cfg(feature = "embeddings")]
+    fn try_load_embedding_model(model: &Path, tokenizer: &Path) -> Option<Arc<dyn SentenceEmbedder>> {
         // Try to load sentence transformer model
-        match std::fs::metadata("models/sentence-transformer.onnx") {
+        match std::fs::metadata(model) {
             Ok(_) => {
                 // Model file exists, try to load it
                 match Session::builder() {
                     Ok(builder) => {
-                        match builder.commit_from_file("models/sentence-transformer.onnx") {
+                        match builder.commit_from_file(model) {
                             Ok(session) => {
-                                match Tokenizer::from_file("models/tokenizer.json") {
+                                match Tokenizer::from_file(tokenizer) {
                                     Ok(tokenizer) => {
                                         tracing::info!("Successfully loaded ONNX sentence transformer model");
-                                        return (Some(session), Some(tokenizer));
+                                        return Some(Arc::new(EmbeddingModel { session: Mutex::new(session), tokenizer }));
                                     },
                                     Err(e) => tracing::warn!("Failed to load tokenizer: {}", e),
                                 }
@@ -350,86 +755,135 @@ impl SemanticAnalyzer {
                     Err(e) => tracing::warn!("Failed to create ONNX session builder: {}", e),
                 }
             },
-            Err(_) => tracing::info!("ONNX model not found at models/sentence-transformer.onnx"),
+            Err(_) => tracing::info!("ONNX model not found at {}", model.display()),
         }
         
-        (None, None)
+        None
     }
 
-    /// Main method for decomposing engineering concepts hierarchically
-    pub fn decompose_concept(&self, concept: &str, max_depth: u8) -> Result<ConceptDecomposition> {
-        let normalized_concept = self.normalize_concept(concept);
+    #[cfg(not(feature = "embeddings"))]
+    fn try_load_embedding_model(_model: &Path, _tokenizer: &Path) -> Option<Arc<dyn SentenceEmbedder>> {
+        tracing::info!("Built without the embeddings feature; using knowledge-based similarity");
+        None
+    }
+
+    /// Main method for decomposing engineering concepts hierarchically.
+    ///
+    /// Known concepts come from the knowledge base; anything else is extracted from
+    /// `content` (the concept's article text), which the caller is responsible for fetching.
+    #[tracing::instrument(skip(self, content))]
+    pub fn decompose_concept(&self, concept: &str, content: Option<&str>, max_depth: u8) -> Result<ConceptDecomposition> {
+        let knowledge = self.knowledge.load();
+        let normalized_concept = knowledge.normalize(concept);
         
         // Try knowledge-base first for known concepts
-        if let Some(components) = self.extract_from_knowledge_base(&normalized_concept) {
+        if let Some(components) = self.extract_from_knowledge_base(&knowledge.knowledge, &normalized_concept, max_depth) {
             return Ok(ConceptDecomposition {
                 concept: concept.to_string(),
                 components,
-                relationships: self.extract_relationships(&normalized_concept),
+                relationships: Self::extract_relationships(&knowledge.knowledge, &normalized_concept),
                 confidence: 0.95,
             });
         }
         
         // Fall back to ML-based extraction from text content
-        let wikipedia_content = self.fetch_concept_content(concept)?;
-        self.extract_components_from_text(&wikipedia_content, max_depth)
+        match content {
+            Some(content) => self.extract_components_from_text(concept, content, max_depth),
+            None => Err(WikiEngineError::Analysis(format!(
+                "No knowledge-base entry or article content to decompose '{}'",
+                concept
+            ))),
+        }
     }
 
-    /// Normalize concept for lookup (handle synonyms, case, etc.)
-    fn normalize_concept(&self, concept: &str) -> String {
-        let concept_lower = concept.to_lowercase();
-        
-        // Check for synonyms
-        for (key, synonyms) in &self.concept_knowledge.synonyms {
-            if key == &concept_lower || synonyms.contains(&concept_lower) {
-                return key.clone();
-            }
-        }
-        
-        concept_lower
+    /// Whether the knowledge base can decompose a concept without any article content
+    pub fn has_knowledge_of(&self, concept: &str) -> bool {
+        let knowledge = self.knowledge.load();
+        knowledge.knowledge.concept_hierarchies.contains_key(&knowledge.normalize(concept))
+    }
+
+    /// Normalize concept for lookup: knowledge-base concepts are matched through their
+    /// synonyms and regardless of inflection ("gears", "flight controls"); anything else is
+    /// just case-folded, so distinct articles such as "Control" and "Controller" stay apart
+    pub fn normalize_concept(&self, concept: &str) -> String {
+        self.knowledge.load().normalize(concept)
     }
 
     /// Extract components from knowledge base
-    fn extract_from_knowledge_base(&self, concept: &str) -> Option<Vec<FoundationalComponent>> {
-        let hierarchies = &self.concept_knowledge.concept_hierarchies;
-        
-        if let Some(sub_concepts) = hierarchies.get(concept) {
-            let mut components = Vec::new();
-            
-            for sub_concept in sub_concepts {
-                let category = self.concept_knowledge.category_mappings
-                    .get(sub_concept)
-                    .cloned()
-                    .unwrap_or(PrincipleCategory::System);
-                
-                let description = self.generate_component_description(sub_concept, &category);
-                let importance = self.calculate_component_importance(sub_concept, concept);
-                
-                // Get sub-components recursively
-                let sub_components = hierarchies.get(sub_concept)
-                    .map(|subs| subs.clone())
-                    .unwrap_or_default();
-                
-                components.push(FoundationalComponent {
-                    name: sub_concept.clone(),
-                    category,
-                    description,
-                    importance,
-                    sub_components,
-                });
+    fn extract_from_knowledge_base(
+        &self,
+        knowledge: &ConceptKnowledgeBase,
+        concept: &str,
+        max_depth: u8,
+    ) -> Option<Vec<FoundationalComponent>> {
+        knowledge.concept_hierarchies.get(concept)?;
+
+        let mut path = HashSet::from([concept.to_string()]);
+        Some(self.build_component_tree(knowledge, concept, 1, max_depth.max(1), &mut path))
+    }
+
+    /// Recursively decompose `concept` through the hierarchy graph. `path` holds the concepts on
+    /// the current branch so cycles (e.g. motor -> engine -> ... -> motor) are cut off.
+    fn build_component_tree(
+        &self,
+        knowledge: &ConceptKnowledgeBase,
+        concept: &str,
+        depth: u8,
+        max_depth: u8,
+        path: &mut HashSet<String>,
+    ) -> Vec<FoundationalComponent> {
+        let hierarchies = &knowledge.concept_hierarchies;
+        let Some(sub_concepts) = hierarchies.get(concept) else {
+            return Vec::new();
+        };
+
+        let mut components = Vec::new();
+        for sub_concept in sub_concepts {
+            if path.contains(sub_concept) {
+                tracing::debug!("Skipping cyclic decomposition '{}' -> '{}'", concept, sub_concept);
+                continue;
             }
-            
-            // Sort by importance
-            components.sort_by(|a, b| b.importance.partial_cmp(&a.importance).unwrap_or(std::cmp::Ordering::Equal));
-            return Some(components);
+
+            let category = knowledge.category_mappings
+                .get(sub_concept)
+                .cloned()
+                .unwrap_or(PrincipleCategory::new("System"));
+
+            let description = self.generate_component_description(sub_concept, &category);
+            let importance = Self::calculate_component_importance(knowledge, sub_concept, concept);
+
+            let sub_components = hierarchies.get(sub_concept)
+                .cloned()
+                .unwrap_or_default();
+
+            let children = if depth < max_depth {
+                path.insert(sub_concept.clone());
+                let children = self.build_component_tree(knowledge, sub_concept, depth + 1, max_depth, path);
+                path.remove(sub_concept);
+                children
+            } else {
+                Vec::new()
+            };
+
+            components.push(FoundationalComponent {
+                name: sub_concept.clone(),
+                category,
+                description,
+                importance,
+                sub_components,
+                children,
+                source: ComponentSource::KnowledgeBase,
+            });
         }
-        
-        None
+
+        // Sort by importance
+        components.sort_by(|a, b| b.importance.partial_cmp(&a.importance).unwrap_or(std::cmp::Ordering::Equal));
+        components
     }
 
     /// Extract relationships for a concept
-    fn extract_relationships(&self, concept: &str) -> Vec<ComponentRelation> {
-        self.concept_knowledge.component_relationships
+    fn extract_relationships(knowledge: &ConceptKnowledgeBase, concept: &str) -> Vec<ComponentRelation> {
+        knowledge.component_relationships
             .get(concept)
             .cloned()
             .unwrap_or_default()
@@ -437,25 +891,17 @@ impl SemanticAnalyzer {
 
     /// Generate description for a component
     fn generate_component_description(&self, component: &str, category: &PrincipleCategory) -> String {
-        match category {
-            PrincipleCategory::Mechanical => format!("{} is a mechanical component that provides movement, force transmission, or mechanical advantage", component),
-            PrincipleCategory::Electrical => format!("{} is an electrical component that manages power, control signals, or energy conversion", component),
-            PrincipleCategory::Structural => format!("{} is a structural component that provides support, stability, or load distribution", component),
-            PrincipleCategory::System => format!("{} is a system component that provides control, coordination, or integration functionality", component),
-            PrincipleCategory::Thermal => format!("{} is a thermal component that manages heat transfer, temperature control, or thermal regulation", component),
-            PrincipleCategory::Material => format!("{} is a material component that provides specific material properties or characteristics", component),
-            _ => format!("{} is an engineering component with specialized functionality", component),
-        }
+        self.description_templates.description(component, category)
     }
 
     /// Calculate component importance based on relationships and context
-    fn calculate_component_importance(&self, component: &str, parent_concept: &str) -> f32 {
+    fn calculate_component_importance(knowledge: &ConceptKnowledgeBase, component: &str, parent_concept: &str) -> f32 {
         let mut importance = 0.5; // Base importance
         
         // Check if it's a critical component based on relationships
-        if let Some(relationships) = self.concept_knowledge.component_relationships.get(parent_concept) {
+        if let Some(relationships) = knowledge.component_relationships.get(parent_concept) {
             for relation in relationships {
-                if relation.component == component {
+                if relation.involves(component) {
                     match relation.relation_type {
                         RelationType::Requires => importance += 0.3,
                         RelationType::Controls => importance += 0.25,
@@ -476,51 +922,59 @@ impl SemanticAnalyzer {
         importance.min(1.0)
     }
 
-    /// Fetch content for concept analysis (placeholder for actual Wikipedia API call)
-    fn fetch_concept_content(&self, _concept: &str) -> Result<String> {
-        // This would normally fetch Wikipedia content
-        // For now, return a placeholder to avoid breaking the system
-        Ok("Placeholder content for concept analysis".to_string())
-    }
-
     /// Extract components from text using ML techniques
-    fn extract_components_from_text(&self, text: &str, _max_depth: u8) -> Result<ConceptDecomposition> {
+    fn extract_components_from_text(&self, concept: &str, text: &str, _max_depth: u8) -> Result<ConceptDecomposition> {
         let mut components = Vec::new();
         let mut relationships = Vec::new();
-        let text_lower = text.to_lowercase();
+        // Later mentions of "ESC" count as the "electronic speed controller" they stand for
+        let text_lower = text::fold_case(&text::expand_acronyms(text, &text::acronyms(text)));
+        let concept_lower = text::fold_case(concept.trim());
         
-        // Use component extractors to find engineering components
-        for extractor in &self.component_extractors {
-            for pattern in &extractor.patterns {
-                for cap in pattern.captures_iter(&text_lower) {
-                    if let Some(component_match) = cap.get(1) {
-                        let component_name = component_match.as_str().to_string();
-                        
-                        // Avoid duplicates
-                        if !components.iter().any(|c: &FoundationalComponent| c.name == component_name) {
-                            let description = self.generate_component_description(&component_name, &extractor.category);
-                            let importance = extractor.weight * 0.8; // Base importance from extractor weight
-                            
-                            components.push(FoundationalComponent {
-                                name: component_name,
-                                category: extractor.category.clone(),
-                                description,
-                                importance,
-                                sub_components: vec![],
-                            });
-                        }
-                    }
+        // Use component extractors to find engineering components: keywords in one pass over
+        // the text, then any extractor-specific patterns
+        let keyword_matches = self.keyword_matcher.find(&text_lower, self.component_extractors.len());
+        for (extractor, keyword_matches) in self.component_extractors.iter().zip(keyword_matches) {
+            let pattern_matches = extractor.patterns.iter().flat_map(|pattern| {
+                pattern.captures_iter(&text_lower).filter_map(|cap| cap.get(1)).map(|m| m.as_str())
+            });
+            for component_name in keyword_matches.into_iter().chain(pattern_matches) {
+                // Avoid duplicates
+                if !components.iter().any(|c: &FoundationalComponent| c.name == component_name) {
+                    let description = self.generate_component_description(component_name, &extractor.category);
+                    let importance = extractor.weight * 0.8; // Base importance from extractor weight
+
+                    components.push(FoundationalComponent {
+                        name: component_name.to_string(),
+                        category: extractor.category.clone(),
+                        description,
+                        importance,
+                        sub_components: vec![],
+                        children: vec![],
+                        source: ComponentSource::Article,
+                    });
                 }
             }
         }
         
+        // Relations are read from the text with "It ..." sentences pointing back at the concept
+        let resolved = text::resolve_pronouns(&text_lower, &concept_lower);
+        let component_names: Vec<&str> = components.iter().map(|c| c.name.as_str()).collect();
+        // The patterns capture single words, so a capture inside the concept or a component
+        // name stands for the whole name
+        let mut phrases: Vec<&str> = component_names.iter().copied().chain([concept_lower.as_str()]).collect();
+        phrases.sort_by_key(|phrase| std::cmp::Reverse(phrase.len()));
+        let whole_name = |capture: regex::Match<'_>| {
+            covering_phrase(&resolved, capture.range(), &phrases).unwrap_or(capture.as_str()).to_string()
+        };
+
         // Extract relationships using relationship patterns
         for pattern_matcher in &self.relationship_patterns {
-            for cap in pattern_matcher.pattern.captures_iter(&text_lower) {
+            for cap in pattern_matcher.pattern.captures_iter(&resolved) {
                 if cap.len() >= 3 {
-                    if let (Some(_comp1), Some(comp2)) = (cap.get(1), cap.get(2)) {
+                    if let (Some(comp1), Some(comp2)) = (cap.get(1), cap.get(2)) {
                         relationships.push(ComponentRelation {
-                            component: comp2.as_str().to_string(),
+                            subject: whole_name(comp1),
+                            component: whole_name(comp2),
                             relation_type: pattern_matcher.relation_type.clone(),
                             confidence: pattern_matcher.confidence,
                         });
@@ -529,6 +983,28 @@ impl SemanticAnalyzer {
             }
         }
         
+        // Phrasings the patterns miss ("the battery feeds the motor") are matched by meaning
+        let composition = self.extract_composition(&concept_lower, &resolved, &component_names);
+        for relation in composition.into_iter().chain(self.extract_relationships_by_embedding(&resolved, &component_names)) {
+            let known = relationships.iter().any(|existing| {
+                existing.subject == relation.subject
+                    && existing.component == relation.component
+                    && existing.relation_type == relation.relation_type
+            });
+            if !known {
+                relationships.push(relation);
+            }
+        }
+        
+        // "x is part of y" makes x a sub-component of y when y was extracted as a component
+        for relation in relationships.iter().filter(|r| r.relation_type == RelationType::PartOf) {
+            if let Some(parent) = components.iter_mut().find(|c| c.name == relation.component) {
+                if !parent.sub_components.contains(&relation.subject) {
+                    parent.sub_components.push(relation.subject.clone());
+                }
+            }
+        }
+        
         // Sort components by importance
         components.sort_by(|a, b| b.importance.partial_cmp(&a.importance).unwrap_or(std::cmp::Ordering::Equal));
         
@@ -540,34 +1016,56 @@ impl SemanticAnalyzer {
         };
         
         Ok(ConceptDecomposition {
-            concept: "extracted_concept".to_string(),
+            concept: concept.to_string(),
             components,
             relationships,
             confidence,
         })
     }
 
-    /// Compute semantic embedding for text (placeholder implementation)
-    fn compute_embedding(&self, text: &str) -> Option<Vec<f32>> {
-        // Check if we have pre-computed embeddings
-        if let Some(embedding) = self.concept_embeddings.get(text) {
-            return Some(embedding.clone());
+    /// Run the component extractors over extra text (e.g. patent abstracts) and add the
+    /// components and relationships the decomposition doesn't already have, flagged with `source`
+    pub fn merge_components_from_text(
+        &self,
+        decomposition: &mut ConceptDecomposition,
+        text: &str,
+        source: ComponentSource,
+    ) -> Result<usize> {
+        let extracted = self.extract_components_from_text(&decomposition.concept, text, 1)?;
+        let mut added = 0;
+
+        for mut component in extracted.components {
+            let known = decomposition.components.iter()
+                .any(|existing| existing.name.eq_ignore_ascii_case(&component.name));
+            if !known {
+                component.source = source;
+                decomposition.components.push(component);
+                added += 1;
+            }
         }
-        
-        // In a real implementation with ONNX, this would:
-        // 1. Tokenize the input text using the tokenizer
-        // 2. Run inference through the ONNX session
-        // 3. Return the sentence embedding vector
-        //
-        // For now, we return None to use the fallback word-based similarity
-        // This allows the system to work without requiring ONNX models
-        
-        if self.embedding_session.is_some() && self.tokenizer.is_some() {
-            // Placeholder: in real implementation, would compute actual embeddings
-            tracing::debug!("Would compute embedding for: {}", text);
+        for relation in extracted.relationships {
+            let known = decomposition.relationships.iter().any(|existing| {
+                existing.subject == relation.subject
+                    && existing.component == relation.component
+                    && existing.relation_type == relation.relation_type
+            });
+            if !known {
+                decomposition.relationships.push(relation);
+            }
+        }
+
+        Ok(added)
+    }
+
+    /// Sentence embedding of `text`, or `None` without an embedding model or when it fails
+    fn compute_embedding(&self, text: &str) -> Option<Vec<f32>> {
+        match self.embedding_model.as_ref()?.embed(text) {
+            Ok(embedding) => Some(embedding),
+            Err(error) => {
+                tracing::warn!("Failed to embed '{}': {}", text, error);
+                None
+            }
         }
-        
-        None
     }
 
     /// Enhanced method for converting decomposition to engineering principles
@@ -579,6 +1077,7 @@ impl SemanticAnalyzer {
         let mut principles = Vec::new();
         
         for component in &decomposition.components {
+            let source_span = EngineeringAnalyzer::sentence_mentioning(&page.extract, &component.name).unwrap_or_default();
             let principle = EngineeringPrinciple {
                 id: uuid::Uuid::new_v4().to_string(),
                 title: self.generate_principle_title(&component.name, &component.category),
@@ -586,7 +1085,13 @@ impl SemanticAnalyzer {
                 category: component.category.clone(),
                 confidence: component.importance,
                 source_url: page.url.clone(),
-                related_terms: component.sub_components.clone(),
+                related_terms: component.sub_components.iter().map(|name| Term::new(name)).collect(),
+                source_span,
+                source_section: EngineeringAnalyzer::section_at(&page.extract, source_span.0),
+                appears_in: vec![],
+                classified_by: None,
+                named_law: laws::detect(&component.description).map(str::to_string),
+                component: Some(component.name.clone()),
             };
             principles.push(principle);
         }
@@ -598,93 +1103,107 @@ impl SemanticAnalyzer {
 
     /// Generate principle title from component
     fn generate_principle_title(&self, component: &str, category: &PrincipleCategory) -> String {
-        match category {
-            PrincipleCategory::Mechanical => format!("{} Mechanism", self.capitalize_words(component)),
-            PrincipleCategory::Electrical => format!("{} Circuit Principle", self.capitalize_words(component)),
-            PrincipleCategory::Structural => format!("{} Structural Design", self.capitalize_words(component)),
-            PrincipleCategory::System => format!("{} System Integration", self.capitalize_words(component)),
-            PrincipleCategory::Thermal => format!("{} Thermal Management", self.capitalize_words(component)),
-            _ => format!("{} Engineering Principle", self.capitalize_words(component)),
-        }
-    }
-
-    /// Capitalize words for titles
-    fn capitalize_words(&self, text: &str) -> String {
-        text.split_whitespace()
-            .map(|word| {
-                let mut chars = word.chars();
-                match chars.next() {
-                    None => String::new(),
-                    Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
-                }
-            })
-            .collect::<Vec<_>>()
-            .join(" ")
+        self.description_templates.title(component, category)
     }
 
     /// Legacy method for compatibility - updated to use new decomposition approach
+    #[tracing::instrument(skip_all, fields(page = %page.title))]
     pub fn analyze_page_semantically(&self, page: &WikipediaPage) -> Result<Vec<EngineeringPrinciple>> {
         // Try to extract concept from page title
-        let concept = page.title.to_lowercase();
+        let concept = text::fold_case(&page.title);
         
-        // Use new decomposition method
-        match self.decompose_concept(&concept, 2) {
-            Ok(decomposition) => {
-                let principles = self.decomposition_to_principles(&decomposition, page);
-                Ok(principles)
-            },
-            Err(_) => {
-                // Fallback to text-based extraction
-                let decomposition = self.extract_components_from_text(&page.extract, 1)?;
-                let principles = self.decomposition_to_principles(&decomposition, page);
-                Ok(principles)
-            }
-        }
+        // Knowledge base first, falling back to the page's own text
+        let decomposition = self.decompose_concept(&concept, Some(&page.extract), 2)?;
+        Ok(self.decomposition_to_principles(&decomposition, page))
     }
 
     /// Calculate semantic similarity using embeddings (with fallback to word overlap)
     fn calculate_semantic_similarity(&self, text1: &str, text2: &str) -> f32 {
         // Try embedding-based similarity first
         if let (Some(emb1), Some(emb2)) = (self.compute_embedding(text1), self.compute_embedding(text2)) {
-            self.cosine_similarity(&emb1, &emb2)
+            text::cosine_similarity(&emb1, &emb2)
         } else {
             // Fallback to word-based similarity
-            self.word_overlap_similarity(text1, text2)
+            text::word_overlap(text1, text2)
         }
     }
 
-    /// Compute cosine similarity between two embeddings
-    fn cosine_similarity(&self, a: &[f32], b: &[f32]) -> f32 {
-        if a.len() != b.len() {
-            return 0.0;
+    /// Category of a sentence by the category prototype its embedding is closest to, or `None`
+    /// without the embedding model or when no prototype is close enough to trust
+    pub fn classify_by_embedding(&self, sentence: &str) -> Option<PrincipleCategory> {
+        if self.category_prototypes.is_empty() {
+            return None;
         }
+        self.classify_embedding(&self.compute_embedding(sentence)?)
+    }
 
-        let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-        let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-        let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    /// `classify_by_embedding` for a sentence already embedded with `sentence_embedding`
+    pub fn classify_embedding(&self, embedding: &[f32]) -> Option<PrincipleCategory> {
+        nearest_prototype(embedding, &self.category_prototypes, self.min_prototype_similarity).map(|(category, _)| category)
+    }
 
-        if norm_a == 0.0 || norm_b == 0.0 {
-            0.0
-        } else {
-            dot_product / (norm_a * norm_b)
+    /// Components listed in "<concept> consists of ..." sentences of `text`, as parts of the concept
+    fn extract_composition(&self, concept: &str, text: &str, component_names: &[&str]) -> Vec<ComponentRelation> {
+        let mut relations = Vec::new();
+        for (_, sentence) in text::sentences(text) {
+            let Some(caps) = COMPOSITION.captures(sentence) else { continue };
+            if caps[1] != *concept {
+                continue;
+            }
+            for name in component_names.iter().filter(|name| caps[2].contains(*name)) {
+                relations.push(ComponentRelation {
+                    subject: name.to_string(),
+                    component: concept.to_string(),
+                    relation_type: RelationType::PartOf,
+                    confidence: 0.85,
+                });
+            }
         }
+        relations
     }
 
-    /// Fallback word overlap similarity
-    fn word_overlap_similarity(&self, text1: &str, text2: &str) -> f32 {
-        let text1_lower = text1.to_lowercase();
-        let text2_lower = text2.to_lowercase();
-        let words1: HashSet<&str> = text1_lower.split_whitespace().collect();
-        let words2: HashSet<&str> = text2_lower.split_whitespace().collect();
-        
-        let intersection = words1.intersection(&words2).count();
-        let union = words1.union(&words2).count();
-        
-        if union == 0 {
-            0.0
-        } else {
-            intersection as f32 / union as f32
+    /// The named law whose statement `text` is closest to in meaning, or `None` without the
+    /// embedding model or when no statement is close enough
+    pub fn named_law_by_embedding(&self, text: &str) -> Option<&'static str> {
+        if self.law_prototypes.is_empty() {
+            return None;
         }
+        self.named_law_of_embedding(&self.compute_embedding(text)?)
+    }
+
+    /// `named_law_by_embedding` for a text already embedded with `sentence_embedding`
+    pub fn named_law_of_embedding(&self, embedding: &[f32]) -> Option<&'static str> {
+        nearest_prototype(embedding, &self.law_prototypes, MIN_LAW_SIMILARITY).map(|(law, _)| law)
+    }
+
+    /// Relations between two components in sentences of `text` whose phrasing, with the
+    /// components masked out, is close to a relation prototype. Empty without the embedding model.
+    fn extract_relationships_by_embedding(&self, text: &str, component_names: &[&str]) -> Vec<ComponentRelation> {
+        if self.relation_prototypes.is_empty() {
+            return Vec::new();
+        }
+
+        text::sentences(text)
+            .into_iter()
+            .filter_map(|(_, sentence)| {
+                let (first, second, masked) = masked_component_pair(sentence, component_names)?;
+                let embedding = self.compute_embedding(&masked)?;
+                let ((relation_type, subject_first), similarity) =
+                    nearest_prototype(&embedding, &self.relation_prototypes, MIN_RELATION_SIMILARITY)?;
+                let (subject, component) = if subject_first { (first, second) } else { (second, first) };
+                Some(ComponentRelation {
+                    subject: subject.to_string(),
+                    component: component.to_string(),
+                    relation_type,
+                    confidence: similarity * 0.9,
+                })
+            })
+            .collect()
+    }
+
+    /// Sentence embedding of `text`, unit length, or `None` without the embedding model
+    pub fn sentence_embedding(&self, text: &str) -> Option<Vec<f32>> {
+        self.compute_embedding(text)
     }
 
     /// Public interface for hierarchical engineering concept analysis
@@ -692,22 +1211,41 @@ impl SemanticAnalyzer {
     pub fn analyze_engineering_concept(
         &self,
         concept: &str,
+        content: Option<&str>,
         max_depth: u8,
         source_url: Option<String>,
     ) -> Result<Vec<EngineeringPrinciple>> {
         // Decompose the concept into foundational components
-        let decomposition = self.decompose_concept(concept, max_depth)?;
+        let decomposition = self.decompose_concept(concept, content, max_depth)?;
         
+        Ok(self.principles_from_decomposition(concept, &decomposition, source_url))
+    }
+
+    /// Convert an existing decomposition (e.g. one served from cache) into engineering principles
+    pub fn principles_from_decomposition(
+        &self,
+        concept: &str,
+        decomposition: &ConceptDecomposition,
+        source_url: Option<String>,
+    ) -> Vec<EngineeringPrinciple> {
         // Create a mock Wikipedia page for compatibility
         let page = WikipediaPage {
             title: concept.to_string(),
             extract: format!("Analysis of {} and its foundational engineering components", concept),
             url: source_url.unwrap_or_else(|| format!("https://en.wikipedia.org/wiki/{}", concept)),
             page_id: 0,
+            revision_id: None,
+            fetched_at: chrono::Utc::now(),
+            thumbnail_url: None,
+            monthly_views: None,
+            curated_links: vec![],
+            etag: None,
+            last_modified: None,
+            source: DocumentSourceKind::Wikipedia,
         };
         
         // Convert decomposition to engineering principles
-        let principles = self.decomposition_to_principles(&decomposition, &page);
+        let principles = self.decomposition_to_principles(decomposition, &page);
         
         tracing::info!(
             "Analyzed concept '{}' -> found {} foundational components with confidence {}",
@@ -716,12 +1254,46 @@ impl SemanticAnalyzer {
             decomposition.confidence
         );
         
-        Ok(principles)
+        principles
     }
 
     /// Get hierarchical breakdown of a concept as a structured tree
-    pub fn get_concept_hierarchy(&self, concept: &str, max_depth: u8) -> Result<ConceptDecomposition> {
-        self.decompose_concept(concept, max_depth)
+    pub fn get_concept_hierarchy(&self, concept: &str, content: Option<&str>, max_depth: u8) -> Result<ConceptDecomposition> {
+        self.decompose_concept(concept, content, max_depth)
+    }
+
+    /// Whether a related concept looks worth recursing into from `parent`.
+    ///
+    /// Concepts the knowledge base knows about always pass; anything else has to be
+    /// semantically close to the parent (embeddings when loaded, word overlap otherwise).
+    pub fn is_related_concept(&self, parent: &str, concept: &str) -> bool {
+        let snapshot = self.knowledge.load();
+        let knowledge = &snapshot.knowledge;
+        let concept_key = snapshot.normalize(concept);
+
+        let in_parent_hierarchy = knowledge.concept_hierarchies
+            .get(&snapshot.normalize(parent))
+            .is_some_and(|subs| subs.contains(&concept_key));
+        let known = knowledge.concept_hierarchies.contains_key(&concept_key)
+            || knowledge.category_mappings.contains_key(&concept_key)
+            || knowledge.synonyms.contains_key(&concept_key);
+
+        in_parent_hierarchy
+            || known
+            || self.calculate_semantic_similarity(parent, concept) >= self.similarity_threshold()
+    }
+
+    /// All concept names the knowledge base knows about, including synonyms
+    pub fn known_concepts(&self) -> Vec<String> {
+        let knowledge = &self.knowledge.load().knowledge;
+        let mut concepts: Vec<String> = knowledge.concept_hierarchies.keys()
+            .chain(knowledge.concept_hierarchies.values().flatten())
+            .chain(knowledge.synonyms.values().flatten())
+            .cloned()
+            .collect();
+        concepts.sort();
+        concepts.dedup();
+        concepts
     }
 
     /// Add new concept knowledge to the knowledge base (for extending the system)
@@ -731,9 +1303,249 @@ impl SemanticAnalyzer {
         components: Vec<String>,
         relationships: Vec<ComponentRelation>,
     ) {
-        self.concept_knowledge.concept_hierarchies.insert(concept.to_string(), components);
-        self.concept_knowledge.component_relationships.insert(concept.to_string(), relationships);
+        let mut knowledge = self.knowledge_base();
+        knowledge.concept_hierarchies.insert(concept.to_string(), components);
+        knowledge.component_relationships.insert(concept.to_string(), relationships);
+        self.set_knowledge_base(knowledge);
         
         tracing::info!("Added knowledge for concept: {}", concept);
     }
-}
\ No newline at end of file
+}
+
+/// The label of the prototype embedding most similar to `embedding`, with its similarity, if
+/// any is at least `min_similarity` similar
+fn nearest_prototype<T: Clone>(embedding: &[f32], prototypes: &[(T, Vec<f32>)], min_similarity: f32) -> Option<(T, f32)> {
+    prototypes
+        .iter()
+        .map(|(label, prototype)| (label, text::cosine_similarity(embedding, prototype)))
+        .filter(|(_, similarity)| *similarity >= min_similarity)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(label, similarity)| (label.clone(), similarity))
+}
+
+/// The first of `phrases` (with more than one word) that has an occurrence in `text` covering
+/// `span`, e.g. "fixture gearbox" for the "gearbox" a pattern captured from "fixture gearbox requires"
+fn covering_phrase<'p>(text: &str, span: std::ops::Range<usize>, phrases: &[&'p str]) -> Option<&'p str> {
+    phrases.iter().copied().filter(|phrase| phrase.contains(' ')).find(|phrase| {
+        (span.end.saturating_sub(phrase.len())..=span.start).any(|start| {
+            text.get(start..start + phrase.len()) == Some(*phrase)
+                && !text[..start].ends_with(|c: char| c.is_alphanumeric())
+                && !text[start + phrase.len()..].starts_with(|c: char| c.is_alphanumeric())
+        })
+    })
+}
+
+/// The first two different components `sentence` mentions, in order, and the sentence with
+/// them replaced by "X" and "Y", so only its phrasing is compared with the relation prototypes
+fn masked_component_pair<'a>(sentence: &str, component_names: &[&'a str]) -> Option<(&'a str, &'a str, String)> {
+    let mut mentions: Vec<(usize, &'a str)> = component_names
+        .iter()
+        .filter_map(|name| sentence.find(name).map(|position| (position, *name)))
+        .collect();
+    // Longer names first where two start together ("speed controller" over "speed")
+    mentions.sort_by_key(|&(position, name)| (position, std::cmp::Reverse(name.len())));
+
+    let (first_at, first) = *mentions.first()?;
+    let (second_at, second) = *mentions.iter().find(|(position, _)| *position >= first_at + first.len())?;
+    let masked = format!(
+        "{}X{}Y{}",
+        &sentence[..first_at],
+        &sentence[first_at + first.len()..second_at],
+        &sentence[second_at + second.len()..]
+    );
+    Some((first, second, masked))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn relation_type_serializes_as_snake_case() {
+        assert_eq!(serde_json::to_value(RelationType::PartOf).unwrap(), json!("part_of"));
+        assert_eq!(serde_json::to_value(RelationType::Converts).unwrap(), json!("converts"));
+        assert_eq!(serde_json::from_value::<RelationType>(json!("controls")).unwrap(), RelationType::Controls);
+    }
+
+    #[test]
+    fn concept_decomposition_round_trips() {
+        let decomposition = ConceptDecomposition {
+            concept: "UAV".to_string(),
+            components: vec![FoundationalComponent {
+                name: "motor".to_string(),
+                category: PrincipleCategory::new("Mechanical"),
+                description: "Converts electrical energy into rotation".to_string(),
+                importance: 0.9,
+                sub_components: vec!["stator".to_string(), "rotor".to_string()],
+                children: vec![],
+                source: ComponentSource::KnowledgeBase,
+            }],
+            relationships: vec![ComponentRelation {
+                subject: "motor".to_string(),
+                component: "uav".to_string(),
+                relation_type: RelationType::PartOf,
+                confidence: 0.8,
+            }],
+            confidence: 0.95,
+        };
+
+        let value = serde_json::to_value(&decomposition).unwrap();
+        assert_eq!(value["concept"], json!("UAV"));
+        assert_eq!(value["components"][0]["category"], json!("Mechanical"));
+        assert_eq!(value["components"][0]["sub_components"], json!(["stator", "rotor"]));
+        assert_eq!(value["relationships"][0]["subject"], json!("motor"));
+        assert_eq!(value["relationships"][0]["relation_type"], json!("part_of"));
+
+        let restored: ConceptDecomposition = serde_json::from_value(value).unwrap();
+        assert_eq!(restored.components[0].sub_components, decomposition.components[0].sub_components);
+        assert_eq!(restored.relationships[0].relation_type, RelationType::PartOf);
+    }
+
+    #[test]
+    fn sentences_take_the_category_of_the_nearest_prototype() {
+        let prototypes = vec![
+            (PrincipleCategory::new("Thermal"), vec![1.0, 0.0, 0.0]),
+            (PrincipleCategory::new("Electrical"), vec![0.0, 1.0, 0.0]),
+        ];
+        assert_eq!(
+            nearest_prototype(&[0.2, 0.9, 0.1], &prototypes, MIN_PROTOTYPE_SIMILARITY).map(|(category, _)| category),
+            Some(PrincipleCategory::new("Electrical"))
+        );
+        assert_eq!(nearest_prototype(&[0.0, 0.1, 1.0], &prototypes, MIN_PROTOTYPE_SIMILARITY), None);
+
+        // Without the model there are no prototypes, and the patterns decide
+        let analyzer = SemanticAnalyzer::new().unwrap();
+        if !analyzer.has_embedding_model() {
+            assert_eq!(analyzer.classify_by_embedding("The windings carry current"), None);
+        }
+    }
+
+    #[test]
+    fn sentences_are_classified_by_their_embedding_not_their_keywords() {
+        let embedder = WordGroupEmbedder(vec![&["heat", "hot", "temperature", "cooling"], &["voltage", "current", "resistance", "circuit"]]);
+        let analyzer = SemanticAnalyzer::new().unwrap().with_embedder(Arc::new(embedder));
+        assert!(analyzer.has_embedding_model());
+
+        // "power" makes the keyword patterns call it mechanical; its meaning is thermal
+        let sentence = "Fins keep the power stage from getting hot";
+        assert_eq!(categories::registry().categorize(sentence), Some(PrincipleCategory::new("Mechanical")));
+        assert_eq!(analyzer.classify_by_embedding(sentence), Some(PrincipleCategory::new("Thermal")));
+        assert_eq!(analyzer.classify_by_embedding("The rotor spins"), None);
+    }
+
+    #[test]
+    fn pronoun_subjects_link_parts_to_the_concept() {
+        let analyzer = SemanticAnalyzer::new().unwrap();
+        let text = "A Fixture gearbox transmits torque. It consists of a shaft, a gear and a bearing. It requires lubrication.";
+        let decomposition = analyzer.decompose_concept("Fixture gearbox", Some(text), 1).unwrap();
+
+        let parts: Vec<&str> = decomposition
+            .relationships
+            .iter()
+            .filter(|r| r.relation_type == RelationType::PartOf && r.component == "fixture gearbox")
+            .map(|r| r.subject.as_str())
+            .collect();
+        assert_eq!(parts.len(), 3, "{:?}", decomposition.relationships);
+        assert!(["shaft", "gear", "bearing"].iter().all(|part| parts.contains(part)));
+        assert!(decomposition.relationships.iter().all(|r| r.subject != "it"));
+    }
+
+    #[test]
+    fn relation_patterns_keep_multi_word_subjects() {
+        let analyzer = SemanticAnalyzer::new().unwrap();
+        let text = "A Fixture gearbox transmits torque. It requires lubrication. The flight controller controls the motor.";
+        let decomposition = analyzer.decompose_concept("Fixture gearbox", Some(text), 1).unwrap();
+        let pairs: Vec<(&str, &str)> =
+            decomposition.relationships.iter().map(|r| (r.subject.as_str(), r.component.as_str())).collect();
+
+        assert!(pairs.contains(&("fixture gearbox", "lubrication")), "{:?}", pairs);
+        assert!(pairs.contains(&("flight controller", "motor")), "{:?}", pairs);
+        assert!(pairs.iter().all(|(subject, _)| *subject != "gearbox" && *subject != "controller"));
+        assert_eq!(covering_phrase("the fixture gearboxes", 12..20, &["fixture gearbox"]), None);
+    }
+
+    #[test]
+    fn relation_candidates_mask_the_two_components() {
+        let names = ["motor", "battery", "electronic speed controller", "speed"];
+        assert_eq!(
+            masked_component_pair("the battery feeds current to each motor.", &names),
+            Some(("battery", "motor", "the X feeds current to each Y.".to_string()))
+        );
+        assert_eq!(
+            masked_component_pair("an electronic speed controller sets the motor speed", &names),
+            Some(("electronic speed controller", "motor", "an X sets the Y speed".to_string()))
+        );
+        assert_eq!(masked_component_pair("the motor spins", &names), None);
+    }
+
+    #[test]
+    fn relations_no_pattern_matches_are_found_by_embedding() {
+        let text = "The drone kit ships with a battery and a motor. The battery feeds the motor.";
+        let requires = |analyzer: &SemanticAnalyzer| {
+            let decomposition = analyzer.decompose_concept("drone kit", Some(text), 1).unwrap();
+            decomposition.relationships.into_iter().find(|r| r.relation_type == RelationType::Requires)
+        };
+        assert!(requires(&SemanticAnalyzer::new().unwrap()).is_none());
+
+        // "feeds" is phrased like "the X powers the Y", where the second component is the subject
+        let embedder = WordGroupEmbedder(vec![&["feeds", "powers", "supplies"], &["mounted", "inside"]]);
+        let relation = requires(&SemanticAnalyzer::new().unwrap().with_embedder(Arc::new(embedder))).unwrap();
+        assert_eq!((relation.subject.as_str(), relation.component.as_str()), ("motor", "battery"));
+        assert!(relation.confidence > 0.5);
+    }
+
+    #[test]
+    fn laws_stated_in_other_words_are_named_by_embedding() {
+        let embedder = WordGroupEmbedder(vec![&["cool", "warm", "loses", "surroundings"], &["rotor", "spin"]]);
+        let analyzer = SemanticAnalyzer::new().unwrap().with_embedder(Arc::new(embedder));
+
+        let description = "The casing cools faster the warmer it is than its surroundings";
+        assert_eq!(laws::detect(description), None);
+        assert_eq!(analyzer.named_law_by_embedding(description), Some("Newton's law of cooling"));
+        assert_eq!(analyzer.named_law_by_embedding("The rotor spins"), None);
+        assert_eq!(SemanticAnalyzer::new().unwrap().named_law_by_embedding(description), None);
+    }
+
+    #[test]
+    fn inflected_concepts_match_the_knowledge_base() {
+        let analyzer = SemanticAnalyzer::new().unwrap();
+        assert_eq!(analyzer.normalize_concept("Motors"), "motor");
+        assert_eq!(analyzer.normalize_concept("Engines"), "motor");
+        assert_eq!(analyzer.normalize_concept("flight controls"), "flight controller");
+        assert_eq!(analyzer.normalize_concept("Controllers"), "controllers");
+
+        assert_eq!(text::word_overlap("meshing gears", "Gear meshing"), 1.0);
+    }
+
+    #[test]
+    fn knowledge_base_decomposition_honors_depth_and_cycles() {
+        let mut analyzer = SemanticAnalyzer::new().unwrap();
+
+        let shallow = analyzer.decompose_concept("uav", None, 1).unwrap();
+        assert!(shallow.components.iter().all(|c| c.children.is_empty()));
+
+        let deep = analyzer.decompose_concept("uav", None, 2).unwrap();
+        let propulsion = deep.components.iter().find(|c| c.name == "propulsion system").unwrap();
+        assert!(propulsion.children.iter().any(|c| c.name == "motor"));
+
+        analyzer.add_concept_knowledge("gearbox", vec!["gear train".to_string()], vec![]);
+        analyzer.add_concept_knowledge("gear train", vec!["gearbox".to_string()], vec![]);
+        let cyclic = analyzer.decompose_concept("gearbox", None, 5).unwrap();
+        assert_eq!(cyclic.components.len(), 1);
+        assert!(cyclic.components[0].children.is_empty());
+    }
+
+    #[test]
+    fn keyword_matches_are_whole_words_and_longest_first() {
+        let extractors = SemanticAnalyzer::default_component_extractors();
+        let matcher = KeywordMatcher::new(&extractors);
+        let text = "the cooling fan near the gps module spins; motorcycles use a flight controller";
+
+        let found = matcher.find(text, extractors.len());
+        assert_eq!(found[0], ["fan"]);
+        assert_eq!(found[3], ["gps", "flight controller"]);
+        assert_eq!(found[4], ["cooling fan"]);
+        assert!(found[1].is_empty() && found[2].is_empty());
+    }
+}
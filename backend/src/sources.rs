@@ -0,0 +1,568 @@
+use crate::text;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::types::FieldError;
+use crate::types::{DocumentSourceKind, Result, WikiEngineError, WikipediaPage};
+use crate::wikipedia::MediaWikiClient;
+use regex::Regex;
+use reqwest::Client;
+use scraper::{Html, Selector};
+use serde::Deserialize;
+#[cfg(not(target_arch = "wasm32"))]
+use futures::StreamExt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::{IpAddr, SocketAddr};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Boxed future returned by sources. Browser fetches are not `Send`, so on WASM it isn't either.
+#[cfg(not(target_arch = "wasm32"))]
+pub type SourceFuture<'a, T> = futures::future::BoxFuture<'a, T>;
+#[cfg(target_arch = "wasm32")]
+pub type SourceFuture<'a, T> = futures::future::LocalBoxFuture<'a, T>;
+
+/// Text the analyzers work from, wherever it came from
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub title: String,
+    pub text: String,
+    pub url: String,
+}
+
+impl Document {
+    /// Wrap the document as a page so it can go through the same analysis pipeline
+    pub fn into_page(self, source: DocumentSourceKind) -> WikipediaPage {
+        WikipediaPage {
+            title: text::nfc(&self.title).into_owned(),
+            extract: text::nfc(&self.text).into_owned(),
+            url: self.url,
+            page_id: 0,
+            revision_id: None,
+            fetched_at: chrono::Utc::now(),
+            thumbnail_url: None,
+            monthly_views: None,
+            curated_links: vec![],
+            etag: None,
+            last_modified: None,
+            source,
+        }
+    }
+}
+
+/// Somewhere a term can be looked up as a document
+pub trait DocumentSource: Send + Sync {
+    fn kind(&self) -> DocumentSourceKind;
+
+    /// The document for `term`, or `None` if the source has nothing on it
+    fn fetch<'a>(&'a self, term: &'a str) -> SourceFuture<'a, Result<Option<Document>>>;
+}
+
+impl DocumentSource for MediaWikiClient {
+    fn kind(&self) -> DocumentSourceKind {
+        DocumentSourceKind::Wikipedia
+    }
+
+    fn fetch<'a>(&'a self, term: &'a str) -> SourceFuture<'a, Result<Option<Document>>> {
+        Box::pin(async move {
+            Ok(self.get_page_extract(term).await?.map(|page| Document {
+                title: page.title,
+                text: page.extract,
+                url: page.url,
+            }))
+        })
+    }
+}
+
+/// Redirects a URL document may go through before it is given up on
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_URL_REDIRECTS: usize = 5;
+
+/// Bytes of a URL document read before it is given up on
+#[cfg(not(target_arch = "wasm32"))]
+pub const MAX_URL_DOCUMENT_BYTES: usize = 5 << 20;
+
+/// Fetches terms that are themselves http(s) URLs and keeps the readable paragraph text.
+///
+/// Only hosts on the allow-list are fetched (`docs.example.org`, or `*.example.org` for its
+/// subdomains), and never at loopback, private or link-local addresses: redirects are followed
+/// one at a time, and each host is checked against the list and its addresses after DNS
+/// resolution, then connected to at exactly those addresses, so requests can't reach the
+/// server's own network.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct UrlSource {
+    allowed_hosts: Vec<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl UrlSource {
+    pub fn new(allowed_hosts: Vec<String>) -> Self {
+        Self {
+            allowed_hosts: allowed_hosts.into_iter().map(|host| host.trim().to_lowercase()).collect(),
+        }
+    }
+
+    /// A client connecting to `host` only at `addrs`, which have been checked, and following no
+    /// redirects, which are checked before they are followed
+    fn pinned_client(host: &str, addrs: &[SocketAddr]) -> Result<Client> {
+        Ok(Client::builder()
+            .user_agent("WikiEngineBackend/1.0 (Educational Purpose)")
+            .timeout(std::time::Duration::from_secs(30))
+            // A proxy would resolve hosts itself, out of reach of the address checks
+            .no_proxy()
+            .redirect(reqwest::redirect::Policy::none())
+            .resolve_to_addrs(host, addrs)
+            .build()?)
+    }
+
+    /// The response for `url` after following its redirects, or `None` if there is no document
+    async fn get_checked(&self, mut url: reqwest::Url) -> Result<Option<reqwest::Response>> {
+        for _ in 0..=MAX_URL_REDIRECTS {
+            check_url(&url, &self.allowed_hosts)
+                .map_err(|reason| WikiEngineError::InvalidRequest(vec![FieldError::new("term", reason)]))?;
+            let host = url.host_str().unwrap_or_default().to_string();
+            let addrs = public_addresses(&host, url.port_or_known_default().unwrap_or(80)).await?;
+
+            let response = Self::pinned_client(&host, &addrs)?.get(url.clone()).send().await?;
+            if response.status().is_redirection() {
+                let location = response
+                    .headers()
+                    .get(reqwest::header::LOCATION)
+                    .and_then(|location| location.to_str().ok())
+                    .and_then(|location| url.join(location).ok())
+                    .ok_or_else(|| WikiEngineError::Analysis(format!("Bad redirect from {}", url)))?;
+                url = location;
+                continue;
+            }
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                return Ok(None);
+            }
+            if !response.status().is_success() {
+                return Err(WikiEngineError::Analysis(format!("HTTP {} for {}", response.status(), url)));
+            }
+            return Ok(Some(response));
+        }
+        Err(WikiEngineError::Analysis(format!("Too many redirects from {}", url)))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DocumentSource for UrlSource {
+    fn kind(&self) -> DocumentSourceKind {
+        DocumentSourceKind::Url
+    }
+
+    fn fetch<'a>(&'a self, term: &'a str) -> SourceFuture<'a, Result<Option<Document>>> {
+        Box::pin(async move {
+            let Ok(url) = reqwest::Url::parse(term) else {
+                return Ok(None);
+            };
+            if !matches!(url.scheme(), "http" | "https") {
+                return Ok(None);
+            }
+
+            let Some(response) = self.get_checked(url.clone()).await? else {
+                return Ok(None);
+            };
+            let body = read_limited(response, MAX_URL_DOCUMENT_BYTES).await?;
+
+            let (title, text) = html_title_and_text(&String::from_utf8_lossy(&body));
+            Ok(Some(Document {
+                title: title.unwrap_or_else(|| url.to_string()),
+                text,
+                url: url.to_string(),
+            }))
+        })
+    }
+}
+
+/// Why `url` may not be fetched, if it may not: a scheme other than http(s), a host off the
+/// allow-list, or an address literal that isn't public
+#[cfg(not(target_arch = "wasm32"))]
+fn check_url(url: &reqwest::Url, allowed_hosts: &[String]) -> std::result::Result<(), String> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("{} is not an http(s) URL", url));
+    }
+    let Some(host) = url.host_str() else {
+        return Err(format!("{} has no host", url));
+    };
+    let host = host.trim_end_matches('.').to_lowercase();
+    if let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        if !is_public_address(ip) {
+            return Err(format!("{} is not a public address", ip));
+        }
+    }
+    if host_allowed(&host, allowed_hosts) {
+        Ok(())
+    } else {
+        Err(format!("{} is not an allowed host for URL documents", host))
+    }
+}
+
+/// Whether the lowercase `host` is on `allowed_hosts`, where `*.example.org` allows the
+/// subdomains of example.org
+pub(crate) fn host_allowed(host: &str, allowed_hosts: &[String]) -> bool {
+    allowed_hosts.iter().any(|allowed| match allowed.strip_prefix("*.") {
+        Some(parent) => host.strip_suffix(parent).is_some_and(|sub| sub.ends_with('.')),
+        None => *allowed == host,
+    })
+}
+
+/// Whether `ip` is reachable on the public internet, rather than this machine, its network or
+/// a cloud metadata endpoint
+#[cfg(not(target_arch = "wasm32"))]
+fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || a == 0
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public_address(IpAddr::V4(mapped));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local, fc00::/7, and link-local, fe80::/10
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// The addresses `host` resolves to, or an error if any of them isn't public
+#[cfg(not(target_arch = "wasm32"))]
+async fn public_addresses(host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+    let literal = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = match literal.parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| WikiEngineError::Network(format!("Cannot resolve {}: {}", host, e)))?
+            .collect(),
+    };
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_address(addr.ip())) {
+        return Err(WikiEngineError::InvalidRequest(vec![FieldError::new(
+            "term",
+            format!("{} resolves to {}, which is not a public address", host, addr.ip()),
+        )]));
+    }
+    Ok(addrs)
+}
+
+/// The response body, or an error once it grows past `limit` bytes
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_limited(response: reqwest::Response, limit: usize) -> Result<Vec<u8>> {
+    let url = response.url().clone();
+    let too_large = || WikiEngineError::Analysis(format!("{} is larger than {} bytes", url, limit));
+    if response.content_length().is_some_and(|len| len > limit as u64) {
+        return Err(too_large());
+    }
+    let mut body = Vec::new();
+    let mut chunks = response.bytes_stream();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk?;
+        if body.len() + chunk.len() > limit {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+fn html_title_and_text(html: &str) -> (Option<String>, String) {
+    let document = Html::parse_document(html);
+    let title_selector = Selector::parse("title").unwrap();
+    let paragraph_selector = Selector::parse("p").unwrap();
+
+    let title = document
+        .select(&title_selector)
+        .next()
+        .map(|title| title.text().collect::<String>().trim().to_string())
+        .filter(|title| !title.is_empty());
+    let text = document
+        .select(&paragraph_selector)
+        .map(|paragraph| paragraph.text().collect::<String>().trim().to_string())
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    (title, text)
+}
+
+/// Markdown or plain-text files (e.g. text extracted from PDFs) in a local folder,
+/// one document per file, matched to terms by file name
+#[cfg(not(target_arch = "wasm32"))]
+pub struct LocalFolderSource {
+    root: PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl LocalFolderSource {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// `Electric motor`, `electric-motor.md` and `Electric_Motor.txt` all match each other
+    fn file_key(name: &str) -> String {
+        name.trim()
+            .to_lowercase()
+            .chars()
+            .map(|c| if c == '_' || c == '-' { ' ' } else { c })
+            .collect()
+    }
+
+    async fn find_file(&self, term: &str) -> Result<Option<PathBuf>> {
+        let wanted = Self::file_key(term);
+        let mut entries = tokio::fs::read_dir(&self.root).await.map_err(|e| {
+            WikiEngineError::Analysis(format!("Cannot read documents folder {}: {}", self.root.display(), e))
+        })?;
+
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            let is_text = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| matches!(ext, "md" | "markdown" | "txt"));
+            let stem_matches = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| Self::file_key(stem) == wanted);
+            if is_text && stem_matches {
+                return Ok(Some(path));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DocumentSource for LocalFolderSource {
+    fn kind(&self) -> DocumentSourceKind {
+        DocumentSourceKind::LocalFolder
+    }
+
+    fn fetch<'a>(&'a self, term: &'a str) -> SourceFuture<'a, Result<Option<Document>>> {
+        Box::pin(async move {
+            let Some(path) = self.find_file(term).await? else {
+                return Ok(None);
+            };
+            let text = tokio::fs::read_to_string(&path).await.map_err(|e| {
+                WikiEngineError::Analysis(format!("Cannot read {}: {}", path.display(), e))
+            })?;
+
+            Ok(Some(Document {
+                title: markdown_title(&text).unwrap_or_else(|| term.to_string()),
+                url: file_url(&path),
+                text,
+            }))
+        })
+    }
+}
+
+/// The first top-level Markdown heading, if any
+#[cfg(not(target_arch = "wasm32"))]
+fn markdown_title(text: &str) -> Option<String> {
+    text.lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn file_url(path: &Path) -> String {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    format!("file://{}", path.display())
+}
+
+/// Abstract of the most relevant arXiv paper for a term, via the arXiv Atom API
+pub struct ArxivSource {
+    client: Client,
+}
+
+impl ArxivSource {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+impl DocumentSource for ArxivSource {
+    fn kind(&self) -> DocumentSourceKind {
+        DocumentSourceKind::Arxiv
+    }
+
+    fn fetch<'a>(&'a self, term: &'a str) -> SourceFuture<'a, Result<Option<Document>>> {
+        Box::pin(async move {
+            let url = format!(
+                "https://export.arxiv.org/api/query?search_query=all:%22{}%22&max_results=1",
+                urlencoding::encode(term)
+            );
+            let response = self.client.get(&url).send().await?;
+            if !response.status().is_success() {
+                return Err(WikiEngineError::Analysis(format!("arXiv API returned HTTP {}", response.status())));
+            }
+            let feed = response.text().await?;
+            Ok(first_arxiv_entry(&feed))
+        })
+    }
+}
+
+static ARXIV_ENTRY: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<entry>(.*?)</entry>").unwrap());
+/// The entry fields read below; the regex crate has no backreferences, so the closing tag is
+/// checked against the opening one after matching
+static ARXIV_FIELD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<(title|summary|id)>(.*?)</(title|summary|id)>").unwrap());
+
+fn first_arxiv_entry(feed: &str) -> Option<Document> {
+    let entry = ARXIV_ENTRY.captures(feed)?.get(1)?.as_str();
+    let field = |name: &str| {
+        ARXIV_FIELD
+            .captures_iter(entry)
+            .find(|caps| &caps[1] == name && &caps[3] == name)
+            .map(|caps| caps[2].split_whitespace().collect::<Vec<_>>().join(" "))
+    };
+
+    Some(Document {
+        title: field("title")?,
+        text: field("summary")?,
+        url: field("id")?,
+    })
+}
+
+/// US patent abstracts from the USPTO PatentsView search API. Patent claims are full of
+/// "comprising a ... connected to a ..." language, which the component extractors thrive on.
+pub struct PatentSource {
+    client: Client,
+    api_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PatentSearchResponse {
+    #[serde(default)]
+    patents: Vec<PatentRecord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PatentRecord {
+    patent_id: String,
+    patent_title: Option<String>,
+    patent_abstract: Option<String>,
+}
+
+impl PatentSource {
+    pub fn new(client: Client, api_key: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_key: api_key.into(),
+        }
+    }
+
+    /// Abstracts of the patents best matching `term`
+    pub async fn search_abstracts(&self, term: &str, limit: usize) -> Result<Vec<Document>> {
+        let query = serde_json::json!({ "_text_all": { "patent_abstract": term } });
+        let fields = serde_json::json!(["patent_id", "patent_title", "patent_abstract"]);
+        let options = serde_json::json!({ "size": limit });
+        let url = format!(
+            "https://search.patentsview.org/api/v1/patent/?q={}&f={}&o={}",
+            urlencoding::encode(&query.to_string()),
+            urlencoding::encode(&fields.to_string()),
+            urlencoding::encode(&options.to_string())
+        );
+
+        let response = self.client.get(&url).header("X-Api-Key", &self.api_key).send().await?;
+        if !response.status().is_success() {
+            return Err(WikiEngineError::Analysis(format!("PatentsView API returned HTTP {}", response.status())));
+        }
+        let results: PatentSearchResponse = response.json().await?;
+
+        Ok(results
+            .patents
+            .into_iter()
+            .filter_map(|patent| {
+                Some(Document {
+                    title: patent.patent_title.unwrap_or_else(|| patent.patent_id.clone()),
+                    text: patent.patent_abstract.filter(|text| !text.is_empty())?,
+                    url: format!("https://patents.google.com/patent/US{}", patent.patent_id),
+                })
+            })
+            .collect())
+    }
+}
+
+impl DocumentSource for PatentSource {
+    fn kind(&self) -> DocumentSourceKind {
+        DocumentSourceKind::Patent
+    }
+
+    fn fetch<'a>(&'a self, term: &'a str) -> SourceFuture<'a, Result<Option<Document>>> {
+        Box::pin(async move { Ok(self.search_abstracts(term, 1).await?.into_iter().next()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_first_arxiv_entry() {
+        let feed = r#"<feed><title>ArXiv Query</title>
+            <entry><id>http://arxiv.org/abs/2101.00001v1</id>
+            <title>Brushless Motor
+              Control</title>
+            <summary>  We study the stator and rotor. </summary></entry>
+            <entry><id>http://arxiv.org/abs/2101.00002v1</id><title>Other</title><summary>x</summary></entry></feed>"#;
+
+        let document = first_arxiv_entry(feed).unwrap();
+        assert_eq!(document.title, "Brushless Motor Control");
+        assert_eq!(document.text, "We study the stator and rotor.");
+        assert_eq!(document.url, "http://arxiv.org/abs/2101.00001v1");
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn url_documents_come_only_from_allowed_public_hosts() {
+        let allowed = vec!["docs.example.org".to_string(), "*.example.com".to_string()];
+        let check = |url: &str| check_url(&reqwest::Url::parse(url).unwrap(), &allowed);
+
+        assert!(check("https://docs.example.org/motor").is_ok());
+        assert!(check("https://wiki.example.com/motor").is_ok());
+        assert!(check("https://example.com/motor").is_err());
+        assert!(check("https://evil-example.com/motor").is_err());
+        assert!(check("https://other.example.org/motor").is_err());
+        assert!(check("file:///etc/passwd").is_err());
+
+        for address in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0", "::1", "fd00::1", "fe80::1", "::ffff:127.0.0.1"] {
+            assert!(!is_public_address(address.parse().unwrap()), "{}", address);
+        }
+        assert!(is_public_address("93.184.216.34".parse().unwrap()));
+        assert!(is_public_address("2606:2800:220:1::".parse().unwrap()));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn url_source_refuses_hosts_resolving_to_private_addresses() {
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::any())
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string("<p>secret</p>"))
+            .mount(&server)
+            .await;
+        let port = server.address().port();
+        let source = UrlSource::new(vec!["localhost".to_string(), "127.0.0.1".to_string()]);
+
+        // Allowed by name, but loopback once resolved
+        let failure = source.fetch(&format!("http://localhost:{}/", port)).await.unwrap_err();
+        assert!(failure.to_string().contains("not a public address"), "{}", failure);
+        let failure = source.fetch(&format!("http://127.0.0.1:{}/", port)).await.unwrap_err();
+        assert!(failure.to_string().contains("not a public address"), "{}", failure);
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+}
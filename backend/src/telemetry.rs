@@ -0,0 +1,49 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::SpanExporter;
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use std::sync::OnceLock;
+use tracing::Subscriber;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+static TRACER_PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+/// Build a tracing layer exporting spans over OTLP/HTTP.
+///
+/// Export is only enabled when `OTEL_EXPORTER_OTLP_ENDPOINT` is set; the exporter
+/// reads the endpoint (and any other standard `OTEL_*` settings) from the environment.
+pub fn otlp_layer<S>() -> Option<impl Layer<S>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok()?;
+
+    let exporter = match SpanExporter::builder().with_http().build() {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            eprintln!("Failed to create OTLP span exporter: {}", e);
+            return None;
+        }
+    };
+
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name("wiki-engine-backend").build())
+        .build();
+    let tracer = provider.tracer("wiki-engine");
+
+    opentelemetry::global::set_tracer_provider(provider.clone());
+    let _ = TRACER_PROVIDER.set(provider);
+
+    Some(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+/// Flush buffered spans and stop the exporter
+pub fn shutdown() {
+    if let Some(provider) = TRACER_PROVIDER.get() {
+        if let Err(e) = provider.shutdown() {
+            tracing::warn!("Failed to shut down OTLP tracer provider: {}", e);
+        }
+    }
+}
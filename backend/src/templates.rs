@@ -0,0 +1,232 @@
+use crate::categories;
+use crate::text;
+use crate::types::PrincipleCategory;
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+/// Templates shipped with the crate, per locale; see `src/templates/en.txt` for the format
+const BUILT_IN: &[(&str, &str)] = &[
+    ("en", include_str!("templates/en.txt")),
+    ("de", include_str!("templates/de.txt")),
+];
+
+/// Locale whose templates stand in for keys another locale lacks
+const FALLBACK_LOCALE: &str = "en";
+
+/// Templates for the descriptions and titles of principles generated from components, per
+/// locale, so deployments can tune the prose without code changes
+#[derive(Debug, Clone)]
+pub struct DescriptionTemplates {
+    locales: HashMap<String, HashMap<String, String>>,
+    locale: String,
+}
+
+impl Default for DescriptionTemplates {
+    fn default() -> Self {
+        let mut templates = Self { locales: HashMap::new(), locale: FALLBACK_LOCALE.to_string() };
+        for (locale, contents) in BUILT_IN {
+            templates = templates.with_templates(locale, contents).expect("built-in templates parse");
+        }
+        templates
+    }
+}
+
+impl DescriptionTemplates {
+    /// The built-in templates overridden by the `<locale>.txt` files in `WIKI_ENGINE_TEMPLATES_DIR`,
+    /// rendered in `WIKI_ENGINE_TEMPLATE_LOCALE` (default `en`). A directory that can't be read
+    /// is logged and the built-in templates are used.
+    pub fn from_env() -> Self {
+        let mut templates = Self::default();
+        if let Some(dir) = std::env::var_os("WIKI_ENGINE_TEMPLATES_DIR").filter(|dir| !dir.is_empty()) {
+            match templates.clone().load_dir(Path::new(&dir)) {
+                Ok(loaded) => templates = loaded,
+                Err(error) => tracing::warn!("Ignoring templates in {}: {}", Path::new(&dir).display(), error),
+            }
+        }
+        match std::env::var("WIKI_ENGINE_TEMPLATE_LOCALE") {
+            Ok(locale) if !locale.is_empty() => templates.with_locale(&locale),
+            _ => templates,
+        }
+    }
+
+    /// Add the templates of every `<locale>.txt` file in `dir`, replacing those with the same key
+    pub fn load_dir(mut self, dir: &Path) -> io::Result<Self> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "txt") {
+                let Some(locale) = path.file_stem().and_then(|stem| stem.to_str()) else { continue };
+                let contents = std::fs::read_to_string(&path)?;
+                self = self
+                    .with_templates(locale, &contents)
+                    .map_err(|error| io::Error::new(error.kind(), format!("{}: {}", path.display(), error)))?;
+            }
+        }
+        Ok(self)
+    }
+
+    /// Add the `key = template` lines of `contents` to `locale`, replacing those with the same key
+    pub fn with_templates(mut self, locale: &str, contents: &str) -> io::Result<Self> {
+        let templates = self.locales.entry(locale.to_string()).or_default();
+        for (number, line) in contents.lines().enumerate().map(|(i, line)| (i + 1, line.trim())) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((key, template)) = line.split_once('=') else {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!("line {}: expected `key = template`", number)));
+            };
+            templates.insert(key.trim().to_string(), template.trim().to_string());
+        }
+        Ok(self)
+    }
+
+    /// Render in `locale` from now on; keys it has no template for use the English one. Region
+    /// subtags are ignored, so `de-AT` renders German.
+    pub fn with_locale(mut self, locale: &str) -> Self {
+        self.locale = primary_language(locale);
+        self
+    }
+
+    /// Copy of the templates rendering in `locale`
+    pub fn for_locale(&self, locale: &str) -> Self {
+        self.clone().with_locale(locale)
+    }
+
+    /// The locale to render in for an `Accept-Language` header: its most preferred language
+    /// that has templates, if any
+    pub fn negotiate(&self, accept_language: &str) -> Option<String> {
+        let mut preferences: Vec<(f32, String)> = accept_language
+            .split(',')
+            .filter_map(|item| {
+                let mut parts = item.split(';').map(str::trim);
+                let language = primary_language(parts.next()?);
+                let quality = parts
+                    .find_map(|param| param.strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+                (quality > 0.0 && self.locales.contains_key(&language)).then_some((quality, language))
+            })
+            .collect();
+        // Stable, so equally preferred languages keep the header's order
+        preferences.sort_by(|a, b| b.0.total_cmp(&a.0));
+        preferences.into_iter().next().map(|(_, language)| language)
+    }
+
+    /// Display name of the category named `name` (as `PrincipleCategory::name` spells it); the
+    /// registry's name for categories without a template
+    pub fn category_label(&self, name: &str) -> String {
+        match self.lookup(&format!("category.{}", name.to_lowercase())) {
+            Some(label) => label.to_string(),
+            None => categories::registry().get(name).map_or(name, |definition| &definition.name).to_string(),
+        }
+    }
+
+    /// Description of `component`, a part in `category`
+    pub fn description(&self, component: &str, category: &PrincipleCategory) -> String {
+        self.render_for("description", component, category)
+    }
+
+    /// Title of the principle generated for `component`
+    pub fn title(&self, component: &str, category: &PrincipleCategory) -> String {
+        self.render_for("title", component, category)
+    }
+
+    /// Subcategories without a template of their own use their nearest ancestor's
+    fn render_for(&self, kind: &str, component: &str, category: &PrincipleCategory) -> String {
+        let template = std::iter::once(category.name())
+            .chain(categories::registry().ancestors(category.name()))
+            .find_map(|id| self.lookup(&format!("{}.{}", kind, id.to_lowercase())))
+            .or_else(|| self.lookup(&format!("{}.default", kind)))
+            .unwrap_or("{component}");
+        render(template, &[("component", component), ("component_title", &text::title_case(component))])
+    }
+
+    fn lookup(&self, key: &str) -> Option<&str> {
+        [self.locale.as_str(), FALLBACK_LOCALE]
+            .into_iter()
+            .find_map(|locale| self.locales.get(locale)?.get(key))
+            .map(String::as_str)
+    }
+}
+
+/// Lowercase primary subtag of a language tag: "de" for "de-AT"
+fn primary_language(tag: &str) -> String {
+    tag.split(['-', '_']).next().unwrap_or_default().trim().to_lowercase()
+}
+
+/// `template` with each `{name}` placeholder replaced by its value in `values`. `{{` and `}}`
+/// stand for literal braces; placeholders without a value are left as they are.
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(brace) = rest.find(['{', '}']) {
+        rendered.push_str(&rest[..brace]);
+        let tail = &rest[brace..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            rendered.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        let value = tail.find('}').filter(|_| tail.starts_with('{')).and_then(|close| {
+            let name = &tail[1..close];
+            values.iter().find(|(key, _)| *key == name).map(|(_, value)| (*value, close))
+        });
+        match value {
+            Some((value, close)) => {
+                rendered.push_str(value);
+                rest = &tail[close + 1..];
+            }
+            None => {
+                rendered.push_str(&tail[..1]);
+                rest = &tail[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_filled_and_braces_escaped() {
+        let values = [("component", "rotor"), ("count", "2")];
+        assert_eq!(render("{component} x{count}", &values), "rotor x2");
+        assert_eq!(render("{{component}} {missing} }", &values), "{component} {missing} }");
+    }
+
+    #[test]
+    fn locales_fall_back_to_english() {
+        let templates = DescriptionTemplates::default();
+        assert_eq!(
+            templates.description("gear", &PrincipleCategory::new("Mechanical")),
+            "gear is a mechanical component that provides movement, force transmission, or mechanical advantage"
+        );
+        assert_eq!(templates.title("speed controller", &PrincipleCategory::new("Design")), "Speed Controller Engineering Principle");
+
+        let templates = templates
+            .with_templates("de", "title.design = Entwurf von {component_title}")
+            .unwrap()
+            .with_locale("de");
+        assert_eq!(templates.title("getriebe", &PrincipleCategory::new("Design")), "Entwurf von Getriebe");
+        assert_eq!(templates.title("getriebe", &PrincipleCategory::new("Thermal")), "Getriebe: Wärmemanagement");
+
+        let templates = templates.with_locale("fr");
+        assert_eq!(templates.title("rotor", &PrincipleCategory::new("Thermal")), "Rotor Thermal Management");
+        assert!(DescriptionTemplates::default().with_templates("en", "no separator").is_err());
+    }
+
+    #[test]
+    fn accept_language_picks_the_preferred_known_locale() {
+        let templates = DescriptionTemplates::default();
+        assert_eq!(templates.negotiate("fr-CH, de-AT;q=0.8, en;q=0.9"), Some("en".to_string()));
+        assert_eq!(templates.negotiate("fr, de-DE;q=0.5"), Some("de".to_string()));
+        assert_eq!(templates.negotiate("fr, *;q=0.1"), None);
+        assert_eq!(templates.negotiate("de;q=0"), None);
+
+        let german = templates.for_locale("de-AT");
+        assert_eq!(german.category_label("Mechanical"), "Mechanik");
+        assert_eq!(german.category_label("Aerodynamics"), "Aerodynamics");
+    }
+}
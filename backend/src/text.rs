@@ -0,0 +1,376 @@
+use regex::Regex;
+use rust_stemmers::{Algorithm, Stemmer};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+use unicode_segmentation::UnicodeSegmentation;
+
+static STEMMER: LazyLock<Stemmer> = LazyLock::new(|| Stemmer::create(Algorithm::English));
+
+/// Hyphens that join a compound into one word; en and em dashes separate words instead
+const JOINING_HYPHENS: [&str; 2] = ["-", "\u{2010}"];
+
+/// An acronym in parentheses, as in "electronic speed controller (ESC)"; a plural "s" is allowed
+static ACRONYM_DEFINITION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\((\p{Lu}[\p{Lu}\d]{1,9})s?\)").unwrap());
+
+/// Pronouns that, opening a sentence, almost always refer to the topic of an article
+const TOPIC_PRONOUNS: [&str; 2] = ["it", "they"];
+
+/// Words too common to be terms, per language; see `src/stopwords/`
+static STOP_WORDS: LazyLock<HashMap<&'static str, HashSet<&'static str>>> = LazyLock::new(|| {
+    [
+        ("en", include_str!("stopwords/en.txt")),
+        ("de", include_str!("stopwords/de.txt")),
+        ("fr", include_str!("stopwords/fr.txt")),
+        ("es", include_str!("stopwords/es.txt")),
+        ("it", include_str!("stopwords/it.txt")),
+        ("pt", include_str!("stopwords/pt.txt")),
+        ("nl", include_str!("stopwords/nl.txt")),
+    ]
+    .into_iter()
+    .map(|(language, list)| {
+        let words = list.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#'));
+        (language, words.collect())
+    })
+    .collect()
+});
+
+/// `text` in Unicode NFC, so precomposed and combining-mark spellings of the same term
+/// ("Å" and "A\u{30A}") compare equal; borrowed when it already is
+pub fn nfc(text: &str) -> Cow<'_, str> {
+    match is_nfc_quick(text.chars()) {
+        IsNormalized::Yes => Cow::Borrowed(text),
+        _ => Cow::Owned(text.nfc().collect()),
+    }
+}
+
+/// NFC-normalized lowercase, for comparing terms without regard to case
+pub fn fold_case(text: &str) -> String {
+    nfc(text).to_lowercase()
+}
+
+/// The words of `text` at Unicode (UAX #29) word boundaries, without the punctuation and
+/// spaces between them. Hyphenated compounds ("heat-resistant", "Navier-Stokes") stay one word;
+/// dashed ranges and pairs ("Maxwell–Boltzmann") are separate words.
+pub fn words(text: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    // Byte range of the word being built, and whether a joining hyphen follows it
+    let mut current: Option<(usize, usize)> = None;
+    let mut hyphenated = false;
+    for (start, segment) in text.split_word_bound_indices() {
+        let end = start + segment.len();
+        if segment.chars().any(char::is_alphanumeric) {
+            current = match current {
+                Some((word_start, _)) if hyphenated => Some((word_start, end)),
+                Some((word_start, word_end)) => {
+                    words.push(&text[word_start..word_end]);
+                    Some((start, end))
+                }
+                None => Some((start, end)),
+            };
+            hyphenated = false;
+        } else if current.is_some() && !hyphenated && JOINING_HYPHENS.contains(&segment) {
+            hyphenated = true;
+        } else {
+            if let Some((word_start, word_end)) = current.take() {
+                words.push(&text[word_start..word_end]);
+            }
+            hyphenated = false;
+        }
+    }
+    if let Some((word_start, word_end)) = current {
+        words.push(&text[word_start..word_end]);
+    }
+    words
+}
+
+/// The case-folded, stemmed words of `text`, so inflections of a word compare equal
+/// ("gears" and "gear", "controls" and "controller")
+pub fn stemmed_words(text: &str) -> Vec<String> {
+    let folded = fold_case(text);
+    words(&folded).into_iter().map(|word| STEMMER.stem(word).into_owned()).collect()
+}
+
+/// `text` as its stemmed words joined by single spaces, for matching whole terms
+pub fn stem_phrase(text: &str) -> String {
+    stemmed_words(text).join(" ")
+}
+
+/// The sentences of `text` with the byte offset each starts at, trimmed. A sentence ends at a
+/// line break or at a terminator followed by whitespace, so "3.5 kHz" stays whole.
+pub fn sentences(text: &str) -> Vec<(usize, &str)> {
+    let mut sentences = Vec::new();
+    let mut push = |start: usize, end: usize| {
+        let sentence = &text[start..end];
+        let trimmed = sentence.trim();
+        if !trimmed.is_empty() {
+            sentences.push((start + sentence.len() - sentence.trim_start().len(), trimmed));
+        }
+    };
+
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        let end = i + c.len_utf8();
+        let terminated = matches!(c, '.' | '!' | '?') && text[end..].chars().next().is_none_or(char::is_whitespace);
+        if c == '\n' || terminated {
+            push(start, end);
+            start = end;
+        }
+    }
+    push(start, text.len());
+    sentences
+}
+
+/// `text` with sentences opening with "It" or "They" rewritten to open with `topic` instead, so
+/// "It consists of a rotor" keeps its subject. Articles are about their title, which makes this
+/// a reasonable stand-in for co-reference resolution.
+pub fn resolve_pronouns(text: &str, topic: &str) -> String {
+    let mut resolved = String::with_capacity(text.len());
+    let mut copied = 0;
+    for (start, sentence) in sentences(text) {
+        let Some(first) = words(sentence).first().copied() else { continue };
+        if sentence.starts_with(first) && TOPIC_PRONOUNS.contains(&fold_case(first).as_str()) {
+            resolved.push_str(&text[copied..start]);
+            resolved.push_str(topic);
+            copied = start + first.len();
+        }
+    }
+    resolved.push_str(&text[copied..]);
+    resolved
+}
+
+/// Acronyms `text` defines, mapped to the phrase they stand for: "electronic speed controller
+/// (ESC)" maps "ESC" to "electronic speed controller". The phrase is the words right before the
+/// parenthesis whose initials spell the acronym, the parts of hyphenated words counting separately.
+pub fn acronyms(text: &str) -> HashMap<String, String> {
+    let mut acronyms = HashMap::new();
+    for caps in ACRONYM_DEFINITION.captures_iter(text) {
+        let (acronym, definition) = (&caps[1], caps.get(0).map_or(0, |m| m.start()));
+        let letters: Vec<char> = acronym.chars().filter(|c| c.is_alphabetic()).flat_map(char::to_lowercase).collect();
+
+        // Last words of the preceding sentence, split at hyphens, closest first
+        let preceding = sentences(&text[..definition]).pop().map_or("", |(_, sentence)| sentence);
+        let parts: Vec<&str> = words(preceding).into_iter().rev().flat_map(|word| word.rsplit(['-', '\u{2010}'])).collect();
+        if parts.len() < letters.len() {
+            continue;
+        }
+        let initials_match = parts[..letters.len()]
+            .iter()
+            .rev()
+            .zip(&letters)
+            .all(|(part, letter)| part.chars().next().into_iter().flat_map(char::to_lowercase).eq([*letter]));
+        if initials_match {
+            // The phrase as written, from its first word to the end of the preceding text
+            let first_part = parts[letters.len() - 1];
+            let start = first_part.as_ptr() as usize - text.as_ptr() as usize;
+            let phrase = text[start..definition].trim_end();
+            acronyms.entry(acronym.to_string()).or_insert_with(|| phrase.to_string());
+        }
+    }
+    acronyms
+}
+
+/// `text` with every whole-word use of an acronym in `acronyms` replaced by the phrase it stands
+/// for, except where it is being defined; borrowed when nothing changes
+pub fn expand_acronyms<'t>(text: &'t str, acronyms: &HashMap<String, String>) -> Cow<'t, str> {
+    if acronyms.is_empty() {
+        return Cow::Borrowed(text);
+    }
+
+    let mut expanded = String::new();
+    let mut copied = 0;
+    for (start, segment) in text.split_word_bound_indices() {
+        let Some(phrase) = acronyms.get(segment) else { continue };
+        let end = start + segment.len();
+        if text[..start].ends_with('(') && text[end..].starts_with([')', 's']) {
+            continue;
+        }
+        expanded.push_str(&text[copied..start]);
+        expanded.push_str(phrase);
+        copied = end;
+    }
+    if copied == 0 {
+        return Cow::Borrowed(text);
+    }
+    expanded.push_str(&text[copied..]);
+    Cow::Owned(expanded)
+}
+
+/// Whether the lowercase `word` is a stop word in `language` (an ISO 639-1 code). Languages
+/// without a list of their own use the English one.
+pub fn is_stop_word(word: &str, language: &str) -> bool {
+    STOP_WORDS
+        .get(language)
+        .or_else(|| STOP_WORDS.get("en"))
+        .is_some_and(|words| words.contains(word))
+}
+
+/// `text` with the first letter of every word capitalized
+pub fn title_case(text: &str) -> String {
+    text.split_whitespace()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                None => String::new(),
+                Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Jaccard similarity of the stemmed word sets of two texts, from 0 (no word in common) to 1
+pub fn word_overlap(text1: &str, text2: &str) -> f32 {
+    let words1: HashSet<String> = stemmed_words(text1).into_iter().collect();
+    let words2: HashSet<String> = stemmed_words(text2).into_iter().collect();
+
+    let union = words1.union(&words2).count();
+    if union == 0 {
+        0.0
+    } else {
+        words1.intersection(&words2).count() as f32 / union as f32
+    }
+}
+
+/// Cosine similarity of two vectors; 0 when their lengths differ or either is zero
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+
+    let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot_product / (norm_a * norm_b)
+    }
+}
+
+/// Average of the token embeddings in `token_embeddings` (`dimensions` values per token) whose
+/// attention mask is set, i.e. the sentence embedding of a transformer without padding
+pub fn mean_pool(token_embeddings: &[f32], attention_mask: &[i64], dimensions: usize) -> Vec<f32> {
+    let mut pooled = vec![0.0; dimensions];
+    let mut attended = 0;
+    for (token, _) in token_embeddings.chunks_exact(dimensions).zip(attention_mask).filter(|(_, &mask)| mask != 0) {
+        pooled.iter_mut().zip(token).for_each(|(sum, value)| *sum += value);
+        attended += 1;
+    }
+    if attended > 0 {
+        pooled.iter_mut().for_each(|sum| *sum /= attended as f32);
+    }
+    pooled
+}
+
+/// Scale `vector` to unit length, leaving a zero vector as it is
+pub fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+/// How alike two spellings are, from 0 to 1: the better of Jaro-Winkler and normalized
+/// Levenshtein similarity of their case-folded forms, so both typos and truncations score high
+pub fn spelling_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (fold_case(a), fold_case(b));
+    strsim::jaro_winkler(&a, &b).max(strsim::normalized_levenshtein(&a, &b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn words_keep_compounds_and_accents() {
+        assert_eq!(
+            words("The Ångström-scale Navier-Stokes flow, per Maxwell–Boltzmann (Poincaré)."),
+            ["The", "Ångström-scale", "Navier-Stokes", "flow", "per", "Maxwell", "Boltzmann", "Poincaré"]
+        );
+        assert_eq!(words("a - b -c 3.5 kHz"), ["a", "b", "c", "3.5", "kHz"]);
+
+        let decomposed = "A\u{30A}ngstro\u{308}m";
+        assert_eq!(nfc(decomposed), "Ångström");
+        assert!(matches!(nfc("Ångström"), Cow::Borrowed(_)));
+        assert_eq!(fold_case(decomposed), "ångström");
+    }
+
+    #[test]
+    fn inflections_share_a_stem() {
+        assert_eq!(stemmed_words("Gears and controls"), ["gear", "and", "control"]);
+        assert_eq!(stem_phrase("Flight Controller"), stem_phrase("flight controls"));
+    }
+
+    #[test]
+    fn sentences_end_at_terminators_and_line_breaks() {
+        let text = "A rotor spins at 3.5 kHz.  The stator stays still!\nWindings\nDo they heat up? Yes";
+        let found = sentences(text);
+        assert_eq!(
+            found.iter().map(|(_, sentence)| *sentence).collect::<Vec<_>>(),
+            ["A rotor spins at 3.5 kHz.", "The stator stays still!", "Windings", "Do they heat up?", "Yes"]
+        );
+        assert!(found.iter().all(|(start, sentence)| text[*start..].starts_with(sentence)));
+    }
+
+    #[test]
+    fn opening_pronouns_refer_to_the_topic() {
+        assert_eq!(
+            resolve_pronouns("An electric motor turns. It consists of a rotor. Items? They wear; it heats.", "electric motor"),
+            "An electric motor turns. electric motor consists of a rotor. Items? electric motor wear; it heats."
+        );
+        assert_eq!(resolve_pronouns("Itself aside, its rotor spins", "motor"), "Itself aside, its rotor spins");
+    }
+
+    #[test]
+    fn acronyms_map_back_to_their_phrase() {
+        let text = "Each electronic speed controller (ESC) drives a motor. Inertial measurement units (IMUs) \
+                    and a Flight Management System (FMS) sit on the Navier-Stokes (NS) board. Stray (XYZ) text.";
+        let found = acronyms(text);
+        assert_eq!(found.len(), 4, "{:?}", found);
+        assert_eq!(found["ESC"], "electronic speed controller");
+        assert_eq!(found["IMU"], "Inertial measurement units");
+        assert_eq!(found["FMS"], "Flight Management System");
+        assert_eq!(found["NS"], "Navier-Stokes");
+
+        assert_eq!(
+            expand_acronyms("The ESC (ESC) reads ESCs; the ESC heats.", &found),
+            "The electronic speed controller (ESC) reads ESCs; the electronic speed controller heats."
+        );
+        assert!(matches!(expand_acronyms("No acronyms here", &found), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn stop_words_depend_on_the_language() {
+        assert!(is_stop_word("der", "de"));
+        assert!(is_stop_word("une", "fr"));
+        assert!(!is_stop_word("der", "en"));
+        assert!(is_stop_word("which", "ru"));
+        assert!(!is_stop_word("motor", "de"));
+    }
+
+    #[test]
+    fn similarity_metrics() {
+        assert_eq!(word_overlap("meshing gears", "Gear meshing"), 1.0);
+        assert_eq!(word_overlap("rotor", "stator"), 0.0);
+        assert_eq!(word_overlap("", ""), 0.0);
+        assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 1.0]) - 0.5f32.sqrt()).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+        assert!(spelling_similarity("Turbofan", "turbofna") > 0.85);
+        assert!(spelling_similarity("Turbofan", "Propeller") < 0.85);
+        assert_eq!(title_case("flight  controller"), "Flight Controller");
+    }
+
+    #[test]
+    fn token_embeddings_are_pooled_over_the_attention_mask() {
+        // The third token is padding and must not pull the average towards it
+        let tokens = [1.0, 2.0, 3.0, 4.0, 100.0, 100.0];
+        let mut pooled = mean_pool(&tokens, &[1, 1, 0], 2);
+        assert_eq!(pooled, vec![2.0, 3.0]);
+        normalize(&mut pooled);
+        assert!((pooled.iter().map(|x| x * x).sum::<f32>() - 1.0).abs() < 1e-6);
+        assert_eq!(mean_pool(&tokens, &[0, 0, 0], 2), vec![0.0, 0.0]);
+    }
+}
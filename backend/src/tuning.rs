@@ -0,0 +1,258 @@
+use crate::categories;
+use crate::config::TuningPolicy;
+use crate::feedback::{FeedbackEntry, FeedbackKind, FeedbackLog};
+use crate::text;
+use crate::types::{AnalysisNode, AnalysisResult, PrincipleCategory};
+use arc_swap::ArcSwap;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+/// Category the labels of every category are pooled under for the overall threshold
+const OVERALL: &str = "*";
+
+/// A principle a user judged, in the category and at the confidence it had then
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfidenceLabel {
+    pub category: String,
+    pub confidence: f32,
+    pub correct: bool,
+}
+
+impl ConfidenceLabel {
+    /// Labels from the feedback on principles that recorded their confidence. Of several
+    /// judgments of the same principle by the same caller only the latest counts.
+    pub fn from_feedback(entries: &[FeedbackEntry]) -> Vec<Self> {
+        let mut latest = HashMap::new();
+        for entry in entries {
+            let feedback = &entry.feedback;
+            let (Some(category), Some(confidence), Some(principle)) = (&entry.category, entry.confidence, &feedback.principle) else {
+                continue;
+            };
+            let key = (entry.caller.as_str(), text::fold_case(&feedback.term), text::fold_case(principle));
+            let correct = feedback.kind == FeedbackKind::CorrectPrinciple;
+            latest.insert(key, Self { category: category.clone(), confidence, correct });
+        }
+        latest.into_values().collect()
+    }
+}
+
+/// Precision and recall of the labeled principles kept by one minimum confidence
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct PrecisionPoint {
+    pub threshold: f32,
+    /// Share of the labels at or above the threshold that were judged correct
+    pub precision: f32,
+    /// Share of the labels judged correct that are at or above the threshold
+    pub recall: f32,
+    /// Labels at or above the threshold
+    pub kept: usize,
+}
+
+/// Fitted precision curve of one category, and the threshold picked from it
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct CategoryTuning {
+    pub category: String,
+    pub labels: usize,
+    pub correct: usize,
+    /// Lowest minimum confidence reaching the target precision; `None` with too few labels or
+    /// when no threshold reaches it
+    pub threshold: Option<f32>,
+    /// Estimated precision at the threshold, or of all principles without one
+    pub precision: f32,
+    /// Estimated recall at the threshold, or of all principles without one
+    pub recall: f32,
+    /// One point per distinct labeled confidence, highest threshold first
+    pub curve: Vec<PrecisionPoint>,
+}
+
+impl CategoryTuning {
+    fn fit(category: &str, labels: &[&ConfidenceLabel], policy: &TuningPolicy) -> Self {
+        let mut labels = labels.to_vec();
+        labels.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        let correct = labels.iter().filter(|label| label.correct).count();
+
+        let mut curve: Vec<PrecisionPoint> = Vec::new();
+        let mut kept_correct = 0;
+        for (i, label) in labels.iter().enumerate() {
+            kept_correct += usize::from(label.correct);
+            // Equal confidences are kept or dropped together, so only the last of them is a point
+            if labels.get(i + 1).is_some_and(|next| next.confidence == label.confidence) {
+                continue;
+            }
+            curve.push(PrecisionPoint {
+                threshold: label.confidence,
+                precision: kept_correct as f32 / (i + 1) as f32,
+                recall: if correct == 0 { 0.0 } else { kept_correct as f32 / correct as f32 },
+                kept: i + 1,
+            });
+        }
+
+        // The lowest threshold, keeping the most correct principles, that is still precise enough
+        let chosen = curve
+            .iter()
+            .rfind(|point| labels.len() >= policy.min_labels && point.precision >= policy.target_precision);
+        let (precision, recall) = match (chosen, curve.last()) {
+            (Some(point), _) | (None, Some(point)) => (point.precision, point.recall),
+            (None, None) => (0.0, 0.0),
+        };
+        Self {
+            category: category.to_string(),
+            labels: labels.len(),
+            correct,
+            threshold: chosen.map(|point| point.threshold),
+            precision,
+            recall,
+            curve,
+        }
+    }
+}
+
+/// Confidence thresholds fitted to users' judgments of principles, per category
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct TuningReport {
+    pub fitted_at: DateTime<Utc>,
+    pub policy: TuningPolicy,
+    /// Every category's labels pooled; its threshold applies to categories without their own
+    pub overall: CategoryTuning,
+    /// Categories with labels, by id
+    pub categories: Vec<CategoryTuning>,
+}
+
+impl TuningReport {
+    pub fn fit(labels: &[ConfidenceLabel], policy: &TuningPolicy) -> Self {
+        let mut by_category: BTreeMap<&str, Vec<&ConfidenceLabel>> = BTreeMap::new();
+        for label in labels {
+            by_category.entry(label.category.as_str()).or_default().push(label);
+        }
+        let all: Vec<&ConfidenceLabel> = labels.iter().collect();
+        Self {
+            fitted_at: Utc::now(),
+            policy: policy.clone(),
+            overall: CategoryTuning::fit(OVERALL, &all, policy),
+            categories: by_category
+                .into_iter()
+                .map(|(category, labels)| CategoryTuning::fit(category, &labels, policy))
+                .collect(),
+        }
+    }
+
+    /// Minimum confidence for principles of `category`: its own threshold, else that of the
+    /// nearest category it refines that has one, else the overall threshold
+    pub fn threshold_for(&self, category: &PrincipleCategory) -> Option<f32> {
+        let own = |id: &str| self.categories.iter().find(|tuning| tuning.category == id).and_then(|tuning| tuning.threshold);
+        std::iter::once(category.name())
+            .chain(categories::registry().ancestors(category.name()))
+            .find_map(own)
+            .or(self.overall.threshold)
+    }
+
+    fn has_thresholds(&self) -> bool {
+        self.overall.threshold.is_some() || self.categories.iter().any(|tuning| tuning.threshold.is_some())
+    }
+
+    /// `result` without the principles below their category's threshold
+    pub fn apply(&self, mut result: AnalysisResult) -> AnalysisResult {
+        if !self.has_thresholds() {
+            return result;
+        }
+        self.filter(Arc::make_mut(&mut result.tree));
+        result.recounted()
+    }
+
+    fn filter(&self, node: &mut AnalysisNode) {
+        node.principles
+            .retain(|principle| self.threshold_for(&principle.category).is_none_or(|threshold| principle.confidence >= threshold));
+        for child in &mut node.children {
+            self.filter(child);
+        }
+    }
+}
+
+/// Refits the confidence thresholds from the feedback log and holds the latest fit, which
+/// analyses apply unless they set `min_confidence` themselves
+#[derive(Debug)]
+pub struct ThresholdTuner {
+    feedback: Arc<FeedbackLog>,
+    policy: TuningPolicy,
+    report: ArcSwap<TuningReport>,
+}
+
+impl ThresholdTuner {
+    pub fn new(feedback: Arc<FeedbackLog>, policy: TuningPolicy) -> Self {
+        let report = ArcSwap::from_pointee(TuningReport::fit(&[], &policy));
+        Self { feedback, policy, report }
+    }
+
+    pub fn policy(&self) -> &TuningPolicy {
+        &self.policy
+    }
+
+    /// The latest fit; without one, a report with no thresholds
+    pub fn report(&self) -> Arc<TuningReport> {
+        self.report.load_full()
+    }
+
+    /// Fit the thresholds to the feedback recorded so far, applying them to analyses from now on
+    pub fn refit(&self) -> io::Result<Arc<TuningReport>> {
+        let labels = ConfidenceLabel::from_feedback(&self.feedback.entries()?);
+        let report = Arc::new(TuningReport::fit(&labels, &self.policy));
+        self.report.store(Arc::clone(&report));
+        Ok(report)
+    }
+}
+
+/// Refit the tuner's thresholds every `WIKI_ENGINE_TUNING_INTERVAL_SECS`
+pub async fn start_tuning_task(tuner: Arc<ThresholdTuner>) {
+    let mut interval = tokio::time::interval(tuner.policy().interval());
+    loop {
+        interval.tick().await;
+        match tuner.refit() {
+            Ok(report) => tracing::debug!(
+                "Refitted confidence thresholds from {} labels, overall threshold {:?}",
+                report.overall.labels,
+                report.overall.threshold
+            ),
+            Err(error) => tracing::warn!("Failed to refit confidence thresholds: {}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(category: &str, judged: &[(f32, bool)]) -> Vec<ConfidenceLabel> {
+        judged
+            .iter()
+            .map(|&(confidence, correct)| ConfidenceLabel { category: category.to_string(), confidence, correct })
+            .collect()
+    }
+
+    #[test]
+    fn thresholds_reach_the_target_precision() {
+        let policy = TuningPolicy { target_precision: 0.75, min_labels: 4, ..Default::default() };
+        let mut judged = labels("Mechanical", &[(0.9, true), (0.8, true), (0.7, false), (0.7, true), (0.5, false), (0.4, false)]);
+        judged.extend(labels("Electrical", &[(0.6, true), (0.3, false)]));
+        let report = TuningReport::fit(&judged, &policy);
+
+        let mechanical = report.categories.iter().find(|tuning| tuning.category == "Mechanical").unwrap();
+        assert_eq!(mechanical.curve.len(), 5);
+        assert_eq!(mechanical.curve[2], PrecisionPoint { threshold: 0.7, precision: 0.75, recall: 1.0, kept: 4 });
+        assert_eq!(mechanical.threshold, Some(0.7));
+        assert_eq!(report.threshold_for(&PrincipleCategory::new("Mechanical")), Some(0.7));
+
+        // Too few labels of its own, so the pooled threshold applies
+        let electrical = report.categories.iter().find(|tuning| tuning.category == "Electrical").unwrap();
+        assert_eq!(electrical.threshold, None);
+        assert_eq!(report.overall.threshold, Some(0.6));
+        assert_eq!(report.threshold_for(&PrincipleCategory::new("Electrical")), Some(0.6));
+
+        let unlabeled = TuningReport::fit(&[], &policy);
+        assert_eq!(unlabeled.threshold_for(&PrincipleCategory::new("Mechanical")), None);
+        assert_eq!((unlabeled.overall.precision, unlabeled.overall.recall), (0.0, 0.0));
+    }
+}
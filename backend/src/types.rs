@@ -1,14 +1,158 @@
+use crate::categories::{self, CategoryDefinition};
+use crate::dedup::NearDuplicateIndex;
+use crate::intern::Term;
+use crate::templates::DescriptionTemplates;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::sync::Arc;
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 pub struct SearchRequest {
     pub term: String,
     pub max_depth: Option<u8>,
     pub max_results: Option<u8>,
+    /// Only return principles in these categories, and only recurse into related concepts that fit them
+    #[serde(default)]
+    pub categories: Option<Vec<PrincipleCategory>>,
+    /// Drop principles below this confidence (0.0-1.0) and prune children left without any
+    #[serde(default)]
+    pub min_confidence: Option<f32>,
+    /// Maximum principles kept per node (defaults to 8, capped server-side)
+    #[serde(default)]
+    pub max_principles_per_node: Option<u8>,
+    /// Drop subtrees whose cumulative principle confidence is below this value
+    #[serde(default)]
+    pub prune_below: Option<f32>,
+    /// Related concepts that are never expanded (case-insensitive)
+    #[serde(default)]
+    pub exclude_terms: Vec<String>,
+    /// Related concepts that are always expanded when encountered, regardless of filters or `max_results`
+    #[serde(default)]
+    pub pin_terms: Vec<String>,
+    /// Where child concepts are drawn from (defaults to combining every source)
+    #[serde(default)]
+    pub concept_source: ConceptSource,
+    /// Where each term's text is fetched from (defaults to Wikipedia)
+    #[serde(default)]
+    pub document_source: DocumentSourceKind,
+    /// Deduplicate principles within each node (default) or across the whole tree
+    #[serde(default)]
+    pub dedupe_scope: DedupeScope,
+    /// Stop expanding the tree after this many milliseconds and return what was analyzed so far
+    /// (defaults to, and is capped at, the server's analysis timeout)
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Language (e.g. `de`) of generated principle titles, descriptions and category names;
+    /// the server uses the `Accept-Language` header when unset, and English is the fallback
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+    /// Named bundle of depth, breadth, budget, source and confidence settings (`quick`,
+    /// `standard`, `deep` or one the server configures) for the settings the request leaves unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// How widely principles are deduplicated
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DedupeScope {
+    /// Each node keeps its own principles, even if another node has the same ones
+    #[default]
+    Node,
+    /// Each principle is kept only at its shallowest node, listing every node it was found under
+    /// in `appears_in`
+    Tree,
+}
+
+/// Where the text for a term comes from
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentSourceKind {
+    /// The configured MediaWiki site
+    #[default]
+    Wikipedia,
+    /// Terms that are http(s) URLs, fetched and stripped to their paragraph text
+    Url,
+    /// Markdown/plain-text files in the configured documents folder
+    LocalFolder,
+    /// The abstract of the best-matching arXiv paper
+    Arxiv,
+    /// The abstract of the best-matching US patent (needs a PatentsView API key)
+    Patent,
+}
+
+impl DocumentSourceKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DocumentSourceKind::Wikipedia => "wikipedia",
+            DocumentSourceKind::Url => "url",
+            DocumentSourceKind::LocalFolder => "local_folder",
+            DocumentSourceKind::Arxiv => "arxiv",
+            DocumentSourceKind::Patent => "patent",
+        }
+    }
+}
+
+/// Source of the related concepts a node is expanded into
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConceptSource {
+    /// Capitalized phrases and engineering terms matched in the page extract
+    ExtractRegex,
+    /// Every article the page links to
+    PageLinks,
+    /// Links from the "See also" section and infobox
+    SeeAlso,
+    /// The page's (non-hidden) categories
+    Categories,
+    /// Components from the built-in knowledge base
+    KnowledgeBase,
+    /// The "See also" and infobox links, then the phrases matched in the extract that aren't
+    /// among them, in that order
+    #[default]
+    SeeAlsoThenExtract,
+    /// All of the above, deduplicated and ranked by per-source weight; costs a page links and a
+    /// categories request per expanded node, so it is only used when asked for
+    Combined,
+}
+
+impl ConceptSource {
+    /// Sources merged by `Combined`, in order of precedence
+    pub const COMBINED: [ConceptSource; 5] = [
+        ConceptSource::SeeAlso,
+        ConceptSource::KnowledgeBase,
+        ConceptSource::ExtractRegex,
+        ConceptSource::PageLinks,
+        ConceptSource::Categories,
+    ];
+
+    /// How much a concept found by this source counts towards its rank when combining
+    pub fn weight(&self) -> f32 {
+        match self {
+            ConceptSource::SeeAlso => 1.0,
+            ConceptSource::KnowledgeBase => 0.9,
+            ConceptSource::ExtractRegex => 0.6,
+            ConceptSource::PageLinks => 0.3,
+            ConceptSource::Categories => 0.2,
+            ConceptSource::SeeAlsoThenExtract | ConceptSource::Combined => 0.0,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConceptSource::ExtractRegex => "extract_regex",
+            ConceptSource::PageLinks => "page_links",
+            ConceptSource::SeeAlso => "see_also",
+            ConceptSource::Categories => "categories",
+            ConceptSource::KnowledgeBase => "knowledge_base",
+            ConceptSource::SeeAlsoThenExtract => "see_also_then_extract",
+            ConceptSource::Combined => "combined",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct EngineeringPrinciple {
     pub id: String,
     pub title: String,
@@ -16,47 +160,761 @@ pub struct EngineeringPrinciple {
     pub category: PrincipleCategory,
     pub confidence: f32,
     pub source_url: String,
-    pub related_terms: Vec<String>,
+    #[schema(value_type = Vec<String>)]
+    pub related_terms: Vec<Term>,
+    /// Byte range of the passage in the source text the principle came from; empty (`(0, 0)`)
+    /// when it isn't tied to a passage, e.g. knowledge-base components the text never mentions
+    #[serde(default)]
+    pub source_span: (usize, usize),
+    /// Heading of the section containing `source_span`; `None` for the lead section
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_section: Option<String>,
+    /// Terms of every node the principle was found under; only filled in with `dedupe_scope: tree`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schema(value_type = Vec<String>)]
+    pub appears_in: Vec<Term>,
+    /// What decided the category of a principle drawn from a sentence; `None` when the
+    /// category came with it, e.g. from the knowledge base
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classified_by: Option<CategoryClassifier>,
+    /// The named law or equation the principle is an instance of (see `crate::laws`), if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub named_law: Option<String>,
+    /// Component the principle was generated for, whose title and description come from the
+    /// description templates; `None` for principles drawn from a sentence
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub component: Option<String>,
+}
+
+impl EngineeringPrinciple {
+    /// SimHash of the title and description, for near-duplicate detection (see `crate::dedup`)
+    pub fn signature(&self) -> u64 {
+        crate::dedup::simhash(&format!("{} {}", self.title, self.description))
+    }
+
+    /// `signature` with the acronyms of the source page spelled out, so "The ESC ..." and
+    /// "The electronic speed controller ..." count as the same principle
+    pub fn signature_expanding(&self, acronyms: &std::collections::HashMap<String, String>) -> u64 {
+        let text = format!("{} {}", self.title, self.description);
+        crate::dedup::simhash(&crate::text::expand_acronyms(&text, acronyms))
+    }
+
+    /// Whether both principles were drawn from overlapping passages of the same source
+    pub fn shares_passage(&self, other: &EngineeringPrinciple) -> bool {
+        let (start, end) = self.source_span;
+        let (other_start, other_end) = other.source_span;
+        start < end && other_start < other_end && self.source_url == other.source_url
+            && start < other_end && other_start < end
+    }
+}
+
+/// How a sentence's principle category was chosen
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CategoryClassifier {
+    /// The category whose keyword patterns matched the sentence most often
+    Patterns,
+    /// The category whose prototype embedding is closest to the sentence's
+    Embeddings,
+}
+
+/// Id of a category in the category registry (see `crate::categories`). Registered categories
+/// serialize as their id, e.g. `"Mechanical"`; others, such as the fallback for sentences no
+/// category matches, as `{"Other": "General"}`, which is how earlier versions spelled them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, ToSchema)]
+#[schema(value_type = String)]
+pub struct PrincipleCategory(Term);
+
+impl PrincipleCategory {
+    /// Category given to principles no registered category matches
+    pub const GENERAL: &'static str = "General";
+
+    pub fn new(id: &str) -> Self {
+        Self(Term::new(id))
+    }
+
+    pub fn general() -> Self {
+        Self::new(Self::GENERAL)
+    }
+
+    /// The category's id, which is also its name in category summaries and template keys
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_registered(&self) -> bool {
+        categories::registry().get(&self.0).is_some()
+    }
+
+    /// The registry entry describing the category, if it is registered
+    pub fn definition(&self) -> Option<&'static CategoryDefinition> {
+        categories::registry().get(&self.0)
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
-pub enum PrincipleCategory {
-    Structural,
-    Mechanical,
-    Electrical,
-    Thermal,
-    Chemical,
-    Material,
-    System,
-    Process,
-    Design,
-    Other(String),
+impl Serialize for PrincipleCategory {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        if self.is_registered() {
+            serializer.serialize_str(&self.0)
+        } else {
+            serializer.serialize_newtype_variant("PrincipleCategory", 0, "Other", self.0.as_str())
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl<'de> Deserialize<'de> for PrincipleCategory {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        enum Unregistered {
+            Other(String),
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Id(String),
+            Unregistered(Unregistered),
+        }
+
+        Ok(match Repr::deserialize(deserializer)? {
+            Repr::Id(id) => Self::new(&id),
+            Repr::Unregistered(Unregistered::Other(name)) => Self::new(&name),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AnalysisNode {
-    pub term: String,
+    #[schema(value_type = String)]
+    pub term: Term,
     pub principles: Vec<EngineeringPrinciple>,
-    pub children: HashMap<String, Box<AnalysisNode>>,
+    /// Child nodes, ordered by descending importance (see `AnalysisNode::importance`)
+    #[schema(no_recursion)]
+    pub children: Vec<AnalysisNode>,
     pub depth: u8,
     pub processing_time_ms: u64,
+    /// Lead image of the node's Wikipedia article
+    #[serde(default)]
+    pub thumbnail_url: Option<String>,
+    /// 0.0-1.0 score from the article's recent pageviews (see `popularity_score`)
+    #[serde(default)]
+    pub popularity: Option<f32>,
+    /// Problems encountered while expanding this node (e.g. children that failed to analyze)
+    #[serde(default)]
+    pub warnings: Vec<AnalysisWarning>,
+    /// A few sentences of the node's text, for display without fetching the article
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// 0.0-1.0 estimate of how advanced the topic is (see `EngineeringAnalyzer::complexity_score`);
+    /// `None` when no text was found for the term
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub complexity: Option<f32>,
+    /// ISO 639-1 code of the language the node's text appears to be in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// ASCII transliteration of `term`, when it isn't ASCII already
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub romanized_term: Option<String>,
+    /// Title of the matching English Wikipedia article, for pages from other-language wikis
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub english_term: Option<String>,
+    /// Terms defined in the node's text; surfaced, deduplicated, through `AnalysisResult::glossary`
+    #[serde(skip)]
+    pub definitions: Vec<GlossaryEntry>,
+    /// How the node was reached from its parent; `None` for the root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<NodeProvenance>,
+    /// The page text was longer than the engine's extract limit and only its start was analyzed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated_source: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A term defined somewhere in the analyzed text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct GlossaryEntry {
+    pub term: String,
+    /// The sentence or parenthetical giving the definition
+    pub definition: String,
+    /// Title of the page the definition was found on
+    pub source_title: String,
+    pub source_url: String,
+}
+
+/// Why a node is in the tree, so a reviewer can audit each expansion
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NodeProvenance {
+    #[schema(value_type = String)]
+    pub parent_term: Term,
+    /// Concept sources that proposed the term under its parent, strongest first
+    pub sources: Vec<ConceptSource>,
+    /// Expanded because of `pin_terms`, bypassing the filters and `max_results`
+    #[serde(default)]
+    pub pinned: bool,
+    /// Sentence of the parent's text that mentions the term, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sentence: Option<String>,
+    /// Byte offsets of `sentence` within the parent's extract
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sentence_span: Option<(usize, usize)>,
+}
+
+impl AnalysisNode {
+    /// A node for `term` with no principles, children or page details, as for a term that
+    /// wasn't expanded
+    pub fn leaf(term: &str, depth: u8) -> Self {
+        Self {
+            term: Term::new(term),
+            principles: vec![],
+            children: vec![],
+            depth,
+            processing_time_ms: 0,
+            thumbnail_url: None,
+            popularity: None,
+            warnings: vec![],
+            summary: None,
+            complexity: None,
+            language: None,
+            romanized_term: None,
+            english_term: None,
+            definitions: vec![],
+            provenance: None,
+            truncated_source: false,
+        }
+    }
+
+    /// Importance of a node: the summed confidence of its principles
+    pub fn importance(&self) -> f32 {
+        self.principles.iter().map(|p| p.confidence).sum()
+    }
+
+    /// Sort children by descending importance, breaking ties by popularity and then by
+    /// term so the order (and therefore the serialized output) is deterministic
+    pub fn sort_children(&mut self) {
+        self.children.sort_by(|a, b| {
+            b.importance()
+                .partial_cmp(&a.importance())
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| {
+                    b.popularity
+                        .unwrap_or(0.0)
+                        .partial_cmp(&a.popularity.unwrap_or(0.0))
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .then_with(|| a.term.cmp(&b.term))
+        });
+    }
+
+    /// Importance of the node plus everything below it
+    pub fn cumulative_importance(&self) -> f32 {
+        self.importance() + self.children.iter().map(AnalysisNode::cumulative_importance).sum::<f32>()
+    }
+
+    /// Remove descendant subtrees whose cumulative importance is below `threshold`
+    pub fn prune_below(&mut self, threshold: f32) {
+        self.children.retain(|child| child.cumulative_importance() >= threshold);
+        for child in &mut self.children {
+            child.prune_below(threshold);
+        }
+    }
+
+    /// Keep each principle only at its shallowest occurrence in the tree, recording in
+    /// `appears_in` every node it was found under
+    pub fn consolidate_principles(&mut self) {
+        let mut index = NearDuplicateIndex::new();
+        // Where each kept principle sits (child indices from the root, position) and its terms
+        let mut kept: Vec<(Vec<usize>, usize, Vec<Term>)> = Vec::new();
+
+        // Breadth-first, so the shallowest copy is the one kept
+        let mut queue = VecDeque::from([Vec::new()]);
+        while let Some(path) = queue.pop_front() {
+            let node = self.descendant_mut(&path);
+            let term = node.term.clone();
+            let mut position = 0;
+            node.principles.retain(|principle| {
+                let terms = if principle.appears_in.is_empty() {
+                    vec![term.clone()]
+                } else {
+                    principle.appears_in.clone()
+                };
+                let signature = principle.signature();
+                if let Some(id) = index.find_near(signature) {
+                    let appears_in = &mut kept[id].2;
+                    for term in terms {
+                        if !appears_in.contains(&term) {
+                            appears_in.push(term);
+                        }
+                    }
+                    return false;
+                }
+                index.insert(signature, kept.len());
+                kept.push((path.clone(), position, terms));
+                position += 1;
+                true
+            });
+            queue.extend((0..node.children.len()).map(|i| [path.as_slice(), &[i]].concat()));
+        }
+
+        for (path, position, appears_in) in kept {
+            self.descendant_mut(&path).principles[position].appears_in = appears_in;
+        }
+    }
+
+    fn descendant_mut(&mut self, path: &[usize]) -> &mut AnalysisNode {
+        path.iter().fold(self, |node, &i| &mut node.children[i])
+    }
+
+    pub fn total_principles(&self) -> u32 {
+        self.principles.len() as u32 + self.children.iter().map(AnalysisNode::total_principles).sum::<u32>()
+    }
+
+    pub fn node_count(&self) -> u32 {
+        1 + self.children.iter().map(AnalysisNode::node_count).sum::<u32>()
+    }
+
+    pub fn max_depth_reached(&self) -> u8 {
+        self.children
+            .iter()
+            .map(AnalysisNode::max_depth_reached)
+            .fold(self.depth, u8::max)
+    }
+
+    /// Principle count and confidence per category across this node and its descendants,
+    /// keyed by category name
+    pub fn category_summary(&self) -> BTreeMap<String, CategoryStats> {
+        let mut totals: BTreeMap<String, (u32, f32)> = BTreeMap::new();
+        self.add_category_totals(&mut totals);
+
+        let total: u32 = totals.values().map(|(count, _)| count).sum();
+        let mut summary: BTreeMap<String, CategoryStats> = totals
+            .into_iter()
+            .map(|(name, (count, confidence))| {
+                let stats = CategoryStats {
+                    count,
+                    share: count as f32 / total as f32,
+                    average_confidence: confidence / count as f32,
+                    rollup_count: count,
+                };
+                (name, stats)
+            })
+            .collect();
+
+        let registry = categories::registry();
+        let direct: Vec<(String, u32)> = summary.iter().map(|(name, stats)| (name.clone(), stats.count)).collect();
+        for (name, count) in direct {
+            for ancestor in registry.ancestors(&name) {
+                summary.entry(ancestor.to_string()).or_default().rollup_count += count;
+            }
+        }
+        summary
+    }
+
+    fn add_category_totals(&self, totals: &mut BTreeMap<String, (u32, f32)>) {
+        for principle in &self.principles {
+            let entry = totals.entry(principle.category.name().to_string()).or_default();
+            entry.0 += 1;
+            entry.1 += principle.confidence;
+        }
+        for child in &self.children {
+            child.add_category_totals(totals);
+        }
+    }
+
+    /// Re-render the titles and descriptions of generated principles here and in every descendant
+    fn localize(&mut self, templates: &DescriptionTemplates) {
+        for principle in &mut self.principles {
+            if let Some(component) = &principle.component {
+                principle.title = templates.title(component, &principle.category);
+                principle.description = templates.description(component, &principle.category);
+            }
+        }
+        for child in &mut self.children {
+            child.localize(templates);
+        }
+    }
+
+    /// Principles of this node and its descendants grouped by the named law they fall under,
+    /// keyed by law name; principles without one are left out
+    pub fn principles_by_law(&self) -> BTreeMap<&str, Vec<&EngineeringPrinciple>> {
+        let mut groups: BTreeMap<&str, Vec<&EngineeringPrinciple>> = BTreeMap::new();
+        let mut queue = VecDeque::from([self]);
+        while let Some(node) = queue.pop_front() {
+            for principle in &node.principles {
+                if let Some(law) = &principle.named_law {
+                    groups.entry(law.as_str()).or_default().push(principle);
+                }
+            }
+            queue.extend(&node.children);
+        }
+        groups
+    }
+
+    /// Definitions from this node and its descendants, one per term (the shallowest wins),
+    /// sorted by term
+    pub fn glossary(&self) -> Vec<GlossaryEntry> {
+        let mut glossary: Vec<GlossaryEntry> = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([self]);
+        while let Some(node) = queue.pop_front() {
+            for entry in &node.definitions {
+                if seen.insert(entry.term.to_lowercase()) {
+                    glossary.push(entry.clone());
+                }
+            }
+            queue.extend(&node.children);
+        }
+        glossary.sort_by_key(|entry| entry.term.to_lowercase());
+        glossary
+    }
+
+    /// Warnings from this node and all of its descendants
+    pub fn all_warnings(&self) -> Vec<AnalysisWarning> {
+        let mut warnings = self.warnings.clone();
+        for child in &self.children {
+            warnings.extend(child.all_warnings());
+        }
+        warnings
+    }
+
+    /// Zero the timings, put every collection in a total order and renumber principle ids from
+    /// `ids`, depth-first in tree order
+    fn normalize(&mut self, ids: &mut SeededIds) {
+        self.processing_time_ms = 0;
+        self.principles.sort_by(|a, b| {
+            b.confidence
+                .total_cmp(&a.confidence)
+                .then_with(|| a.title.cmp(&b.title))
+                .then_with(|| a.description.cmp(&b.description))
+                .then_with(|| a.source_span.cmp(&b.source_span))
+        });
+        for principle in &mut self.principles {
+            principle.id = ids.next_id();
+        }
+        self.sort_children();
+        for child in &mut self.children {
+            child.normalize(ids);
+        }
+    }
+}
+
+/// UUID v4-shaped ids drawn from a SplitMix64 sequence, so a seed always yields the same ids
+struct SeededIds(u64);
+
+impl SeededIds {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn next_id(&mut self) -> String {
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&self.next_u64().to_le_bytes());
+        bytes[8..].copy_from_slice(&self.next_u64().to_le_bytes());
+        uuid::Builder::from_random_bytes(bytes).into_uuid().to_string()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AnalysisResult {
     pub root_term: String,
-    pub tree: AnalysisNode,
+    /// Shared with the analysis cache, so it is only copied when a result option rewrites it
+    pub tree: Arc<AnalysisNode>,
     pub total_processing_time_ms: u64,
     pub total_principles: u32,
     pub max_depth_reached: u8,
+    /// All warnings raised anywhere in the tree
+    #[serde(default)]
+    pub warnings: Vec<AnalysisWarning>,
+    /// Id under which the result is stored (see `/results/{id}`), if it was stored
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result_id: Option<String>,
+    /// Version of the knowledge base the tree was decomposed with; pinning the server to it
+    /// (`WIKI_ENGINE_KB_VERSION`) reproduces the analysis
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kb_version: Option<String>,
+    /// Wikipedia traffic this analysis caused; all zero when it was served from cache
+    #[serde(default)]
+    pub network_stats: NetworkStats,
+    /// The deadline passed before the tree was fully expanded; `tree` holds the partial result
+    #[serde(default)]
+    pub deadline_exceeded: bool,
+    /// Principles per category across the whole tree, keyed by category name
+    #[serde(default)]
+    pub category_summary: BTreeMap<String, CategoryStats>,
+    /// Terms defined in any of the tree's pages, one entry per term
+    #[serde(default)]
+    pub glossary: Vec<GlossaryEntry>,
+}
+
+/// How much of a tree's principles fall in one category
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CategoryStats {
+    pub count: u32,
+    /// Fraction (0.0-1.0) of all the tree's principles in this category
+    pub share: f32,
+    pub average_confidence: f32,
+    /// Principles in this category or any of its subcategories; parent categories appear in the
+    /// summary with a `count` of 0 when only their subcategories have principles
+    #[serde(default)]
+    pub rollup_count: u32,
+}
+
+/// What an analysis would cost, estimated without fetching anything
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct AnalysisPlan {
+    pub term: String,
+    /// Nodes the tree would have
+    pub estimated_nodes: u32,
+    /// Nodes found by walking cached pages and the knowledge base; the rest are extrapolated
+    /// from the average branching factor of those
+    pub known_nodes: u32,
+    /// Nodes whose page is already cached
+    pub cached_pages: u32,
+    pub estimated_wikipedia_calls: u32,
+    pub estimated_seconds: f32,
+    /// The whole tree is cached, so the analysis would return straight away
+    pub cached_result: bool,
+    /// The estimate is longer than the request's timeout, so the tree would likely be cut short
+    pub exceeds_timeout: bool,
+}
+
+/// Wikipedia API traffic, either for one analysis or for the whole process
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct NetworkStats {
+    pub requests: u64,
+    /// Response body bytes received
+    pub bytes: u64,
+    /// Failed requests: network errors, HTTP errors, rate limiting and MediaWiki API errors
+    pub errors: u64,
+}
+
+impl AnalysisResult {
+    /// Build a result from a finished tree, deriving the summary fields from it
+    pub fn from_tree(root_term: String, tree: impl Into<Arc<AnalysisNode>>, total_processing_time_ms: u64) -> Self {
+        let tree = tree.into();
+        Self {
+            root_term,
+            total_principles: tree.total_principles(),
+            category_summary: tree.category_summary(),
+            glossary: tree.glossary(),
+            max_depth_reached: tree.max_depth_reached(),
+            warnings: tree.all_warnings(),
+            tree,
+            total_processing_time_ms,
+            result_id: None,
+            kb_version: None,
+            network_stats: NetworkStats::default(),
+            deadline_exceeded: false,
+        }
+    }
+
+    /// The result with each principle kept only once across the tree (see `AnalysisNode::consolidate_principles`)
+    pub fn consolidated(mut self) -> Self {
+        Arc::make_mut(&mut self.tree).consolidate_principles();
+        self.recounted()
+    }
+
+    /// The result with its totals, summaries and warnings recomputed from `tree`, after the
+    /// tree was edited
+    pub fn recounted(self) -> Self {
+        Self {
+            result_id: self.result_id,
+            kb_version: self.kb_version,
+            network_stats: self.network_stats,
+            deadline_exceeded: self.deadline_exceeded,
+            ..Self::from_tree(self.root_term, self.tree, self.total_processing_time_ms)
+        }
+    }
+
+    /// Make the result a function of its inputs alone: timings are zeroed, collections sorted
+    /// and principle ids drawn from `seed`, so the same pages always serialize byte for byte
+    /// the same (see `WikiEngineBuilder::deterministic`)
+    pub fn make_deterministic(&mut self, seed: u64) {
+        Arc::make_mut(&mut self.tree).normalize(&mut SeededIds(seed));
+        self.total_processing_time_ms = 0;
+        self.warnings = self.tree.all_warnings();
+    }
+
+    /// Render generated principle titles and descriptions, and the category summary's names, in
+    /// the locale of `templates`
+    pub fn localize(&mut self, templates: &DescriptionTemplates) {
+        Arc::make_mut(&mut self.tree).localize(templates);
+        self.category_summary = std::mem::take(&mut self.category_summary)
+            .into_iter()
+            .map(|(name, stats)| (templates.category_label(&name), stats))
+            .collect();
+    }
+
+    /// Copy of the result with low-importance subtrees removed (see `AnalysisNode::prune_below`)
+    pub fn pruned(&self, threshold: f32) -> Self {
+        let mut tree = AnalysisNode::clone(&self.tree);
+        tree.prune_below(threshold);
+        Self {
+            result_id: self.result_id.clone(),
+            kb_version: self.kb_version.clone(),
+            network_stats: self.network_stats.clone(),
+            deadline_exceeded: self.deadline_exceeded,
+            ..Self::from_tree(self.root_term.clone(), tree, self.total_processing_time_ms)
+        }
+    }
+}
+
+/// A non-fatal problem encountered during analysis of a single term
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AnalysisWarning {
+    pub term: String,
+    pub depth: u8,
+    pub kind: AnalysisWarningKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalysisWarningKind {
+    PageNotFound,
+    WikipediaApi,
+    Network,
+    Analysis,
+    Serialization,
+    RateLimited,
+    ReplicationLag,
+    InvalidTitle,
+    Overloaded,
+    Disambiguation,
+    Timeout,
+    Cancelled,
+    ModelUnavailable,
+    InvalidRequest,
+    ResultNotFound,
+    /// Expanding the branch panicked; the rest of the tree was still analyzed
+    Panicked,
+}
+
+impl From<&WikiEngineError> for AnalysisWarningKind {
+    fn from(error: &WikiEngineError) -> Self {
+        match error {
+            WikiEngineError::WikipediaApi(_) => AnalysisWarningKind::WikipediaApi,
+            WikiEngineError::Analysis(_) => AnalysisWarningKind::Analysis,
+            WikiEngineError::Network(_) => AnalysisWarningKind::Network,
+            WikiEngineError::Serialization(_) => AnalysisWarningKind::Serialization,
+            WikiEngineError::RateLimited { .. } => AnalysisWarningKind::RateLimited,
+            WikiEngineError::ReplicationLag { .. } => AnalysisWarningKind::ReplicationLag,
+            WikiEngineError::InvalidTitle(_) => AnalysisWarningKind::InvalidTitle,
+            WikiEngineError::Overloaded { .. } => AnalysisWarningKind::Overloaded,
+            WikiEngineError::PageNotFound(_) => AnalysisWarningKind::PageNotFound,
+            WikiEngineError::Disambiguation(_) => AnalysisWarningKind::Disambiguation,
+            WikiEngineError::Timeout => AnalysisWarningKind::Timeout,
+            WikiEngineError::Cancelled => AnalysisWarningKind::Cancelled,
+            WikiEngineError::ModelUnavailable(_) => AnalysisWarningKind::ModelUnavailable,
+            WikiEngineError::InvalidRequest(_) => AnalysisWarningKind::InvalidRequest,
+            WikiEngineError::ResultNotFound(_) => AnalysisWarningKind::ResultNotFound,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchAnalyzeRequest {
+    pub terms: Vec<String>,
+    pub max_depth: Option<u8>,
+    pub max_results: Option<u8>,
+    /// URL to POST a signed `WebhookEvent` to once every term is analyzed; `/analyze/batch`
+    /// then answers 202 right away instead of waiting for the results
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+}
+
+/// Answer to a batch whose results will be sent to its webhook
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchAccepted {
+    /// Id the webhook event will carry
+    pub id: String,
+}
+
+/// Outcome of a single term within a batch analysis
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchAnalysisItem {
+    pub term: String,
+    pub result: Option<AnalysisResult>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SearchSuggestion {
+    pub term: String,
+    pub confidence: f32,
+    pub category: String,
+    pub matched_via: SuggestionMatch,
+}
+
+/// How a suggestion was matched against the query
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionMatch {
+    /// The title starts with (or equals) the query
+    Exact,
+    /// Typo-tolerant match against known titles or Wikipedia's fuzzy search
+    Fuzzy,
+    /// Returned by Wikipedia's title search
+    Search,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct WikipediaPage {
     pub title: String,
     pub extract: String,
     pub url: String,
     pub page_id: u64,
+    /// Latest revision id at fetch time, used to detect edits since
+    #[serde(default)]
+    pub revision_id: Option<u64>,
+    #[serde(default = "chrono::Utc::now")]
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+    /// Lead image thumbnail, when the article has one
+    #[serde(default)]
+    pub thumbnail_url: Option<String>,
+    /// Views over the last 30 days, if the pageviews API answered
+    #[serde(default)]
+    pub monthly_views: Option<u64>,
+    /// Article titles linked from the "See also" section and the infobox
+    #[serde(default)]
+    pub curated_links: Vec<String>,
+    /// HTTP validators from the fetch, sent back when revalidating the page
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    /// Which document source the text came from
+    #[serde(default)]
+    pub source: DocumentSourceKind,
+}
+
+impl WikipediaPage {
+    pub fn has_validators(&self) -> bool {
+        self.etag.is_some() || self.last_modified.is_some()
+    }
+}
+
+/// Map monthly pageviews onto 0.0-1.0 on a log scale (10M+ views a month scores 1.0)
+pub fn popularity_score(monthly_views: u64) -> f32 {
+    ((monthly_views as f64 + 1.0).log10() / 7.0).min(1.0) as f32
+}
+
+/// A single rejected field in an incoming request
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -66,9 +924,226 @@ pub enum WikiEngineError {
     #[error("Analysis error: {0}")]
     Analysis(String),
     #[error("Network error: {0}")]
-    Network(#[from] reqwest::Error),
+    Network(String),
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+    #[error("Rate limited by Wikipedia{}", retry_after.map(|secs| format!(", retry after {}s", secs)).unwrap_or_default())]
+    RateLimited { retry_after: Option<u64> },
+    #[error("Wikipedia replicas are lagged{}", retry_after.map(|secs| format!(", retry after {}s", secs)).unwrap_or_default())]
+    ReplicationLag { retry_after: Option<u64> },
+    #[error("Invalid page title: {0}")]
+    InvalidTitle(String),
+    #[error("Too many analyses in progress, try again later")]
+    Overloaded { retry_after: Option<u64> },
+    #[error("No Wikipedia page found for '{0}'")]
+    PageNotFound(String),
+    #[error("Ambiguous term, candidates: {}", .0.join(", "))]
+    Disambiguation(Vec<String>),
+    #[error("Request timed out")]
+    Timeout,
+    #[error("Analysis was cancelled")]
+    Cancelled,
+    #[error("Model unavailable: {0}")]
+    ModelUnavailable(String),
+    #[error("Invalid request: {}", .0.iter().map(|e| format!("{} {}", e.field, e.message)).collect::<Vec<_>>().join("; "))]
+    InvalidRequest(Vec<FieldError>),
+    #[error("No stored result with id '{0}'")]
+    ResultNotFound(String),
 }
 
-pub type Result<T> = std::result::Result<T, WikiEngineError>;
\ No newline at end of file
+#[cfg(feature = "network")]
+impl From<reqwest::Error> for WikiEngineError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            WikiEngineError::Timeout
+        } else {
+            WikiEngineError::Network(error.to_string())
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, WikiEngineError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn principle(title: &str) -> EngineeringPrinciple {
+        EngineeringPrinciple {
+            id: title.to_string(),
+            title: title.to_string(),
+            description: format!("{} governs how current flows through the windings of the machine", title),
+            category: PrincipleCategory::new("Electrical"),
+            confidence: 0.8,
+            source_url: String::new(),
+            related_terms: vec![],
+            source_span: (0, 0),
+            source_section: None,
+            appears_in: vec![],
+            classified_by: None,
+            named_law: None,
+            component: None,
+        }
+    }
+
+    fn node(term: &str, depth: u8, principles: Vec<EngineeringPrinciple>, children: Vec<AnalysisNode>) -> AnalysisNode {
+        AnalysisNode { principles, children, ..AnalysisNode::leaf(term, depth) }
+    }
+
+    #[test]
+    fn consolidation_keeps_the_shallowest_copy() {
+        let deep = node("Coil", 2, vec![principle("Ohm's law")], vec![]);
+        let stator = node("Stator", 1, vec![principle("Faraday's law")], vec![deep]);
+        let rotor = node("Rotor", 1, vec![principle("Ohm's law"), principle("Faraday's law")], vec![]);
+        let mut root = node("Electric motor", 0, vec![], vec![stator, rotor]);
+
+        root.consolidate_principles();
+
+        let stator = &root.children[0];
+        assert_eq!(stator.principles.len(), 1);
+        assert_eq!(stator.principles[0].appears_in, ["Stator", "Rotor"]);
+        assert!(stator.children[0].principles.is_empty());
+        let rotor = &root.children[1];
+        assert_eq!(rotor.principles.len(), 1);
+        assert_eq!(rotor.principles[0].appears_in, ["Rotor", "Coil"]);
+        assert_eq!(root.total_principles(), 2);
+        let summary = root.category_summary();
+        assert_eq!(summary["Electrical"], CategoryStats { count: 2, share: 1.0, average_confidence: 0.8, rollup_count: 2 });
+    }
+
+    #[test]
+    fn subcategory_counts_roll_up_to_their_parents() {
+        let in_category = |title: &str, category: &str| EngineeringPrinciple {
+            category: PrincipleCategory::new(category),
+            ..principle(title)
+        };
+        let root = node(
+            "Motor drive",
+            0,
+            vec![in_category("Inverter switching", "Power Electronics"), in_category("Current sensing", "Signal Processing")],
+            vec![node("Winding", 1, vec![in_category("Winding resistance", "Electrical")], vec![])],
+        );
+
+        let summary = root.category_summary();
+        assert_eq!(summary["Electrical"].count, 1);
+        assert_eq!(summary["Electrical"].rollup_count, 3);
+        assert_eq!(summary["Power Electronics"].rollup_count, 1);
+
+        let root = node("Converter", 0, vec![in_category("Inverter switching", "Power Electronics")], vec![]);
+        let summary = root.category_summary();
+        assert_eq!(summary["Electrical"], CategoryStats { rollup_count: 1, ..Default::default() });
+    }
+
+    #[test]
+    fn principles_group_by_named_law() {
+        let with_law = |title: &str, law: Option<&str>| EngineeringPrinciple {
+            named_law: law.map(str::to_string),
+            ..principle(title)
+        };
+        let coil = node("Coil", 2, vec![with_law("Winding resistance", Some("Ohm's law"))], vec![]);
+        let stator = node("Stator", 1, vec![with_law("Induced voltage", Some("Faraday's law of induction"))], vec![coil]);
+        let root = node("Electric motor", 0, vec![with_law("Terminal voltage", Some("Ohm's law")), with_law("Torque", None)], vec![stator]);
+
+        let groups = root.principles_by_law();
+        assert_eq!(groups.keys().copied().collect::<Vec<_>>(), ["Faraday's law of induction", "Ohm's law"]);
+        let ohm: Vec<&str> = groups["Ohm's law"].iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(ohm, ["Terminal voltage", "Winding resistance"]);
+    }
+
+    #[test]
+    fn categories_keep_their_enum_wire_format() {
+        let json = serde_json::to_value([PrincipleCategory::new("Mechanical"), PrincipleCategory::general()]).unwrap();
+        assert_eq!(json, serde_json::json!(["Mechanical", { "Other": "General" }]));
+        let restored: Vec<PrincipleCategory> = serde_json::from_value(json).unwrap();
+        assert_eq!(restored, [PrincipleCategory::new("Mechanical"), PrincipleCategory::general()]);
+    }
+
+    #[test]
+    fn localizing_rerenders_generated_principles() {
+        let generated = EngineeringPrinciple {
+            title: "Gearbox Mechanical Design".to_string(),
+            category: PrincipleCategory::new("Mechanical"),
+            component: Some("gearbox".to_string()),
+            ..principle("Gearbox")
+        };
+        let rotor = node("Rotor", 1, vec![generated], vec![]);
+        let root = node("Electric motor", 0, vec![principle("Ohm's law")], vec![rotor]);
+        let mut result = AnalysisResult::from_tree("Electric motor".to_string(), root, 0);
+
+        result.localize(&DescriptionTemplates::default().for_locale("de"));
+
+        let german = DescriptionTemplates::default().for_locale("de");
+        let localized = &result.tree.children[0].principles[0];
+        assert_eq!(localized.title, german.title("gearbox", &PrincipleCategory::new("Mechanical")));
+        assert_eq!(localized.description, german.description("gearbox", &PrincipleCategory::new("Mechanical")));
+        assert_eq!(result.tree.principles[0].title, "Ohm's law");
+        assert_eq!(result.category_summary.keys().collect::<Vec<_>>(), ["Elektrik", "Mechanik"]);
+    }
+
+    const TERMS: [&str; 6] = ["Electric motor", "Stator", "Rotor", "Coil", "Bearing", "Shaft"];
+    const LAWS: [&str; 5] = ["Ohm's law", "Faraday's law", "Lenz's law", "Hooke's law", "Joule heating"];
+
+    /// Trees of up to four levels whose nodes carry principles drawn from a small pool, so
+    /// the same principle often appears under several nodes
+    fn arbitrary_tree() -> impl Strategy<Value = AnalysisNode> {
+        let principles = prop::collection::vec((prop::sample::select(&LAWS[..]), 0.0f32..1.0), 0..4)
+            .prop_map(|laws| {
+                laws.into_iter()
+                    .map(|(law, confidence)| EngineeringPrinciple { confidence, ..principle(law) })
+                    .collect::<Vec<_>>()
+            });
+        let leaf = (prop::sample::select(&TERMS[..]), principles.clone())
+            .prop_map(|(term, principles)| node(term, 0, principles, vec![]));
+        let tree = leaf.prop_recursive(3, 24, 4, move |inner| {
+            (prop::sample::select(&TERMS[..]), principles.clone(), prop::collection::vec(inner, 0..4))
+                .prop_map(|(term, principles, children)| node(term, 0, principles, children))
+        });
+        tree.prop_map(|mut tree| {
+            set_depths(&mut tree, 0);
+            tree
+        })
+    }
+
+    fn set_depths(node: &mut AnalysisNode, depth: u8) {
+        node.depth = depth;
+        for child in &mut node.children {
+            set_depths(child, depth + 1);
+        }
+    }
+
+    /// Checks every node's depth is one more than its parent's; returns the number of
+    /// principles in the tree and the deepest depth found by walking it
+    fn walk(node: &AnalysisNode, depth: u8) -> (u32, u8) {
+        assert_eq!(node.depth, depth, "depth of '{}' doesn't match its nesting", node.term);
+        node.children.iter().map(|child| walk(child, depth + 1)).fold(
+            (node.principles.len() as u32, depth),
+            |(principles, deepest), (child_principles, child_deepest)| {
+                (principles + child_principles, deepest.max(child_deepest))
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn tree_summaries_match_the_tree(tree in arbitrary_tree(), threshold in 0.0f32..1.0) {
+            let pruned = AnalysisResult::from_tree("Electric motor".to_string(), tree.clone(), 0).pruned(threshold);
+            let consolidated = AnalysisResult::from_tree("Electric motor".to_string(), tree.clone(), 0).consolidated();
+
+            for result in [AnalysisResult::from_tree("Electric motor".to_string(), tree, 0), pruned, consolidated] {
+                let (principles, deepest) = walk(&result.tree, 0);
+                prop_assert_eq!(result.total_principles, principles);
+                prop_assert_eq!(result.max_depth_reached, deepest);
+                let categorized: u32 = result.category_summary.values().map(|stats| stats.count).sum();
+                prop_assert_eq!(categorized, principles);
+            }
+        }
+
+        #[test]
+        fn trees_survive_a_json_round_trip(tree in arbitrary_tree()) {
+            let json = serde_json::to_value(&tree).unwrap();
+            let restored: AnalysisNode = serde_json::from_value(json.clone()).unwrap();
+            prop_assert_eq!(serde_json::to_value(&restored).unwrap(), json);
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use crate::config::AnalysisLimits;
+use crate::feedback::Feedback;
+use crate::types::{BatchAnalyzeRequest, FieldError, PrincipleCategory, Result, SearchRequest, WikiEngineError};
+
+/// Longest principle title or id feedback may name
+const MAX_PRINCIPLE_LENGTH: usize = 300;
+
+/// Longest comment accepted with feedback
+const MAX_FEEDBACK_COMMENT_LENGTH: usize = 2000;
+
+/// Validate a search request against the configured limits, returning it with a trimmed term
+pub fn validate_search_request(mut request: SearchRequest, limits: &AnalysisLimits) -> Result<SearchRequest> {
+    let mut errors = Vec::new();
+
+    request.term = request.term.trim().to_string();
+    check_term("term", &request.term, limits, &mut errors);
+    check_ceiling("max_depth", request.max_depth, limits.max_depth, &mut errors);
+    check_ceiling("max_results", request.max_results, limits.max_results, &mut errors);
+    check_ceiling(
+        "max_principles_per_node",
+        request.max_principles_per_node,
+        limits.max_principles_per_node,
+        &mut errors,
+    );
+    if request.categories.as_ref().is_some_and(Vec::is_empty) {
+        errors.push(FieldError::new("categories", "must contain at least one category when provided"));
+    }
+    for (i, category) in request.categories.iter().flatten().enumerate() {
+        if !category.is_registered() && category.name() != PrincipleCategory::GENERAL {
+            errors.push(FieldError::new(format!("categories[{}]", i), format!("unknown category `{}`", category.name())));
+        }
+    }
+    for (field, terms) in [("exclude_terms", &mut request.exclude_terms), ("pin_terms", &mut request.pin_terms)] {
+        for (i, term) in terms.iter_mut().enumerate() {
+            *term = term.trim().to_string();
+            check_term(&format!("{}[{}]", field, i), term, limits, &mut errors);
+        }
+    }
+    match request.timeout_ms {
+        Some(0) => errors.push(FieldError::new("timeout_ms", "must be at least 1")),
+        Some(timeout_ms) if timeout_ms > limits.analysis_timeout_secs * 1000 => errors.push(FieldError::new(
+            "timeout_ms",
+            format!("must be at most {}", limits.analysis_timeout_secs * 1000),
+        )),
+        _ => {}
+    }
+    if request.prune_below.is_some_and(|threshold| !threshold.is_finite() || threshold < 0.0) {
+        errors.push(FieldError::new("prune_below", "must be a non-negative number"));
+    }
+    if let Some(min_confidence) = request.min_confidence {
+        if !(0.0..=1.0).contains(&min_confidence) {
+            errors.push(FieldError::new("min_confidence", "must be between 0.0 and 1.0"));
+        }
+    }
+    let is_language_tag = |locale: &str| {
+        (1..=35).contains(&locale.len()) && locale.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+    if request.locale.as_deref().is_some_and(|locale| !is_language_tag(locale)) {
+        errors.push(FieldError::new("locale", "must be a language tag such as `de` or `pt-BR`"));
+    }
+
+    into_result(request, errors)
+}
+
+/// Validate a batch request, checking every term and the batch size
+pub fn validate_batch_request(mut request: BatchAnalyzeRequest, limits: &AnalysisLimits) -> Result<BatchAnalyzeRequest> {
+    let mut errors = Vec::new();
+
+    if request.terms.is_empty() {
+        errors.push(FieldError::new("terms", "batch must contain at least one term"));
+    } else if request.terms.len() > limits.max_batch_terms {
+        errors.push(FieldError::new(
+            "terms",
+            format!("batch contains {} terms, maximum is {}", request.terms.len(), limits.max_batch_terms),
+        ));
+    }
+
+    for (i, term) in request.terms.iter_mut().enumerate() {
+        *term = term.trim().to_string();
+        check_term(&format!("terms[{}]", i), term, limits, &mut errors);
+    }
+    check_ceiling("max_depth", request.max_depth, limits.max_depth, &mut errors);
+    check_ceiling("max_results", request.max_results, limits.max_results, &mut errors);
+
+    into_result(request, errors)
+}
+
+/// Validate a suggestion query, returning the trimmed query
+pub fn validate_suggest_query(query: &str, limit: Option<u8>, limits: &AnalysisLimits) -> Result<String> {
+    let mut errors = Vec::new();
+
+    let query = query.trim().to_string();
+    check_term("query", &query, limits, &mut errors);
+    check_ceiling("limit", limit, limits.max_suggestions, &mut errors);
+
+    into_result(query, errors)
+}
+
+/// Validate feedback on an analysis: the page term, the principle or concept the kind of
+/// feedback is about (and not the other one) and the comment's length
+pub fn validate_feedback(mut feedback: Feedback, limits: &AnalysisLimits) -> Result<Feedback> {
+    let mut errors = Vec::new();
+
+    feedback.term = feedback.term.trim().to_string();
+    check_term("term", &feedback.term, limits, &mut errors);
+
+    let trim = |value: &mut Option<String>| *value = value.take().map(|value| value.trim().to_string());
+    trim(&mut feedback.principle);
+    trim(&mut feedback.concept);
+    let (required, other) = match feedback.kind.flags_principle() {
+        true => (("principle", &feedback.principle), ("concept", &feedback.concept)),
+        false => (("concept", &feedback.concept), ("principle", &feedback.principle)),
+    };
+    match required.1 {
+        None => errors.push(FieldError::new(required.0, "is required for this kind of feedback")),
+        Some(value) if required.0 == "concept" => check_term("concept", value, limits, &mut errors),
+        Some(value) if value.is_empty() => errors.push(FieldError::new("principle", "must not be empty")),
+        Some(value) if value.chars().count() > MAX_PRINCIPLE_LENGTH => errors.push(FieldError::new(
+            "principle",
+            format!("must be at most {} characters", MAX_PRINCIPLE_LENGTH),
+        )),
+        Some(_) => {}
+    }
+    if other.1.is_some() {
+        errors.push(FieldError::new(other.0, "does not apply to this kind of feedback"));
+    }
+
+    if feedback.comment.as_ref().is_some_and(|comment| comment.chars().count() > MAX_FEEDBACK_COMMENT_LENGTH) {
+        errors.push(FieldError::new(
+            "comment",
+            format!("must be at most {} characters", MAX_FEEDBACK_COMMENT_LENGTH),
+        ));
+    }
+
+    into_result(feedback, errors)
+}
+
+/// Validate a tenant name from the `X-Tenant` header
+pub fn validate_tenant(tenant: &str) -> Result<&str> {
+    let mut errors = Vec::new();
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.');
+    if !(1..=64).contains(&tenant.len()) || !tenant.chars().all(is_name_char) {
+        errors.push(FieldError::new("X-Tenant", "must be 1 to 64 letters, digits, `-`, `_` or `.`"));
+    }
+
+    into_result(tenant, errors)
+}
+
+fn check_term(field: &str, term: &str, limits: &AnalysisLimits, errors: &mut Vec<FieldError>) {
+    if term.is_empty() {
+        errors.push(FieldError::new(field, "must not be empty"));
+    } else if term.chars().count() > limits.max_term_length {
+        errors.push(FieldError::new(
+            field,
+            format!("must be at most {} characters", limits.max_term_length),
+        ));
+    }
+
+    if term.chars().any(char::is_control) {
+        errors.push(FieldError::new(field, "must not contain control characters"));
+    }
+}
+
+fn check_ceiling(field: &str, value: Option<u8>, ceiling: u8, errors: &mut Vec<FieldError>) {
+    match value {
+        Some(0) => errors.push(FieldError::new(field, "must be at least 1")),
+        Some(value) if value > ceiling => {
+            errors.push(FieldError::new(field, format!("must be at most {}", ceiling)))
+        }
+        _ => {}
+    }
+}
+
+fn into_result<T>(value: T, errors: Vec<FieldError>) -> Result<T> {
+    if errors.is_empty() {
+        Ok(value)
+    } else {
+        Err(WikiEngineError::InvalidRequest(errors))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feedback::FeedbackKind;
+
+    /// Field and message of every error a validation returned
+    fn rejections<T: std::fmt::Debug>(result: Result<T>) -> Vec<(String, String)> {
+        match result {
+            Err(WikiEngineError::InvalidRequest(errors)) => errors.into_iter().map(|e| (e.field, e.message)).collect(),
+            other => panic!("expected a rejected request, got {:?}", other),
+        }
+    }
+
+    fn request(term: &str) -> SearchRequest {
+        SearchRequest { term: term.to_string(), ..Default::default() }
+    }
+
+    fn feedback(kind: FeedbackKind, principle: Option<&str>, concept: Option<&str>) -> Feedback {
+        Feedback {
+            kind,
+            term: "Gearbox".to_string(),
+            principle: principle.map(str::to_string),
+            concept: concept.map(str::to_string),
+            comment: None,
+        }
+    }
+
+    #[test]
+    fn terms_are_trimmed_and_must_be_printable_text() {
+        let limits = AnalysisLimits::default();
+
+        assert_eq!(validate_search_request(request("  Steam engine\n"), &limits).unwrap().term, "Steam engine");
+        assert_eq!(rejections(validate_search_request(request(" \t "), &limits)), [("term".to_string(), "must not be empty".to_string())]);
+        assert_eq!(
+            rejections(validate_search_request(request("Steam\u{7}engine"), &limits)),
+            [("term".to_string(), "must not contain control characters".to_string())]
+        );
+        assert_eq!(
+            rejections(validate_search_request(request(&"a".repeat(limits.max_term_length + 1)), &limits)),
+            [("term".to_string(), format!("must be at most {} characters", limits.max_term_length))]
+        );
+        assert!(validate_search_request(request(&"ü".repeat(limits.max_term_length)), &limits).is_ok());
+    }
+
+    #[test]
+    fn counts_must_be_between_one_and_their_ceiling() {
+        let limits = AnalysisLimits::default();
+        let with = |max_depth, max_results| SearchRequest { max_depth, max_results, ..request("Gearbox") };
+
+        assert!(validate_search_request(with(Some(limits.max_depth), Some(1)), &limits).is_ok());
+        assert!(validate_search_request(with(None, None), &limits).is_ok());
+        assert_eq!(
+            rejections(validate_search_request(with(Some(0), Some(0)), &limits)),
+            [
+                ("max_depth".to_string(), "must be at least 1".to_string()),
+                ("max_results".to_string(), "must be at least 1".to_string()),
+            ]
+        );
+        assert_eq!(
+            rejections(validate_search_request(with(Some(255), Some(limits.max_results + 1)), &limits)),
+            [
+                ("max_depth".to_string(), format!("must be at most {}", limits.max_depth)),
+                ("max_results".to_string(), format!("must be at most {}", limits.max_results)),
+            ]
+        );
+    }
+
+    #[test]
+    fn timeouts_must_fit_the_analysis_timeout() {
+        let limits = AnalysisLimits::default();
+        let with = |timeout_ms| SearchRequest { timeout_ms: Some(timeout_ms), ..request("Gearbox") };
+
+        assert!(validate_search_request(with(1), &limits).is_ok());
+        assert!(validate_search_request(with(limits.analysis_timeout_secs * 1000), &limits).is_ok());
+        assert_eq!(rejections(validate_search_request(with(0), &limits)), [("timeout_ms".to_string(), "must be at least 1".to_string())]);
+        assert_eq!(
+            rejections(validate_search_request(with(limits.analysis_timeout_secs * 1000 + 1), &limits)),
+            [("timeout_ms".to_string(), format!("must be at most {}", limits.analysis_timeout_secs * 1000))]
+        );
+    }
+
+    #[test]
+    fn thresholds_must_be_in_range() {
+        let limits = AnalysisLimits::default();
+        let prune = |prune_below| SearchRequest { prune_below: Some(prune_below), ..request("Gearbox") };
+        let confidence = |min_confidence| SearchRequest { min_confidence: Some(min_confidence), ..request("Gearbox") };
+
+        assert!(validate_search_request(prune(0.0), &limits).is_ok());
+        assert!(validate_search_request(prune(12.5), &limits).is_ok());
+        for invalid in [f32::NAN, f32::INFINITY, -0.5] {
+            assert_eq!(rejections(validate_search_request(prune(invalid), &limits))[0].0, "prune_below");
+        }
+
+        assert!(validate_search_request(confidence(0.0), &limits).is_ok());
+        assert!(validate_search_request(confidence(1.0), &limits).is_ok());
+        for invalid in [f32::NAN, -0.1, 1.1] {
+            assert_eq!(
+                rejections(validate_search_request(confidence(invalid), &limits)),
+                [("min_confidence".to_string(), "must be between 0.0 and 1.0".to_string())]
+            );
+        }
+    }
+
+    #[test]
+    fn locales_must_be_language_tags() {
+        let limits = AnalysisLimits::default();
+        let with = |locale: &str| SearchRequest { locale: Some(locale.to_string()), ..request("Gearbox") };
+
+        for valid in ["de", "pt-BR", "zh_Hant"] {
+            assert!(validate_search_request(with(valid), &limits).is_ok(), "{}", valid);
+        }
+        for invalid in ["", "de/../en", "pt BR", &"x".repeat(36)] {
+            assert_eq!(rejections(validate_search_request(with(invalid), &limits))[0].0, "locale", "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn batches_must_hold_one_to_the_maximum_terms() {
+        let limits = AnalysisLimits::default();
+        let batch = |count: usize| BatchAnalyzeRequest {
+            terms: (0..count).map(|i| format!(" Part {} ", i)).collect(),
+            max_depth: None,
+            max_results: None,
+            webhook_url: None,
+        };
+
+        let request = validate_batch_request(batch(limits.max_batch_terms), &limits).unwrap();
+        assert_eq!(request.terms[0], "Part 0");
+        assert_eq!(
+            rejections(validate_batch_request(batch(0), &limits)),
+            [("terms".to_string(), "batch must contain at least one term".to_string())]
+        );
+        assert_eq!(
+            rejections(validate_batch_request(batch(limits.max_batch_terms + 1), &limits)),
+            [(
+                "terms".to_string(),
+                format!("batch contains {} terms, maximum is {}", limits.max_batch_terms + 1, limits.max_batch_terms)
+            )]
+        );
+
+        let mut request = batch(2);
+        request.terms[1] = "  ".to_string();
+        assert_eq!(rejections(validate_batch_request(request, &limits)), [("terms[1]".to_string(), "must not be empty".to_string())]);
+    }
+
+    #[test]
+    fn feedback_names_what_its_kind_is_about_and_nothing_else() {
+        let limits = AnalysisLimits::default();
+
+        let valid = validate_feedback(feedback(FeedbackKind::WrongPrinciple, Some(" Ohm's law "), None), &limits).unwrap();
+        assert_eq!(valid.principle.as_deref(), Some("Ohm's law"));
+        assert!(validate_feedback(feedback(FeedbackKind::OffTopicConcept, None, Some("Clutch")), &limits).is_ok());
+
+        assert_eq!(
+            rejections(validate_feedback(feedback(FeedbackKind::IrrelevantPrinciple, None, None), &limits)),
+            [("principle".to_string(), "is required for this kind of feedback".to_string())]
+        );
+        assert_eq!(
+            rejections(validate_feedback(feedback(FeedbackKind::OffTopicConcept, None, None), &limits)),
+            [("concept".to_string(), "is required for this kind of feedback".to_string())]
+        );
+        assert_eq!(
+            rejections(validate_feedback(feedback(FeedbackKind::CorrectPrinciple, Some("Ohm's law"), Some("Clutch")), &limits)),
+            [("concept".to_string(), "does not apply to this kind of feedback".to_string())]
+        );
+        assert_eq!(
+            rejections(validate_feedback(feedback(FeedbackKind::OffTopicConcept, Some("Ohm's law"), Some("Clutch")), &limits)),
+            [("principle".to_string(), "does not apply to this kind of feedback".to_string())]
+        );
+        assert_eq!(
+            rejections(validate_feedback(feedback(FeedbackKind::WrongPrinciple, Some("  "), None), &limits)),
+            [("principle".to_string(), "must not be empty".to_string())]
+        );
+        assert_eq!(
+            rejections(validate_feedback(feedback(FeedbackKind::OffTopicConcept, None, Some("Clu\u{0}tch")), &limits)),
+            [("concept".to_string(), "must not contain control characters".to_string())]
+        );
+
+        let long = "p".repeat(MAX_PRINCIPLE_LENGTH + 1);
+        assert_eq!(rejections(validate_feedback(feedback(FeedbackKind::WrongPrinciple, Some(&long), None), &limits))[0].0, "principle");
+        let mut commented = feedback(FeedbackKind::WrongPrinciple, Some("Ohm's law"), None);
+        commented.comment = Some("c".repeat(MAX_FEEDBACK_COMMENT_LENGTH + 1));
+        assert_eq!(rejections(validate_feedback(commented, &limits))[0].0, "comment");
+    }
+
+    #[test]
+    fn tenants_are_short_plain_names() {
+        for valid in ["acme", "team-a.eu_1", &"t".repeat(64)] {
+            assert_eq!(validate_tenant(valid).unwrap(), valid);
+        }
+        for invalid in ["", "acme corp", "tenant/../other", "tenänt", &"t".repeat(65)] {
+            assert_eq!(rejections(validate_tenant(invalid))[0].0, "X-Tenant", "{:?}", invalid);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! JavaScript bindings for running the engine in the browser.
+//!
+//! Build with `wasm-pack build --target web -- --no-default-features --features network`. Pages are
+//! fetched through the browser's `fetch` (reqwest's WASM backend), so the MediaWiki API must allow
+//! cross-origin requests; Wikimedia wikis do for anonymous calls.
+
+use crate::cache::WikiEngineCache;
+use crate::config::{AnalysisLimits, HttpClientConfig, MediaWikiSite};
+use crate::types::SearchRequest;
+use crate::validation::{validate_search_request, validate_suggest_query};
+use crate::wikipedia::MediaWikiClient;
+use crate::WikiEngine;
+use std::sync::Arc;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct WasmWikiEngine {
+    engine: WikiEngine,
+}
+
+#[wasm_bindgen]
+impl WasmWikiEngine {
+    /// `apiUrl` points the engine at another MediaWiki `api.php`, such as a mirror or a CORS proxy
+    #[wasm_bindgen(constructor)]
+    pub fn new(api_url: Option<String>) -> Result<WasmWikiEngine, JsValue> {
+        let site = match api_url {
+            Some(api_url) => MediaWikiSite { api_url, pageviews_project: None, ..MediaWikiSite::default() },
+            None => MediaWikiSite::default(),
+        };
+        let engine = WikiEngine::builder()
+            .with_cache(Arc::new(WikiEngineCache::new()))
+            .with_wikipedia_client(MediaWikiClient::with_config(&HttpClientConfig::default(), site).map_err(to_js_error)?)
+            .without_env_sources()
+            .build()
+            .map_err(to_js_error)?;
+
+        Ok(WasmWikiEngine { engine })
+    }
+
+    /// Recursively analyze a term, resolving to an `AnalysisResult`. The request is held to the
+    /// same default limits as the HTTP API, so a huge `maxDepth` is rejected rather than run.
+    pub async fn analyze(&self, term: String, max_depth: Option<u8>) -> Result<JsValue, JsValue> {
+        let request = SearchRequest {
+            term,
+            max_depth,
+            max_results: Some(10),
+            ..Default::default()
+        };
+        let request = validate_search_request(request, &AnalysisLimits::default()).map_err(to_js_error)?;
+
+        let result = self.engine.analyze_recursive(&request).await.map_err(to_js_error)?;
+        serde_wasm_bindgen::to_value(&result).map_err(to_js_error)
+    }
+
+    /// Break a concept into its foundational components using only the built-in knowledge base,
+    /// or the regex extractors over `text` for concepts it doesn't know. Runs synchronously with
+    /// no network access, so frontends can show a preview while the server does the deep analysis.
+    pub fn decompose(&self, concept: String, max_depth: Option<u8>, text: Option<String>) -> Result<JsValue, JsValue> {
+        let decomposition = self
+            .engine
+            .decompose_offline(concept.trim(), max_depth.unwrap_or(2), text.as_deref())
+            .map_err(to_js_error)?;
+        serde_wasm_bindgen::to_value(&decomposition).map_err(to_js_error)
+    }
+
+    /// Like `decompose`, but fetches the Wikipedia article for concepts outside the knowledge base
+    #[wasm_bindgen(js_name = decomposeOnline)]
+    pub async fn decompose_online(&self, concept: String, max_depth: Option<u8>) -> Result<JsValue, JsValue> {
+        let decomposition = self
+            .engine
+            .get_engineering_concept_hierarchy(concept.trim(), max_depth.unwrap_or(2))
+            .await
+            .map_err(to_js_error)?;
+        serde_wasm_bindgen::to_value(&decomposition).map_err(to_js_error)
+    }
+
+    /// Engineering terms matching a query, resolving to `SearchSuggestion[]`
+    pub async fn suggest(&self, query: String, limit: Option<u8>) -> Result<JsValue, JsValue> {
+        let query = validate_suggest_query(&query, limit, &AnalysisLimits::default()).map_err(to_js_error)?;
+        let suggestions = self
+            .engine
+            .suggest_terms(&query, limit.unwrap_or(8))
+            .await
+            .map_err(to_js_error)?;
+        serde_wasm_bindgen::to_value(&suggestions).map_err(to_js_error)
+    }
+}
+
+fn to_js_error(error: impl std::fmt::Display) -> JsValue {
+    JsValue::from_str(&error.to_string())
+}
@@ -0,0 +1,240 @@
+//! Signed notifications sent to a caller-supplied URL when a batch analysis finishes, so long
+//! analyses can feed external pipelines without a client waiting on the connection.
+//!
+//! Every delivery is a JSON `WebhookEvent` POSTed with three headers: `X-Wiki-Engine-Delivery`
+//! (the event id, the same on every retry), `X-Wiki-Engine-Timestamp` (Unix seconds) and
+//! `X-Wiki-Engine-Signature`, `sha256=` followed by the hex HMAC-SHA256 of
+//! `<timestamp>.<body>` under `WIKI_ENGINE_WEBHOOK_SECRET`.
+
+use crate::config::WebhookConfig;
+use crate::sources::host_allowed;
+use crate::types::{BatchAnalysisItem, FieldError, Result, WikiEngineError};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::time::Duration;
+use utoipa::ToSchema;
+
+pub const DELIVERY_HEADER: &str = "X-Wiki-Engine-Delivery";
+pub const TIMESTAMP_HEADER: &str = "X-Wiki-Engine-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-Wiki-Engine-Signature";
+
+/// Longest wait between two delivery attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// What a webhook receives once every term of a batch is analyzed
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookEvent {
+    /// Id of the batch, as `/analyze/batch` answered it
+    pub id: String,
+    /// Endpoint the batch was submitted to
+    pub endpoint: String,
+    pub items: Vec<BatchAnalysisItem>,
+}
+
+/// Sends webhook events, retrying failed deliveries with exponential backoff
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    client: Client,
+    config: WebhookConfig,
+}
+
+impl WebhookNotifier {
+    pub fn new(mut config: WebhookConfig) -> Result<Self> {
+        config.allowed_hosts = config.allowed_hosts.iter().map(|host| host.trim().to_lowercase()).collect();
+        // Redirects aren't followed, so a delivery can't be bounced to a host off the allow-list
+        let client = Client::builder()
+            .user_agent("WikiEngineBackend/1.0 (Educational Purpose)")
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+        Ok(Self { client, config })
+    }
+
+    /// Parse a caller's webhook URL, refusing it unless webhooks are enabled and it is an
+    /// http(s) URL on an allowed host
+    pub fn check_url(&self, url: &str) -> Result<Url> {
+        let refuse = |message: String| WikiEngineError::InvalidRequest(vec![FieldError::new("webhook_url", message)]);
+        if self.config.secret.is_none() {
+            return Err(refuse("webhooks are disabled; set WIKI_ENGINE_WEBHOOK_SECRET to enable them".to_string()));
+        }
+        let url = Url::parse(url).map_err(|e| refuse(format!("is not a valid URL: {}", e)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(refuse("must be an http(s) URL".to_string()));
+        }
+        let host = url.host_str().unwrap_or_default().trim_end_matches('.').to_lowercase();
+        if !host_allowed(&host, &self.config.allowed_hosts) {
+            return Err(refuse(format!("{} is not an allowed webhook host", host)));
+        }
+        Ok(url)
+    }
+
+    /// POST `event` to `url`, retrying connection failures, timeouts, 429s and 5xx answers
+    /// until `max_attempts` deliveries were tried. Other answers are final.
+    pub async fn deliver(&self, url: &Url, event: &WebhookEvent) -> Result<()> {
+        let Some(secret) = &self.config.secret else {
+            return Err(WikiEngineError::Analysis("Webhooks are disabled".to_string()));
+        };
+        let body = serde_json::to_vec(event)?;
+        let mut backoff = Duration::from_millis(self.config.initial_backoff_ms);
+        let mut attempt = 1;
+        loop {
+            // Signed afresh for every attempt, so receivers can reject stale timestamps
+            let timestamp = chrono::Utc::now().timestamp();
+            let response = self
+                .client
+                .post(url.clone())
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(DELIVERY_HEADER, &event.id)
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(SIGNATURE_HEADER, sign(secret.as_bytes(), timestamp, &body))
+                .body(body.clone())
+                .send()
+                .await;
+            let error = match response {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) if !retryable(response.status()) => {
+                    return Err(WikiEngineError::Network(format!("Webhook {} answered {}", url, response.status())));
+                }
+                Ok(response) => format!("answered {}", response.status()),
+                Err(e) => e.to_string(),
+            };
+            if attempt >= self.config.max_attempts {
+                return Err(WikiEngineError::Network(format!(
+                    "Webhook {} failed after {} attempts: {}",
+                    url, attempt, error
+                )));
+            }
+            tracing::warn!("Webhook delivery {} to {} failed ({}), retrying in {:?}", event.id, url, error, backoff);
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+            attempt += 1;
+        }
+    }
+}
+
+fn retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Signature header value for a delivery of `body` at `timestamp`: `sha256=` and the hex
+/// HMAC-SHA256 of `<timestamp>.<body>` under `secret`
+pub fn sign(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+    format!("sha256={}", digest.iter().map(|byte| format!("{:02x}", byte)).collect::<String>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use wiremock::matchers::{header_exists, method, path};
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    fn notifier(allowed_hosts: &[&str], max_attempts: u32) -> WebhookNotifier {
+        WebhookNotifier::new(WebhookConfig {
+            secret: Some("hook-secret".to_string()),
+            allowed_hosts: allowed_hosts.iter().map(|host| host.to_string()).collect(),
+            max_attempts,
+            initial_backoff_ms: 10,
+            timeout_secs: 5,
+        })
+        .unwrap()
+    }
+
+    fn event() -> WebhookEvent {
+        WebhookEvent {
+            id: "batch-1".to_string(),
+            endpoint: "/analyze/batch".to_string(),
+            items: vec![BatchAnalysisItem { term: "Gearbox".to_string(), result: None, error: Some("No page".to_string()) }],
+        }
+    }
+
+    /// Fails with a 503 until `failures` requests were answered
+    struct FlakyReceiver {
+        calls: Arc<AtomicUsize>,
+        failures: usize,
+    }
+
+    impl Respond for FlakyReceiver {
+        fn respond(&self, _: &Request) -> ResponseTemplate {
+            match self.calls.fetch_add(1, Ordering::SeqCst) < self.failures {
+                true => ResponseTemplate::new(503),
+                false => ResponseTemplate::new(204),
+            }
+        }
+    }
+
+    #[test]
+    fn signatures_cover_the_timestamp_and_body() {
+        let body = br#"{"id":"batch-1"}"#;
+        let signature = sign(b"hook-secret", 1_700_000_000, body);
+
+        // As `openssl dgst -sha256 -hmac hook-secret` computes it over `1700000000.{"id":"batch-1"}`
+        assert_eq!(signature, "sha256=7a5a3dfd8c247562e87f4f7f7a2e02a9ae3629fb4ffc6b0a1d859c3fb7074273");
+        assert_ne!(signature, sign(b"hook-secret", 1_700_000_001, body));
+        assert_ne!(signature, sign(b"other-secret", 1_700_000_000, body));
+    }
+
+    #[test]
+    fn only_allowed_hosts_receive_webhooks() {
+        let notifier = notifier(&["hooks.example.org", "*.example.com"], 1);
+
+        assert!(notifier.check_url("https://hooks.example.org/wiki").is_ok());
+        assert!(notifier.check_url("https://ci.example.com/wiki").is_ok());
+        for refused in ["https://example.com/", "https://evil.org/", "ftp://hooks.example.org/", "not a url"] {
+            assert!(matches!(notifier.check_url(refused), Err(WikiEngineError::InvalidRequest(_))), "{}", refused);
+        }
+
+        let disabled = WebhookNotifier::new(WebhookConfig { allowed_hosts: vec!["hooks.example.org".to_string()], ..WebhookConfig::default() }).unwrap();
+        assert!(disabled.check_url("https://hooks.example.org/wiki").is_err());
+    }
+
+    #[tokio::test]
+    async fn deliveries_are_signed_and_retried_until_accepted() {
+        let server = MockServer::start().await;
+        let calls = Arc::new(AtomicUsize::new(0));
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .and(header_exists(SIGNATURE_HEADER))
+            .respond_with(FlakyReceiver { calls: Arc::clone(&calls), failures: 2 })
+            .mount(&server)
+            .await;
+        let notifier = notifier(&["127.0.0.1"], 3);
+        let url = notifier.check_url(&format!("{}/hook", server.uri())).unwrap();
+
+        notifier.deliver(&url, &event()).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let requests = server.received_requests().await.unwrap();
+        let last = requests.last().unwrap();
+        let timestamp: i64 = last.headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+        assert_eq!(last.headers[SIGNATURE_HEADER].to_str().unwrap(), sign(b"hook-secret", timestamp, &last.body));
+        assert_eq!(last.headers[DELIVERY_HEADER].to_str().unwrap(), "batch-1");
+        let delivered: WebhookEvent = serde_json::from_slice(&last.body).unwrap();
+        assert_eq!(delivered.items[0].term, "Gearbox");
+    }
+
+    #[tokio::test]
+    async fn deliveries_give_up_after_the_last_attempt_or_a_client_error() {
+        let server = MockServer::start().await;
+        Mock::given(path("/down")).respond_with(ResponseTemplate::new(500)).mount(&server).await;
+        Mock::given(path("/gone")).respond_with(ResponseTemplate::new(410)).mount(&server).await;
+        let notifier = notifier(&["127.0.0.1"], 3);
+
+        let down = notifier.check_url(&format!("{}/down", server.uri())).unwrap();
+        assert!(notifier.deliver(&down, &event()).await.is_err());
+        let gone = notifier.check_url(&format!("{}/gone", server.uri())).unwrap();
+        assert!(notifier.deliver(&gone, &event()).await.is_err());
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.iter().filter(|request| request.url.path() == "/down").count(), 3);
+        assert_eq!(requests.iter().filter(|request| request.url.path() == "/gone").count(), 1);
+    }
+}
@@ -1,7 +1,20 @@
-use crate::types::{Result, WikipediaPage};
-use reqwest::Client;
+use crate::config::{HttpClientConfig, MediaWikiSite};
+use crate::monitoring;
+use crate::text;
+use crate::types::{DocumentSourceKind, Result, WikiEngineError, WikipediaPage};
+use regex::Regex;
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::{Client, StatusCode};
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use dashmap::DashMap;
 use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
+use web_time::Instant;
+use tokio::sync::Semaphore;
+
+/// Width in pixels of the lead-image thumbnails requested alongside extracts
+const THUMBNAIL_SIZE: u32 = 320;
 
 #[derive(Debug, Deserialize)]
 struct WikipediaApiResponse {
@@ -18,35 +31,238 @@ struct WikipediaPageData {
     pageid: Option<u64>,
     title: Option<String>,
     extract: Option<String>,
-    missing: Option<bool>,
+    // The API signals missing pages by the mere presence of this key
+    missing: Option<serde_json::Value>,
+    pageprops: Option<HashMap<String, serde_json::Value>>,
+    lastrevid: Option<u64>,
+    thumbnail: Option<PageThumbnail>,
+}
+
+/// The `error` object the action API returns (with HTTP 200) when a request is refused
+#[derive(Debug, Deserialize)]
+struct ApiErrorBody {
+    code: String,
+    #[serde(default)]
+    info: String,
+    /// Replication lag in seconds, present on `maxlag` errors
+    lag: Option<f64>,
+}
+
+impl ApiErrorBody {
+    fn into_error(self, retry_after: Option<u64>) -> WikiEngineError {
+        match self.code.as_str() {
+            "maxlag" => WikiEngineError::ReplicationLag {
+                retry_after: retry_after.or(self.lag.map(|lag| lag.ceil() as u64)),
+            },
+            "ratelimited" => WikiEngineError::RateLimited { retry_after },
+            "badtitle" | "invalidtitle" => WikiEngineError::InvalidTitle(self.info),
+            _ => WikiEngineError::WikipediaApi(format!("{}: {}", self.code, self.info)),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PageThumbnail {
+    source: String,
+}
+
+impl WikipediaPageData {
+    fn into_page(self, site: &MediaWikiSite) -> Option<WikipediaPage> {
+        let (title, extract, page_id) = (self.title?, self.extract?, self.pageid?);
+        Some(WikipediaPage {
+            url: site.article_url(&title),
+            title: text::nfc(&title).into_owned(),
+            extract: text::nfc(&extract).into_owned(),
+            page_id,
+            revision_id: self.lastrevid,
+            fetched_at: chrono::Utc::now(),
+            thumbnail_url: self.thumbnail.map(|thumbnail| thumbnail.source),
+            monthly_views: None,
+            curated_links: vec![],
+            etag: None,
+            last_modified: None,
+            source: DocumentSourceKind::Wikipedia,
+        })
+    }
+
+    fn is_disambiguation(&self) -> bool {
+        self.pageprops
+            .as_ref()
+            .is_some_and(|props| props.contains_key("disambiguation"))
+    }
+}
+
+/// Outcome of revalidating a cached page against Wikipedia
+#[derive(Debug)]
+pub enum PageFetch {
+    /// The server answered 304, the cached copy is still current
+    NotModified,
+    /// The page changed (or the server ignored the validators) and was downloaded again
+    Modified(Box<WikipediaPage>),
+    /// The page no longer exists
+    Missing,
 }
 
-pub struct WikipediaClient {
+/// Client for the action API of a single MediaWiki site (English Wikipedia unless configured otherwise).
+/// Cheap to clone: clones share the connection pool and the concurrency limits.
+#[derive(Clone)]
+pub struct MediaWikiClient {
     client: Client,
-    base_url: String,
+    site: MediaWikiSite,
+    host_permits: Arc<DashMap<String, Arc<Semaphore>>>,
+    max_concurrent_requests_per_host: usize,
+    /// Optional cap on requests in flight across all hosts, usually shared server-wide
+    fetch_permits: Option<Arc<Semaphore>>,
 }
 
-impl WikipediaClient {
-    pub fn new() -> Self {
-        Self {
-            client: Client::builder()
-                .user_agent("WikiEngineBackend/1.0 (Educational Purpose)")
-                .timeout(std::time::Duration::from_secs(30))
-                .build()
-                .expect("Failed to create HTTP client"),
-            base_url: "https://en.wikipedia.org/api/rest_v1".to_string(),
+/// Wikipedia is still the site almost everyone points the engine at
+pub type WikipediaClient = MediaWikiClient;
+
+impl MediaWikiClient {
+    pub fn new() -> Result<Self> {
+        Self::with_config(&HttpClientConfig::from_env(), MediaWikiSite::from_env())
+    }
+
+    /// Fails when the HTTP client can't be built, such as for an invalid proxy URL: requests
+    /// must never quietly bypass a proxy configured for egress control
+    pub fn with_config(config: &HttpClientConfig, site: MediaWikiSite) -> Result<Self> {
+        let builder = Client::builder().user_agent("WikiEngineBackend/1.0 (Educational Purpose)");
+        // In the browser requests go through `fetch`, which manages connections itself
+        #[cfg(not(target_arch = "wasm32"))]
+        let builder = Self::configure_native(builder, config)?;
+
+        Ok(Self {
+            client: builder.build()?,
+            site,
+            host_permits: Arc::new(DashMap::new()),
+            max_concurrent_requests_per_host: config.max_concurrent_requests_per_host.max(1),
+            fetch_permits: None,
+        })
+    }
+
+    /// Also hold one of `permits` for every request, so several clients (or engines) can share
+    /// one limit on total in-flight fetches
+    pub fn with_fetch_permits(mut self, permits: Arc<Semaphore>) -> Self {
+        self.fetch_permits = Some(permits);
+        self
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn configure_native(builder: reqwest::ClientBuilder, config: &HttpClientConfig) -> Result<reqwest::ClientBuilder> {
+        use std::time::Duration;
+
+        let mut builder = builder
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .pool_max_idle_per_host(config.pool_max_idle_per_host)
+            .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout_secs))
+            .tcp_keepalive(Duration::from_secs(config.tcp_keepalive_secs));
+        if config.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        if let Some(proxy_url) = &config.proxy {
+            let proxy = reqwest::Proxy::all(proxy_url)
+                .map_err(|e| WikiEngineError::Network(format!("Invalid proxy URL '{}': {}", proxy_url, e)))?;
+            builder = builder.proxy(proxy);
         }
+        Ok(builder)
+    }
+
+    /// Concurrency limiter for the host of `url`
+    fn host_permits(&self, url: &str) -> Arc<Semaphore> {
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        self.host_permits
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_concurrent_requests_per_host)))
+            .clone()
+    }
+
+    async fn send(&self, url: &str) -> Result<ApiResponse> {
+        self.send_with_headers(url, HeaderMap::new()).await
+    }
+
+    /// Issue a GET request and read its body, mapping rate limiting and HTTP error statuses to
+    /// engine errors. A 304 is passed through so conditional requests can tell it apart. The
+    /// concurrency permits are held until the body is read, so they limit transfers too.
+    #[tracing::instrument(name = "wikipedia_request", skip(self, headers))]
+    async fn send_with_headers(&self, url: &str, headers: HeaderMap) -> Result<ApiResponse> {
+        let _fetch_permit = match &self.fetch_permits {
+            Some(permits) => Some(permits.acquire().await.expect("fetch semaphore is never closed")),
+            None => None,
+        };
+        let permits = self.host_permits(url);
+        let _permit = permits.acquire().await.expect("host semaphore is never closed");
+
+        // Anonymous cross-origin calls to the action API must opt in with `origin=*`
+        #[cfg(target_arch = "wasm32")]
+        let url = &if url.contains("?action=") { format!("{}&origin=*", url) } else { url.to_string() };
+
+        let start = Instant::now();
+        let response = match self.client.get(url).headers(headers).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                monitoring::record_wikipedia_call("network_error", start.elapsed());
+                return Err(e.into());
+            }
+        };
+        let status = response.status();
+        let outcome = if status == StatusCode::TOO_MANY_REQUESTS {
+            "rate_limited"
+        } else if status == StatusCode::NOT_MODIFIED {
+            "not_modified"
+        } else if status.is_success() {
+            "success"
+        } else {
+            "http_error"
+        };
+        monitoring::record_wikipedia_call(outcome, start.elapsed());
+
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(WikiEngineError::RateLimited { retry_after: retry_after(response.headers()) });
+        }
+        if !status.is_success() && status != StatusCode::NOT_MODIFIED {
+            return Err(WikiEngineError::WikipediaApi(format!("HTTP {} for {}", status, url)));
+        }
+
+        let headers = response.headers().clone();
+        let body = response.bytes().await?;
+        monitoring::record_wikipedia_bytes(body.len());
+        Ok(ApiResponse {
+            status,
+            headers,
+            body: body.into(),
+        })
+    }
+
+    /// Lightweight request used to check that the API is reachable
+    pub async fn probe(&self) -> Result<()> {
+        let url = format!("{}?action=query&meta=siteinfo&format=json", self.site.api_url);
+        self.send(&url).await?;
+        Ok(())
     }
 
     pub async fn search_pages(&self, query: &str, limit: u8) -> Result<Vec<String>> {
+        self.opensearch(query, limit, "strict").await
+    }
+
+    /// Title search using Wikipedia's typo-tolerant search profile
+    pub async fn search_pages_fuzzy(&self, query: &str, limit: u8) -> Result<Vec<String>> {
+        self.opensearch(query, limit, "fuzzy").await
+    }
+
+    async fn opensearch(&self, query: &str, limit: u8, profile: &str) -> Result<Vec<String>> {
         let url = format!(
-            "https://en.wikipedia.org/w/api.php?action=opensearch&format=json&search={}&limit={}",
+            "{}?action=opensearch&format=json&search={}&limit={}&profile={}",
+            self.site.api_url,
             urlencoding::encode(query),
-            limit
+            limit,
+            profile
         );
 
-        let response = self.client.get(&url).send().await?;
-        let results: serde_json::Value = response.json().await?;
+        let response = self.send(&url).await?;
+        let results: serde_json::Value = read_json(response)?;
 
         if let Some(titles) = results.get(1).and_then(|v| v.as_array()) {
             Ok(titles
@@ -59,29 +275,69 @@ impl WikipediaClient {
     }
 
     pub async fn get_page_extract(&self, title: &str) -> Result<Option<WikipediaPage>> {
-        let url = format!(
-            "https://en.wikipedia.org/w/api.php?action=query&format=json&titles={}&prop=extracts&exintro=&explaintext=&exsectionformat=plain",
-            urlencoding::encode(title)
-        );
+        let response = self.send(&self.page_extract_url(title)).await?;
+        self.parse_page_extract(title, response).await
+    }
+
+    /// Re-fetch a cached page, sending its ETag/Last-Modified so an unchanged page costs a 304
+    pub async fn revalidate_page(&self, page: &WikipediaPage) -> Result<PageFetch> {
+        let mut headers = HeaderMap::new();
+        let validators = [
+            (reqwest::header::IF_NONE_MATCH, &page.etag),
+            (reqwest::header::IF_MODIFIED_SINCE, &page.last_modified),
+        ];
+        for (name, value) in validators {
+            if let Some(value) = value.as_deref().and_then(|value| HeaderValue::from_str(value).ok()) {
+                headers.insert(name, value);
+            }
+        }
+
+        let response = self.send_with_headers(&self.page_extract_url(&page.title), headers).await?;
+        if response.status == StatusCode::NOT_MODIFIED {
+            return Ok(PageFetch::NotModified);
+        }
+        Ok(match self.parse_page_extract(&page.title, response).await? {
+            Some(page) => PageFetch::Modified(Box::new(page)),
+            None => PageFetch::Missing,
+        })
+    }
 
-        let response = self.client.get(&url).send().await?;
-        let api_response: WikipediaApiResponse = response.json().await?;
+    fn page_extract_url(&self, title: &str) -> String {
+        format!(
+            "{}?action=query&format=json&titles={}&prop=extracts|pageprops|info|pageimages&ppprop=disambiguation&piprop=thumbnail&pithumbsize={}&exintro=&explaintext=&exsectionformat=plain&redirects=1",
+            self.site.api_url,
+            urlencoding::encode(title),
+            THUMBNAIL_SIZE
+        )
+    }
+
+    async fn parse_page_extract(&self, title: &str, response: ApiResponse) -> Result<Option<WikipediaPage>> {
+        let header = |name| {
+            response
+                .headers
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(reqwest::header::ETAG);
+        let last_modified = header(reqwest::header::LAST_MODIFIED);
+        let api_response: WikipediaApiResponse = read_json(response)?;
 
         for (_, page_data) in api_response.query.pages {
-            if page_data.missing.unwrap_or(false) {
+            if page_data.missing.is_some() {
                 return Ok(None);
             }
 
-            if let (Some(page_title), Some(extract), Some(page_id)) =
-                (page_data.title, page_data.extract, page_data.pageid)
-            {
-                return Ok(Some(WikipediaPage {
-                    title: page_title.clone(),
-                    extract,
-                    url: format!("https://en.wikipedia.org/wiki/{}", 
-                        urlencoding::encode(&page_title)),
-                    page_id,
-                }));
+            if page_data.is_disambiguation() {
+                let page_title = page_data.title.as_deref().unwrap_or(title);
+                let candidates = self.get_page_links(page_title, 20).await?;
+                return Err(WikiEngineError::Disambiguation(candidates));
+            }
+
+            if let Some(mut page) = page_data.into_page(&self.site) {
+                page.etag = etag;
+                page.last_modified = last_modified;
+                return Ok(Some(page));
             }
         }
 
@@ -90,12 +346,13 @@ impl WikipediaClient {
 
     pub async fn get_page_sections(&self, title: &str) -> Result<Vec<String>> {
         let url = format!(
-            "https://en.wikipedia.org/w/api.php?action=parse&format=json&page={}&prop=sections",
+            "{}?action=parse&format=json&page={}&prop=sections",
+            self.site.api_url,
             urlencoding::encode(title)
         );
 
-        let response = self.client.get(&url).send().await?;
-        let result: serde_json::Value = response.json().await?;
+        let response = self.send(&url).await?;
+        let result: serde_json::Value = read_json(response)?;
 
         if let Some(sections) = result
             .get("parse")
@@ -113,15 +370,36 @@ impl WikipediaClient {
         }
     }
 
+    /// Links from the article's "See also" section and infobox, which usually name exactly
+    /// the sibling and component concepts a tech tree wants
+    pub async fn get_curated_links(&self, title: &str) -> Result<Vec<String>> {
+        let url = format!(
+            "{}?action=parse&format=json&formatversion=2&prop=wikitext&redirects=1&page={}",
+            self.site.api_url,
+            urlencoding::encode(title)
+        );
+
+        let response = self.send(&url).await?;
+        let result: serde_json::Value = read_json(response)?;
+        let wikitext = result
+            .get("parse")
+            .and_then(|parse| parse.get("wikitext"))
+            .and_then(|wikitext| wikitext.as_str())
+            .unwrap_or_default();
+
+        Ok(curated_links_from_wikitext(wikitext))
+    }
+
     pub async fn get_page_links(&self, title: &str, limit: u8) -> Result<Vec<String>> {
         let url = format!(
-            "https://en.wikipedia.org/w/api.php?action=query&format=json&titles={}&prop=links&pllimit={}",
+            "{}?action=query&format=json&titles={}&prop=links&pllimit={}",
+            self.site.api_url,
             urlencoding::encode(title),
             limit
         );
 
-        let response = self.client.get(&url).send().await?;
-        let result: serde_json::Value = response.json().await?;
+        let response = self.send(&url).await?;
+        let result: serde_json::Value = read_json(response)?;
 
         if let Some(pages) = result.get("query").and_then(|q| q.get("pages")) {
             for (_, page) in pages.as_object().unwrap_or(&serde_json::Map::new()) {
@@ -140,6 +418,47 @@ impl WikipediaClient {
         Ok(vec![])
     }
 
+    /// Names of the page's visible categories, without the `Category:` prefix
+    pub async fn get_page_categories(&self, title: &str) -> Result<Vec<String>> {
+        let url = format!(
+            "{}?action=query&format=json&formatversion=2&prop=categories&clshow=!hidden&cllimit=max&redirects=1&titles={}",
+            self.site.api_url,
+            urlencoding::encode(title)
+        );
+
+        let response = self.send(&url).await?;
+        let result: serde_json::Value = read_json(response)?;
+
+        Ok(result
+            .pointer("/query/pages/0/categories")
+            .and_then(|categories| categories.as_array())
+            .map(|categories| {
+                categories
+                    .iter()
+                    .filter_map(|category| category.get("title").and_then(|t| t.as_str()))
+                    .map(|title| title.trim_start_matches("Category:").to_string())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Title of the English Wikipedia article linked to this page, if the page has one
+    pub async fn get_english_title(&self, title: &str) -> Result<Option<String>> {
+        let url = format!(
+            "{}?action=query&format=json&formatversion=2&prop=langlinks&lllang=en&redirects=1&titles={}",
+            self.site.api_url,
+            urlencoding::encode(title)
+        );
+
+        let response = self.send(&url).await?;
+        let result: serde_json::Value = read_json(response)?;
+
+        Ok(result
+            .pointer("/query/pages/0/langlinks/0/title")
+            .and_then(|title| title.as_str())
+            .map(|title| title.to_string()))
+    }
+
     pub async fn batch_get_extracts(&self, titles: &[String]) -> Result<Vec<WikipediaPage>> {
         if titles.is_empty() {
             return Ok(vec![]);
@@ -147,32 +466,266 @@ impl WikipediaClient {
 
         let titles_str = titles.join("|");
         let url = format!(
-            "https://en.wikipedia.org/w/api.php?action=query&format=json&titles={}&prop=extracts&exintro=&explaintext=&exsectionformat=plain",
-            urlencoding::encode(&titles_str)
+            "{}?action=query&format=json&titles={}&prop=extracts|info|pageimages&piprop=thumbnail&pithumbsize={}&exintro=&explaintext=&exsectionformat=plain",
+            self.site.api_url,
+            urlencoding::encode(&titles_str),
+            THUMBNAIL_SIZE
         );
 
-        let response = self.client.get(&url).send().await?;
-        let api_response: WikipediaApiResponse = response.json().await?;
+        let response = self.send(&url).await?;
+        let api_response: WikipediaApiResponse = read_json(response)?;
 
         let mut results = Vec::new();
         for (_, page_data) in api_response.query.pages {
-            if page_data.missing.unwrap_or(false) {
+            if page_data.missing.is_some() {
                 continue;
             }
 
-            if let (Some(page_title), Some(extract), Some(page_id)) =
-                (page_data.title, page_data.extract, page_data.pageid)
-            {
-                results.push(WikipediaPage {
-                    title: page_title.clone(),
-                    extract,
-                    url: format!("https://en.wikipedia.org/wiki/{}", 
-                        urlencoding::encode(&page_title)),
-                    page_id,
-                });
+            if let Some(page) = page_data.into_page(&self.site) {
+                results.push(page);
             }
         }
 
         Ok(results)
     }
-}
\ No newline at end of file
+
+    /// The underlying HTTP client, for other sources that want to share its connection pool
+    pub fn http_client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn site(&self) -> &MediaWikiSite {
+        &self.site
+    }
+
+    /// Total user pageviews of an article over the last 30 days, from the Wikimedia pageviews API
+    pub async fn get_monthly_pageviews(&self, title: &str) -> Result<u64> {
+        let Some(project) = &self.site.pageviews_project else {
+            return Err(WikiEngineError::WikipediaApi(format!("No pageviews available for {}", self.site.name)));
+        };
+        let end = chrono::Utc::now().date_naive();
+        let start = end - chrono::Duration::days(30);
+        let url = format!(
+            "https://wikimedia.org/api/rest_v1/metrics/pageviews/per-article/{}/all-access/user/{}/daily/{}/{}",
+            project,
+            urlencoding::encode(&title.replace(' ', "_")),
+            start.format("%Y%m%d"),
+            end.format("%Y%m%d")
+        );
+
+        let response = self.send(&url).await?;
+        let result: serde_json::Value = read_json(response)?;
+
+        Ok(result
+            .get("items")
+            .and_then(|items| items.as_array())
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item.get("views").and_then(|views| views.as_u64()))
+                    .sum()
+            })
+            .unwrap_or(0))
+    }
+
+    /// Current revision ids for the given titles (missing pages are omitted)
+    pub async fn get_revision_ids(&self, titles: &[String]) -> Result<HashMap<String, u64>> {
+        let mut revisions = HashMap::new();
+
+        // The query API accepts at most 50 titles per request
+        for chunk in titles.chunks(50) {
+            let url = format!(
+                "{}?action=query&format=json&titles={}&prop=info",
+                self.site.api_url,
+                urlencoding::encode(&chunk.join("|"))
+            );
+
+            let response = self.send(&url).await?;
+            let api_response: WikipediaApiResponse = read_json(response)?;
+
+            for (_, page_data) in api_response.query.pages {
+                if let (Some(title), Some(revision_id)) = (page_data.title, page_data.lastrevid) {
+                    revisions.insert(title, revision_id);
+                }
+            }
+        }
+
+        Ok(revisions)
+    }
+}
+
+fn retry_after(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<u64>().ok())
+}
+
+/// A response whose body was read while its request still held its concurrency permits
+struct ApiResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+/// Decode an API response body, surfacing an `error` envelope as a specific engine error
+/// instead of an opaque deserialization failure
+fn read_json<T: DeserializeOwned>(response: ApiResponse) -> Result<T> {
+    let decoded = decode_api_body(&response.body, retry_after(&response.headers));
+    if decoded.is_err() {
+        monitoring::record_wikipedia_api_error();
+    }
+    decoded
+}
+
+fn decode_api_body<T: DeserializeOwned>(body: &[u8], retry_after: Option<u64>) -> Result<T> {
+    let value: serde_json::Value = serde_json::from_slice(body)?;
+    if let Some(error) = value.get("error") {
+        if let Ok(error) = ApiErrorBody::deserialize(error) {
+            return Err(error.into_error(retry_after));
+        }
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// See-also links first, then infobox links, deduplicated in order of appearance
+fn curated_links_from_wikitext(wikitext: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+
+    let sections = [see_also_section(wikitext), infobox(wikitext)];
+    for section in sections.into_iter().flatten() {
+        for link in wiki_links(section) {
+            if seen.insert(link.to_lowercase()) {
+                links.push(link);
+            }
+        }
+    }
+    links
+}
+
+static SEE_ALSO_HEADING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?m)^==\s*See also\s*==\s*$").unwrap());
+static SECTION_HEADING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?m)^==[^=]").unwrap());
+static WIKI_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[\[([^\[\]|#]+)(?:#[^\[\]|]*)?(?:\|[^\[\]]*)?\]\]").unwrap());
+
+fn see_also_section(wikitext: &str) -> Option<&str> {
+    let start = SEE_ALSO_HEADING.find(wikitext)?.end();
+    let rest = &wikitext[start..];
+    let end = SECTION_HEADING.find(rest).map_or(rest.len(), |m| m.start());
+    Some(&rest[..end])
+}
+
+fn infobox(wikitext: &str) -> Option<&str> {
+    let start = wikitext.find("{{Infobox")?;
+    let mut depth = 0usize;
+    let bytes = wikitext.as_bytes();
+    let mut i = start;
+    while i + 1 < bytes.len() {
+        match &bytes[i..i + 2] {
+            b"{{" => {
+                depth += 1;
+                i += 2;
+            }
+            b"}}" => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return Some(&wikitext[start..i]);
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Article targets of `[[Target]]`/`[[Target|label]]` links, skipping namespaced links (File:, Category:, ...)
+fn wiki_links(text: &str) -> Vec<String> {
+    WIKI_LINK
+        .captures_iter(text)
+        .filter_map(|caps| caps.get(1))
+        .map(|target| target.as_str().trim())
+        .filter(|target| !target.is_empty() && !target.contains(':'))
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_proxy_fails_instead_of_connecting_directly() {
+        let config = HttpClientConfig { proxy: Some("http://proxy internal:3128".to_string()), ..HttpClientConfig::default() };
+        let error = MediaWikiClient::with_config(&config, MediaWikiSite::default()).err().unwrap();
+        assert!(matches!(error, WikiEngineError::Network(ref message) if message.contains("Invalid proxy URL")), "{}", error);
+
+        let config = HttpClientConfig { proxy: Some("http://proxy.internal:3128".to_string()), ..HttpClientConfig::default() };
+        assert!(MediaWikiClient::with_config(&config, MediaWikiSite::default()).is_ok());
+    }
+
+    #[test]
+    fn api_error_envelopes_map_to_specific_errors() {
+        let maxlag = br#"{"error":{"code":"maxlag","info":"Waiting for db1: 5.2 seconds lagged","lag":5.2}}"#;
+        assert!(matches!(
+            decode_api_body::<serde_json::Value>(maxlag, None),
+            Err(WikiEngineError::ReplicationLag { retry_after: Some(6) })
+        ));
+
+        let ratelimited = br#"{"error":{"code":"ratelimited","info":"You've exceeded your rate limit."}}"#;
+        assert!(matches!(
+            decode_api_body::<serde_json::Value>(ratelimited, Some(30)),
+            Err(WikiEngineError::RateLimited { retry_after: Some(30) })
+        ));
+
+        let badtitle = br#"{"error":{"code":"invalidtitle","info":"Bad title \"<>\"."}}"#;
+        assert!(matches!(
+            decode_api_body::<serde_json::Value>(badtitle, None),
+            Err(WikiEngineError::InvalidTitle(_))
+        ));
+    }
+
+    #[test]
+    fn curated_links_come_from_see_also_and_infobox() {
+        let wikitext = "{{Infobox machine\n| components = [[Rotor (electric)|rotor]], [[Stator]]\n| image = [[File:Motor.jpg]]\n}}\n\
+            An '''electric motor''' uses [[Magnetism]].\n\
+            == See also ==\n* [[Brushless DC electric motor]]\n* [[Stator]]\n\
+            == References ==\n* [[Not included]]\n";
+
+        assert_eq!(
+            curated_links_from_wikitext(wikitext),
+            vec!["Brushless DC electric motor", "Stator", "Rotor (electric)"]
+        );
+    }
+
+    #[tokio::test]
+    async fn permits_are_held_until_the_body_is_read() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Sends the headers at once and the body only when told to
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_url = format!("http://{}/w/api.php", listener.local_addr().unwrap());
+        let (send_body, body_wanted) = tokio::sync::oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let _ = socket.read(&mut [0; 4096]).await.unwrap();
+            let body = br#"["motor",["Electric motor"]]"#;
+            let head = format!("HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n", body.len());
+            socket.write_all(head.as_bytes()).await.unwrap();
+            body_wanted.await.unwrap();
+            socket.write_all(body).await.unwrap();
+        });
+
+        let permits = Arc::new(Semaphore::new(1));
+        let site = MediaWikiSite { api_url, ..MediaWikiSite::default() };
+        let client = MediaWikiClient::with_config(&HttpClientConfig::default(), site).unwrap().with_fetch_permits(Arc::clone(&permits));
+        let search = tokio::spawn(async move { client.search_pages("motor", 1).await });
+
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert_eq!(permits.available_permits(), 0, "the permit must cover the body transfer");
+        send_body.send(()).unwrap();
+        assert_eq!(search.await.unwrap().unwrap(), ["Electric motor"]);
+        assert_eq!(permits.available_permits(), 1);
+    }
+}
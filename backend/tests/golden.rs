@@ -0,0 +1,168 @@
+//! End-to-end `analyze_recursive` runs against recorded MediaWiki API responses, compared with
+//! golden result snapshots. Regenerate the snapshots with `UPDATE_GOLDEN=1 cargo test --test golden`.
+#![cfg(feature = "network")]
+
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use wiki_engine::cache::WikiEngineCache;
+use wiki_engine::config::{ConceptFilterConfig, HttpClientConfig, MediaWikiSite};
+use wiki_engine::types::{SearchRequest, WikiEngineError};
+use wiki_engine::wikipedia::MediaWikiClient;
+use wiki_engine::WikiEngine;
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+/// Scores and other floats may drift this much before a snapshot counts as changed
+const FLOAT_TOLERANCE: f64 = 1e-3;
+
+/// Fields that differ between runs even with identical responses
+const VOLATILE_FIELDS: &[&str] = &["processing_time_ms", "total_processing_time_ms", "id", "network_stats"];
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/wikipedia")
+}
+
+/// Answers action API requests from `tests/fixtures/wikipedia/<Title>.<kind>.json`. Titles without
+/// a fixture are reported missing, and other lookups come back empty.
+struct RecordedWiki;
+
+impl Respond for RecordedWiki {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let param = |name: &str| {
+            request
+                .url
+                .query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.into_owned())
+                .unwrap_or_default()
+        };
+        let prop = param("prop");
+        let (kind, title) = match param("action").as_str() {
+            "parse" if prop == "wikitext" => ("wikitext", param("page")),
+            "query" if prop.contains("extracts") => ("extract", param("titles")),
+            "query" if prop == "links" => ("links", param("titles")),
+            "query" if prop == "categories" => ("categories", param("titles")),
+            _ => ("other", param("titles")),
+        };
+
+        // MediaWiki titles are case-insensitive in their first letter only
+        let mut letters = title.chars();
+        let title: String = letters.next().into_iter().flat_map(char::to_uppercase).chain(letters).collect();
+        let fixture = fixtures_dir().join(format!("{}.{}.json", title.replace(' ', "_"), kind));
+        let body = match std::fs::read_to_string(&fixture) {
+            Ok(body) => serde_json::from_str(&body).unwrap(),
+            Err(_) if kind == "extract" => {
+                json!({ "batchcomplete": "", "query": { "pages": { "-1": { "ns": 0, "title": title, "missing": "" } } } })
+            }
+            Err(_) => json!({ "batchcomplete": "" }),
+        };
+        ResponseTemplate::new(200).set_body_json(body)
+    }
+}
+
+async fn recorded_engine() -> (MockServer, WikiEngine) {
+    let server = MockServer::start().await;
+    Mock::given(wiremock::matchers::path("/w/api.php")).respond_with(RecordedWiki).mount(&server).await;
+
+    let site = MediaWikiSite {
+        name: "recorded".to_string(),
+        api_url: format!("{}/w/api.php", server.uri()),
+        article_url_template: "https://en.wikipedia.org/wiki/$1".to_string(),
+        pageviews_project: None,
+    };
+    let engine = WikiEngine::builder()
+        .with_cache(Arc::new(WikiEngineCache::new()))
+        .with_wikipedia_client(MediaWikiClient::with_config(&HttpClientConfig::default(), site).unwrap())
+        .with_concept_filter(ConceptFilterConfig { min_relevance: 1, ..Default::default() })
+        .without_env_sources()
+        .build()
+        .unwrap();
+    (server, engine)
+}
+
+fn request(term: &str) -> SearchRequest {
+    SearchRequest {
+        term: term.to_string(),
+        max_depth: Some(2),
+        max_results: Some(5),
+        ..Default::default()
+    }
+}
+
+fn strip_volatile(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|key, _| !VOLATILE_FIELDS.contains(&key.as_str()));
+            map.values_mut().for_each(strip_volatile);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_volatile),
+        _ => {}
+    }
+}
+
+/// Path of the first difference between `actual` and `expected`, numbers compared within tolerance
+fn first_difference(actual: &Value, expected: &Value, path: &str) -> Option<String> {
+    match (actual, expected) {
+        (Value::Number(a), Value::Number(b)) => {
+            let (a, b) = (a.as_f64()?, b.as_f64()?);
+            ((a - b).abs() > FLOAT_TOLERANCE).then(|| format!("{}: {} != {}", path, a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            let keys = a.keys().chain(b.keys().filter(|key| !a.contains_key(*key)));
+            keys.into_iter().find_map(|key| match (a.get(key), b.get(key)) {
+                (Some(a), Some(b)) => first_difference(a, b, &format!("{}.{}", path, key)),
+                _ => Some(format!("{}.{}: present on only one side", path, key)),
+            })
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => a
+            .iter()
+            .zip(b)
+            .enumerate()
+            .find_map(|(i, (a, b))| first_difference(a, b, &format!("{}[{}]", path, i))),
+        _ => (actual != expected).then(|| format!("{}: {} != {}", path, actual, expected)),
+    }
+}
+
+fn assert_matches_golden(name: &str, mut actual: Value) {
+    strip_volatile(&mut actual);
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.json", name));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&golden, serde_json::to_string_pretty(&actual).unwrap() + "\n").unwrap();
+        return;
+    }
+
+    let expected: Value = serde_json::from_str(&std::fs::read_to_string(&golden).unwrap()).unwrap();
+    if let Some(difference) = first_difference(&actual, &expected, name) {
+        panic!("result differs from {} ({}); rerun with UPDATE_GOLDEN=1 if intended", golden.display(), difference);
+    }
+}
+
+#[tokio::test]
+async fn bridge_matches_golden() {
+    let (_server, engine) = recorded_engine().await;
+    let result = engine.analyze_recursive(&request("bridge")).await.unwrap();
+
+    assert_eq!(result.tree.term, "bridge");
+    assert!(!result.tree.children.is_empty());
+    assert_matches_golden("bridge", serde_json::to_value(&result).unwrap());
+}
+
+#[tokio::test]
+async fn uav_follows_redirect_and_matches_golden() {
+    let (_server, engine) = recorded_engine().await;
+    let result = engine.analyze_recursive(&request("UAV")).await.unwrap();
+
+    assert!(result.tree.principles.iter().all(|p| p.source_url.ends_with("/Unmanned_aerial_vehicle")));
+    assert_matches_golden("uav", serde_json::to_value(&result).unwrap());
+}
+
+#[tokio::test]
+async fn disambiguation_page_lists_candidates() {
+    let (_server, engine) = recorded_engine().await;
+    let error = engine.analyze_recursive(&request("Mercury")).await.unwrap_err();
+
+    let WikiEngineError::Disambiguation(candidates) = error else {
+        panic!("expected a disambiguation error, got {:?}", error);
+    };
+    assert_matches_golden("mercury", json!({ "candidates": candidates }));
+}
@@ -0,0 +1,50 @@
+//! Run with `wasm-pack test --headless --firefox -- --no-default-features --features network`
+#![cfg(all(target_arch = "wasm32", feature = "network"))]
+
+use wasm_bindgen_test::*;
+use wiki_engine::semantic_analyzer::ConceptDecomposition;
+use wiki_engine::wasm::WasmWikiEngine;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn decomposes_known_concept_without_fetching() {
+    let engine = WasmWikiEngine::new(None).unwrap();
+
+    let value = engine.decompose("UAV".to_string(), Some(2), None).unwrap();
+    let decomposition: ConceptDecomposition = serde_wasm_bindgen::from_value(value).unwrap();
+
+    assert_eq!(decomposition.concept, "UAV");
+    assert!(!decomposition.components.is_empty());
+}
+
+#[wasm_bindgen_test]
+fn decomposes_unknown_concept_from_supplied_text_only() {
+    let engine = WasmWikiEngine::new(None).unwrap();
+    let text = "The grinder is driven by an electric motor through a gear and a steel shaft.".to_string();
+
+    let value = engine.decompose("Coffee grinder".to_string(), Some(2), Some(text)).unwrap();
+    let decomposition: ConceptDecomposition = serde_wasm_bindgen::from_value(value).unwrap();
+    assert!(!decomposition.components.is_empty());
+
+    assert!(engine.decompose("Coffee grinder".to_string(), Some(2), None).is_err());
+}
+
+#[wasm_bindgen_test]
+async fn rejects_unreachable_api_as_js_error() {
+    let engine = WasmWikiEngine::new(Some("http://127.0.0.1:9/w/api.php".to_string())).unwrap();
+
+    let error = engine.suggest("transistor".to_string(), Some(3)).await.unwrap_err();
+    assert!(error.as_string().is_some());
+}
+
+#[wasm_bindgen_test]
+async fn rejects_requests_beyond_the_default_limits_before_fetching() {
+    let engine = WasmWikiEngine::new(Some("http://127.0.0.1:9/w/api.php".to_string())).unwrap();
+
+    let error = engine.analyze("Turbine".to_string(), Some(255)).await.unwrap_err();
+    assert_eq!(error.as_string().unwrap(), "Invalid request: max_depth must be at most 5");
+
+    let error = engine.analyze(" \u{7} ".to_string(), None).await.unwrap_err();
+    assert!(error.as_string().unwrap().starts_with("Invalid request: term"));
+}
@@ -4,7 +4,9 @@ import {
   AnalysisResult, 
   ApiResponse, 
   SearchSuggestion, 
-  CacheStats 
+  CacheStats,
+  BatchAnalysisItem,
+  ResultRefresh
 } from '../types';
 
 import { API_CONFIG } from '../config';
@@ -115,6 +117,19 @@ export class WikiEngineAPI {
     }
   }
 
+  // Re-analyze only the stale nodes of a previously stored result
+  static async refreshResult(resultId: string): Promise<ResultRefresh> {
+    const response: AxiosResponse<ApiResponse<ResultRefresh>> = await apiClient.post(
+      `/results/${encodeURIComponent(resultId)}/refresh`
+    );
+
+    if (response.data.success && response.data.data) {
+      return response.data.data;
+    } else {
+      throw new Error(response.data.error || 'Failed to refresh result');
+    }
+  }
+
   static async clearCache(): Promise<string> {
     const response: AxiosResponse<ApiResponse<string>> = await apiClient.post('/cache/clear');
     
@@ -125,20 +140,20 @@ export class WikiEngineAPI {
     }
   }
 
-  // Batch analysis (if the backend supports it)
+  // Batch analysis, run concurrently on the backend
   static async batchAnalyze(terms: string[], maxDepth: number = 2): Promise<AnalysisResult[]> {
-    const requests = terms.map(term => ({ term, max_depth: maxDepth, max_results: 5 }));
-    
-    // Since we don't have a batch endpoint, we'll make concurrent requests
-    const promises = requests.map(request => this.analyzeTermRecursive(request));
-    
     try {
-      const results = await Promise.allSettled(promises);
-      return results
-        .filter((result): result is PromiseFulfilledResult<AnalysisResult> => 
-          result.status === 'fulfilled'
-        )
-        .map(result => result.value);
+      const response: AxiosResponse<ApiResponse<BatchAnalysisItem[]>> = await apiClient.post(
+        '/analyze/batch',
+        { terms, max_depth: maxDepth, max_results: 5 }
+      );
+
+      if (response.data.success && response.data.data) {
+        return response.data.data
+          .filter((item): item is BatchAnalysisItem & { result: AnalysisResult } => !!item.result)
+          .map(item => item.result);
+      }
+      return [];
     } catch (error) {
       console.error('Batch analysis failed:', error);
       return [];
@@ -2,8 +2,33 @@ export interface SearchRequest {
   term: string;
   max_depth?: number;
   max_results?: number;
+  categories?: PrincipleCategory[];
+  min_confidence?: number;
+  max_principles_per_node?: number;
+  prune_below?: number;
+  exclude_terms?: string[];
+  pin_terms?: string[];
+  concept_source?: ConceptSource;
+  document_source?: DocumentSourceKind;
+  dedupe_scope?: DedupeScope;
+  timeout_ms?: number;
+  locale?: string;
+  preset?: string;
 }
 
+export type DedupeScope = 'node' | 'tree';
+
+export type DocumentSourceKind = 'wikipedia' | 'url' | 'local_folder' | 'arxiv' | 'patent';
+
+export type ConceptSource =
+  | 'extract_regex'
+  | 'page_links'
+  | 'see_also'
+  | 'categories'
+  | 'knowledge_base'
+  | 'see_also_then_extract'
+  | 'combined';
+
 export enum PrincipleCategory {
   Structural = 'Structural',
   Mechanical = 'Mechanical',
@@ -17,6 +42,15 @@ export enum PrincipleCategory {
   Other = 'Other'
 }
 
+export interface CategoryDefinition {
+  id: string;
+  name: string;
+  parent?: string;
+  color?: string;
+  patterns: string[];
+  prototype?: string;
+}
+
 export interface EngineeringPrinciple {
   id: string;
   title: string;
@@ -25,14 +59,51 @@ export interface EngineeringPrinciple {
   confidence: number;
   source_url: string;
   related_terms: string[];
+  source_span: [number, number];
+  source_section?: string;
+  appears_in?: string[];
+  classified_by?: 'patterns' | 'embeddings';
+  named_law?: string;
+  component?: string;
 }
 
 export interface AnalysisNode {
   term: string;
   principles: EngineeringPrinciple[];
-  children: { [key: string]: AnalysisNode };
+  children: AnalysisNode[];
   depth: number;
   processing_time_ms: number;
+  warnings: AnalysisWarning[];
+  thumbnail_url?: string;
+  popularity?: number;
+  summary?: string;
+  complexity?: number;
+  language?: string;
+  romanized_term?: string;
+  english_term?: string;
+  provenance?: NodeProvenance;
+  truncated_source?: boolean;
+}
+
+export interface NodeProvenance {
+  parent_term: string;
+  sources: ConceptSource[];
+  pinned: boolean;
+  sentence?: string;
+  sentence_span?: [number, number];
+}
+
+export interface ResultRefresh {
+  result: AnalysisResult;
+  refreshed_terms: string[];
+  nodes_checked: number;
+}
+
+export interface AnalysisWarning {
+  term: string;
+  depth: number;
+  kind: 'page_not_found' | 'wikipedia_api' | 'network' | 'analysis' | 'serialization';
+  message: string;
 }
 
 export interface AnalysisResult {
@@ -41,6 +112,49 @@ export interface AnalysisResult {
   total_processing_time_ms: number;
   total_principles: number;
   max_depth_reached: number;
+  warnings: AnalysisWarning[];
+  result_id?: string;
+  network_stats: NetworkStats;
+  deadline_exceeded: boolean;
+  category_summary: Record<string, CategoryStats>;
+  glossary: GlossaryEntry[];
+}
+
+export interface GlossaryEntry {
+  term: string;
+  definition: string;
+  source_title: string;
+  source_url: string;
+}
+
+export interface CategoryStats {
+  count: number;
+  share: number;
+  average_confidence: number;
+  rollup_count: number;
+}
+
+export interface AnalysisPlan {
+  term: string;
+  estimated_nodes: number;
+  known_nodes: number;
+  cached_pages: number;
+  estimated_wikipedia_calls: number;
+  estimated_seconds: number;
+  cached_result: boolean;
+  exceeds_timeout: boolean;
+}
+
+export interface NetworkStats {
+  requests: number;
+  bytes: number;
+  errors: number;
+}
+
+export interface BatchAnalysisItem {
+  term: string;
+  result?: AnalysisResult;
+  error?: string;
 }
 
 export interface ApiResponse<T> {
@@ -54,12 +168,15 @@ export interface SearchSuggestion {
   term: string;
   confidence: number;
   category: string;
+  matched_via: 'exact' | 'fuzzy' | 'search';
 }
 
 export interface CacheStats {
   wikipedia_pages_count: number;
   principles_count: number;
   analysis_nodes_count: number;
+  suggestions_count: number;
+  decompositions_count: number;
   total_memory_usage: number;
 }
 
@@ -74,6 +191,34 @@ export interface TreeNodeData {
   y?: number;
 }
 
+export interface Flashcard {
+  question: string;
+  answer: string;
+  term: string;
+  category: PrincipleCategory | { Other: string };
+  source_url: string;
+}
+
+export interface LearningPath {
+  concept: string;
+  stages: LearningStage[];
+  total_effort_minutes: number;
+}
+
+export interface LearningStage {
+  stage: number;
+  topics: LearningTopic[];
+  effort_minutes: number;
+}
+
+export interface LearningTopic {
+  term: string;
+  prerequisites: string[];
+  complexity?: number;
+  principles: number;
+  effort_minutes: number;
+}
+
 export interface AnalysisProgress {
   current_term: string;
   current_depth: number;
@@ -9,7 +9,7 @@ import {
 export class DataTransformUtils {
   // Transform backend AnalysisNode to frontend TreeNodeData
   static transformToTreeData(node: AnalysisNode): TreeNodeData {
-    const children = Object.values(node.children || {}).map(child => 
+    const children = (node.children || []).map(child => 
       this.transformToTreeData(child)
     );
 